@@ -66,7 +66,15 @@ pub fn find_all() -> anyhow::Result<Vec<AllocatorLib>> {
 
             for path in paths {
                 if seen_paths.insert(path.clone()) {
-                    results.push(AllocatorLib { kind: *kind, path });
+                    let tcmalloc_variant = (*kind == AllocatorKind::Tcmalloc)
+                        .then(|| super::static_linked::find_statically_linked_allocator(&path))
+                        .flatten()
+                        .and_then(|detected| detected.tcmalloc_variant);
+                    results.push(AllocatorLib {
+                        kind: *kind,
+                        path,
+                        tcmalloc_variant,
+                    });
                 }
             }
         }
@@ -3,13 +3,20 @@
 //! This module provides a framework for discovering and attaching to different
 //! memory allocators. It's designed to be easily extensible for adding new allocators.
 
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
 
 mod dynamic;
+mod report;
 mod static_linked;
 
+pub use report::{AllocatorDiscovery, DiscoveryReport, DiscoverySource};
+pub use static_linked::{DetectedAllocator, find_statically_linked_allocator};
+
 /// Represents the different allocator types we support.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum AllocatorKind {
     /// Standard C library (glibc, musl, etc.)
     Libc,
@@ -25,10 +32,12 @@ pub enum AllocatorKind {
     /// - **gperftools** (github.com/gperftools/gperftools): Original ~2005 release.
     ///   Exports both standard symbols (malloc/free) AND tc_* prefixed symbols.
     /// - **google/tcmalloc** (github.com/google/tcmalloc): Modern ~2020 rewrite.
-    ///   Exports ONLY standard symbols (malloc/free/etc.) - no tc_* prefix.
+    ///   Exports ONLY standard symbols (malloc/free/etc.) - no tc_* prefix. Detected in
+    ///   statically linked binaries through its `TCMallocInternal*` symbols instead.
     ///
-    /// We'll always try to attach to both the standard and `tc_*` API. If the newer rewrite is
-    /// used, we'll only attach to the standard API.
+    /// The variant is reported in [`AllocatorLib::tcmalloc_variant`]: we attach to both the
+    /// standard and `tc_*` API, unless the newer rewrite is used, where we only attach to the
+    /// standard API.
     Tcmalloc,
     // Future allocators:
     // Hoard,
@@ -61,19 +70,50 @@ impl AllocatorKind {
     }
 }
 
+/// The implementation of a [`AllocatorKind::Tcmalloc`] allocator, which tells the API to attach to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TcmallocVariant {
+    /// gperftools, exporting the `tc_*` API on top of the standard one
+    Gperftools,
+    /// google/tcmalloc, exporting only the standard API
+    Google,
+}
+
 /// Discovered allocator library with its kind and path.
 #[derive(Debug, Clone)]
 pub struct AllocatorLib {
     pub kind: AllocatorKind,
     pub path: PathBuf,
+    /// The implementation of a TCMalloc allocator, `None` for the other kinds or when unknown
+    pub tcmalloc_variant: Option<TcmallocVariant>,
 }
 
 impl AllocatorLib {
     pub fn find_all() -> anyhow::Result<Vec<AllocatorLib>> {
-        let mut allocators = static_linked::find_all()?;
-        allocators.extend(Self::find_from_env());
-        allocators.extend(dynamic::find_all()?);
-        Ok(allocators)
+        Ok(Self::discover()?.0)
+    }
+
+    /// Discover all the allocators, along with a [`DiscoveryReport`] of where each kind was
+    /// found.
+    ///
+    /// Paths are canonical, and each `(kind, path)` pair is returned once even when found by
+    /// several discovery methods.
+    pub fn discover() -> anyhow::Result<(Vec<AllocatorLib>, DiscoveryReport)> {
+        let static_allocators = static_linked::find_all()?
+            .into_iter()
+            .chain(Self::find_from_env())
+            .unique_by(|allocator| (allocator.kind, allocator.path.clone()))
+            .collect::<Vec<_>>();
+        let dynamic_allocators = dynamic::find_all()?;
+
+        let report = DiscoveryReport::new(&static_allocators, &dynamic_allocators);
+        let allocators = static_allocators
+            .into_iter()
+            .chain(dynamic_allocators)
+            .unique_by(|allocator| (allocator.kind, allocator.path.clone()))
+            .collect();
+        Ok((allocators, report))
     }
 
     /// Discover allocators from binaries listed in the `CODSPEED_MEMTRACK_BINARIES` env var.
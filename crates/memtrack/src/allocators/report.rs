@@ -0,0 +1,174 @@
+use super::{AllocatorKind, AllocatorLib};
+use itertools::Itertools;
+use runner_shared::artifacts::ArtifactExt;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+/// Where an allocator kind was found during the discovery.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DiscoverySource {
+    NotFound,
+    /// Statically linked in a binary of the build directories or of `CODSPEED_MEMTRACK_BINARIES`
+    Static,
+    /// Shared library installed on the system
+    Dynamic,
+    Both,
+}
+
+/// Discovery result of a single allocator kind.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AllocatorDiscovery {
+    pub kind: AllocatorKind,
+    pub source: DiscoverySource,
+    pub static_paths: Vec<PathBuf>,
+    pub dynamic_paths: Vec<PathBuf>,
+}
+
+/// Summary of the allocator discovery, explaining where memtrack attached its probes.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DiscoveryReport {
+    /// One entry per [`AllocatorKind`], in the order of [`AllocatorKind::all`]
+    pub allocators: Vec<AllocatorDiscovery>,
+}
+
+impl ArtifactExt for DiscoveryReport {}
+
+impl DiscoveryReport {
+    pub fn new(static_allocators: &[AllocatorLib], dynamic_allocators: &[AllocatorLib]) -> Self {
+        let paths_of = |allocators: &[AllocatorLib], kind: AllocatorKind| {
+            allocators
+                .iter()
+                .filter(|allocator| allocator.kind == kind)
+                .map(|allocator| allocator.path.clone())
+                .collect::<Vec<_>>()
+        };
+
+        let allocators = AllocatorKind::all()
+            .iter()
+            .map(|&kind| {
+                let static_paths = paths_of(static_allocators, kind);
+                let dynamic_paths = paths_of(dynamic_allocators, kind);
+                let source = match (static_paths.is_empty(), dynamic_paths.is_empty()) {
+                    (true, true) => DiscoverySource::NotFound,
+                    (false, true) => DiscoverySource::Static,
+                    (true, false) => DiscoverySource::Dynamic,
+                    (false, false) => DiscoverySource::Both,
+                };
+                AllocatorDiscovery {
+                    kind,
+                    source,
+                    static_paths,
+                    dynamic_paths,
+                }
+            })
+            .collect();
+
+        Self { allocators }
+    }
+
+    pub fn get(&self, kind: AllocatorKind) -> Option<&AllocatorDiscovery> {
+        self.allocators
+            .iter()
+            .find(|allocator| allocator.kind == kind)
+    }
+}
+
+impl std::fmt::Display for DiscoveryReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for allocator in &self.allocators {
+            write!(f, "{}: ", allocator.kind.name())?;
+            if allocator.source == DiscoverySource::NotFound {
+                writeln!(f, "not found")?;
+                continue;
+            }
+
+            let sources = [
+                ("static", &allocator.static_paths),
+                ("dynamic", &allocator.dynamic_paths),
+            ];
+            let found = sources
+                .iter()
+                .filter(|(_, paths)| !paths.is_empty())
+                .map(|(source, paths)| {
+                    let paths = paths.iter().map(|path| path.display()).join(", ");
+                    format!("{source} ({paths})")
+                })
+                .join(", ");
+            writeln!(f, "{found}")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn allocator(kind: AllocatorKind, path: &str) -> AllocatorLib {
+        AllocatorLib {
+            kind,
+            path: PathBuf::from(path),
+            tcmalloc_variant: None,
+        }
+    }
+
+    #[test]
+    fn test_report_from_mixed_discovery() {
+        let static_allocators = [
+            allocator(AllocatorKind::Jemalloc, "/build/target/release/bench"),
+            allocator(AllocatorKind::Libc, "/build/target/release/bench"),
+        ];
+        let dynamic_allocators = [
+            allocator(AllocatorKind::Libc, "/usr/lib/libc.so.6"),
+            allocator(AllocatorKind::LibCpp, "/usr/lib/libstdc++.so.6"),
+        ];
+
+        let report = DiscoveryReport::new(&static_allocators, &dynamic_allocators);
+
+        let sources = report
+            .allocators
+            .iter()
+            .map(|allocator| (allocator.kind, allocator.source))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            sources,
+            vec![
+                (AllocatorKind::Jemalloc, DiscoverySource::Static),
+                (AllocatorKind::Mimalloc, DiscoverySource::NotFound),
+                (AllocatorKind::Tcmalloc, DiscoverySource::NotFound),
+                (AllocatorKind::LibCpp, DiscoverySource::Dynamic),
+                (AllocatorKind::Libc, DiscoverySource::Both),
+            ]
+        );
+
+        let libc = report.get(AllocatorKind::Libc).unwrap();
+        assert_eq!(
+            libc.static_paths,
+            vec![PathBuf::from("/build/target/release/bench")]
+        );
+        assert_eq!(
+            libc.dynamic_paths,
+            vec![PathBuf::from("/usr/lib/libc.so.6")]
+        );
+
+        assert_eq!(
+            report.to_string(),
+            "jemalloc: static (/build/target/release/bench)\n\
+             mimalloc: not found\n\
+             tcmalloc: not found\n\
+             libc++: dynamic (/usr/lib/libstdc++.so.6)\n\
+             libc: static (/build/target/release/bench), dynamic (/usr/lib/libc.so.6)\n"
+        );
+    }
+
+    #[test]
+    fn test_report_round_trips_through_the_artifact_encoding() {
+        let report =
+            DiscoveryReport::new(&[allocator(AllocatorKind::Mimalloc, "/build/bench")], &[]);
+
+        let mut encoded = Vec::new();
+        report.encode_to_writer(&mut encoded).unwrap();
+        let decoded = DiscoveryReport::decode_from_reader(encoded.as_slice()).unwrap();
+        assert_eq!(decoded, report);
+    }
+}
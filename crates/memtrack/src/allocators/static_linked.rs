@@ -2,7 +2,7 @@ use std::collections::HashSet;
 use std::fs;
 use std::path::{Path, PathBuf};
 
-use crate::allocators::{AllocatorKind, AllocatorLib};
+use crate::allocators::{AllocatorKind, AllocatorLib, TcmallocVariant};
 
 impl AllocatorKind {
     /// Returns the symbol names used to detect this allocator in binaries.
@@ -12,7 +12,26 @@ impl AllocatorKind {
             AllocatorKind::LibCpp => &["_Znwm", "_Znam", "_ZdlPv", "_ZdaPv"],
             AllocatorKind::Jemalloc => &["_rjem_malloc", "je_malloc", "je_malloc_default"],
             AllocatorKind::Mimalloc => &["mi_malloc_aligned", "mi_malloc", "mi_free"],
-            AllocatorKind::Tcmalloc => &["tc_malloc", "tc_free", "tc_version"],
+            // google/tcmalloc has no `tc_*` API, its standard functions alias the internal ones
+            AllocatorKind::Tcmalloc => &[
+                "tc_malloc",
+                "tc_free",
+                "tc_version",
+                "TCMallocInternalMalloc",
+                "TCMallocInternalFree",
+            ],
+        }
+    }
+}
+
+impl TcmallocVariant {
+    /// The variant of a TCMalloc allocator detected by `matched_symbols`: only gperftools
+    /// exports the `tc_*` API.
+    fn from_matched_symbols(matched_symbols: &[&str]) -> Self {
+        if matched_symbols.iter().any(|s| s.starts_with("tc_")) {
+            TcmallocVariant::Gperftools
+        } else {
+            TcmallocVariant::Google
         }
     }
 }
@@ -90,7 +109,16 @@ fn find_binaries_in_dir(dir: &Path) -> Vec<PathBuf> {
         .collect::<Vec<_>>()
 }
 
-fn find_statically_linked_allocator(path: &Path) -> Option<AllocatorKind> {
+/// Allocator statically linked in a binary, with the symbols it was detected by.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DetectedAllocator {
+    pub kind: AllocatorKind,
+    pub matched_symbols: Vec<&'static str>,
+    pub tcmalloc_variant: Option<TcmallocVariant>,
+}
+
+/// Detect the allocator statically linked in the binary at `path`, from the symbols it defines.
+pub fn find_statically_linked_allocator(path: &Path) -> Option<DetectedAllocator> {
     use object::{Object, ObjectSymbol};
 
     let data = fs::read(path).ok()?;
@@ -105,40 +133,171 @@ fn find_statically_linked_allocator(path: &Path) -> Option<AllocatorKind> {
 
     // FIXME: We don't support multiple statically linked allocators for now
 
-    AllocatorKind::all()
-        .iter()
-        .find(|kind| kind.symbols().iter().any(|s| symbols.contains(s)))
-        .copied()
+    AllocatorKind::all().iter().find_map(|&kind| {
+        let matched_symbols = kind
+            .symbols()
+            .iter()
+            .copied()
+            .filter(|s| symbols.contains(s))
+            .collect::<Vec<_>>();
+        if matched_symbols.is_empty() {
+            return None;
+        }
+        let tcmalloc_variant = (kind == AllocatorKind::Tcmalloc)
+            .then(|| TcmallocVariant::from_matched_symbols(&matched_symbols));
+        Some(DetectedAllocator {
+            kind,
+            matched_symbols,
+            tcmalloc_variant,
+        })
+    })
 }
 
 pub fn find_all() -> anyhow::Result<Vec<AllocatorLib>> {
-    let build_dirs = find_build_dirs();
-    if build_dirs.is_empty() {
-        return Ok(vec![]);
-    }
+    Ok(find_in_build_dirs(&find_build_dirs()))
+}
 
+/// Find the statically linked allocators of the binaries in `build_dirs`.
+///
+/// Paths are canonicalized, so that a binary reachable through several paths (e.g. a symlink
+/// to a build output, or nested build directories) is only reported once.
+fn find_in_build_dirs(build_dirs: &[PathBuf]) -> Vec<AllocatorLib> {
     let mut allocators = Vec::new();
+    let mut seen_paths: HashSet<PathBuf> = HashSet::new();
     for build_dir in build_dirs {
-        let bins = find_binaries_in_dir(&build_dir);
+        let bins = find_binaries_in_dir(build_dir)
+            .into_iter()
+            .filter_map(|bin| bin.canonicalize().ok());
 
         for bin in bins {
-            let Some(kind) = find_statically_linked_allocator(&bin) else {
+            if !seen_paths.insert(bin.clone()) {
+                continue;
+            }
+            let Some(detected) = find_statically_linked_allocator(&bin) else {
                 continue;
             };
 
-            allocators.push(AllocatorLib { kind, path: bin });
+            allocators.push(AllocatorLib {
+                kind: detected.kind,
+                path: bin,
+                tcmalloc_variant: detected.tcmalloc_variant,
+            });
         }
     }
 
-    Ok(allocators)
+    allocators
 }
 
 impl AllocatorLib {
     pub fn from_path_static(path: &Path) -> Result<Self, Box<dyn std::error::Error>> {
-        let kind = find_statically_linked_allocator(path).ok_or("No allocator found")?;
+        let detected = find_statically_linked_allocator(path).ok_or("No allocator found")?;
         Ok(Self {
-            kind,
-            path: path.to_path_buf(),
+            kind: detected.kind,
+            path: path.canonicalize()?,
+            tcmalloc_variant: detected.tcmalloc_variant,
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A binary defining an allocator: the libc of the system, which defines `malloc`
+    fn allocator_binary() -> PathBuf {
+        super::super::dynamic::find_all()
+            .unwrap()
+            .into_iter()
+            .find(|allocator| allocator.kind == AllocatorKind::Libc)
+            .expect("the system libc should be discovered")
+            .path
+    }
+
+    #[rstest::rstest]
+    #[case::gperftools(&["tc_malloc", "tc_free", "tc_version"], TcmallocVariant::Gperftools)]
+    #[case::google(&["TCMallocInternalMalloc", "TCMallocInternalFree"], TcmallocVariant::Google)]
+    fn test_tcmalloc_variant_from_matched_symbols(
+        #[case] matched_symbols: &[&str],
+        #[case] variant: TcmallocVariant,
+    ) {
+        assert_eq!(
+            TcmallocVariant::from_matched_symbols(matched_symbols),
+            variant
+        );
+    }
+
+    #[test]
+    fn test_symlinked_build_output_is_reported_once() {
+        let tmp = tempfile::tempdir().unwrap();
+        let build_dir = tmp.path().join("build");
+        fs::create_dir_all(build_dir.join("release")).unwrap();
+        let bench = build_dir.join("release/bench");
+        fs::copy(allocator_binary(), &bench).unwrap();
+        std::os::unix::fs::symlink(&bench, build_dir.join("bench")).unwrap();
+        // Another build directory pointing to the same outputs
+        let linked_build_dir = tmp.path().join("bazel-bin");
+        std::os::unix::fs::symlink(&build_dir, &linked_build_dir).unwrap();
+
+        let allocators = find_in_build_dirs(&[build_dir, linked_build_dir]);
+
+        assert_eq!(allocators.len(), 1);
+        assert_eq!(allocators[0].kind, AllocatorKind::Libc);
+        assert_eq!(allocators[0].path, bench.canonicalize().unwrap());
+    }
+
+    /// Fixtures built by `testdata/allocators/build.sh`: binaries linking the real allocators, and
+    /// stubs exporting the symbols of the ones that are not built
+    fn fixture(file_name: &str) -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("testdata/allocators")
+            .join(file_name)
+    }
+
+    #[rstest::rstest]
+    #[case::mimalloc_real(
+        "mimalloc_static",
+        AllocatorKind::Mimalloc,
+        &["mi_malloc_aligned", "mi_malloc", "mi_free"]
+    )]
+    #[case::libstdcxx_real(
+        "libstdcxx_static",
+        AllocatorKind::LibCpp,
+        &["_Znwm", "_Znam", "_ZdlPv", "_ZdaPv"]
+    )]
+    #[case::jemalloc("jemalloc.so", AllocatorKind::Jemalloc, &["_rjem_malloc"])]
+    #[case::mimalloc("mimalloc.so", AllocatorKind::Mimalloc, &["mi_malloc_aligned", "mi_malloc", "mi_free"])]
+    #[case::tcmalloc_gperftools(
+        "tcmalloc_gperftools.so",
+        AllocatorKind::Tcmalloc,
+        &["tc_malloc", "tc_free", "tc_version"]
+    )]
+    #[case::tcmalloc_google(
+        "tcmalloc_google_static",
+        AllocatorKind::Tcmalloc,
+        &["TCMallocInternalMalloc", "TCMallocInternalFree"]
+    )]
+    #[case::libcpp("libcpp.so", AllocatorKind::LibCpp, &["_Znwm", "_Znam", "_ZdlPv", "_ZdaPv"])]
+    fn test_detect_allocator_fixture(
+        #[case] file_name: &str,
+        #[case] kind: AllocatorKind,
+        #[case] matched_symbols: &[&str],
+    ) {
+        let detected = find_statically_linked_allocator(&fixture(file_name)).unwrap();
+        assert_eq!(detected.kind, kind);
+        assert_eq!(detected.matched_symbols, matched_symbols);
+    }
+
+    #[rstest::rstest]
+    #[case::gperftools("tcmalloc_gperftools.so", TcmallocVariant::Gperftools)]
+    #[case::google("tcmalloc_google_static", TcmallocVariant::Google)]
+    fn test_detect_tcmalloc_variant(#[case] file_name: &str, #[case] variant: TcmallocVariant) {
+        let allocator = AllocatorLib::from_path_static(&fixture(file_name)).unwrap();
+        assert_eq!(allocator.kind, AllocatorKind::Tcmalloc);
+        assert_eq!(allocator.tcmalloc_variant, Some(variant));
+    }
+
+    #[test]
+    fn test_detect_no_allocator() {
+        assert_eq!(find_statically_linked_allocator(&fixture("none.so")), None);
+    }
+}
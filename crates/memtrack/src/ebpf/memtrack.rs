@@ -7,7 +7,7 @@ use paste::paste;
 use std::mem::MaybeUninit;
 use std::path::Path;
 
-use crate::allocators::AllocatorKind;
+use crate::allocators::{AllocatorKind, AllocatorLib, TcmallocVariant};
 use crate::ebpf::poller::RingBufferPoller;
 
 pub mod memtrack_skel {
@@ -283,7 +283,8 @@ impl MemtrackBpf {
     /// Attach probes for a specific allocator kind.
     /// This attaches both standard probes (if the allocator exports them) and
     /// allocator-specific prefixed probes.
-    pub fn attach_allocator_probes(&mut self, kind: AllocatorKind, lib_path: &Path) -> Result<()> {
+    pub fn attach_allocator_probes(&mut self, lib: &AllocatorLib) -> Result<()> {
+        let (kind, lib_path) = (lib.kind, lib.path.as_path());
         debug!(
             "Attaching {} probes to: {}",
             kind.name(),
@@ -313,9 +314,13 @@ impl MemtrackBpf {
             }
             AllocatorKind::Tcmalloc => {
                 // Tcmalloc exposes libc/libcpp compatible allocator functions:
-                let _ = self.attach_libc_probes(lib_path);
+                let libc_probes = self.attach_libc_probes(lib_path);
                 let _ = self.attach_libcpp_probes(lib_path);
-                self.attach_tcmalloc_probes(lib_path)
+                // ...which are the only API of google/tcmalloc, so they must succeed
+                if lib.tcmalloc_variant == Some(TcmallocVariant::Google) {
+                    libc_probes?;
+                }
+                self.attach_tcmalloc_probes(lib_path, lib.tcmalloc_variant)
             }
         }
     }
@@ -432,13 +437,19 @@ impl MemtrackBpf {
         Ok(())
     }
 
-    /// Attach TCMalloc probes ( tc_* API).
+    /// Attach TCMalloc probes (tc_* API, unless the variant is known not to export it).
     ///
     /// See:
     /// - https://github.com/google/tcmalloc/blob/master/docs/reference.md
     /// - https://github.com/gperftools/gperftools/blob/a47243150ec41097602730ff8779fafcc172d1fb/src/tcmalloc.cc#L178-L190
-    fn attach_tcmalloc_probes(&mut self, lib_path: &Path) -> Result<()> {
-        self.attach_standard_probes(lib_path, &["tc_"], &[])?;
+    fn attach_tcmalloc_probes(
+        &mut self,
+        lib_path: &Path,
+        variant: Option<TcmallocVariant>,
+    ) -> Result<()> {
+        if variant != Some(TcmallocVariant::Google) {
+            self.attach_standard_probes(lib_path, &["tc_"], &[])?;
+        }
 
         self.try_attach_free(lib_path, "free_sized");
         self.try_attach_free(lib_path, "free_aligned_sized");
@@ -1,10 +1,12 @@
 use crate::prelude::*;
-use crate::{AllocatorLib, ebpf::MemtrackBpf};
+use crate::{AllocatorLib, DiscoveryReport, ebpf::MemtrackBpf};
 use runner_shared::artifacts::MemtrackEvent as Event;
 use std::sync::mpsc::{self, Receiver};
 
 pub struct Tracker {
     bpf: MemtrackBpf,
+    /// Where the allocators were found, empty when created without allocators
+    discovery_report: DiscoveryReport,
 }
 
 impl Tracker {
@@ -18,9 +20,10 @@ impl Tracker {
     pub fn new() -> Result<Self> {
         let mut instance = Self::new_without_allocators()?;
 
-        let allocators = AllocatorLib::find_all()?;
+        let (allocators, discovery_report) = AllocatorLib::discover()?;
         debug!("Found {} allocator instance(s)", allocators.len());
         instance.attach_allocators(&allocators)?;
+        instance.discovery_report = discovery_report;
 
         Ok(instance)
     }
@@ -32,20 +35,27 @@ impl Tracker {
         let mut bpf = MemtrackBpf::new()?;
         bpf.attach_tracepoints()?;
 
-        Ok(Self { bpf })
+        Ok(Self {
+            bpf,
+            discovery_report: DiscoveryReport::default(),
+        })
     }
 
     pub fn attach_allocators(&mut self, libs: &[AllocatorLib]) -> Result<()> {
         for allocator in libs {
-            self.bpf
-                .attach_allocator_probes(allocator.kind, &allocator.path)?;
+            self.bpf.attach_allocator_probes(allocator)?;
         }
 
         Ok(())
     }
 
     pub fn attach_allocator(&mut self, lib: &AllocatorLib) -> Result<()> {
-        self.bpf.attach_allocator_probes(lib.kind, &lib.path)
+        self.bpf.attach_allocator_probes(lib)
+    }
+
+    /// Where the allocators were found by [`Tracker::new`].
+    pub fn discovery_report(&self) -> &DiscoveryReport {
+        &self.discovery_report
     }
 
     /// Start tracking allocations for a specific PID
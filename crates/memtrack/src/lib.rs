@@ -4,7 +4,10 @@ mod ebpf;
 mod ipc;
 pub mod prelude;
 
-pub use allocators::{AllocatorKind, AllocatorLib};
+pub use allocators::{
+    AllocatorDiscovery, AllocatorKind, AllocatorLib, DetectedAllocator, DiscoveryReport,
+    DiscoverySource, TcmallocVariant, find_statically_linked_allocator,
+};
 pub use ipc::{
     IpcCommand as MemtrackIpcCommand, IpcMessage as MemtrackIpcMessage,
     IpcResponse as MemtrackIpcResponse, MemtrackIpcClient, MemtrackIpcServer,
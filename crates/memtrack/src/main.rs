@@ -89,6 +89,9 @@ fn track_command(
     };
 
     let tracker = Tracker::new()?;
+    let discovery_report = tracker.discovery_report();
+    debug!("Allocator discovery:\n{discovery_report}");
+    discovery_report.save_to(out_dir)?;
     let tracker_arc = Arc::new(Mutex::new(tracker));
 
     // Spawn IPC handler thread with the now-available tracker
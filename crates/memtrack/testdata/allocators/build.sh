@@ -0,0 +1,32 @@
+#!/usr/bin/env bash
+# Build the allocator detection fixtures:
+# - shared libraries exporting the symbols of each allocator, without any actual implementation,
+#   and statically linked executables for the `*_static.c` sources,
+# - executables statically linking the real allocators: mimalloc, built from its sources
+#   (downloaded unless `MIMALLOC_SRC` points to a checkout), and libstdc++.
+# The fixtures are committed, rebuild them after editing their sources.
+set -euo pipefail
+cd "$(dirname "$0")"
+
+MIMALLOC_VERSION=2.2.4
+
+if [ -z "${MIMALLOC_SRC:-}" ]; then
+  tmp=$(mktemp -d)
+  trap 'rm -rf "$tmp"' EXIT
+  curl -sSfL "https://github.com/microsoft/mimalloc/archive/refs/tags/v$MIMALLOC_VERSION.tar.gz" |
+    tar xz -C "$tmp"
+  MIMALLOC_SRC="$tmp/mimalloc-$MIMALLOC_VERSION"
+fi
+
+for source in *.c; do
+  case "$source" in
+  mimalloc_static.c)
+    gcc -O2 -DMI_MALLOC_OVERRIDE -I"$MIMALLOC_SRC/include" -o "${source%.c}" \
+      "$source" "$MIMALLOC_SRC/src/static.c" -lpthread
+    ;;
+  *_static.c) gcc -static -nostdlib -fno-builtin -o "${source%.c}" "$source" ;;
+  *) gcc -shared -fPIC -nostdlib -fno-builtin -o "${source%.c}.so" "$source" ;;
+  esac
+done
+
+g++ -static-libstdc++ -o libstdcxx_static libstdcxx_static.cpp
@@ -0,0 +1,9 @@
+/* jemalloc as built by tikv-jemallocator, the prefixed API of the Rust projects. */
+#include "stub.h"
+
+STUB(_rjem_malloc)
+STUB(_rjem_calloc)
+STUB(_rjem_realloc)
+STUB(_rjem_free)
+STUB(_rjem_mallocx)
+STUB(_rjem_sdallocx)
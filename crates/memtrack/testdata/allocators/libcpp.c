@@ -0,0 +1,7 @@
+/* A C++ standard library: the `operator new` and `operator delete` overloads. */
+#include "stub.h"
+
+STUB(_Znwm)
+STUB(_Znam)
+STUB(_ZdlPv)
+STUB(_ZdaPv)
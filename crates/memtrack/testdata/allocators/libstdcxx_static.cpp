@@ -0,0 +1,8 @@
+// A binary statically linking the real libstdc++, and its `operator new` and `operator delete`.
+int main() {
+  int *value = new int(1);
+  int *values = new int[16];
+  delete value;
+  delete[] values;
+  return 0;
+}
@@ -0,0 +1,10 @@
+/* mimalloc overriding the standard API. */
+#include "stub.h"
+
+STUB(mi_malloc)
+STUB(mi_malloc_aligned)
+STUB(mi_calloc)
+STUB(mi_realloc)
+STUB(mi_free)
+STUB(malloc)
+STUB(free)
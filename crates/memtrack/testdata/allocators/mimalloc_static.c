@@ -0,0 +1,11 @@
+/* A binary statically linking the real mimalloc, overriding the standard API. */
+#include <mimalloc.h>
+#include <stdlib.h>
+
+int main(void) {
+  void *p = malloc(64);
+  void *q = mi_malloc_aligned(64, 32);
+  free(p);
+  mi_free(q);
+  return 0;
+}
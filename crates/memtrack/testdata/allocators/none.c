@@ -0,0 +1,4 @@
+/* A binary without any allocator. */
+#include "stub.h"
+
+STUB(bench_main)
@@ -0,0 +1,3 @@
+/* Defines an exported function named `name`: the detection only looks at the symbol names. */
+#define STUB(name) \
+    void name(void) {}
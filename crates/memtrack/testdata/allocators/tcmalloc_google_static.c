@@ -0,0 +1,21 @@
+/* google/tcmalloc statically linked in a binary: the standard API aliases its internal
+ * `TCMallocInternal*` functions, and there is no `tc_*` API. */
+#include "stub.h"
+
+#define ALIAS(name, target) void name(void) __attribute__((alias(#target)));
+
+STUB(TCMallocInternalMalloc)
+STUB(TCMallocInternalCalloc)
+STUB(TCMallocInternalRealloc)
+STUB(TCMallocInternalFree)
+STUB(TCMallocInternalNew)
+STUB(TCMallocInternalDelete)
+ALIAS(malloc, TCMallocInternalMalloc)
+ALIAS(calloc, TCMallocInternalCalloc)
+ALIAS(realloc, TCMallocInternalRealloc)
+ALIAS(free, TCMallocInternalFree)
+ALIAS(_Znwm, TCMallocInternalNew)
+ALIAS(_ZdlPv, TCMallocInternalDelete)
+
+/* Entry point of the executable, never run: the detection only reads the symbols. */
+STUB(_start)
@@ -0,0 +1,12 @@
+/* gperftools' tcmalloc: the standard API and the `tc_*` one. */
+#include "stub.h"
+
+STUB(tc_malloc)
+STUB(tc_calloc)
+STUB(tc_realloc)
+STUB(tc_free)
+STUB(tc_version)
+STUB(malloc)
+STUB(free)
+STUB(_Znwm)
+STUB(_ZdlPv)
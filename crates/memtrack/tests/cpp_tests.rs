@@ -1,7 +1,7 @@
 #[macro_use]
 mod shared;
 
-use memtrack::AllocatorLib;
+use memtrack::{AllocatorKind, AllocatorLib, TcmallocVariant, find_statically_linked_allocator};
 use rstest::rstest;
 use std::path::Path;
 use std::process::Command;
@@ -69,3 +69,42 @@ fn test_cpp_alloc_tracking(#[case] target: &str) -> Result<(), Box<dyn std::erro
     thread_handle.join().unwrap();
     Ok(())
 }
+
+#[test_with::env(GITHUB_ACTIONS)]
+#[rstest]
+#[case("alloc_cpp_system", None)]
+#[case("alloc_cpp_libstdcxx_static", Some(AllocatorKind::LibCpp))]
+#[case("alloc_cpp_jemalloc_static", Some(AllocatorKind::Jemalloc))]
+#[case("alloc_cpp_mimalloc_static", Some(AllocatorKind::Mimalloc))]
+#[case("alloc_cpp_tcmalloc_static", Some(AllocatorKind::Tcmalloc))]
+fn test_cpp_static_allocator_detection(
+    #[case] target: &str,
+    #[case] kind: Option<AllocatorKind>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let project_path = Path::new("testdata/alloc_cpp");
+    let binary = compile_cpp_project(project_path, target)?;
+
+    let detected = find_statically_linked_allocator(&binary);
+    assert_eq!(detected.as_ref().map(|detected| detected.kind), kind);
+    // The detection must be backed by the symbols of the allocator
+    if let Some(detected) = detected {
+        assert!(!detected.matched_symbols.is_empty());
+    }
+    Ok(())
+}
+
+#[test_with::env(GITHUB_ACTIONS)]
+#[test]
+fn test_cpp_tcmalloc_variant_detection() -> Result<(), Box<dyn std::error::Error>> {
+    let project_path = Path::new("testdata/alloc_cpp");
+    let binary = compile_cpp_project(project_path, "alloc_cpp_tcmalloc_static")?;
+
+    // The system packages ship gperftools, google/tcmalloc is only built with Bazel
+    let allocator = AllocatorLib::from_path_static(&binary)?;
+    assert_eq!(allocator.kind, AllocatorKind::Tcmalloc);
+    assert_eq!(
+        allocator.tcmalloc_variant,
+        Some(TcmallocVariant::Gperftools)
+    );
+    Ok(())
+}
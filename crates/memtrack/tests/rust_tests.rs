@@ -1,7 +1,7 @@
 #[macro_use]
 mod shared;
 
-use memtrack::AllocatorLib;
+use memtrack::{AllocatorKind, AllocatorLib, find_statically_linked_allocator};
 use rstest::rstest;
 use std::path::Path;
 
@@ -30,3 +30,21 @@ fn test_rust_alloc_tracking(
     thread_handle.join().unwrap();
     Ok(())
 }
+
+#[test_with::env(GITHUB_ACTIONS)]
+#[rstest]
+#[case(&["with-jemalloc"], AllocatorKind::Jemalloc, "_rjem_malloc")]
+#[case(&["with-mimalloc"], AllocatorKind::Mimalloc, "mi_malloc")]
+fn test_rust_static_allocator_detection(
+    #[case] features: &[&str],
+    #[case] kind: AllocatorKind,
+    #[case] symbol: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let crate_path = Path::new("testdata/alloc_rust");
+    let binary = shared::compile_rust_binary(crate_path, "alloc_rust", features)?;
+
+    let detected = find_statically_linked_allocator(&binary).ok_or("No allocator detected")?;
+    assert_eq!(detected.kind, kind);
+    assert!(detected.matched_symbols.contains(&symbol));
+    Ok(())
+}
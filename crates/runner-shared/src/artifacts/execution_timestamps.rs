@@ -6,6 +6,9 @@ use crate::fifo::MarkerType;
 pub struct ExecutionTimestamps {
     pub uri_by_ts: Vec<(u64, String)>,
     pub markers: Vec<MarkerType>,
+    /// Named regions of code tagged by the integration, sorted by start timestamp
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub regions: Vec<Region>,
 }
 impl super::ArtifactExt for ExecutionTimestamps {}
 
@@ -14,6 +17,15 @@ impl ExecutionTimestamps {
         Self {
             uri_by_ts: uri_by_ts.to_vec(),
             markers: markers.to_vec(),
+            regions: vec![],
         }
     }
 }
+
+/// A named region of code, between the `RegionStart` and `RegionEnd` FIFO commands.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Region {
+    pub name: String,
+    pub start: u64,
+    pub end: u64,
+}
@@ -28,7 +28,7 @@ pub struct MappedProcessDebugInfo {
     pub load_bias: u64,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct ModuleDebugInfo {
     /// The path to the object file on disk (e.g. `/usr/lib/libc.so.6`)
     pub object_path: String,
@@ -43,4 +43,28 @@ pub struct ModuleDebugInfo {
 
     /// The debug info for this module, sorted by address.
     pub debug_infos: Vec<DebugInfo>,
+
+    /// The chains of inlined frames of this module, sorted by address. Only recorded when
+    /// inline frames are requested.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub inline_ranges: Vec<InlineRange>,
+}
+
+/// A frame of an inline chain.
+#[derive(Debug, Hash, PartialEq, Eq, Clone, Serialize, Deserialize)]
+pub struct InlineFrame {
+    pub name: String,
+    pub file: String,
+    pub line: Option<u32>,
+}
+
+/// An address range `[start, end)` sharing the same chain of inlined frames.
+#[derive(Debug, Hash, PartialEq, Eq, Clone, Serialize, Deserialize)]
+pub struct InlineRange {
+    pub start: u64,
+    pub end: u64,
+
+    /// The frames covering the range, from the innermost inlined function to the outermost
+    /// non-inlined caller.
+    pub frames: Vec<InlineFrame>,
 }
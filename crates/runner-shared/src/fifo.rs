@@ -63,4 +63,11 @@ pub enum Command {
     SetVersion(u64),
     GetIntegrationMode,
     IntegrationModeResponse(IntegrationMode),
+    /// Start of a named region of code within the current benchmark, e.g. `setup` or `parse`.
+    ///
+    /// Regions are optional: integrations that never send them keep working, so they don't
+    /// require a new protocol version.
+    RegionStart(String),
+    /// End of the innermost region started with the same name.
+    RegionEnd(String),
 }
@@ -1,4 +1,5 @@
-use anyhow::Context;
+use anyhow::{Context, bail};
+use itertools::Itertools;
 use libc::pid_t;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
@@ -11,6 +12,12 @@ use crate::fifo::MarkerType;
 use crate::module_symbols::MappedProcessModuleSymbols;
 use crate::unwind_data::MappedProcessUnwindData;
 
+/// The latest version of the [`WalltimeMetadata`] format, written by this runner.
+pub const WALLTIME_METADATA_CURRENT_VERSION: u64 = 1;
+
+/// Name of the file [`WalltimeMetadata::save_to`] writes in the profile folder.
+pub const WALLTIME_METADATA_FILENAME: &str = "walltime.metadata";
+
 #[derive(Serialize, Deserialize, Default)]
 pub struct WalltimeMetadata {
     /// The version of this metadata format.
@@ -42,6 +49,11 @@ pub struct WalltimeMetadata {
     #[serde(default, skip_serializing_if = "HashMap::is_empty")]
     pub mapped_process_module_symbols: HashMap<pid_t, Vec<MappedProcessModuleSymbols>>,
 
+    /// Number of lines of each `<path_key>.symbols.map` file, checked after writing them.
+    /// Modules whose file failed the check are left out of `mapped_process_module_symbols`.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub module_symbols_line_count: HashMap<String, u64>,
+
     /// Mapping from semantic `path_key` to original binary path on host disk
     /// Used by `mapped_process_debug_info_by_pid`, `mapped_process_unwind_data_by_pid` and
     /// `mapped_process_module_symbols` the deduplicated entries
@@ -50,6 +62,11 @@ pub struct WalltimeMetadata {
     #[serde(default, skip_serializing_if = "HashMap::is_empty")]
     pub path_key_to_path: HashMap<String, PathBuf>,
 
+    /// Allow-listed environment variables (e.g. `RUSTC`) and `uname.*` information of the
+    /// machine that produced the profile, kept for reproducibility
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub environment: HashMap<String, String>,
+
     // Deprecated fields below are kept for backward compatibility, since this struct is used in
     // the parser and older versions of the runner still generate them
     //
@@ -72,12 +89,130 @@ pub struct WalltimeMetadata {
 }
 
 impl WalltimeMetadata {
+    /// Parse and validate the metadata.
+    ///
+    /// Fails on metadata written with a newer format version, whose new fields would
+    /// otherwise be silently dropped.
     pub fn from_reader<R: std::io::Read>(reader: R) -> anyhow::Result<Self> {
-        serde_json::from_reader(reader).context("Could not parse walltime metadata from JSON")
+        let metadata: Self = serde_json::from_reader(reader)
+            .context("Could not parse walltime metadata from JSON")?;
+        metadata.validate()?;
+        Ok(metadata)
+    }
+
+    /// Combine the metadata of several perf segments of the same benchmark run.
+    ///
+    /// Per-pid entries are appended, `self`'s first, and the deduplicated `debug_info` and
+    /// `path_key_to_path` maps are unioned. Fails if both sides use the same key for
+    /// different content, or if they were written by different integrations or format versions.
+    #[allow(deprecated)]
+    pub fn merge(mut self, other: Self) -> anyhow::Result<Self> {
+        if self.version != other.version {
+            bail!(
+                "Cannot merge walltime metadata of different versions ({} and {})",
+                self.version,
+                other.version
+            );
+        }
+        if self.integration != other.integration {
+            bail!(
+                "Cannot merge walltime metadata of different integrations ({:?} and {:?})",
+                self.integration,
+                other.integration
+            );
+        }
+
+        merge_unique(&mut self.debug_info, other.debug_info, "debug info")?;
+        merge_unique(&mut self.path_key_to_path, other.path_key_to_path, "path")?;
+        merge_unique(
+            &mut self.module_symbols_line_count,
+            other.module_symbols_line_count,
+            "symbols line count",
+        )?;
+        merge_unique(&mut self.environment, other.environment, "environment")?;
+
+        merge_by_pid(
+            &mut self.ignored_modules_by_pid,
+            other.ignored_modules_by_pid,
+        );
+        merge_by_pid(
+            &mut self.mapped_process_debug_info_by_pid,
+            other.mapped_process_debug_info_by_pid,
+        );
+        merge_by_pid(
+            &mut self.mapped_process_unwind_data_by_pid,
+            other.mapped_process_unwind_data_by_pid,
+        );
+        merge_by_pid(
+            &mut self.mapped_process_module_symbols,
+            other.mapped_process_module_symbols,
+        );
+        merge_by_pid(&mut self.debug_info_by_pid, other.debug_info_by_pid);
+
+        self.uri_by_ts.extend(other.uri_by_ts);
+        self.uri_by_ts.sort_by_key(|(ts, _)| *ts);
+        self.markers.extend(other.markers);
+        self.ignored_modules.extend(other.ignored_modules);
+        self.ignored_modules.sort();
+        self.ignored_modules.dedup();
+
+        Ok(self)
+    }
+
+    fn validate(&self) -> anyhow::Result<()> {
+        if self.version > WALLTIME_METADATA_CURRENT_VERSION {
+            bail!(
+                "The walltime metadata uses format version {}, but this version of the runner only supports up to version {}. \
+                Please update the runner to read it.",
+                self.version,
+                WALLTIME_METADATA_CURRENT_VERSION
+            );
+        }
+        let (integration_name, _) = &self.integration;
+        if integration_name.is_empty() {
+            bail!("Invalid walltime metadata: the integration name is empty");
+        }
+        Ok(())
+    }
+
+    /// Load the metadata [`Self::save_to`] wrote in the `path` folder.
+    pub fn load_from<P: AsRef<Path>>(path: P) -> anyhow::Result<Self> {
+        let metadata_path = path.as_ref().join(WALLTIME_METADATA_FILENAME);
+        let file = std::fs::File::open(&metadata_path)
+            .with_context(|| format!("Failed to open {}", metadata_path.display()))?;
+        Self::from_reader(std::io::BufReader::new(file))
+            .with_context(|| format!("Failed to load {}", metadata_path.display()))
+    }
+
+    /// Replace the absolute paths of the modules (e.g. `/home/user/project/target/release/bench`)
+    /// with their file names, so that the uploaded metadata doesn't reveal the layout of the host.
+    ///
+    /// The path keys are left untouched, so the symbols, unwind data and debug info are still
+    /// mapped to the processes. Absolute paths in the captured environment are stripped too,
+    /// including the ones embedded in compiler flags, see [`strip_paths_in_flags`].
+    #[allow(deprecated)]
+    pub fn strip_paths(&mut self) {
+        for path in self.path_key_to_path.values_mut() {
+            *path = PathBuf::from(strip_path(&path.to_string_lossy()));
+        }
+        for debug_info in self.debug_info.values_mut() {
+            debug_info.object_path = strip_path(&debug_info.object_path);
+        }
+        let ignored_modules = self
+            .ignored_modules_by_pid
+            .values_mut()
+            .flatten()
+            .chain(self.ignored_modules.iter_mut());
+        for (name, _, _) in ignored_modules {
+            *name = strip_path(name);
+        }
+        for value in self.environment.values_mut() {
+            *value = strip_paths_in_flags(value);
+        }
     }
 
     pub fn save_to<P: AsRef<Path>>(&self, path: P) -> anyhow::Result<()> {
-        let file = std::fs::File::create(path.as_ref().join("walltime.metadata"))?;
+        let file = std::fs::File::create(path.as_ref().join(WALLTIME_METADATA_FILENAME))?;
         const BUFFER_SIZE: usize = 256 * 1024 /* 256 KB */;
 
         let writer = BufWriter::with_capacity(BUFFER_SIZE, file);
@@ -85,3 +220,233 @@ impl WalltimeMetadata {
         Ok(())
     }
 }
+
+/// The file name of `path` when it is absolute, `path` unchanged otherwise.
+fn strip_path(path: &str) -> String {
+    let path = Path::new(path);
+    match path.file_name() {
+        Some(file_name) if path.is_absolute() => file_name.to_string_lossy().into_owned(),
+        _ => path.to_string_lossy().into_owned(),
+    }
+}
+
+/// Placeholder of the absolute paths of the compiler flags that are not files.
+const PATH_PLACEHOLDER: &str = "<path>";
+
+/// The file name of an absolute path found in a compiler flag when it is an existing file (e.g.
+/// the compiler or a linker script), [`PATH_PLACEHOLDER`] otherwise: the file name of a directory
+/// such as `/home/user` would still reveal the host.
+fn strip_path_in_flag(path: &str) -> String {
+    if Path::new(path).is_file() {
+        strip_path(path)
+    } else {
+        PATH_PLACEHOLDER.to_string()
+    }
+}
+
+/// Strip the absolute paths of a command line or a list of compiler flags, e.g. `CC`, `CFLAGS`
+/// or `RUSTFLAGS`, see [`strip_path_in_flag`].
+///
+/// Each space-separated flag is split on `=`, `:` and `,`, so that the paths of
+/// `--remap-path-prefix=/home/user/src=/src`, `-Wl,-rpath,/home/user/lib` or `PATH`-like lists
+/// are stripped, as well as the ones attached to a short flag, e.g. `-I/home/user/include`.
+fn strip_paths_in_flags(value: &str) -> String {
+    value
+        .split(' ')
+        .map(|flag| {
+            flag.split_inclusive(['=', ':', ','])
+                .map(|part| {
+                    let (part, separator) = match part.char_indices().last() {
+                        Some((index, '=' | ':' | ',')) => part.split_at(index),
+                        _ => (part, ""),
+                    };
+                    format!("{}{separator}", strip_path_in_flag_part(part))
+                })
+                .collect::<String>()
+        })
+        .join(" ")
+}
+
+/// Strip the absolute path at the start of `part`, or following the name of a short flag.
+/// URLs (`https://host/path`, split at `:`) are left untouched.
+fn strip_path_in_flag_part(part: &str) -> String {
+    if part.starts_with("//") {
+        return part.to_string();
+    }
+    let path_start = part.strip_prefix('-').map_or(Some(0), |flag| {
+        flag.find('/')
+            .filter(|&index| flag[..index].chars().all(|c| c.is_ascii_alphanumeric()))
+            .map(|index| index + 1)
+    });
+    match path_start {
+        Some(start) if part[start..].starts_with('/') => {
+            format!("{}{}", &part[..start], strip_path_in_flag(&part[start..]))
+        }
+        _ => part.to_string(),
+    }
+}
+
+/// Insert the entries of `other` into `into`, failing when a key maps to different values.
+fn merge_unique<V: PartialEq>(
+    into: &mut HashMap<String, V>,
+    other: HashMap<String, V>,
+    kind: &str,
+) -> anyhow::Result<()> {
+    // Sorted so that the reported conflict doesn't depend on the hash map ordering
+    for (key, value) in other.into_iter().sorted_by(|(a, _), (b, _)| a.cmp(b)) {
+        match into.get(&key) {
+            Some(existing) if *existing != value => {
+                bail!("Cannot merge walltime metadata: conflicting {kind} entries for key `{key}`")
+            }
+            Some(_) => {}
+            None => {
+                into.insert(key, value);
+            }
+        }
+    }
+    Ok(())
+}
+
+fn merge_by_pid<T>(into: &mut HashMap<pid_t, Vec<T>>, other: HashMap<pid_t, Vec<T>>) {
+    for (pid, entries) in other {
+        into.entry(pid).or_default().extend(entries);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_reader_accepts_current_version() {
+        let json = r#"{"version": 1, "integration": ["codspeed-rust", "4.0.0"], "uri_by_ts": [], "ignored_modules": [], "markers": []}"#;
+        let metadata = WalltimeMetadata::from_reader(json.as_bytes()).unwrap();
+        assert_eq!(metadata.integration.0, "codspeed-rust");
+    }
+
+    #[test]
+    fn test_from_reader_rejects_newer_version() {
+        let json = r#"{"version": 2, "integration": ["codspeed-rust", "4.0.0"], "uri_by_ts": [], "ignored_modules": [], "markers": []}"#;
+        let err = WalltimeMetadata::from_reader(json.as_bytes())
+            .err()
+            .unwrap();
+        assert!(err.to_string().contains("update the runner"));
+    }
+
+    #[test]
+    fn test_from_reader_rejects_missing_integration() {
+        let json = r#"{"version": 1, "uri_by_ts": [], "ignored_modules": [], "markers": []}"#;
+        let err = WalltimeMetadata::from_reader(json.as_bytes())
+            .err()
+            .unwrap();
+        assert!(format!("{err:#}").contains("integration"));
+
+        let json = r#"{"version": 1, "integration": ["", "4.0.0"], "uri_by_ts": [], "ignored_modules": [], "markers": []}"#;
+        let err = WalltimeMetadata::from_reader(json.as_bytes())
+            .err()
+            .unwrap();
+        assert!(err.to_string().contains("integration name is empty"));
+    }
+
+    fn metadata_with(key: &str, object_path: &str, pid: pid_t) -> WalltimeMetadata {
+        WalltimeMetadata {
+            version: WALLTIME_METADATA_CURRENT_VERSION,
+            integration: ("codspeed-rust".into(), "4.0.0".into()),
+            debug_info: HashMap::from([(
+                key.to_string(),
+                ModuleDebugInfo {
+                    object_path: object_path.into(),
+                    addr_bounds: (0x1000, 0x2000),
+                    load_bias: 0,
+                    debug_infos: vec![],
+                    inline_ranges: vec![],
+                },
+            )]),
+            mapped_process_debug_info_by_pid: HashMap::from([(
+                pid,
+                vec![MappedProcessDebugInfo {
+                    debug_info_key: key.to_string(),
+                    load_bias: 0x7f00_0000,
+                }],
+            )]),
+            path_key_to_path: HashMap::from([(key.to_string(), PathBuf::from(object_path))]),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_merge_unions_entries() {
+        let merged = metadata_with("0-libc", "/usr/lib/libc.so.6", 1)
+            .merge(metadata_with("1-libm", "/usr/lib/libm.so.6", 1))
+            .unwrap()
+            .merge(metadata_with("0-libc", "/usr/lib/libc.so.6", 2))
+            .unwrap();
+
+        assert_eq!(merged.debug_info.len(), 2);
+        assert_eq!(merged.path_key_to_path.len(), 2);
+        let keys: Vec<&str> = merged.mapped_process_debug_info_by_pid[&1]
+            .iter()
+            .map(|m| m.debug_info_key.as_str())
+            .collect();
+        assert_eq!(keys, vec!["0-libc", "1-libm"]);
+        assert_eq!(merged.mapped_process_debug_info_by_pid[&2].len(), 1);
+    }
+
+    #[test]
+    fn test_merge_rejects_conflicting_keys() {
+        let err = metadata_with("0-lib", "/usr/lib/libc.so.6", 1)
+            .merge(metadata_with("0-lib", "/usr/lib/libm.so.6", 2))
+            .err()
+            .unwrap();
+        assert!(err.to_string().contains("`0-lib`"));
+    }
+
+    #[test]
+    fn test_strip_paths_leaves_no_absolute_path() {
+        // An existing file, standing for the compiler
+        let compiler = std::env::current_exe().unwrap();
+        let mut metadata = metadata_with("0-bench", "/home/alice/project/target/release/bench", 1);
+        metadata.ignored_modules_by_pid = HashMap::from([(
+            1,
+            vec![("/home/alice/.venv/bin/python3".to_string(), 0x1000, 0x2000)],
+        )]);
+        metadata.environment = HashMap::from([
+            (
+                "RUSTC".to_string(),
+                "/home/alice/.cargo/bin/rustc".to_string(),
+            ),
+            (
+                "RUSTFLAGS".to_string(),
+                "-C target-cpu=native --remap-path-prefix=/home/alice/project=/src".to_string(),
+            ),
+            (
+                "CFLAGS".to_string(),
+                "-O2 -I/home/alice/include -Wl,-rpath,/home/alice/lib -fdebug-prefix-map=/home/alice=."
+                    .to_string(),
+            ),
+            ("CC".to_string(), format!("ccache {}", compiler.display())),
+            ("uname.release".to_string(), "6.8.0".to_string()),
+        ]);
+
+        metadata.strip_paths();
+
+        assert_eq!(metadata.path_key_to_path["0-bench"], PathBuf::from("bench"));
+        assert_eq!(metadata.debug_info["0-bench"].object_path, "bench");
+        assert_eq!(metadata.environment["uname.release"], "6.8.0");
+        assert_eq!(
+            metadata.environment["RUSTFLAGS"],
+            "-C target-cpu=native --remap-path-prefix=<path>=<path>"
+        );
+        assert_eq!(
+            metadata.environment["CFLAGS"],
+            "-O2 -I<path> -Wl,-rpath,<path> -fdebug-prefix-map=<path>=."
+        );
+        assert_eq!(metadata.environment["RUSTC"], "<path>");
+        assert_eq!(
+            metadata.environment["CC"],
+            format!("ccache {}", compiler.file_name().unwrap().to_string_lossy())
+        );
+        let serialized = serde_json::to_string(&metadata).unwrap();
+        assert!(!serialized.contains("alice"), "{serialized}");
+    }
+}
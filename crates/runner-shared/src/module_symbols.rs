@@ -1,4 +1,5 @@
 use serde::{Deserialize, Serialize};
+use std::ops::Range;
 
 /// File suffix used when registering module symbols in a PID agnostic way.
 pub const SYMBOLS_MAP_SUFFIX: &str = "symbols.map";
@@ -8,4 +9,8 @@ pub const SYMBOLS_MAP_SUFFIX: &str = "symbols.map";
 pub struct MappedProcessModuleSymbols {
     pub perf_map_key: String,
     pub load_bias: u64,
+    /// The runtime address ranges of the executable segments of the module in the process.
+    /// Empty when they are unknown, e.g. in the metadata of older runners.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub avma_ranges: Vec<Range<u64>>,
 }
@@ -1,22 +1,36 @@
 use std::fmt::Display;
 
 use crate::executor::ExecutorName;
+use crate::failure::FailureClass;
+use crate::graphql_client::GraphQLClient;
 use crate::prelude::*;
 use crate::run_environment::RepositoryProvider;
 use console::style;
-use gql_client::{Client as GQLClient, ClientConfig};
 use nestify::nest;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Header carrying the run-scoped correlation ID, used by support to find a run in backend logs.
+pub const CORRELATION_ID_HEADER: &str = "X-CodSpeed-Correlation-Id";
 
 pub struct CodSpeedAPIClient {
-    gql_client: GQLClient,
-    unauthenticated_gql_client: GQLClient,
+    gql_client: GraphQLClient,
+    unauthenticated_gql_client: GraphQLClient,
     api_url: String,
     /// The token this client authenticates with. Exposed so downstream
     /// consumers (the uploader's `Authorization` header, the executor's
     /// `CODSPEED_OAUTH_TOKEN` env injection) don't have to thread the
     /// token separately from the client.
     token: Option<String>,
+    /// Run-scoped ID sent with every request, see [`Self::set_correlation_id`].
+    correlation_id: Option<String>,
+}
+
+/// Error of the queries rejected because the token is no longer valid.
+fn session_expired_error() -> anyhow::Error {
+    FailureClass::Auth.wrap(anyhow!(
+        "Your session has expired, please login again using `codspeed auth login`"
+    ))
 }
 
 impl CodSpeedAPIClient {
@@ -28,17 +42,32 @@ impl CodSpeedAPIClient {
     /// [`Self::token`] and don't have to thread the token separately.
     pub fn new(token: Option<String>, api_url: String) -> Self {
         Self {
-            gql_client: build_gql_api_client(token.as_deref(), api_url.clone()),
-            unauthenticated_gql_client: build_gql_api_client(None, api_url.clone()),
+            gql_client: build_gql_api_client(
+                build_headers(token.as_deref(), None),
+                api_url.clone(),
+            ),
+            unauthenticated_gql_client: build_gql_api_client(
+                build_headers(None, None),
+                api_url.clone(),
+            ),
             api_url,
             token,
+            correlation_id: None,
         }
     }
 
     /// Returns a client that uses `token` for authentication, regardless of
     /// the token this client was built with.
     pub fn with_token(&self, token: String) -> Self {
-        Self::new(Some(token), self.api_url.clone())
+        self.rebuild(Some(token))
+    }
+
+    fn rebuild(&self, token: Option<String>) -> Self {
+        let mut client = Self::new(token, self.api_url.clone());
+        if let Some(correlation_id) = &self.correlation_id {
+            client.set_correlation_id(correlation_id.clone());
+        }
+        client
     }
 
     /// The token this client currently authenticates with, if any.
@@ -54,28 +83,58 @@ impl CodSpeedAPIClient {
     /// requests and that the uploader pulls for its `Authorization`
     /// header. The single mutation point for the credentials.
     pub fn set_token(&mut self, token: Option<String>) {
-        self.gql_client = build_gql_api_client(token.as_deref(), self.api_url.clone());
         self.token = token;
+        self.gql_client = build_gql_api_client(self.request_headers(), self.api_url.clone());
+    }
+
+    /// Attach a run-scoped correlation ID to every subsequent request, GraphQL
+    /// queries and uploads alike.
+    pub fn set_correlation_id(&mut self, correlation_id: String) {
+        self.correlation_id = Some(correlation_id);
+        self.gql_client = build_gql_api_client(self.request_headers(), self.api_url.clone());
+        self.unauthenticated_gql_client = build_gql_api_client(
+            build_headers(None, self.correlation_id.as_deref()),
+            self.api_url.clone(),
+        );
+    }
+
+    pub fn correlation_id(&self) -> Option<&str> {
+        self.correlation_id.as_deref()
+    }
+
+    /// Headers sent with every authenticated request to the CodSpeed API.
+    pub fn request_headers(&self) -> HashMap<String, String> {
+        build_headers(self.token.as_deref(), self.correlation_id.as_deref())
     }
 }
 
-fn build_gql_api_client(token: Option<&str>, api_url: String) -> GQLClient {
-    let headers = match token {
-        Some(token) => {
-            let mut headers = std::collections::HashMap::new();
-            headers.insert("Authorization".to_string(), token.to_owned());
-            headers
-        }
-        None => Default::default(),
-    };
+/// A clone has its own token, so that the concurrent uploads can each rotate theirs.
+impl Clone for CodSpeedAPIClient {
+    fn clone(&self) -> Self {
+        self.rebuild(self.token.clone())
+    }
+}
 
-    GQLClient::new_with_config(ClientConfig {
-        endpoint: api_url,
-        // Slightly high to account for cold starts
-        timeout: Some(20),
-        headers: Some(headers),
-        proxy: None,
-    })
+fn build_headers(token: Option<&str>, correlation_id: Option<&str>) -> HashMap<String, String> {
+    let mut headers = HashMap::new();
+    if let Some(token) = token {
+        headers.insert("Authorization".to_string(), token.to_owned());
+    }
+    if let Some(correlation_id) = correlation_id {
+        headers.insert(CORRELATION_ID_HEADER.to_string(), correlation_id.to_owned());
+    }
+    headers
+}
+
+/// Timeout of a single API request, in seconds. Slightly high to account for cold starts.
+const API_REQUEST_TIMEOUT_SECS: u64 = 20;
+
+fn build_gql_api_client(headers: HashMap<String, String>, api_url: String) -> GraphQLClient {
+    GraphQLClient::new(
+        api_url,
+        headers,
+        std::time::Duration::from_secs(API_REQUEST_TIMEOUT_SECS),
+    )
 }
 
 nest! {
@@ -494,9 +553,7 @@ impl CodSpeedAPIClient {
             Ok(response) => Ok(CompareRunsOutcome::Success(CompareRunsResponse {
                 comparison: response.repository.paginated_compare_runs,
             })),
-            Err(err) if err.contains_error_code("UNAUTHENTICATED") => {
-                bail!("Your session has expired, please login again using `codspeed auth login`")
-            }
+            Err(err) if err.contains_error_code("UNAUTHENTICATED") => Err(session_expired_error()),
             Err(err) if err.contains_error_code("RUN_NOT_FOUND") => {
                 Ok(CompareRunsOutcome::BaseRunNotFound)
             }
@@ -519,9 +576,7 @@ impl CodSpeedAPIClient {
             Ok(response) => Ok(FetchLocalRunResponse {
                 run: response.repository.run,
             }),
-            Err(err) if err.contains_error_code("UNAUTHENTICATED") => {
-                bail!("Your session has expired, please login again using `codspeed auth login`")
-            }
+            Err(err) if err.contains_error_code("UNAUTHENTICATED") => Err(session_expired_error()),
             Err(err) => bail!("Failed to fetch local run: {err}"),
         }
     }
@@ -542,9 +597,7 @@ impl CodSpeedAPIClient {
             .await;
         match response {
             Ok(response) => Ok(response.get_or_create_project_repository),
-            Err(err) if err.contains_error_code("UNAUTHENTICATED") => {
-                bail!("Your session has expired, please login again using `codspeed auth login`")
-            }
+            Err(err) if err.contains_error_code("UNAUTHENTICATED") => Err(session_expired_error()),
             Err(err) => bail!("Failed to get or create project repository: {err}"),
         }
     }
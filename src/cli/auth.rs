@@ -10,11 +10,13 @@ use crate::cli::run::helpers::{
 };
 use crate::config::CodSpeedConfig;
 use crate::prelude::*;
+use crate::prompt::{self, WithoutTty};
 use clap::{Args, Subcommand};
 use console::style;
 use git2::Repository;
 use tokio::time::{Instant, sleep};
 
+use super::config::REDACTED_TOKEN;
 use super::status::{check_mark, cross_mark};
 
 #[derive(Debug, Args)]
@@ -27,8 +29,9 @@ pub struct AuthArgs {
 enum AuthCommands {
     /// Login to CodSpeed
     Login {
-        /// Read the token from standard input instead of running the OAuth flow
-        #[arg(long)]
+        /// Read the token from standard input instead of running the OAuth flow, e.g.
+        /// `codspeed auth login --stdin < token.txt`. The token is never echoed nor logged
+        #[arg(long, alias = "stdin")]
         with_token: bool,
     },
     /// Show the authentication status
@@ -55,19 +58,13 @@ const LOGIN_SESSION_MAX_DURATION: Duration = Duration::from_secs(60 * 5); // 5 m
 async fn login(
     api_client: &CodSpeedAPIClient,
     config_name: Option<&str>,
-    mut config: CodSpeedConfig,
+    config: CodSpeedConfig,
     with_token: bool,
 ) -> Result<()> {
     debug!("Login to CodSpeed");
 
     let token = if with_token {
-        let mut buf = String::new();
-        std::io::stdin().read_to_string(&mut buf)?;
-        let token = buf.trim().to_owned();
-        if token.is_empty() {
-            bail!("No token provided on stdin");
-        }
-        token
+        read_token(std::io::stdin())?
     } else {
         start_group!("Creating login session");
         let login_session_payload = api_client.create_login_session().await?;
@@ -110,6 +107,32 @@ async fn login(
         token
     };
 
+    save_token(api_client, config_name, config, token, prompt::confirm).await
+}
+
+/// Read a token piped to the CLI, ignoring the surrounding whitespace.
+fn read_token(mut input: impl Read) -> Result<String> {
+    let mut buf = String::new();
+    input.read_to_string(&mut buf)?;
+    let token = buf.trim().to_owned();
+    if token.is_empty() {
+        bail!("No token provided on stdin");
+    }
+    Ok(token)
+}
+
+/// Validate the token against the API and persist it in the selected profile. The token is
+/// never part of the logs nor of the errors.
+///
+/// Replacing another token of the profile is confirmed with `confirm`, and refused without
+/// `--yes` when nobody can be asked.
+async fn save_token(
+    api_client: &CodSpeedAPIClient,
+    config_name: Option<&str>,
+    mut config: CodSpeedConfig,
+    token: String,
+    confirm: impl FnOnce(&str, WithoutTty) -> Result<()>,
+) -> Result<()> {
     // Validate the token before persisting
     let api_client_with_token = api_client.with_token(token.clone());
     api_client_with_token
@@ -123,7 +146,19 @@ async fn login(
         })?;
 
     let selected = config.selected_profile_name().to_owned();
-    config.profile_mut(&selected).auth.token = Some(token);
+    let profile = config.profile_mut(&selected);
+    if profile
+        .auth
+        .token
+        .as_ref()
+        .is_some_and(|existing| *existing != token)
+    {
+        confirm(
+            &format!("The profile {selected} already has a token. Replace it?"),
+            WithoutTty::Refuse,
+        )?;
+    }
+    profile.auth.token = Some(token);
     config.persist(config_name)?;
     debug!("Token saved to configuration file");
 
@@ -145,9 +180,27 @@ fn detect_repository() -> Option<ParsedRepository> {
     parse_repository_from_remote(url).ok()
 }
 
+/// Whether the configured token is accepted by CodSpeed.
+#[derive(Debug)]
+enum TokenState {
+    Missing,
+    /// The API rejected the token: it is malformed, revoked or expired
+    Rejected,
+    Valid(SessionPayload),
+}
+
+impl TokenState {
+    fn from_session(session: Option<SessionPayload>) -> Self {
+        match session {
+            Some(session) => TokenState::Valid(session),
+            None => TokenState::Rejected,
+        }
+    }
+}
+
 /// Outcome of resolving the auth status, before rendering.
 struct AuthStatus {
-    session: Option<SessionPayload>,
+    token: TokenState,
     /// `Some(parsed)` when we detected a git remote and tried to look it up;
     /// the inner `Option<RepositoryOverviewPayload>` is `None` if the repo
     /// is not on CodSpeed (or we don't have a token to verify it with).
@@ -162,7 +215,7 @@ pub async fn status(api_client: &CodSpeedAPIClient, config: &CodSpeedConfig) ->
         resolve_auth_status(api_client, parsed).await?
     } else {
         AuthStatus {
-            session: None,
+            token: TokenState::Missing,
             detected_repository: parsed.map(|p| (p, None)),
         }
     };
@@ -172,7 +225,7 @@ pub async fn status(api_client: &CodSpeedAPIClient, config: &CodSpeedConfig) ->
         style("Authentication").bold(),
         config.selected_profile_name()
     );
-    print_authentication_section(has_token, auth_status.session.as_ref());
+    print_authentication_section(&auth_status.token);
     info!("");
 
     info!("{}", style("Repository").bold());
@@ -200,7 +253,7 @@ async fn resolve_auth_status(
             Err(SessionError::Other(err)) => return Err(err),
         };
         return Ok(AuthStatus {
-            session,
+            token: TokenState::from_session(session),
             detected_repository: None,
         });
     };
@@ -215,34 +268,39 @@ async fn resolve_auth_status(
 
     match combined {
         Ok(payload) => Ok(AuthStatus {
-            session: Some(payload.session),
+            token: TokenState::Valid(payload.session),
             detected_repository: Some((parsed, payload.repository_overview)),
         }),
         Err(SessionAndRepositoryOverviewError::Unauthenticated) => Ok(AuthStatus {
-            session: None,
+            token: TokenState::Rejected,
             detected_repository: Some((parsed, None)),
         }),
         Err(SessionAndRepositoryOverviewError::Other(err)) => Err(err),
     }
 }
 
-fn print_authentication_section(has_token: bool, session: Option<&SessionPayload>) {
-    let Some(session) = session else {
-        if has_token {
+fn print_authentication_section(token: &TokenState) {
+    let session = match token {
+        TokenState::Missing => {
             info!(
-                "  {} Token expired (run {} to re-authenticate)",
+                "  {} Not logged in (run {} to authenticate)",
                 cross_mark(),
                 style("codspeed auth login").cyan()
             );
-        } else {
+            return;
+        }
+        TokenState::Rejected => {
+            info!("  Token: {REDACTED_TOKEN}");
             info!(
-                "  {} Not logged in (run {} to authenticate)",
+                "  {} Token rejected by CodSpeed, it is invalid or has expired (run {} to re-authenticate)",
                 cross_mark(),
                 style("codspeed auth login").cyan()
             );
+            return;
         }
-        return;
+        TokenState::Valid(session) => session,
     };
+    info!("  Token: {REDACTED_TOKEN}");
 
     if let Some(user) = &session.user {
         info!(
@@ -305,3 +363,148 @@ fn print_repository_section(
         false
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+
+    /// Answers the next GraphQL request with `body`. Returns the URL of the endpoint.
+    fn spawn_mock_graphql(body: &'static str) -> (String, std::thread::JoinHandle<()>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/graphql", listener.local_addr().unwrap());
+        let handle = std::thread::spawn(move || {
+            let Some(Ok(mut stream)) = listener.incoming().next() else {
+                return;
+            };
+            let mut buf = [0u8; 4096];
+            let _ = stream.read(&mut buf);
+            let response = format!(
+                "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+                body.len()
+            );
+            let _ = stream.write_all(response.as_bytes());
+        });
+        (url, handle)
+    }
+
+    async fn resolve_token_state(body: &'static str) -> TokenState {
+        let (url, server) = spawn_mock_graphql(body);
+        let api_client =
+            CodSpeedAPIClient::create_test_client_with_url(url).with_token("token".to_string());
+        let auth_status = resolve_auth_status(&api_client, None).await.unwrap();
+        server.join().unwrap();
+        auth_status.token
+    }
+
+    const SESSION_RESPONSE: &str =
+        r#"{"data":{"session":{"user":{"login":"octocat","provider":"GITHUB"}}}}"#;
+    const UNAUTHENTICATED_RESPONSE: &str = r#"{"data":null,"errors":[{"message":"Unauthenticated","extensions":{"code":"UNAUTHENTICATED"}}]}"#;
+
+    fn load_config() -> CodSpeedConfig {
+        CodSpeedConfig::load_with_profile(None, None, Default::default(), true).unwrap()
+    }
+
+    #[test]
+    fn test_read_token_trims_the_piped_token() {
+        assert_eq!(
+            read_token("secret-token\n".as_bytes()).unwrap(),
+            "secret-token"
+        );
+        assert!(read_token("  \n".as_bytes()).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_token_piped_on_stdin_is_persisted() {
+        let config_home = tempfile::tempdir().unwrap();
+        temp_env::async_with_vars([("XDG_CONFIG_HOME", Some(config_home.path()))], async {
+            let (url, server) = spawn_mock_graphql(SESSION_RESPONSE);
+            let api_client = CodSpeedAPIClient::create_test_client_with_url(url);
+            let token = read_token("secret-token\n".as_bytes()).unwrap();
+
+            save_token(&api_client, None, load_config(), token, prompt::confirm)
+                .await
+                .unwrap();
+            server.join().unwrap();
+
+            assert_eq!(load_config().auth.token.as_deref(), Some("secret-token"));
+        })
+        .await;
+    }
+
+    #[tokio::test]
+    async fn test_rejected_token_is_not_persisted_nor_leaked() {
+        let config_home = tempfile::tempdir().unwrap();
+        temp_env::async_with_vars([("XDG_CONFIG_HOME", Some(config_home.path()))], async {
+            let (url, server) = spawn_mock_graphql(UNAUTHENTICATED_RESPONSE);
+            let api_client = CodSpeedAPIClient::create_test_client_with_url(url);
+
+            let error = save_token(
+                &api_client,
+                None,
+                load_config(),
+                "secret-token".into(),
+                prompt::confirm,
+            )
+            .await
+            .unwrap_err();
+            server.join().unwrap();
+
+            assert!(!format!("{error:?}").contains("secret-token"));
+            assert_eq!(load_config().auth.token, None);
+        })
+        .await;
+    }
+
+    /// Save `token` as if the CLI ran without a TTY, with or without `--yes`.
+    async fn save_token_without_tty(token: &str, assume_yes: bool) -> Result<()> {
+        let (url, server) = spawn_mock_graphql(SESSION_RESPONSE);
+        let api_client = CodSpeedAPIClient::create_test_client_with_url(url);
+        let result = save_token(
+            &api_client,
+            None,
+            load_config(),
+            token.into(),
+            |question, without_tty| prompt::confirm_with(question, without_tty, assume_yes, false),
+        )
+        .await;
+        server.join().unwrap();
+        result
+    }
+
+    #[tokio::test]
+    async fn test_token_replacement_requires_yes_without_tty() {
+        let config_home = tempfile::tempdir().unwrap();
+        temp_env::async_with_vars([("XDG_CONFIG_HOME", Some(config_home.path()))], async {
+            save_token_without_tty("old-token", false).await.unwrap();
+
+            let error = save_token_without_tty("new-token", false)
+                .await
+                .unwrap_err();
+            assert!(error.to_string().contains("--yes"), "got {error:?}");
+            assert_eq!(load_config().auth.token.as_deref(), Some("old-token"));
+
+            save_token_without_tty("new-token", true).await.unwrap();
+            assert_eq!(load_config().auth.token.as_deref(), Some("new-token"));
+        })
+        .await;
+    }
+
+    #[tokio::test]
+    async fn test_valid_token_reports_the_account() {
+        let token = resolve_token_state(SESSION_RESPONSE).await;
+
+        let TokenState::Valid(session) = token else {
+            panic!("expected a valid token, got {token:?}");
+        };
+        assert_eq!(session.user.unwrap().login, "octocat");
+    }
+
+    #[tokio::test]
+    async fn test_rejected_token_is_not_valid() {
+        let token = resolve_token_state(UNAUTHENTICATED_RESPONSE).await;
+
+        assert!(matches!(token, TokenState::Rejected), "got {token:?}");
+    }
+}
@@ -0,0 +1,256 @@
+use crate::config::CodSpeedConfig;
+use crate::executor::config::OrchestratorConfig;
+use crate::instruments::InstrumentName;
+use crate::prelude::*;
+use crate::runner_mode::{RunnerMode, load_shell_session_mode};
+use clap::{Args, Subcommand, ValueEnum};
+use serde::Serialize;
+use std::fmt;
+use std::path::PathBuf;
+
+/// Printed in place of the auth token, which must never end up in the logs.
+pub(super) const REDACTED_TOKEN: &str = "<redacted>";
+
+#[derive(Debug, Args)]
+pub struct ConfigArgs {
+    #[command(subcommand)]
+    command: ConfigCommands,
+}
+
+#[derive(Debug, Subcommand)]
+enum ConfigCommands {
+    /// Show the configuration resolved from the CodSpeed profile, the environment variables and
+    /// the shell session
+    Show {
+        /// The format to print the configuration in
+        #[arg(long, value_enum, default_value_t)]
+        format: ConfigFormat,
+    },
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum ConfigFormat {
+    #[default]
+    Text,
+    Json,
+}
+
+/// The configuration that actually takes effect, once the CLI flags, the environment variables,
+/// the shell session and the CodSpeed profile are resolved.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct EffectiveConfig {
+    profile: String,
+    api_url: String,
+    upload_url: String,
+    base_url: Option<String>,
+    /// Redacted, only tells whether a token is set
+    token: Option<&'static str>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    session_modes: Vec<RunnerMode>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    run: Option<EffectiveRunConfig>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "kebab-case")]
+struct EffectiveRunConfig {
+    modes: Vec<RunnerMode>,
+    working_directory: Option<String>,
+    profile_folder: Option<PathBuf>,
+    skip_upload: bool,
+    skip_run: bool,
+    skip_setup: bool,
+    allow_empty: bool,
+    enable_profiler: bool,
+    walltime_profiler: Option<String>,
+    perf_unwinding_mode: Option<String>,
+    inline_frames: bool,
+    ignored_objects: Vec<String>,
+    simulation_tool: Option<String>,
+    instruments: Vec<InstrumentName>,
+}
+
+/// The name of `value` on the command line, e.g. `fp` for [`crate::cli::UnwindingMode::FramePointer`].
+fn value_name(value: &impl ValueEnum) -> Option<String> {
+    value
+        .to_possible_value()
+        .map(|value| value.get_name().to_string())
+}
+
+impl EffectiveConfig {
+    pub fn new(config: &CodSpeedConfig, token: Option<&str>) -> Self {
+        Self {
+            profile: config.selected_profile_name().to_string(),
+            api_url: config.api_url.clone(),
+            upload_url: config.upload_url.clone(),
+            base_url: config.base_url.as_ref().map(|url| url.to_string()),
+            token: token.map(|_| REDACTED_TOKEN),
+            session_modes: vec![],
+            run: None,
+        }
+    }
+
+    /// Add the settings of a run, resolved from the `codspeed run` arguments.
+    pub fn with_run(mut self, config: &OrchestratorConfig) -> Self {
+        self.upload_url = config.upload_url.to_string();
+        self.run = Some(EffectiveRunConfig {
+            modes: config.modes.clone(),
+            working_directory: config.working_directory.clone(),
+            profile_folder: config.profile_folder.clone(),
+            skip_upload: config.skip_upload,
+            skip_run: config.skip_run,
+            skip_setup: config.skip_setup,
+            allow_empty: config.allow_empty,
+            enable_profiler: config.enable_profiler,
+            walltime_profiler: config.walltime_profiler.as_ref().and_then(value_name),
+            perf_unwinding_mode: config.perf_unwinding_mode.as_ref().and_then(value_name),
+            inline_frames: config.inline_frames,
+            ignored_objects: config.ignored_objects.clone(),
+            simulation_tool: value_name(&config.simulation_tool),
+            instruments: config.instruments.get_active_instrument_names(),
+        });
+        self
+    }
+
+    pub fn print(&self, format: ConfigFormat) -> Result<()> {
+        match format {
+            ConfigFormat::Text => print!("{self}"),
+            ConfigFormat::Json => println!("{}", serde_json::to_string_pretty(self)?),
+        }
+        Ok(())
+    }
+}
+
+fn write_entry(
+    f: &mut fmt::Formatter<'_>,
+    key: &str,
+    value: Option<impl fmt::Display>,
+) -> fmt::Result {
+    match value {
+        Some(value) => writeln!(f, "  {key}: {value}"),
+        None => writeln!(f, "  {key}: -"),
+    }
+}
+
+fn join_values(values: impl IntoIterator<Item = impl fmt::Display>) -> Option<String> {
+    Some(values.into_iter().join(", ")).filter(|values| !values.is_empty())
+}
+
+impl fmt::Display for EffectiveConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Profile: {}", self.profile)?;
+        write_entry(f, "API URL", Some(&self.api_url))?;
+        write_entry(f, "Upload URL", Some(&self.upload_url))?;
+        write_entry(f, "Base URL", self.base_url.as_ref())?;
+        write_entry(f, "Token", self.token)?;
+        if !self.session_modes.is_empty() {
+            write_entry(
+                f,
+                "Shell session modes",
+                join_values(self.session_modes.iter().filter_map(value_name)),
+            )?;
+        }
+
+        let Some(run) = &self.run else {
+            return Ok(());
+        };
+        writeln!(f, "Run:")?;
+        write_entry(
+            f,
+            "Modes",
+            join_values(run.modes.iter().filter_map(value_name)),
+        )?;
+        write_entry(f, "Working directory", run.working_directory.as_ref())?;
+        write_entry(
+            f,
+            "Profile folder",
+            run.profile_folder.as_ref().map(|folder| folder.display()),
+        )?;
+        write_entry(f, "Skip upload", Some(run.skip_upload))?;
+        write_entry(f, "Skip run", Some(run.skip_run))?;
+        write_entry(f, "Skip setup", Some(run.skip_setup))?;
+        write_entry(f, "Allow empty", Some(run.allow_empty))?;
+        write_entry(f, "Profiler", Some(run.enable_profiler))?;
+        write_entry(f, "Walltime profiler", run.walltime_profiler.as_ref())?;
+        write_entry(f, "Perf unwinding mode", run.perf_unwinding_mode.as_ref())?;
+        write_entry(f, "Inline frames", Some(run.inline_frames))?;
+        write_entry(f, "Ignored objects", join_values(&run.ignored_objects))?;
+        write_entry(f, "Simulation tool", run.simulation_tool.as_ref())?;
+        write_entry(
+            f,
+            "Instruments",
+            join_values(run.instruments.iter().map(|name| format!("{name:?}"))),
+        )
+    }
+}
+
+pub fn run(args: ConfigArgs, config: &CodSpeedConfig, token: Option<&str>) -> Result<()> {
+    match args.command {
+        ConfigCommands::Show { format } => EffectiveConfig {
+            session_modes: load_shell_session_mode()?,
+            ..EffectiveConfig::new(config, token)
+        }
+        .print(format),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::UnwindingMode;
+    use crate::config::ConfigOverrides;
+
+    fn load_config(token: &str) -> CodSpeedConfig {
+        let config_home = tempfile::tempdir().unwrap();
+        temp_env::with_var("XDG_CONFIG_HOME", Some(config_home.path()), || {
+            CodSpeedConfig::load_with_profile(
+                None,
+                Some("default"),
+                ConfigOverrides {
+                    oauth_token: Some(token),
+                    ..Default::default()
+                },
+                true,
+            )
+            .unwrap()
+        })
+    }
+
+    fn effective_config() -> EffectiveConfig {
+        let config = load_config("secret-token");
+        let run_config = OrchestratorConfig {
+            modes: vec![RunnerMode::Walltime],
+            profile_folder: Some(PathBuf::from("/tmp/profile")),
+            skip_upload: true,
+            perf_unwinding_mode: Some(UnwindingMode::FramePointer),
+            ..OrchestratorConfig::test()
+        };
+        EffectiveConfig::new(&config, config.auth.token.as_deref()).with_run(&run_config)
+    }
+
+    #[test]
+    fn test_text_config_redacts_the_token() {
+        let text = effective_config().to_string();
+
+        assert!(!text.contains("secret-token"));
+        assert!(text.contains("  Token: <redacted>\n"));
+        assert!(text.contains("Profile: default\n"));
+        assert!(text.contains("  Modes: walltime\n"));
+        assert!(text.contains("  Profile folder: /tmp/profile\n"));
+        assert!(text.contains("  Skip upload: true\n"));
+        assert!(text.contains("  Perf unwinding mode: fp\n"));
+    }
+
+    #[test]
+    fn test_json_config_redacts_the_token() {
+        let json = serde_json::to_value(effective_config()).unwrap();
+
+        assert!(!json.to_string().contains("secret-token"));
+        assert_eq!(json["token"], "<redacted>");
+        assert_eq!(json["profile"], "default");
+        assert_eq!(json["run"]["modes"], serde_json::json!(["walltime"]));
+        assert_eq!(json["run"]["profile-folder"], "/tmp/profile");
+        assert_eq!(json["run"]["perf-unwinding-mode"], "fp");
+    }
+}
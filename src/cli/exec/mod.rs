@@ -29,6 +29,12 @@ pub struct ExecArgs {
     #[arg(long)]
     pub name: Option<String>,
 
+    /// Profile the whole execution of the command with perf, without the exec-harness nor a
+    /// CodSpeed integration. The results are not uploaded, the profile folder is kept to be
+    /// inspected or exported (e.g. with `codspeed export-folded`). Only supports the walltime mode
+    #[arg(long, conflicts_with = "name")]
+    pub raw: bool,
+
     /// The command to execute with the exec harness
     pub command: Vec<String>,
 }
@@ -39,6 +45,8 @@ impl ExecArgs {
     /// CLI arguments take precedence over config values.
     pub fn merge_with_project_config(mut self, project_config: Option<&ProjectConfig>) -> Self {
         if let Some(project_config) = project_config {
+            self.shared
+                .merge_with_project_options(project_config.options.as_ref());
             self.walltime_args = ConfigMerger::merge_walltime_options(
                 &self.walltime_args,
                 project_config
@@ -80,16 +88,38 @@ fn build_orchestrator_config(
         walltime_profiler: args.shared.walltime_profiler,
         simulation_tool: args.shared.simulation_tool.unwrap_or_default(),
         profile_folder: args.shared.profile_folder,
-        skip_upload: args.shared.skip_upload,
+        clear_profile_folder: args.shared.clear_profile_folder,
+        // Raw profiles have no benchmark results to upload
+        skip_upload: args.shared.skip_upload || args.raw,
         skip_run: args.shared.skip_run,
         skip_setup: args.shared.skip_setup,
         allow_empty: args.shared.allow_empty,
         go_runner_version: args.shared.go_runner_version,
         show_full_output: args.shared.show_full_output,
+        cpu: args.shared.cpu,
+        strip_paths: args.shared.strip_paths,
+        use_cache: args.shared.use_cache,
+        benchmark_timeout: args
+            .shared
+            .benchmark_timeout
+            .map(std::time::Duration::from_secs),
+        upload_concurrency: args.shared.upload_concurrency,
+        clean_tmp_maps: args.shared.clean_tmp_maps,
+        raw: args.raw,
+        inline_frames: args.shared.inline_frames,
+        parallel_modes: args.shared.parallel_modes,
+        compress_artifacts: args.shared.compress_artifacts,
+        ignored_objects: args.shared.ignore_objects.clone(),
+        valgrind_extra_args: args.shared.valgrind_args.clone(),
+        keep_profile_folder: args.shared.keep_profile_folder || args.raw,
+        skip_unsupported_modes: args.shared.skip_unsupported_modes,
+        capture_output: args.shared.capture_output,
+        integration_mode_override: args.shared.integration_mode,
         poll_results_options,
         extra_env: HashMap::new(),
         fair_sched: args.shared.experimental.experimental_fair_sched,
         cycle_estimation: args.shared.experimental.cycle_estimation,
+        command_line_options: args.shared.command_line_options,
     })
 }
 
@@ -101,15 +131,24 @@ pub async fn run(
 ) -> Result<()> {
     let merged_args = args.merge_with_project_config(project_config);
     let base_run_id = merged_args.shared.base.clone();
-    let target = executor::BenchmarkTarget::Exec {
-        command: merged_args.command.clone(),
-        name: merged_args.name.clone(),
-        walltime_args: merged_args.walltime_args.clone(),
+    let fail_on_regression = merged_args.shared.fail_on_regression;
+    let results_view = merged_args.shared.results_view();
+    let target = if merged_args.raw {
+        executor::BenchmarkTarget::Entrypoint {
+            command: shell_words::join(&merged_args.command),
+            name: None,
+        }
+    } else {
+        executor::BenchmarkTarget::Exec {
+            command: merged_args.command.clone(),
+            name: merged_args.name.clone(),
+            walltime_args: merged_args.walltime_args.clone(),
+        }
     };
     let config = build_orchestrator_config(
         merged_args,
         target,
-        PollResultsOptions::new(false, base_run_id),
+        PollResultsOptions::new(false, base_run_id, fail_on_regression, results_view),
     )?;
 
     execute_config(config, api_client, setup_cache_dir).await
@@ -0,0 +1,180 @@
+use super::resolve::Symbolizer;
+use crate::executor::{PERF_PIPEDATA_FILE_NAME, for_each_sample_callchain};
+use crate::prelude::*;
+use clap::Args;
+use libc::pid_t;
+use runner_shared::metadata::WalltimeMetadata;
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// Name of the frames whose address is not covered by any symbol, as in `perf script`.
+const UNKNOWN_FRAME: &str = "[unknown]";
+
+#[derive(Args, Debug)]
+pub struct ExportFoldedArgs {
+    /// The walltime profile folder of the run, containing the perf data, the walltime metadata
+    /// and the symbols files
+    pub profile_folder: PathBuf,
+
+    /// The file to write the folded stacks to, e.g. `profile.folded`
+    pub output: PathBuf,
+
+    /// Demangle the Rust and C++ symbol names
+    #[arg(long)]
+    pub demangle: bool,
+}
+
+/// Sample counts by folded stack, e.g. `main;bench::run;bench::fib`.
+type FoldedStacks = BTreeMap<String, u64>;
+
+/// Fold the `samples` into stacks of symbol names, from the outermost frame to the innermost
+/// one. Samples of the processes without recorded symbols are skipped.
+fn fold_samples(
+    symbolizer: &mut Symbolizer,
+    samples: impl IntoIterator<Item = (pid_t, Vec<u64>)>,
+) -> Result<FoldedStacks> {
+    let mut folded_stacks = FoldedStacks::new();
+    for (pid, frames) in samples {
+        if let Some(stack) = fold_stack(symbolizer, pid, &frames)? {
+            *folded_stacks.entry(stack).or_default() += 1;
+        }
+    }
+    Ok(folded_stacks)
+}
+
+/// Fold the `frames` of a sample, from the innermost frame to the outermost one.
+fn fold_stack(symbolizer: &mut Symbolizer, pid: pid_t, frames: &[u64]) -> Result<Option<String>> {
+    if !symbolizer.has_pid(pid) {
+        return Ok(None);
+    }
+
+    let mut names = Vec::with_capacity(frames.len());
+    for (index, &address) in frames.iter().enumerate() {
+        // The callers' frames hold return addresses, which can point right after the end of
+        // the calling function
+        let address = if index == 0 {
+            address
+        } else {
+            address.saturating_sub(1)
+        };
+        let name = match symbolizer.resolve(pid, address)? {
+            // `;` separates the frames of a folded stack
+            Some(symbol) => symbol.name.replace(';', ":"),
+            None => UNKNOWN_FRAME.to_string(),
+        };
+        names.push(name);
+    }
+    names.reverse();
+    Ok(Some(names.join(";")))
+}
+
+fn write_folded_stacks(folded_stacks: &FoldedStacks, output: &Path) -> Result<()> {
+    let mut writer = std::io::BufWriter::new(
+        std::fs::File::create(output)
+            .with_context(|| format!("Failed to create {}", output.display()))?,
+    );
+    for (stack, count) in folded_stacks {
+        writeln!(writer, "{stack} {count}")?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+pub fn run(args: ExportFoldedArgs) -> Result<()> {
+    let metadata = WalltimeMetadata::load_from(&args.profile_folder)?;
+    let perf_file_path = args.profile_folder.join(PERF_PIPEDATA_FILE_NAME);
+    ensure!(
+        perf_file_path.is_file(),
+        "No perf data found in {}, only the profile folders of the perf walltime profiler can be exported",
+        args.profile_folder.display()
+    );
+
+    let mut samples = Vec::new();
+    for_each_sample_callchain(&perf_file_path, |pid, frames| {
+        samples.push((pid, frames.to_vec()))
+    })?;
+    let sample_count = samples.len();
+
+    let mut symbolizer =
+        Symbolizer::new(&args.profile_folder, &metadata).with_demangling(args.demangle);
+    let folded_stacks = fold_samples(&mut symbolizer, samples)?;
+    write_folded_stacks(&folded_stacks, &args.output)?;
+
+    let folded_sample_count: u64 = folded_stacks.values().sum();
+    info!(
+        "Wrote {} stacks from {folded_sample_count} samples to {}",
+        folded_stacks.len(),
+        args.output.display()
+    );
+    if folded_sample_count < sample_count as u64 {
+        debug!(
+            "Skipped {} samples of processes without recorded symbols",
+            sample_count as u64 - folded_sample_count
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use runner_shared::metadata::WALLTIME_METADATA_CURRENT_VERSION;
+    use runner_shared::module_symbols::{MappedProcessModuleSymbols, SYMBOLS_MAP_SUFFIX};
+    use std::collections::HashMap;
+
+    const LOAD_BIAS: u64 = 0x5555_5555_4000;
+
+    fn write_profile_folder(folder: &Path) -> WalltimeMetadata {
+        std::fs::write(
+            folder.join(format!("0-bench.{SYMBOLS_MAP_SUFFIX}")),
+            "1000 100 main\n2000 40 bench::fib\n3000 80 bench::run\n",
+        )
+        .unwrap();
+
+        let metadata = WalltimeMetadata {
+            version: WALLTIME_METADATA_CURRENT_VERSION,
+            integration: ("codspeed-rust".to_string(), "1.0.0".to_string()),
+            mapped_process_module_symbols: HashMap::from([(
+                42,
+                vec![MappedProcessModuleSymbols {
+                    perf_map_key: "0-bench".to_string(),
+                    load_bias: LOAD_BIAS,
+                    avma_ranges: vec![LOAD_BIAS + 0x1000..LOAD_BIAS + 0x4000],
+                }],
+            )]),
+            ..Default::default()
+        };
+        metadata.save_to(folder).unwrap();
+        metadata
+    }
+
+    #[test]
+    fn test_fold_samples_from_fixture() {
+        let folder = tempfile::tempdir().unwrap();
+        let metadata = write_profile_folder(folder.path());
+        let mut symbolizer = Symbolizer::new(folder.path(), &metadata);
+
+        // Frames from the innermost to the outermost, the callers' ones being return addresses
+        let fib_stack = vec![LOAD_BIAS + 0x2010, LOAD_BIAS + 0x3080, LOAD_BIAS + 0x1020];
+        let samples = vec![
+            (42, fib_stack.clone()),
+            (42, fib_stack),
+            (42, vec![LOAD_BIAS + 0x3004, LOAD_BIAS + 0x1020]),
+            (42, vec![LOAD_BIAS + 0x9000, LOAD_BIAS + 0x1020]),
+            // Not a benchmark process
+            (7, vec![LOAD_BIAS + 0x2010]),
+        ];
+        let folded_stacks = fold_samples(&mut symbolizer, samples).unwrap();
+
+        let output = folder.path().join("profile.folded");
+        write_folded_stacks(&folded_stacks, &output).unwrap();
+        let folded = std::fs::read_to_string(output).unwrap();
+        assert_eq!(
+            folded,
+            "main;[unknown] 1\n\
+             main;bench::run 1\n\
+             main;bench::run;bench::fib 2\n"
+        );
+    }
+}
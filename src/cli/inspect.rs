@@ -0,0 +1,350 @@
+use crate::prelude::*;
+use clap::Args;
+use console::style;
+use libc::pid_t;
+use runner_shared::metadata::WalltimeMetadata;
+use runner_shared::module_symbols::SYMBOLS_MAP_SUFFIX;
+use runner_shared::unwind_data::UNWIND_FILE_EXT;
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+
+#[derive(Args, Debug)]
+pub struct InspectArgs {
+    /// The profile folder to inspect, containing the walltime metadata and its artifacts
+    pub profile_folder: PathBuf,
+}
+
+/// Why a `path_key` referenced by a per-pid entry can't be resolved.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum DanglingReason {
+    /// The key has no entry in `path_key_to_path`
+    MissingPath,
+    /// The file the key points to is not in the profile folder
+    MissingFile,
+    /// The key has no entry in the deduplicated `debug_info` map
+    MissingDebugInfo,
+}
+
+impl std::fmt::Display for DanglingReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DanglingReason::MissingPath => write!(f, "missing from path_key_to_path"),
+            DanglingReason::MissingFile => write!(f, "no backing file"),
+            DanglingReason::MissingDebugInfo => write!(f, "missing from debug_info"),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+struct DanglingKey {
+    kind: &'static str,
+    key: String,
+    reason: DanglingReason,
+}
+
+struct InspectReport {
+    version: u64,
+    integration: (String, String),
+    /// Allow-listed environment variables and `uname` information captured during the run
+    environment: BTreeMap<String, String>,
+    /// Number of distinct modules mapped in each process
+    modules_by_pid: BTreeMap<pid_t, usize>,
+    unwind_data_keys: BTreeSet<String>,
+    symbols_keys: BTreeSet<String>,
+    debug_info_keys: BTreeSet<String>,
+    dangling_keys: Vec<DanglingKey>,
+}
+
+impl InspectReport {
+    fn from_profile_folder(profile_folder: &Path) -> Result<Self> {
+        let metadata = WalltimeMetadata::load_from(profile_folder)?;
+        Ok(Self::new(profile_folder, metadata))
+    }
+
+    fn new(profile_folder: &Path, metadata: WalltimeMetadata) -> Self {
+        let mut modules_by_pid: BTreeMap<pid_t, BTreeSet<&str>> = BTreeMap::new();
+        let mut unwind_data_keys = BTreeSet::new();
+        let mut symbols_keys = BTreeSet::new();
+        let mut debug_info_keys = BTreeSet::new();
+
+        for (pid, mappings) in &metadata.mapped_process_unwind_data_by_pid {
+            for mapping in mappings {
+                let key = mapping.unwind_data_key.as_str();
+                modules_by_pid.entry(*pid).or_default().insert(key);
+                unwind_data_keys.insert(key.to_string());
+            }
+        }
+        for (pid, mappings) in &metadata.mapped_process_module_symbols {
+            for mapping in mappings {
+                let key = mapping.perf_map_key.as_str();
+                modules_by_pid.entry(*pid).or_default().insert(key);
+                symbols_keys.insert(key.to_string());
+            }
+        }
+        for (pid, mappings) in &metadata.mapped_process_debug_info_by_pid {
+            for mapping in mappings {
+                let key = mapping.debug_info_key.as_str();
+                modules_by_pid.entry(*pid).or_default().insert(key);
+                debug_info_keys.insert(key.to_string());
+            }
+        }
+
+        let keyed_file_exists = |key: &str, extension: &str| {
+            profile_folder.join(format!("{key}.{extension}")).is_file()
+        };
+        let mut dangling_keys = Vec::new();
+        let mut check = |kind: &'static str,
+                         keys: &BTreeSet<String>,
+                         is_backed: &dyn Fn(&str) -> bool,
+                         missing_reason: DanglingReason| {
+            for key in keys {
+                let reason = if !metadata.path_key_to_path.contains_key(key) {
+                    DanglingReason::MissingPath
+                } else if !is_backed(key) {
+                    missing_reason
+                } else {
+                    continue;
+                };
+                dangling_keys.push(DanglingKey {
+                    kind,
+                    key: key.clone(),
+                    reason,
+                });
+            }
+        };
+        check(
+            "unwind data",
+            &unwind_data_keys,
+            &|key| keyed_file_exists(key, UNWIND_FILE_EXT),
+            DanglingReason::MissingFile,
+        );
+        check(
+            "symbols",
+            &symbols_keys,
+            &|key| keyed_file_exists(key, SYMBOLS_MAP_SUFFIX),
+            DanglingReason::MissingFile,
+        );
+        check(
+            "debug info",
+            &debug_info_keys,
+            &|key| metadata.debug_info.contains_key(key),
+            DanglingReason::MissingDebugInfo,
+        );
+
+        let modules_by_pid = modules_by_pid
+            .into_iter()
+            .map(|(pid, keys)| (pid, keys.len()))
+            .collect();
+        Self {
+            version: metadata.version,
+            integration: metadata.integration,
+            environment: metadata.environment.into_iter().collect(),
+            modules_by_pid,
+            unwind_data_keys,
+            symbols_keys,
+            debug_info_keys,
+            dangling_keys,
+        }
+    }
+
+    fn log(&self) {
+        let (name, version) = &self.integration;
+        info!("{}", style("Metadata").bold());
+        info!("  format version {}", self.version);
+        info!("  integration {name} {version}");
+        info!("");
+
+        if !self.environment.is_empty() {
+            info!("{}", style("Environment").bold());
+            for (key, value) in &self.environment {
+                info!("  {key}={value}");
+            }
+            info!("");
+        }
+
+        info!("{}", style("Processes").bold());
+        if self.modules_by_pid.is_empty() {
+            info!("  No mapped modules");
+        }
+        for (pid, module_count) in &self.modules_by_pid {
+            info!("  pid {pid}: {module_count} module(s)");
+        }
+        info!("");
+
+        info!("{}", style("Entries").bold());
+        info!("  {} unwind data", self.unwind_data_keys.len());
+        info!("  {} symbols", self.symbols_keys.len());
+        info!("  {} debug info", self.debug_info_keys.len());
+
+        if !self.dangling_keys.is_empty() {
+            info!("");
+            info!("{}", style("Dangling path keys").bold());
+            for dangling in &self.dangling_keys {
+                warn!(
+                    "  {} `{}`: {}",
+                    dangling.kind, dangling.key, dangling.reason
+                );
+            }
+        }
+    }
+}
+
+pub fn run(args: InspectArgs) -> Result<()> {
+    let report = InspectReport::from_profile_folder(&args.profile_folder)?;
+    report.log();
+
+    if !report.dangling_keys.is_empty() {
+        bail!(
+            "Found {} dangling path key(s) in {}",
+            report.dangling_keys.len(),
+            args.profile_folder.display()
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use runner_shared::debug_info::{MappedProcessDebugInfo, ModuleDebugInfo};
+    use runner_shared::metadata::WALLTIME_METADATA_CURRENT_VERSION;
+    use runner_shared::module_symbols::MappedProcessModuleSymbols;
+    use runner_shared::unwind_data::{MappedProcessUnwindData, ProcessUnwindData};
+    use std::collections::HashMap;
+
+    const LIBC_KEY: &str = "0-libc.so.6";
+    const BENCH_KEY: &str = "1-bench";
+
+    fn unwind_mapping(key: &str) -> MappedProcessUnwindData {
+        MappedProcessUnwindData {
+            unwind_data_key: key.to_string(),
+            inner: ProcessUnwindData {
+                timestamp: None,
+                avma_range: 0x1000..0x2000,
+                base_avma: 0x1000,
+            },
+        }
+    }
+
+    fn symbols_mapping(key: &str) -> MappedProcessModuleSymbols {
+        MappedProcessModuleSymbols {
+            perf_map_key: key.to_string(),
+            load_bias: 0,
+            avma_ranges: vec![],
+        }
+    }
+
+    /// Write a profile folder where two processes map libc and the benchmark binary.
+    fn write_profile_folder(folder: &Path) -> WalltimeMetadata {
+        let keys = [LIBC_KEY, BENCH_KEY];
+        for key in keys {
+            std::fs::write(folder.join(format!("{key}.{UNWIND_FILE_EXT}")), [0u8]).unwrap();
+            std::fs::write(
+                folder.join(format!("{key}.{SYMBOLS_MAP_SUFFIX}")),
+                "1000 10 main\n",
+            )
+            .unwrap();
+        }
+
+        WalltimeMetadata {
+            version: WALLTIME_METADATA_CURRENT_VERSION,
+            integration: ("codspeed-rust".to_string(), "1.0.0".to_string()),
+            mapped_process_unwind_data_by_pid: HashMap::from([
+                (1, keys.iter().map(|key| unwind_mapping(key)).collect()),
+                (2, vec![unwind_mapping(LIBC_KEY)]),
+            ]),
+            mapped_process_module_symbols: HashMap::from([
+                (1, keys.iter().map(|key| symbols_mapping(key)).collect()),
+                (2, vec![symbols_mapping(LIBC_KEY)]),
+            ]),
+            debug_info: HashMap::from([(
+                BENCH_KEY.to_string(),
+                ModuleDebugInfo {
+                    object_path: "/tmp/bench".to_string(),
+                    addr_bounds: (0x1000, 0x2000),
+                    load_bias: 0,
+                    debug_infos: vec![],
+                    inline_ranges: vec![],
+                },
+            )]),
+            mapped_process_debug_info_by_pid: HashMap::from([(
+                1,
+                vec![MappedProcessDebugInfo {
+                    debug_info_key: BENCH_KEY.to_string(),
+                    load_bias: 0,
+                }],
+            )]),
+            path_key_to_path: HashMap::from([
+                (LIBC_KEY.to_string(), PathBuf::from("/usr/lib/libc.so.6")),
+                (BENCH_KEY.to_string(), PathBuf::from("/tmp/bench")),
+            ]),
+            environment: HashMap::from([
+                ("RUSTC".to_string(), "rustc".to_string()),
+                ("uname.sysname".to_string(), "Linux".to_string()),
+            ]),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_inspect_well_formed_profile_folder() {
+        let folder = tempfile::tempdir().unwrap();
+        write_profile_folder(folder.path())
+            .save_to(folder.path())
+            .unwrap();
+
+        let report = InspectReport::from_profile_folder(folder.path()).unwrap();
+
+        assert_eq!(report.integration.0, "codspeed-rust");
+        assert_eq!(
+            report.environment.keys().collect::<Vec<_>>(),
+            vec!["RUSTC", "uname.sysname"]
+        );
+        assert_eq!(report.modules_by_pid, BTreeMap::from([(1, 2), (2, 1)]));
+        assert_eq!(report.unwind_data_keys.len(), 2);
+        assert_eq!(report.symbols_keys.len(), 2);
+        assert_eq!(report.debug_info_keys.len(), 1);
+        assert!(report.dangling_keys.is_empty());
+        run(InspectArgs {
+            profile_folder: folder.path().to_path_buf(),
+        })
+        .unwrap();
+    }
+
+    #[test]
+    fn test_inspect_reports_dangling_keys() {
+        let folder = tempfile::tempdir().unwrap();
+        let mut metadata = write_profile_folder(folder.path());
+        metadata.path_key_to_path.remove(LIBC_KEY);
+        metadata.debug_info.clear();
+        std::fs::remove_file(
+            folder
+                .path()
+                .join(format!("{BENCH_KEY}.{SYMBOLS_MAP_SUFFIX}")),
+        )
+        .unwrap();
+        metadata.save_to(folder.path()).unwrap();
+
+        let report = InspectReport::from_profile_folder(folder.path()).unwrap();
+
+        let dangling = |kind, key: &str, reason| DanglingKey {
+            kind,
+            key: key.to_string(),
+            reason,
+        };
+        assert_eq!(
+            report.dangling_keys,
+            vec![
+                dangling("unwind data", LIBC_KEY, DanglingReason::MissingPath),
+                dangling("symbols", LIBC_KEY, DanglingReason::MissingPath),
+                dangling("symbols", BENCH_KEY, DanglingReason::MissingFile),
+                dangling("debug info", BENCH_KEY, DanglingReason::MissingDebugInfo),
+            ]
+        );
+        let err = run(InspectArgs {
+            profile_folder: folder.path().to_path_buf(),
+        })
+        .unwrap_err();
+        assert!(err.to_string().contains("4 dangling path key(s)"));
+    }
+}
@@ -0,0 +1,264 @@
+use super::config::ConfigFormat;
+use super::status::{check_mark, cross_mark};
+use crate::executor::{
+    ExecutorName, ExecutorSupport, PrivilegeStatus, SimulationTool, ToolInstallStatus, ToolStatus,
+    get_executor_from_mode,
+};
+use crate::prelude::*;
+use crate::runner_mode::RunnerMode;
+use crate::system::{SupportedOs, SystemInfo};
+use clap::Args;
+use serde::Serialize;
+
+#[derive(Debug, Args)]
+pub struct ListModesArgs {
+    /// The format to print the modes in
+    #[arg(long, value_enum, default_value_t)]
+    format: ConfigFormat,
+}
+
+/// Whether a mode can run on this machine, and what is missing otherwise.
+#[derive(Debug, PartialEq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+struct ModeReadiness {
+    mode: RunnerMode,
+    executor: ExecutorName,
+    ready: bool,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    issues: Vec<String>,
+}
+
+/// The modes a benchmark can be run with, `auto` aside.
+fn listed_modes() -> Vec<RunnerMode> {
+    #[cfg_attr(not(target_os = "linux"), allow(unused_mut))]
+    let mut modes = vec![RunnerMode::Simulation, RunnerMode::Walltime];
+    #[cfg(target_os = "linux")]
+    modes.push(RunnerMode::Memory);
+    modes
+}
+
+/// Check the readiness of `mode` from the capabilities probed for its executor.
+///
+/// The tool and privileges are only checked on the platforms the executor supports, and the
+/// privileges once the tool is installed, as `codspeed setup status` does.
+fn mode_readiness(
+    mode: RunnerMode,
+    executor: ExecutorName,
+    os: &SupportedOs,
+    support: ExecutorSupport,
+    tool_status: impl FnOnce() -> Option<ToolStatus>,
+    privilege_status: impl FnOnce() -> Option<PrivilegeStatus>,
+) -> ModeReadiness {
+    let mut issues = vec![];
+    if support == ExecutorSupport::Unsupported {
+        issues.push(format!(
+            "not supported on {os}, it only runs on {}",
+            executor.supported_platforms()
+        ));
+    } else {
+        let tool_installed = match tool_status() {
+            None => true,
+            Some(ToolStatus { tool_name, status }) => match status {
+                ToolInstallStatus::Installed { .. } => true,
+                ToolInstallStatus::IncorrectVersion { version, message } => {
+                    issues.push(format!("{tool_name} {version}: {message}"));
+                    false
+                }
+                ToolInstallStatus::NotInstalled
+                    if support == ExecutorSupport::RequiresManualInstallation =>
+                {
+                    issues.push(format!(
+                        "{tool_name} is not installed and can't be installed automatically on {os}"
+                    ));
+                    false
+                }
+                ToolInstallStatus::NotInstalled => {
+                    issues.push(format!(
+                        "{tool_name} is not installed, run `codspeed setup --mode {mode}`"
+                    ));
+                    false
+                }
+            },
+        };
+        if tool_installed {
+            if let Some(PrivilegeStatus::Missing { message }) = privilege_status() {
+                issues.push(message);
+            }
+        }
+    }
+
+    ModeReadiness {
+        mode,
+        executor,
+        ready: issues.is_empty(),
+        issues,
+    }
+}
+
+fn print_text(readiness: &[ModeReadiness]) {
+    for mode in readiness {
+        let mark = if mode.ready {
+            check_mark()
+        } else {
+            cross_mark()
+        };
+        info!("  {mark} {} ({} executor)", mode.mode, mode.executor);
+        for issue in &mode.issues {
+            info!("      {issue}");
+        }
+    }
+}
+
+pub fn run(args: ListModesArgs) -> Result<()> {
+    let system_info = SystemInfo::new()?;
+    let readiness = listed_modes()
+        .into_iter()
+        .map(|mode| {
+            let executor = get_executor_from_mode(&mode, None, SimulationTool::default())?;
+            Ok(mode_readiness(
+                mode,
+                executor.name(),
+                &system_info.os,
+                executor.support_level(&system_info),
+                || executor.tool_status(),
+                || executor.privilege_status(),
+            ))
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    match args.format {
+        ConfigFormat::Text => print_text(&readiness),
+        ConfigFormat::Json => println!("{}", serde_json::to_string_pretty(&readiness)?),
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::system::LinuxDistribution;
+
+    fn ubuntu() -> SupportedOs {
+        SupportedOs::Linux(LinuxDistribution::Ubuntu {
+            version: "24.04".into(),
+        })
+    }
+
+    fn tool(status: ToolInstallStatus) -> Option<ToolStatus> {
+        Some(ToolStatus {
+            tool_name: "perf".into(),
+            status,
+        })
+    }
+
+    fn walltime_readiness(
+        support: ExecutorSupport,
+        tool_status: Option<ToolStatus>,
+        privilege_status: Option<PrivilegeStatus>,
+    ) -> ModeReadiness {
+        mode_readiness(
+            RunnerMode::Walltime,
+            ExecutorName::WallTime,
+            &ubuntu(),
+            support,
+            || tool_status,
+            || privilege_status,
+        )
+    }
+
+    #[test]
+    fn test_mode_is_ready_with_its_tool_and_privileges() {
+        let readiness = walltime_readiness(
+            ExecutorSupport::FullySupported,
+            tool(ToolInstallStatus::Installed {
+                version: "6.8".into(),
+            }),
+            Some(PrivilegeStatus::Satisfied {
+                detail: "perf_event_paranoid is 1".into(),
+            }),
+        );
+        assert!(readiness.ready);
+        assert!(readiness.issues.is_empty());
+    }
+
+    #[test]
+    fn test_missing_tool_is_reported() {
+        let readiness = walltime_readiness(
+            ExecutorSupport::FullySupported,
+            tool(ToolInstallStatus::NotInstalled),
+            Some(PrivilegeStatus::Missing {
+                message: "unreachable".into(),
+            }),
+        );
+        assert!(!readiness.ready);
+        assert_eq!(
+            readiness.issues,
+            vec!["perf is not installed, run `codspeed setup --mode walltime`"]
+        );
+
+        let readiness = walltime_readiness(
+            ExecutorSupport::RequiresManualInstallation,
+            tool(ToolInstallStatus::NotInstalled),
+            None,
+        );
+        assert_eq!(
+            readiness.issues,
+            vec!["perf is not installed and can't be installed automatically on ubuntu 24.04"]
+        );
+    }
+
+    #[test]
+    fn test_missing_privileges_are_reported() {
+        let readiness = walltime_readiness(
+            ExecutorSupport::FullySupported,
+            tool(ToolInstallStatus::Installed {
+                version: "6.8".into(),
+            }),
+            Some(PrivilegeStatus::Missing {
+                message: "perf_event_paranoid is 4, run `codspeed setup`".into(),
+            }),
+        );
+        assert!(!readiness.ready);
+        assert_eq!(
+            readiness.issues,
+            vec!["perf_event_paranoid is 4, run `codspeed setup`"]
+        );
+    }
+
+    #[test]
+    fn test_unsupported_mode_is_not_probed() {
+        let readiness = mode_readiness(
+            RunnerMode::Simulation,
+            ExecutorName::Valgrind,
+            &SupportedOs::Macos {
+                version: "15.0".into(),
+            },
+            ExecutorSupport::Unsupported,
+            || panic!("the tool of an unsupported executor is probed"),
+            || panic!("the privileges of an unsupported executor are probed"),
+        );
+        assert!(!readiness.ready);
+        assert_eq!(
+            readiness.issues,
+            vec!["not supported on macos 15.0, it only runs on Linux"]
+        );
+    }
+
+    #[test]
+    fn test_readiness_json() {
+        let readiness = walltime_readiness(
+            ExecutorSupport::FullySupported,
+            tool(ToolInstallStatus::NotInstalled),
+            None,
+        );
+        assert_eq!(
+            serde_json::to_value(&readiness).unwrap(),
+            serde_json::json!({
+                "mode": "walltime",
+                "executor": "walltime",
+                "ready": false,
+                "issues": ["perf is not installed, run `codspeed setup --mode walltime`"],
+            })
+        );
+    }
+}
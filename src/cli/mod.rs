@@ -1,9 +1,15 @@
 mod auth;
+mod config;
 pub(crate) mod exec;
 pub(crate) mod experimental;
+mod export_folded;
+mod inspect;
+mod list_modes;
 mod profile;
+mod resolve;
 pub(crate) mod run;
 pub(crate) mod samply;
+mod selftest;
 mod setup;
 mod shared;
 mod show;
@@ -24,7 +30,7 @@ use crate::{
     project_config::DiscoveredProjectConfig,
 };
 use clap::{
-    Parser, Subcommand,
+    CommandFactory, FromArgMatches, Parser, Subcommand,
     builder::{Styles, styling},
 };
 
@@ -45,6 +51,11 @@ pub struct Cli {
     #[arg(long, env = "CODSPEED_API_URL", global = true, hide = true)]
     pub api_url: Option<String>,
 
+    /// The base URL of a self-hosted CodSpeed instance.
+    /// The API and upload URLs are derived from it unless set explicitly.
+    #[arg(long, env = "CODSPEED_BASE_URL", global = true)]
+    pub base_url: Option<String>,
+
     /// The OAuth token to use for all requests
     #[arg(long, env = "CODSPEED_OAUTH_TOKEN", global = true, hide = true)]
     pub oauth_token: Option<String>,
@@ -72,6 +83,21 @@ pub struct Cli {
     #[arg(long, env = "CODSPEED_SETUP_CACHE_DIR", global = true)]
     pub setup_cache_dir: Option<String>,
 
+    /// Answer yes to every confirmation prompt, for non-interactive usage
+    /// (e.g. modifying system settings, installing dependencies or replacing the saved token)
+    #[arg(short = 'y', long, env = "CODSPEED_ASSUME_YES", global = true)]
+    pub yes: bool,
+
+    /// Seed for the randomized behavior of the runner, for reproducible runs.
+    /// A random seed is used if not provided.
+    #[arg(long, env = "CODSPEED_SEED", global = true)]
+    pub seed: Option<u64>,
+
+    /// Path to a PEM bundle of extra CA certificates to trust, e.g. the one of a proxy
+    /// intercepting the TLS traffic. The proxy itself is read from `HTTPS_PROXY`/`HTTP_PROXY`
+    #[arg(long, env = "CODSPEED_CA_BUNDLE", global = true)]
+    pub ca_bundle: Option<PathBuf>,
+
     #[command(subcommand)]
     command: Commands,
 }
@@ -93,12 +119,27 @@ enum Commands {
     Setup(setup::SetupArgs),
     /// Show the overall status of CodSpeed (authentication, tools, system)
     Status,
+    /// List the modes and whether their tools and privileges are ready on this machine
+    ListModes(list_modes::ListModesArgs),
+    /// Inspect the configuration resolved for this invocation
+    Config(config::ConfigArgs),
     /// Set the codspeed mode for the rest of the shell session
     Use(use_mode::UseArgs),
     /// Show the codspeed mode previously set in this shell session with `codspeed use`
     Show,
     /// Update the CodSpeed CLI to the latest version
     Update,
+    /// Inspect the walltime profile folder of a run, reporting its modules and dangling entries
+    Inspect(inspect::InspectArgs),
+    /// Resolve a runtime address of a process to a symbol, using the symbols of a profile folder
+    Resolve(resolve::ResolveArgs),
+    /// Export the samples of a walltime profile folder as folded stacks, for flamegraph tools
+    /// like `inferno` or `flamegraph.pl`. The profile must be recorded with the frame pointer
+    /// unwinding
+    ExportFolded(export_folded::ExportFoldedArgs),
+    /// Profile a tiny built-in benchmark with the walltime mode and check the saved artifacts,
+    /// without uploading anything. Validates that profiling works on this machine
+    Selftest(selftest::SelftestArgs),
 
     #[command(flatten)]
     Internal(InternalCommands),
@@ -130,7 +171,18 @@ impl InternalCommands {
 }
 
 pub async fn run() -> Result<()> {
-    let cli = Cli::parse();
+    let matches = Cli::command().get_matches();
+    let mut cli = Cli::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+    if let Some((_, command_matches)) = matches.subcommand() {
+        match &mut cli.command {
+            Commands::Run(args) => args.shared.record_command_line_options(command_matches),
+            Commands::Exec(args) => args.shared.record_command_line_options(command_matches),
+            _ => {}
+        }
+    }
+    crate::prompt::set_assume_yes(cli.yes);
+    crate::seed::init_seed(cli.seed);
+    crate::request_client::init_ca_bundle(cli.ca_bundle.as_deref())?;
     let codspeed_config = load_config(&cli)?;
     let mut api_client = build_api_client(&cli, &codspeed_config);
 
@@ -148,7 +200,10 @@ pub async fn run() -> Result<()> {
     let setup_cache_dir = setup_cache_dir.as_deref();
 
     match cli.command {
-        Commands::Run(_) | Commands::Exec(_) | Commands::Internal(InternalCommands::Samply(_)) => {} // these are responsible for their own logger initialization
+        Commands::Run(_)
+        | Commands::Exec(_)
+        | Commands::Selftest(_)
+        | Commands::Internal(InternalCommands::Samply(_)) => {} // these are responsible for their own logger initialization
         _ => {
             init_local_logger()?;
         }
@@ -164,6 +219,7 @@ pub async fn run() -> Result<()> {
             run::run(
                 args,
                 &mut api_client,
+                &codspeed_config,
                 discovered_config.as_ref(),
                 setup_cache_dir,
             )
@@ -195,9 +251,15 @@ pub async fn run() -> Result<()> {
         }
         Commands::Setup(args) => setup::run(args, setup_cache_dir).await?,
         Commands::Status => status::run(&api_client, &codspeed_config).await?,
+        Commands::ListModes(args) => list_modes::run(args)?,
+        Commands::Config(args) => config::run(args, &codspeed_config, api_client.token())?,
         Commands::Use(args) => use_mode::run(args)?,
         Commands::Show => show::run()?,
         Commands::Update => update::run().await?,
+        Commands::Inspect(args) => inspect::run(args)?,
+        Commands::Resolve(args) => resolve::run(args)?,
+        Commands::ExportFolded(args) => export_folded::run(args)?,
+        Commands::Selftest(args) => selftest::run(args, &mut api_client, setup_cache_dir).await?,
         Commands::Internal(InternalCommands::Samply(args)) => samply::run(args)?,
     }
     Ok(())
@@ -226,6 +288,7 @@ fn load_config(cli: &Cli) -> Result<CodSpeedConfig> {
             oauth_token: cli.oauth_token.as_deref(),
             api_url: cli.api_url.as_deref(),
             upload_url: None,
+            base_url: cli.base_url.as_deref(),
         },
         matches!(&cli.command, Commands::Auth(_) | Commands::Profile(_)),
     )
@@ -30,6 +30,9 @@ enum ProfileCommands {
         /// The URL to use for uploading results
         #[arg(long)]
         upload_url: Option<String>,
+        /// The base URL of a self-hosted CodSpeed instance
+        #[arg(long)]
+        base_url: Option<String>,
     },
     /// Set the active profile for the current shell session
     Use {
@@ -50,7 +53,8 @@ pub fn run(
             name,
             api_url,
             upload_url,
-        } => set(config_name, &name, api_url, upload_url),
+            base_url,
+        } => set(config_name, &name, api_url, upload_url, base_url),
         ProfileCommands::Use { name } => use_profile(config_name, &name),
     }
 }
@@ -86,6 +90,9 @@ fn show(config_name: Option<&str>, profile_name: Option<&str>) -> Result<()> {
         style("Profile").bold(),
         config.selected_profile_name()
     );
+    if let Some(base_url) = &config.base_url {
+        info!("  base url: {base_url}");
+    }
     info!("  api url: {}", config.api_url);
     info!("  upload url: {}", config.upload_url);
     info!(
@@ -105,6 +112,7 @@ fn set(
     profile_name: &str,
     api_url: Option<String>,
     upload_url: Option<String>,
+    base_url: Option<String>,
 ) -> Result<()> {
     let mut config =
         CodSpeedConfig::load_with_profile(config_name, None, ConfigOverrides::default(), true)?;
@@ -116,6 +124,10 @@ fn set(
     if let Some(upload_url) = upload_url {
         profile.upload_url = Some(upload_url);
     }
+    if let Some(base_url) = base_url {
+        crate::config::parse_base_url(&base_url)?;
+        profile.base_url = Some(base_url);
+    }
 
     config.persist(config_name)?;
     info!("Profile `{profile_name}` saved");
@@ -0,0 +1,280 @@
+use crate::executor::ModuleSymbols;
+use crate::prelude::*;
+use clap::Args;
+use libc::pid_t;
+use runner_shared::metadata::WalltimeMetadata;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+#[derive(Args, Debug)]
+pub struct ResolveArgs {
+    /// The profile folder of the run, containing the walltime metadata and symbols files
+    pub profile_folder: PathBuf,
+
+    /// The pid of the process the address was sampled in
+    pub pid: pid_t,
+
+    /// The runtime address to resolve, in hexadecimal (e.g. `0x55555555a1f0`)
+    #[arg(value_parser = parse_hex_address)]
+    pub address: u64,
+
+    /// Demangle the Rust and C++ symbol names
+    #[arg(long)]
+    pub demangle: bool,
+}
+
+fn parse_hex_address(value: &str) -> Result<u64> {
+    let digits = value
+        .strip_prefix("0x")
+        .or_else(|| value.strip_prefix("0X"))
+        .unwrap_or(value);
+    u64::from_str_radix(digits, 16).with_context(|| format!("Invalid hexadecimal address {value}"))
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub(super) struct ResolvedSymbol {
+    /// The `path_key` of the module containing the symbol
+    pub module_key: String,
+    pub name: String,
+    /// Offset of the address within the symbol
+    pub offset: u64,
+}
+
+/// Resolves runtime addresses to symbols with the symbols files of a profile folder, loading
+/// each of them once.
+pub(super) struct Symbolizer<'a> {
+    profile_folder: &'a Path,
+    metadata: &'a WalltimeMetadata,
+    symbols_by_key: HashMap<String, ModuleSymbols>,
+    demangle: bool,
+}
+
+impl<'a> Symbolizer<'a> {
+    pub fn new(profile_folder: &'a Path, metadata: &'a WalltimeMetadata) -> Self {
+        Self {
+            profile_folder,
+            metadata,
+            symbols_by_key: HashMap::new(),
+            demangle: false,
+        }
+    }
+
+    /// Demangle the names of the resolved symbols.
+    pub fn with_demangling(mut self, demangle: bool) -> Self {
+        self.demangle = demangle;
+        self
+    }
+
+    /// Whether symbols were recorded for `pid`.
+    pub fn has_pid(&self, pid: pid_t) -> bool {
+        self.metadata
+            .mapped_process_module_symbols
+            .contains_key(&pid)
+    }
+
+    /// Resolve a runtime `address` of `pid` to a symbol, applying the load bias recorded for
+    /// the module mapped at this address in the process.
+    ///
+    /// Only the modules whose executable segments contain `address` are tried, since the symbols
+    /// of another module, rebased with its own load bias, may cover it too. The modules whose
+    /// segments were not recorded are tried as a fallback.
+    pub fn resolve(&mut self, pid: pid_t, address: u64) -> Result<Option<ResolvedSymbol>> {
+        let Some(mappings) = self.metadata.mapped_process_module_symbols.get(&pid) else {
+            bail!("No symbols were recorded for pid {pid}");
+        };
+
+        let (mapped_at_address, unknown_ranges): (Vec<_>, Vec<_>) = mappings
+            .iter()
+            .filter(|mapping| {
+                mapping.avma_ranges.is_empty()
+                    || mapping
+                        .avma_ranges
+                        .iter()
+                        .any(|range| range.contains(&address))
+            })
+            .partition(|mapping| !mapping.avma_ranges.is_empty());
+
+        for mapping in mapped_at_address.into_iter().chain(unknown_ranges) {
+            let Some(elf_address) = address.checked_sub(mapping.load_bias) else {
+                continue;
+            };
+            let key = &mapping.perf_map_key;
+            if !self.symbols_by_key.contains_key(key) {
+                let module_symbols = ModuleSymbols::from_keyed_file(self.profile_folder, key)
+                    .with_context(|| format!("Failed to load the symbols of {key}"))?;
+                self.symbols_by_key.insert(key.clone(), module_symbols);
+            }
+            if let Some(symbol) = self.symbols_by_key[key].find(elf_address) {
+                let name = if self.demangle {
+                    symbol.demangled_name().into_owned()
+                } else {
+                    symbol.name.clone()
+                };
+                return Ok(Some(ResolvedSymbol {
+                    module_key: key.clone(),
+                    name,
+                    offset: elf_address - symbol.addr,
+                }));
+            }
+        }
+
+        Ok(None)
+    }
+}
+
+/// Resolve a runtime `address` of `pid` to a symbol.
+fn resolve_address(
+    profile_folder: &Path,
+    metadata: &WalltimeMetadata,
+    pid: pid_t,
+    address: u64,
+    demangle: bool,
+) -> Result<Option<ResolvedSymbol>> {
+    Symbolizer::new(profile_folder, metadata)
+        .with_demangling(demangle)
+        .resolve(pid, address)
+}
+
+pub fn run(args: ResolveArgs) -> Result<()> {
+    let metadata = WalltimeMetadata::load_from(&args.profile_folder)?;
+    let Some(resolved) = resolve_address(
+        &args.profile_folder,
+        &metadata,
+        args.pid,
+        args.address,
+        args.demangle,
+    )?
+    else {
+        bail!(
+            "No symbol found for address {:#x} in pid {}",
+            args.address,
+            args.pid
+        );
+    };
+
+    let module = metadata
+        .path_key_to_path
+        .get(&resolved.module_key)
+        .map(|path| path.display().to_string())
+        .unwrap_or(resolved.module_key);
+    info!(
+        "{:#x} => {}+{:#x} ({module})",
+        args.address, resolved.name, resolved.offset
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use runner_shared::metadata::WALLTIME_METADATA_CURRENT_VERSION;
+    use runner_shared::module_symbols::{MappedProcessModuleSymbols, SYMBOLS_MAP_SUFFIX};
+    use std::collections::HashMap;
+
+    const LOAD_BIAS: u64 = 0x5555_5555_4000;
+    const LIBC_LOAD_BIAS: u64 = LOAD_BIAS - 0x10_0000;
+
+    fn write_profile_folder(folder: &Path) -> WalltimeMetadata {
+        std::fs::write(
+            folder.join(format!("0-bench.{SYMBOLS_MAP_SUFFIX}")),
+            "2000 40 bench::fib\n1000 10 main\n3000 20 _ZN5bench3fibEi\n",
+        )
+        .unwrap();
+        // libc is mapped below the benchmark: once rebased, its data symbol covers `bench::fib`
+        std::fs::write(
+            folder.join(format!("1-libc.so.6.{SYMBOLS_MAP_SUFFIX}")),
+            "1000 100 malloc\n102000 40 __libc_data\n",
+        )
+        .unwrap();
+
+        let metadata = WalltimeMetadata {
+            version: WALLTIME_METADATA_CURRENT_VERSION,
+            integration: ("codspeed-rust".to_string(), "1.0.0".to_string()),
+            mapped_process_module_symbols: HashMap::from([(
+                42,
+                vec![
+                    MappedProcessModuleSymbols {
+                        perf_map_key: "1-libc.so.6".to_string(),
+                        load_bias: LIBC_LOAD_BIAS,
+                        avma_ranges: vec![LIBC_LOAD_BIAS + 0x1000..LIBC_LOAD_BIAS + 0x2000],
+                    },
+                    MappedProcessModuleSymbols {
+                        perf_map_key: "0-bench".to_string(),
+                        load_bias: LOAD_BIAS,
+                        avma_ranges: vec![LOAD_BIAS + 0x1000..LOAD_BIAS + 0x4000],
+                    },
+                ],
+            )]),
+            ..Default::default()
+        };
+        metadata.save_to(folder).unwrap();
+        metadata
+    }
+
+    #[test]
+    fn test_resolve_address_in_fixture() {
+        let folder = tempfile::tempdir().unwrap();
+        let metadata = write_profile_folder(folder.path());
+
+        let resolved =
+            resolve_address(folder.path(), &metadata, 42, LOAD_BIAS + 0x2024, false).unwrap();
+        assert_eq!(
+            resolved,
+            Some(ResolvedSymbol {
+                module_key: "0-bench".to_string(),
+                name: "bench::fib".to_string(),
+                offset: 0x24,
+            })
+        );
+
+        // Between two symbols
+        let resolved =
+            resolve_address(folder.path(), &metadata, 42, LOAD_BIAS + 0x1800, false).unwrap();
+        assert_eq!(resolved, None);
+
+        let resolved =
+            resolve_address(folder.path(), &metadata, 42, LIBC_LOAD_BIAS + 0x1010, false).unwrap();
+        assert_eq!(resolved.unwrap().name, "malloc");
+
+        assert!(resolve_address(folder.path(), &metadata, 7, LOAD_BIAS, false).is_err());
+    }
+
+    #[test]
+    fn test_resolve_address_without_recorded_ranges() {
+        let folder = tempfile::tempdir().unwrap();
+        let mut metadata = write_profile_folder(folder.path());
+        // Metadata of an older runner: the first module covering the address is used
+        for mapping in metadata.mapped_process_module_symbols.get_mut(&42).unwrap() {
+            mapping.avma_ranges.clear();
+        }
+
+        let resolved =
+            resolve_address(folder.path(), &metadata, 42, LOAD_BIAS + 0x2024, false).unwrap();
+        assert_eq!(resolved.unwrap().name, "__libc_data");
+    }
+
+    #[test]
+    fn test_resolve_demangled_address() {
+        let folder = tempfile::tempdir().unwrap();
+        let metadata = write_profile_folder(folder.path());
+
+        let resolve = |demangle| {
+            resolve_address(folder.path(), &metadata, 42, LOAD_BIAS + 0x3004, demangle)
+                .unwrap()
+                .unwrap()
+                .name
+        };
+        assert_eq!(resolve(false), "_ZN5bench3fibEi");
+        assert_eq!(resolve(true), "bench::fib(int)");
+    }
+
+    #[test]
+    fn test_parse_hex_address() {
+        assert_eq!(
+            parse_hex_address("0x55555555a1f0").unwrap(),
+            0x5555_5555_a1f0
+        );
+        assert_eq!(parse_hex_address("a1f0").unwrap(), 0xa1f0);
+        assert!(parse_hex_address("0xzz").is_err());
+    }
+}
@@ -1,5 +1,6 @@
 use crate::binary_pins::PinnedBinary;
-use crate::{prelude::*, request_client::REQUEST_CLIENT};
+use crate::prelude::*;
+use crate::request_client::{REQUEST_CLIENT, request_error};
 use std::path::Path;
 
 use url::Url;
@@ -10,7 +11,7 @@ async fn download_file(url: &Url, path: &Path) -> Result<()> {
         .get(url.clone())
         .send()
         .await
-        .map_err(|e| anyhow!("Failed to download file: {e}"))?;
+        .map_err(|e| request_error(e).context("Failed to download file"))?;
     if !response.status().is_success() {
         bail!("Failed to download file: {}", response.status());
     }
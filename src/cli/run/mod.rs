@@ -1,5 +1,7 @@
 use super::ExecAndRunSharedArgs;
+use super::config::{ConfigFormat, EffectiveConfig};
 use crate::api_client::CodSpeedAPIClient;
+use crate::config::CodSpeedConfig;
 use crate::executor;
 use crate::executor::config::{OrchestratorConfig, RepositoryOverride};
 use crate::instruments::Instruments;
@@ -34,6 +36,19 @@ pub struct RunArgs {
     #[arg(long, hide = true)]
     pub message_format: Option<MessageFormat>,
 
+    /// Print the configuration resolved from the flags, the environment variables, the shell
+    /// session and the CodSpeed profile, then exit without running the benchmarks.
+    /// Use `--print-config=json` for a JSON output.
+    #[arg(
+        long,
+        value_enum,
+        value_name = "FORMAT",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "text"
+    )]
+    pub print_config: Option<ConfigFormat>,
+
     /// The bench command to run
     pub command: Vec<String>,
 }
@@ -62,13 +77,34 @@ impl RunArgs {
                 simulation_tool: None,
                 walltime_profiler: None,
                 profile_folder: None,
+                clear_profile_folder: false,
                 skip_upload: false,
                 skip_run: false,
                 skip_setup: false,
                 allow_empty: false,
                 go_runner_version: None,
                 show_full_output: false,
+                cpu: None,
+                strip_paths: false,
+                use_cache: false,
+                benchmark_timeout: None,
+                upload_concurrency: 2,
+                clean_tmp_maps: false,
+                inline_frames: false,
+                parallel_modes: false,
+                compress_artifacts: false,
+                ignore_objects: vec![],
+                valgrind_args: vec![],
+                keep_profile_folder: false,
+                skip_unsupported_modes: false,
+                capture_output: false,
+                integration_mode: None,
                 base: None,
+                fail_on_regression: false,
+                results_sort: None,
+                results_top: None,
+                results_format: Default::default(),
+                results_output: None,
                 profiler_run_args: ProfilerRunArgs {
                     enable_profiler: false,
                     enable_perf: None,
@@ -80,10 +116,13 @@ impl RunArgs {
                     experimental_fair_sched: false,
                     cycle_estimation: false,
                 },
+                project_modes: vec![],
+                command_line_options: vec![],
             },
             instruments: vec![],
             mongo_uri_env_name: None,
             message_format: None,
+            print_config: None,
             command: vec![],
         }
     }
@@ -119,16 +158,37 @@ fn build_orchestrator_config(
         walltime_profiler: args.shared.walltime_profiler,
         simulation_tool: args.shared.simulation_tool.unwrap_or_default(),
         profile_folder: args.shared.profile_folder,
+        clear_profile_folder: args.shared.clear_profile_folder,
         skip_upload: args.shared.skip_upload,
         skip_run: args.shared.skip_run,
         skip_setup: args.shared.skip_setup,
         allow_empty: args.shared.allow_empty,
         go_runner_version: args.shared.go_runner_version,
         show_full_output: args.shared.show_full_output,
+        cpu: args.shared.cpu,
+        strip_paths: args.shared.strip_paths,
+        use_cache: args.shared.use_cache,
+        benchmark_timeout: args
+            .shared
+            .benchmark_timeout
+            .map(std::time::Duration::from_secs),
+        upload_concurrency: args.shared.upload_concurrency,
+        clean_tmp_maps: args.shared.clean_tmp_maps,
+        raw: false,
+        inline_frames: args.shared.inline_frames,
+        parallel_modes: args.shared.parallel_modes,
+        compress_artifacts: args.shared.compress_artifacts,
+        ignored_objects: args.shared.ignore_objects.clone(),
+        valgrind_extra_args: args.shared.valgrind_args.clone(),
+        keep_profile_folder: args.shared.keep_profile_folder,
+        skip_unsupported_modes: args.shared.skip_unsupported_modes,
+        capture_output: args.shared.capture_output,
+        integration_mode_override: args.shared.integration_mode,
         poll_results_options,
         extra_env: HashMap::new(),
         fair_sched: args.shared.experimental.experimental_fair_sched,
         cycle_estimation: args.shared.experimental.cycle_estimation,
+        command_line_options: args.shared.command_line_options,
     })
 }
 
@@ -146,14 +206,25 @@ enum RunTarget<'a> {
 }
 
 pub async fn run(
-    args: RunArgs,
+    mut args: RunArgs,
     api_client: &mut CodSpeedAPIClient,
+    codspeed_config: &CodSpeedConfig,
     discovered_config: Option<&DiscoveredProjectConfig>,
     setup_cache_dir: Option<&Path>,
 ) -> Result<()> {
-    let output_json = args.message_format == Some(MessageFormat::Json);
     let project_config = discovered_config.map(|d| &d.config);
+    args.shared
+        .merge_with_project_options(project_config.and_then(|c| c.options.as_ref()));
+    let output_json = args.message_format == Some(MessageFormat::Json);
+    let print_config = args.print_config;
+    let print_effective_config = |config: &OrchestratorConfig, format: ConfigFormat| {
+        EffectiveConfig::new(codspeed_config, api_client.token())
+            .with_run(config)
+            .print(format)
+    };
     let base_run_id = args.shared.base.clone();
+    let fail_on_regression = args.shared.fail_on_regression;
+    let results_view = args.shared.results_view();
 
     let run_target = if args.command.is_empty() {
         // No command provided - check for targets in project config
@@ -182,7 +253,8 @@ pub async fn run(
             // SingleCommand: working_directory comes from --working-directory CLI flag only.
             // Config file's working-directory is NOT used.
             let command = args.command.join(" ");
-            let poll_opts = PollResultsOptions::new(output_json, base_run_id);
+            let poll_opts =
+                PollResultsOptions::new(output_json, base_run_id, fail_on_regression, results_view);
             let config = build_orchestrator_config(
                 args,
                 vec![executor::BenchmarkTarget::Entrypoint {
@@ -191,6 +263,9 @@ pub async fn run(
                 }],
                 poll_opts,
             )?;
+            if let Some(format) = print_config {
+                return print_effective_config(&config, format);
+            }
 
             let orchestrator = executor::Orchestrator::new(config, api_client).await?;
 
@@ -244,9 +319,12 @@ pub async fn run(
             let mut config = build_orchestrator_config(
                 args,
                 benchmark_targets,
-                PollResultsOptions::new(false, base_run_id),
+                PollResultsOptions::new(false, base_run_id, fail_on_regression, results_view),
             )?;
             config.working_directory = resolved_working_directory;
+            if let Some(format) = print_config {
+                return print_effective_config(&config, format);
+            }
             super::exec::execute_config(config, api_client, setup_cache_dir).await?;
         }
     }
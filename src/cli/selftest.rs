@@ -0,0 +1,188 @@
+use super::exec::{self, ExecArgs};
+use super::status::{check_mark, cross_mark};
+use crate::api_client::CodSpeedAPIClient;
+use crate::executor::PERF_PIPEDATA_FILE_NAME;
+use crate::prelude::*;
+use clap::{Args, Parser};
+use runner_shared::metadata::WalltimeMetadata;
+use runner_shared::module_symbols::SYMBOLS_MAP_SUFFIX;
+use std::path::{Path, PathBuf};
+
+/// The built-in benchmark: a small loop, profiled without any CodSpeed integration.
+const BENCHMARK_COMMAND: [&str; 3] = [
+    "sh",
+    "-c",
+    "i=0; while [ $i -lt 100000 ]; do i=$((i+1)); done",
+];
+
+#[derive(Args, Debug)]
+pub struct SelftestArgs {
+    /// Keep the profile folder of the self-test at this path, e.g. to attach it to a support
+    /// request. A temporary folder is used otherwise
+    #[arg(long)]
+    pub profile_folder: Option<PathBuf>,
+}
+
+/// `codspeed exec`, as parsed from the command line built for the self-test.
+#[derive(Parser)]
+struct SelftestExec {
+    #[command(flatten)]
+    exec: ExecArgs,
+}
+
+/// A check of the profile folder produced by the self-test.
+struct Check {
+    name: &'static str,
+    result: Result<()>,
+}
+
+fn check_perf_data(profile_folder: &Path) -> Result<()> {
+    let path = profile_folder.join(PERF_PIPEDATA_FILE_NAME);
+    let size = std::fs::metadata(&path)
+        .with_context(|| format!("{} is missing", path.display()))?
+        .len();
+    ensure!(size > 0, "{} is empty", path.display());
+    Ok(())
+}
+
+fn check_symbols(profile_folder: &Path) -> Result<()> {
+    let has_symbols = std::fs::read_dir(profile_folder)?
+        .filter_map(|entry| entry.ok())
+        .any(|entry| {
+            entry
+                .file_name()
+                .to_string_lossy()
+                .ends_with(SYMBOLS_MAP_SUFFIX)
+        });
+    ensure!(has_symbols, "no `.{SYMBOLS_MAP_SUFFIX}` file was saved");
+    Ok(())
+}
+
+/// Check that the walltime pipeline saved the perf data, the symbols of the profiled modules and
+/// a parseable metadata file in `profile_folder`.
+fn check_profile_folder(profile_folder: &Path) -> Vec<Check> {
+    vec![
+        Check {
+            name: "perf data recorded",
+            result: check_perf_data(profile_folder),
+        },
+        Check {
+            name: "symbols saved",
+            result: check_symbols(profile_folder),
+        },
+        Check {
+            name: "walltime metadata written",
+            result: WalltimeMetadata::load_from(profile_folder).map(|_| ()),
+        },
+    ]
+}
+
+pub async fn run(
+    args: SelftestArgs,
+    api_client: &mut CodSpeedAPIClient,
+    setup_cache_dir: Option<&Path>,
+) -> Result<()> {
+    let temp_dir = tempfile::tempdir()?;
+    let profile_folder = args
+        .profile_folder
+        .unwrap_or_else(|| temp_dir.path().join("profile"));
+
+    let exec_args = SelftestExec::try_parse_from(
+        [
+            "selftest",
+            "--mode",
+            "walltime",
+            "--raw",
+            "--profile-folder",
+        ]
+        .into_iter()
+        .map(String::from)
+        .chain([profile_folder.to_string_lossy().into_owned(), "--".into()])
+        .chain(BENCHMARK_COMMAND.map(String::from)),
+    )
+    .context("Failed to build the self-test command")?
+    .exec;
+    exec::run(exec_args, api_client, None, setup_cache_dir)
+        .await
+        .context("The self-test failed to profile the built-in benchmark")?;
+
+    info!("\nSelf-test of the walltime pipeline:");
+    let mut failed = 0;
+    for check in &check_profile_folder(&profile_folder) {
+        match &check.result {
+            Ok(()) => info!("  {} {}", check_mark(), check.name),
+            Err(error) => {
+                failed += 1;
+                info!("  {} {}: {error:#}", cross_mark(), check.name);
+            }
+        }
+    }
+    if failed > 0 {
+        bail!("The self-test failed, {failed} check(s) did not pass");
+    }
+    info!("The self-test passed");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn failed_checks(profile_folder: &Path) -> Vec<&'static str> {
+        check_profile_folder(profile_folder)
+            .into_iter()
+            .filter(|check| check.result.is_err())
+            .map(|check| check.name)
+            .collect()
+    }
+
+    #[test]
+    fn test_checks_of_the_profile_folder() {
+        let profile_folder = tempfile::tempdir().unwrap();
+        let path = profile_folder.path();
+        assert_eq!(
+            failed_checks(path),
+            vec![
+                "perf data recorded",
+                "symbols saved",
+                "walltime metadata written"
+            ]
+        );
+
+        std::fs::write(path.join(PERF_PIPEDATA_FILE_NAME), b"PERFILE2").unwrap();
+        std::fs::write(
+            path.join(format!("0-sh.{SYMBOLS_MAP_SUFFIX}")),
+            "1000 10 main\n",
+        )
+        .unwrap();
+        WalltimeMetadata::default().save_to(path).unwrap();
+        assert_eq!(failed_checks(path), Vec::<&str>::new());
+    }
+}
+
+/// Runs the whole pipeline, so it needs perf like the walltime executor tests
+#[cfg(all(test, target_os = "linux"))]
+#[test_with::env(GITHUB_ACTIONS)]
+mod walltime {
+    use super::*;
+    use crate::executor::tests::walltime::acquire_walltime_lock;
+
+    #[test_log::test(tokio::test)]
+    async fn test_selftest_passes() {
+        let _permit = acquire_walltime_lock().await;
+        let mut api_client = CodSpeedAPIClient::create_test_client();
+        // Outside of the GitHub Actions run environment, which needs its event payload
+        temp_env::async_with_vars(&[("GITHUB_ACTIONS", None::<&str>)], async {
+            run(
+                SelftestArgs {
+                    profile_folder: None,
+                },
+                &mut api_client,
+                None,
+            )
+            .await
+            .unwrap();
+        })
+        .await;
+    }
+}
@@ -1,6 +1,7 @@
+use crate::executor::orchestrator::resolve_modes;
 use crate::executor::{
-    Executor, ExecutorSupport, PrivilegeStatus, ToolInstallStatus, get_all_executors,
-    get_executor_from_mode,
+    Executor, ExecutorSupport, PrivilegeStatus, SimulationTool, ToolInstallStatus,
+    get_all_executors, get_executor_from_mode,
 };
 use crate::prelude::*;
 use crate::runner_mode::RunnerMode;
@@ -42,21 +43,25 @@ pub async fn run(args: SetupArgs, setup_cache_dir: Option<&Path>) -> Result<()>
 
 /// Resolve the executors to operate on from the requested modes.
 ///
-/// An empty list of modes means "every executor".
-fn get_executors_from_modes(modes: &[RunnerMode]) -> Vec<Box<dyn Executor>> {
+/// An empty list of modes means "every executor", `auto` is resolved to the mode it picks on
+/// this machine.
+fn get_executors_from_modes(
+    modes: &[RunnerMode],
+    system_info: &SystemInfo,
+) -> Result<Vec<Box<dyn Executor>>> {
     if modes.is_empty() {
-        get_all_executors()
-    } else {
-        modes
-            .iter()
-            .map(|mode| get_executor_from_mode(mode, None))
-            .collect()
+        return Ok(get_all_executors());
     }
+
+    resolve_modes(modes, None, system_info)?
+        .iter()
+        .map(|mode| get_executor_from_mode(mode, None, SimulationTool::default()))
+        .collect()
 }
 
 async fn setup(modes: &[RunnerMode], setup_cache_dir: Option<&Path>) -> Result<()> {
     let system_info = SystemInfo::new()?;
-    let executors = get_executors_from_modes(modes);
+    let executors = get_executors_from_modes(modes, &system_info)?;
     start_group!("Setting up the environment");
     for executor in executors {
         setup_executor(executor.as_ref(), &system_info, setup_cache_dir).await?;
@@ -105,7 +110,7 @@ async fn setup_executor(
 pub fn status(modes: &[RunnerMode]) -> Result<()> {
     let system_info = SystemInfo::new()?;
     info!("{}", style("Tools").bold());
-    for executor in get_executors_from_modes(modes) {
+    for executor in get_executors_from_modes(modes, &system_info)? {
         // Don't probe for tooling that can't be used on this OS anyway.
         if executor.support_level(&system_info) == ExecutorSupport::Unsupported {
             continue;
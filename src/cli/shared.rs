@@ -2,10 +2,16 @@ use super::experimental::ExperimentalArgs;
 use crate::VERSION;
 use crate::executor::config::{SimulationTool, WalltimeProfiler};
 use crate::prelude::*;
+use crate::project_config::ProjectOptions;
 use crate::run_environment::interfaces::RepositoryProvider;
 use crate::runner_mode::{RunnerMode, load_shell_session_mode};
+use crate::upload::{ResultsFormat, ResultsSort, ResultsView};
 use clap::Args;
 use clap::ValueEnum;
+use clap::parser::{ArgMatches, ValueSource};
+use runner_shared::fifo::IntegrationMode;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
 
 pub(crate) fn show_banner() {
@@ -78,6 +84,11 @@ pub struct ExecAndRunSharedArgs {
     #[arg(long)]
     pub profile_folder: Option<PathBuf>,
 
+    /// Delete the content of the `--profile-folder` before the run. Without it, a non-empty
+    /// profile folder is refused, so that a folder given by mistake is never wiped
+    #[arg(long, default_value = "false", requires = "profile_folder")]
+    pub clear_profile_folder: bool,
+
     /// Only for debugging purposes, skips the upload of the results
     #[arg(
         long,
@@ -109,45 +120,237 @@ pub struct ExecAndRunSharedArgs {
     #[arg(long, default_value = "false")]
     pub show_full_output: bool,
 
+    /// Pin the benchmark processes to this CPU, in the walltime mode. The
+    /// scheduler no longer migrates the benchmark across cores, which makes the measurements more
+    /// stable, at the cost of some realism: the benchmark runs on a single core
+    #[arg(long, value_name = "N", env = "CODSPEED_CPU")]
+    pub cpu: Option<usize>,
+
+    /// Replace the absolute paths of the profiled binaries with their file names in the uploaded
+    /// walltime metadata, so that they don't reveal user names or the directory layout of the
+    /// machine.
+    ///
+    /// Only the metadata is scrubbed: the other files of the profile (perf maps, symbols and unwind
+    /// data) may still contain absolute paths.
+    #[arg(long, default_value = "false", env = "CODSPEED_STRIP_PATHS")]
+    pub strip_paths: bool,
+
+    /// Reuse the local results of a previous run when the benchmarked executables are unchanged.
+    /// Only the exec targets of native executables are cached, in the setup cache directory
+    #[arg(long, default_value = "false", env = "CODSPEED_USE_CACHE")]
+    pub use_cache: bool,
+
+    /// Kill the benchmark command and all its processes if it runs for longer than this many
+    /// seconds, failing the run. No timeout by default
+    #[arg(long, value_name = "SECS", env = "CODSPEED_BENCHMARK_TIMEOUT")]
+    pub benchmark_timeout: Option<u64>,
+
+    /// Number of modes whose results are uploaded concurrently, at least 1
+    #[arg(long, default_value_t = 2, env = "CODSPEED_UPLOAD_CONCURRENCY")]
+    pub upload_concurrency: usize,
+
+    /// Remove the perf maps and JIT dumps (`/tmp/perf-<pid>.map`, `/tmp/jit-<pid>.dump`) of the
+    /// benchmark processes once they are harvested into the profile folder. Only the files
+    /// written by this run and owned by the current user are removed
+    #[arg(long, default_value = "false", env = "CODSPEED_CLEAN_TMP_MAPS")]
+    pub clean_tmp_maps: bool,
+
+    /// Record the chains of inlined functions from the DWARF debug info in the walltime profile,
+    /// so that the frames of inlined functions are shown. Makes the profile artifacts larger
+    #[arg(long, default_value = "false", env = "CODSPEED_INLINE_FRAMES")]
+    pub inline_frames: bool,
+
+    /// Run the non-conflicting modes of each command concurrently (e.g. `simulation` and
+    /// `memory`), to cut the duration of the run. The `walltime` mode always runs alone, since
+    /// the load of another mode would skew its measurements
+    #[arg(long, default_value = "false", env = "CODSPEED_PARALLEL_MODES")]
+    pub parallel_modes: bool,
+
+    /// Always compress the uploaded profile archive with zstd, which reduces the upload size of
+    /// runs with many symbols and unwind data files
+    #[arg(long, default_value = "false", env = "CODSPEED_COMPRESS_ARTIFACTS")]
+    pub compress_artifacts: bool,
+
+    /// Glob matched against the full path of the modules loaded by the benchmark, whose frames
+    /// are ignored by the perf profiler in the walltime mode (e.g. `*/libasan.so*`). Can be repeated
+    #[arg(long = "ignore-object", value_name = "GLOB")]
+    pub ignore_objects: Vec<String>,
+
+    /// Extra argument passed to valgrind in the simulation mode, e.g. `--valgrind-arg=--fair-sched=yes`.
+    /// Can be repeated. Arguments controlling the tool, its output or the traced processes are rejected
+    #[arg(long = "valgrind-arg", value_name = "ARG", allow_hyphen_values = true)]
+    pub valgrind_args: Vec<String>,
+
+    /// Print the path of every profile folder at the end of the run, even when it fails,
+    /// so their content can be inspected or uploaded later
+    #[arg(long, default_value = "false", env = "CODSPEED_KEEP_PROFILE_FOLDER")]
+    pub keep_profile_folder: bool,
+
+    /// Skip the requested modes that can't run on this machine (e.g. missing tooling)
+    /// instead of failing, as long as at least one mode remains
+    #[arg(long, default_value = "false", env = "CODSPEED_SKIP_UNSUPPORTED_MODES")]
+    pub skip_unsupported_modes: bool,
+
+    /// Write the benchmark's own stdout and stderr to files in the profile folder
+    /// (included in the upload), separately from the runner logs
+    #[arg(long, default_value = "false", env = "CODSPEED_CAPTURE_OUTPUT")]
+    pub capture_output: bool,
+
+    /// Advanced, for debugging only: force the integration mode reported to the
+    /// benchmark integration in walltime mode (e.g. `simulation` to disable the
+    /// integration's perf-specific handling).
+    #[arg(
+        long,
+        env = "CODSPEED_INTEGRATION_MODE",
+        hide = true,
+        value_parser = parse_integration_mode
+    )]
+    pub integration_mode: Option<IntegrationMode>,
+
     /// Compare the results against this base run ID
     #[arg(long)]
     pub base: Option<String>,
 
+    /// Exit with code 6 when a benchmark regresses compared to `--base`, beyond the allowed
+    /// regression of the project. Requires `--base`, and fails if the results can't be compared
+    /// against it. Has no effect with `--skip-upload`
+    #[arg(long)]
+    pub fail_on_regression: bool,
+
+    /// Order of the benchmarks in the results table. Defaults to the order of the report
+    #[arg(long, value_enum)]
+    pub results_sort: Option<ResultsSort>,
+
+    /// Only show the first N benchmarks of the results table
+    #[arg(long, value_name = "N")]
+    pub results_top: Option<usize>,
+
+    /// Format of the results table, e.g. to paste it in a pull request or a spreadsheet
+    #[arg(long, value_enum, default_value_t)]
+    pub results_format: ResultsFormat,
+
+    /// Write the results table to this file, e.g. a Markdown job summary. Without it, the CSV and
+    /// Markdown tables are printed on the standard output, apart from the logs
+    #[arg(long, value_name = "PATH")]
+    pub results_output: Option<PathBuf>,
+
     #[command(flatten)]
     pub profiler_run_args: ProfilerRunArgs,
 
     #[command(flatten)]
     pub experimental: ExperimentalArgs,
+
+    /// Default modes of the project config file, used when neither `--mode` nor the shell
+    /// session sets them
+    #[arg(skip)]
+    pub project_modes: Vec<RunnerMode>,
+
+    /// The mode-specific options given explicitly on the command line, see
+    /// [`ExecAndRunSharedArgs::record_command_line_options`]
+    #[arg(skip)]
+    pub command_line_options: Vec<&'static str>,
 }
 
+/// The options that only apply to some modes, as `(clap argument id, flag)`.
+const MODE_SPECIFIC_OPTIONS: [(&str, &str); 9] = [
+    ("perf_unwinding_mode", "--perf-unwinding-mode"),
+    ("walltime_profiler", "--walltime-profiler"),
+    ("ignore_objects", "--ignore-object"),
+    ("inline_frames", "--inline-frames"),
+    ("cpu", "--cpu"),
+    ("simulation_tool", "--simulation-tool"),
+    ("experimental_fair_sched", "--experimental-fair-sched"),
+    ("cycle_estimation", "--cycle-estimation"),
+    ("valgrind_args", "--valgrind-arg"),
+];
+
 impl ExecAndRunSharedArgs {
-    /// Resolves the runner modes from CLI argument, shell session, or returns an error.
+    pub fn results_view(&self) -> ResultsView {
+        ResultsView {
+            sort: self.results_sort,
+            top: self.results_top,
+            format: self.results_format,
+            output: self.results_output.clone(),
+        }
+    }
+
+    /// Remember which mode-specific options were given on the command line, as opposed to the
+    /// environment. Only those are rejected when their mode isn't selected, since the
+    /// environment is often shared by runs of different modes (e.g. in CI).
+    pub fn record_command_line_options(&mut self, matches: &ArgMatches) {
+        self.command_line_options = MODE_SPECIFIC_OPTIONS
+            .iter()
+            .filter(|(id, _)| matches.value_source(id) == Some(ValueSource::CommandLine))
+            .map(|(_, flag)| *flag)
+            .collect();
+    }
+
+    /// Fill the options not set on the command line or through the environment with the
+    /// defaults of the project config file.
+    ///
+    /// The walltime-specific defaults are only used when the walltime mode may run.
+    pub fn merge_with_project_options(&mut self, options: Option<&ProjectOptions>) {
+        let Some(options) = options else {
+            return;
+        };
+
+        if let Some(modes) = &options.modes {
+            self.project_modes = modes.clone();
+        }
+
+        // The perf defaults of the project only apply to the walltime mode, the other modes
+        // would ignore them
+        let may_run_walltime = self.resolve_modes().is_ok_and(|modes| {
+            modes
+                .iter()
+                .any(|mode| matches!(mode, RunnerMode::Walltime | RunnerMode::Auto))
+        });
+        if !may_run_walltime {
+            return;
+        }
+        let perf_unwinding_mode = &mut self.profiler_run_args.perf.perf_unwinding_mode;
+        if perf_unwinding_mode.is_none() {
+            *perf_unwinding_mode = options.perf_unwinding_mode;
+        }
+        if self.ignore_objects.is_empty() {
+            self.ignore_objects = options.ignore_objects.clone().unwrap_or_default();
+        }
+    }
+
+    /// Resolves the runner modes from CLI argument, shell session, project config, or returns
+    /// an error.
     ///
     /// Priority:
     /// 1. CLI argument (--mode or -m)
     /// 2. Shell session mode (set via `codspeed use <mode>`)
-    /// 3. Error if neither is available
+    /// 3. `modes` option of the project config file
+    /// 4. Error if none is available
     pub fn resolve_modes(&self) -> Result<Vec<RunnerMode>> {
         if !self.mode.is_empty() {
             return Ok(self.mode.clone());
         }
 
         let modes = load_shell_session_mode()?;
+        if !modes.is_empty() {
+            return Ok(modes);
+        }
 
-        if modes.is_empty() {
-            return Err(anyhow!(
-                "No runner mode specified. Use --mode <mode> or set the mode for this shell session with `codspeed use <mode>`."
-            ));
+        if !self.project_modes.is_empty() {
+            return Ok(self.project_modes.clone());
         }
 
-        Ok(modes)
+        Err(anyhow!(
+            "No runner mode specified. Use --mode <mode> or set the mode for this shell session with `codspeed use <mode>`."
+        ))
     }
 }
 
-#[derive(Debug, Copy, Clone, PartialEq, ValueEnum, Default)]
+#[derive(Debug, Copy, Clone, PartialEq, ValueEnum, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
 pub enum UnwindingMode {
     /// Use the frame pointer for unwinding. Requires the binary to be compiled with frame pointers enabled.
     #[clap(name = "fp")]
+    #[serde(rename = "fp")]
     FramePointer,
 
     /// Use DWARF unwinding. This does not require any special compilation flags and is enabled by default.
@@ -195,3 +398,14 @@ impl ProfilerRunArgs {
 fn parse_version(s: &str) -> Result<semver::Version, String> {
     semver::Version::parse(s).map_err(|e| format!("Invalid semantic version: {e}"))
 }
+
+fn parse_integration_mode(s: &str) -> Result<IntegrationMode, String> {
+    match s.to_ascii_lowercase().as_str() {
+        "walltime" => Ok(IntegrationMode::Walltime),
+        "simulation" => Ok(IntegrationMode::Simulation),
+        "analysis" => Ok(IntegrationMode::Analysis),
+        _ => Err(format!(
+            "Invalid integration mode: {s} (expected one of: walltime, simulation, analysis)"
+        )),
+    }
+}
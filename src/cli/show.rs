@@ -1,5 +1,7 @@
+use crate::executor::orchestrator::resolve_auto_mode;
 use crate::prelude::*;
-use crate::runner_mode::load_shell_session_mode;
+use crate::runner_mode::{RunnerMode, load_shell_session_mode};
+use crate::system::SystemInfo;
 
 pub fn run() -> Result<()> {
     let modes = load_shell_session_mode()?;
@@ -9,8 +11,17 @@ pub fn run() -> Result<()> {
     } else {
         let modes_str = modes
             .iter()
-            .map(|m| format!("{m:?}"))
-            .collect::<Vec<_>>()
+            .map(|m| match m {
+                RunnerMode::Auto => {
+                    let system_info = SystemInfo::new()?;
+                    let resolved_mode = resolve_auto_mode(None, &system_info)?;
+                    Ok(format!(
+                        "{m:?} (resolves to {resolved_mode:?} on this machine)"
+                    ))
+                }
+                _ => Ok(format!("{m:?}")),
+            })
+            .collect::<Result<Vec<_>>>()?
             .join(", ");
         info!("{modes_str}");
     }
@@ -3,6 +3,7 @@ use std::{collections::BTreeMap, env, fs, path::PathBuf};
 use crate::prelude::*;
 use crate::shell_session_store::{self, SessionKind};
 use serde::{Deserialize, Serialize};
+use url::Url;
 
 pub const DEFAULT_API_URL: &str = "https://gql.codspeed.io/";
 pub const DEFAULT_UPLOAD_URL: &str = "https://api.codspeed.io/upload";
@@ -42,6 +43,10 @@ pub struct ProfileConfig {
     pub api_url: Option<String>,
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub upload_url: Option<String>,
+    /// Base URL of a self-hosted CodSpeed instance, from which the API and
+    /// upload URLs are derived when they are not set explicitly
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub base_url: Option<String>,
 }
 
 /// Raw shape read from disk. Captures every YAML field we have ever
@@ -90,6 +95,7 @@ pub struct ConfigOverrides<'a> {
     pub oauth_token: Option<&'a str>,
     pub api_url: Option<&'a str>,
     pub upload_url: Option<&'a str>,
+    pub base_url: Option<&'a str>,
 }
 
 /// Configuration as seen at runtime: the persisted state plus the
@@ -105,6 +111,8 @@ pub struct CodSpeedConfig {
     pub auth: AuthConfig,
     pub api_url: String,
     pub upload_url: String,
+    /// Base URL of the self-hosted instance, if any
+    pub base_url: Option<Url>,
     selected_profile: String,
 }
 
@@ -120,6 +128,30 @@ fn default_upload_url() -> String {
     DEFAULT_UPLOAD_URL.to_owned()
 }
 
+/// Parse the base URL of a self-hosted CodSpeed instance.
+///
+/// Only https is accepted, except on loopback hosts, which are used for local
+/// testing.
+pub fn parse_base_url(base_url: &str) -> Result<Url> {
+    let mut url =
+        Url::parse(base_url).with_context(|| format!("Invalid CodSpeed base URL: {base_url}"))?;
+    let is_loopback = matches!(url.host_str(), Some("localhost" | "127.0.0.1" | "[::1]"));
+    ensure!(
+        url.scheme() == "https" || (url.scheme() == "http" && is_loopback),
+        "Invalid CodSpeed base URL: {base_url}, expected an https URL"
+    );
+    ensure!(
+        url.query().is_none() && url.fragment().is_none(),
+        "Invalid CodSpeed base URL: {base_url}, expected no query or fragment"
+    );
+
+    // Make sure the endpoints are joined under the base path rather than replacing its last segment
+    if !url.path().ends_with('/') {
+        url.set_path(&format!("{}/", url.path()));
+    }
+    Ok(url)
+}
+
 /// Get the path to the configuration file, following the XDG Base Directory Specification
 /// at https://specifications.freedesktop.org/basedir-spec/basedir-spec-latest.html
 ///
@@ -150,6 +182,7 @@ impl CodSpeedConfig {
             auth: AuthConfig::default(),
             api_url: default_api_url(),
             upload_url: default_upload_url(),
+            base_url: None,
             selected_profile: default_profile_name(),
         }
     }
@@ -319,16 +352,30 @@ impl CodSpeedConfig {
                 .map(ToOwned::to_owned)
                 .or(profile.auth.token),
         };
-        self.api_url = overrides
-            .api_url
+        self.base_url = overrides
+            .base_url
             .map(ToOwned::to_owned)
-            .or(profile.api_url)
-            .unwrap_or_else(default_api_url);
-        self.upload_url = overrides
+            .or(profile.base_url)
+            .map(|base_url| parse_base_url(&base_url))
+            .transpose()?;
+        let endpoint_from_base_url = |path: &str| {
+            self.base_url
+                .as_ref()
+                .map(|base_url| base_url.join(path).map(String::from))
+                .transpose()
+        };
+        self.api_url = match overrides.api_url.map(ToOwned::to_owned).or(profile.api_url) {
+            Some(api_url) => api_url,
+            None => endpoint_from_base_url("graphql")?.unwrap_or_else(default_api_url),
+        };
+        self.upload_url = match overrides
             .upload_url
             .map(ToOwned::to_owned)
             .or(profile.upload_url)
-            .unwrap_or_else(default_upload_url);
+        {
+            Some(upload_url) => upload_url,
+            None => endpoint_from_base_url("upload")?.unwrap_or_else(default_upload_url),
+        };
 
         Ok(())
     }
@@ -445,6 +492,7 @@ profiles:
                     oauth_token: Some("override-token"),
                     api_url: Some("https://gql.override.example/"),
                     upload_url: None,
+                    base_url: None,
                 },
                 false,
             )
@@ -456,6 +504,53 @@ profiles:
         assert_eq!(config.upload_url, "https://api.staging.example/upload");
     }
 
+    #[test]
+    fn derives_endpoints_from_base_url() {
+        let mut config = CodSpeedConfig::from_persisted(PersistedConfig::default());
+        config.profile_mut("self-hosted").base_url =
+            Some("https://codspeed.corp.example/api".into());
+
+        config
+            .resolve_selected_profile(Some("self-hosted"), ConfigOverrides::default(), false)
+            .unwrap();
+
+        assert_eq!(config.api_url, "https://codspeed.corp.example/api/graphql");
+        assert_eq!(
+            config.upload_url,
+            "https://codspeed.corp.example/api/upload"
+        );
+    }
+
+    #[test]
+    fn builds_api_client_against_mock_base_url() {
+        let mut config = CodSpeedConfig::from_persisted(PersistedConfig::default());
+        config
+            .resolve_selected_profile(
+                None,
+                ConfigOverrides {
+                    base_url: Some("http://127.0.0.1:8123"),
+                    // An explicit endpoint still wins over the base URL
+                    upload_url: Some("http://127.0.0.1:9000/upload"),
+                    ..Default::default()
+                },
+                true,
+            )
+            .unwrap();
+
+        assert_eq!(config.api_url, "http://127.0.0.1:8123/graphql");
+        assert_eq!(config.upload_url, "http://127.0.0.1:9000/upload");
+        let api_client = crate::api_client::CodSpeedAPIClient::new(None, config.api_url.clone());
+        assert!(api_client.token().is_none());
+    }
+
+    #[test]
+    fn rejects_invalid_base_urls() {
+        assert!(parse_base_url("http://codspeed.corp.example").is_err());
+        assert!(parse_base_url("codspeed.corp.example").is_err());
+        assert!(parse_base_url("https://codspeed.corp.example/?profile=1").is_err());
+        assert!(parse_base_url("https://codspeed.corp.example").is_ok());
+    }
+
     #[test]
     fn load_rewrites_legacy_file_in_canonical_form() {
         let tmp = TempDir::new().unwrap();
@@ -5,9 +5,11 @@ use crate::run_environment::RepositoryProvider;
 use crate::runner_mode::RunnerMode;
 use crate::upload::poll_results::PollResultsOptions;
 use clap::ValueEnum;
+use runner_shared::fifo::IntegrationMode;
 use semver::Version;
 use std::collections::HashMap;
 use std::path::PathBuf;
+use std::time::Duration;
 use url::Url;
 
 /// A benchmark target from project configuration.
@@ -76,6 +78,9 @@ pub struct OrchestratorConfig {
     pub simulation_tool: SimulationTool,
 
     pub profile_folder: Option<PathBuf>,
+    /// If true, delete the content of the profile folder before the run instead of refusing a
+    /// non-empty one
+    pub clear_profile_folder: bool,
     pub skip_upload: bool,
     pub skip_run: bool,
     pub skip_setup: bool,
@@ -93,6 +98,44 @@ pub struct OrchestratorConfig {
     pub fair_sched: bool,
     /// Enable valgrind's --cycle-estimation option.
     pub cycle_estimation: bool,
+    /// Integration mode reported over the FIFO in walltime mode, for debugging
+    pub integration_mode_override: Option<IntegrationMode>,
+    /// If true, write the benchmark's stdout/stderr to files in the profile folder
+    pub capture_output: bool,
+    /// If true, drop the modes that can't run on this machine instead of failing
+    pub skip_unsupported_modes: bool,
+    /// If true, print the profile folder of every run part at the end of the run
+    pub keep_profile_folder: bool,
+    /// Extra arguments appended to the valgrind command line
+    pub valgrind_extra_args: Vec<String>,
+    /// Globs of the modules ignored by the perf profiler, on top of the built-in ones
+    pub ignored_objects: Vec<String>,
+    /// If true, compress the uploaded profile archive with zstd
+    pub compress_artifacts: bool,
+    /// If true, run the modes of each command concurrently
+    pub parallel_modes: bool,
+    /// If true, record the chains of inlined frames in the perf debug info
+    pub inline_frames: bool,
+    /// If true, profile the whole execution of the command without a CodSpeed integration
+    pub raw: bool,
+    /// If true, remove the perf maps and JIT dumps of `/tmp` once harvested
+    pub clean_tmp_maps: bool,
+    /// Number of run parts whose results are uploaded concurrently
+    pub upload_concurrency: usize,
+    /// Maximum duration of a benchmark command, after which its process tree is killed
+    pub benchmark_timeout: Option<Duration>,
+    /// Reuse the results cached for the run parts whose inputs are unchanged
+    pub use_cache: bool,
+    /// If true, replace the absolute paths of the binaries with their file names in the walltime
+    /// metadata
+    pub strip_paths: bool,
+    /// CPU the walltime benchmark processes are pinned to, validated against the CPUs the runner
+    /// may run on
+    pub cpu: Option<usize>,
+    /// The mode-specific options given explicitly on the command line, rejected when their mode
+    /// isn't selected. The other ones come from the environment or the project config and are
+    /// ignored instead, see [`OrchestratorConfig::ignore_inapplicable_options`]
+    pub command_line_options: Vec<&'static str>,
 }
 
 /// Per-execution configuration passed to executors.
@@ -128,6 +171,30 @@ pub struct ExecutorConfig {
     pub fair_sched: bool,
     /// Enable valgrind's --cycle-estimation option.
     pub cycle_estimation: bool,
+    /// Integration mode reported over the FIFO in walltime mode, for debugging
+    pub integration_mode_override: Option<IntegrationMode>,
+    /// If true, write the benchmark's stdout/stderr to files in the profile folder
+    pub capture_output: bool,
+    /// Extra arguments appended to the valgrind command line
+    pub valgrind_extra_args: Vec<String>,
+    /// Globs of the modules ignored by the perf profiler, on top of the built-in ones
+    pub ignored_objects: Vec<String>,
+    /// If true, record the chains of inlined frames in the perf debug info
+    pub inline_frames: bool,
+    /// If true, profile the whole execution of the command without a CodSpeed integration
+    pub raw: bool,
+    /// If true, remove the perf maps and JIT dumps of `/tmp` once harvested
+    pub clean_tmp_maps: bool,
+    /// Where the perf profiler caches the symbols of the system libraries across runs, set
+    /// when a setup cache dir is provided
+    pub symbols_cache_dir: Option<PathBuf>,
+    /// Maximum duration of the benchmark command, after which its process tree is killed
+    pub benchmark_timeout: Option<Duration>,
+    /// If true, replace the absolute paths of the binaries with their file names in the walltime
+    /// metadata
+    pub strip_paths: bool,
+    /// CPU the walltime benchmark processes are pinned to
+    pub cpu: Option<usize>,
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -174,6 +241,222 @@ impl OrchestratorConfig {
         (invocation_count * self.modes.len()) as u32
     }
 
+    fn has_walltime(&self) -> bool {
+        self.modes.contains(&RunnerMode::Walltime)
+    }
+
+    #[allow(deprecated)]
+    fn has_simulation(&self) -> bool {
+        self.modes
+            .iter()
+            .any(|mode| matches!(mode, RunnerMode::Simulation | RunnerMode::Instrumentation))
+    }
+
+    /// Reset the mode-specific options that don't apply to any of the selected modes and weren't
+    /// given on the command line, returning the message to log for each of them.
+    ///
+    /// These come from the environment or the project config, which are often shared by runs of
+    /// different modes (e.g. a CI-wide `CODSPEED_PERF_UNWINDING_MODE`), so they must not fail a
+    /// run whose modes don't use them. Must be called before [`OrchestratorConfig::validate`].
+    pub fn ignore_inapplicable_options(&mut self) -> Vec<(log::Level, String)> {
+        let mut messages = vec![];
+        let command_line_options = self.command_line_options.clone();
+        let mut should_ignore = |flag: &'static str, is_set: bool, mode: RunnerMode| {
+            let ignore = is_set && !command_line_options.contains(&flag);
+            if ignore {
+                // `CODSPEED_INLINE_FRAMES` is usually set once for all the runs of a CI, only
+                // the walltime ones use it
+                let level = if flag == "--inline-frames" {
+                    log::Level::Debug
+                } else {
+                    log::Level::Warn
+                };
+                messages.push((level, format!(
+                    "Ignoring the {flag} option set in the environment or the project config, it only applies to the {mode} mode, which is not selected"
+                )));
+            }
+            ignore
+        };
+
+        if !self.has_walltime() {
+            if should_ignore(
+                "--perf-unwinding-mode",
+                self.perf_unwinding_mode.is_some(),
+                RunnerMode::Walltime,
+            ) {
+                self.perf_unwinding_mode = None;
+            }
+            if should_ignore(
+                "--walltime-profiler",
+                self.walltime_profiler.is_some(),
+                RunnerMode::Walltime,
+            ) {
+                self.walltime_profiler = None;
+            }
+            if should_ignore(
+                "--ignore-object",
+                !self.ignored_objects.is_empty(),
+                RunnerMode::Walltime,
+            ) {
+                self.ignored_objects.clear();
+            }
+            if should_ignore("--inline-frames", self.inline_frames, RunnerMode::Walltime) {
+                self.inline_frames = false;
+            }
+            if should_ignore("--cpu", self.cpu.is_some(), RunnerMode::Walltime) {
+                self.cpu = None;
+            }
+        }
+
+        if !self.has_simulation() {
+            if should_ignore(
+                "--simulation-tool",
+                self.simulation_tool != SimulationTool::default(),
+                RunnerMode::Simulation,
+            ) {
+                self.simulation_tool = SimulationTool::default();
+            }
+            if should_ignore(
+                "--experimental-fair-sched",
+                self.fair_sched,
+                RunnerMode::Simulation,
+            ) {
+                self.fair_sched = false;
+            }
+            if should_ignore(
+                "--cycle-estimation",
+                self.cycle_estimation,
+                RunnerMode::Simulation,
+            ) {
+                self.cycle_estimation = false;
+            }
+            if should_ignore(
+                "--valgrind-arg",
+                !self.valgrind_extra_args.is_empty(),
+                RunnerMode::Simulation,
+            ) {
+                self.valgrind_extra_args.clear();
+            }
+        }
+
+        messages
+    }
+
+    /// Reject option combinations that can't apply to any of the selected modes.
+    ///
+    /// Options are accepted as long as at least one selected mode uses them, so that
+    /// running several modes at once with mode-specific options keeps working.
+    pub fn validate(&self) -> Result<()> {
+        let has_simulation = self.has_simulation();
+        let has_walltime = self.has_walltime();
+
+        if !has_walltime {
+            ensure!(
+                self.perf_unwinding_mode.is_none(),
+                "--perf-unwinding-mode only applies to the walltime mode, which is not selected"
+            );
+            ensure!(
+                self.walltime_profiler.is_none(),
+                "--walltime-profiler only applies to the walltime mode, which is not selected"
+            );
+            ensure!(
+                self.ignored_objects.is_empty(),
+                "--ignore-object only applies to the walltime mode, which is not selected"
+            );
+            ensure!(
+                !self.inline_frames,
+                "--inline-frames only applies to the walltime mode, which is not selected"
+            );
+            ensure!(
+                self.cpu.is_none(),
+                "--cpu only applies to the walltime mode, which is not selected"
+            );
+        }
+        for object_glob in &self.ignored_objects {
+            glob::Pattern::new(object_glob)
+                .with_context(|| format!("Invalid --ignore-object glob `{object_glob}`"))?;
+        }
+        ensure!(
+            !(self.perf_unwinding_mode.is_some()
+                && self.walltime_profiler == Some(WalltimeProfiler::Samply)),
+            "--perf-unwinding-mode can't be used with the samply walltime profiler"
+        );
+        ensure!(
+            !(self.inline_frames && self.walltime_profiler == Some(WalltimeProfiler::Samply)),
+            "--inline-frames can't be used with the samply walltime profiler"
+        );
+
+        if !has_simulation {
+            ensure!(
+                self.simulation_tool == SimulationTool::default(),
+                "--simulation-tool only applies to the simulation mode, which is not selected"
+            );
+            ensure!(
+                !self.fair_sched,
+                "--experimental-fair-sched only applies to the simulation mode, which is not selected"
+            );
+            ensure!(
+                !self.cycle_estimation,
+                "--cycle-estimation only applies to the simulation mode, which is not selected"
+            );
+            ensure!(
+                self.valgrind_extra_args.is_empty(),
+                "--valgrind-arg only applies to the simulation mode, which is not selected"
+            );
+        }
+        super::valgrind::validate_valgrind_extra_args(&self.valgrind_extra_args)?;
+
+        if self.raw {
+            ensure!(
+                self.modes == [RunnerMode::Walltime],
+                "--raw only supports the walltime mode"
+            );
+            ensure!(
+                cfg!(target_os = "linux")
+                    && self.walltime_profiler != Some(WalltimeProfiler::Samply),
+                "--raw is only supported with the perf walltime profiler"
+            );
+            ensure!(
+                self.enable_profiler,
+                "--raw requires the profiler to be enabled"
+            );
+        }
+
+        if self.parallel_modes {
+            for (a, b) in self.modes.iter().tuple_combinations() {
+                ensure!(
+                    !a.conflicts_with(b),
+                    "The {a} and {b} modes can't run in parallel, remove --parallel-modes or one of the modes"
+                );
+            }
+        }
+
+        ensure!(
+            self.upload_concurrency > 0,
+            "--upload-concurrency must be at least 1"
+        );
+        ensure!(
+            self.benchmark_timeout != Some(Duration::ZERO),
+            "--benchmark-timeout must be at least 1 second"
+        );
+        // Without upload, `--fail-on-regression` is a no-op reported by the orchestrator
+        ensure!(
+            !self.poll_results_options.fail_on_regression
+                || self.skip_upload
+                || self.poll_results_options.base_run_id.is_some(),
+            "--fail-on-regression needs --base, the run to compare the results against"
+        );
+        super::shared::fifo::integration_from_env()?;
+
+        #[cfg(target_os = "linux")]
+        ensure!(
+            !(self.instruments.is_mongodb_enabled() && self.modes.contains(&RunnerMode::Memory)),
+            "The mongodb instrument is not supported in the memory mode"
+        );
+
+        Ok(())
+    }
+
     /// Produce a per-execution [`ExecutorConfig`] for the given command and mode.
     ///
     /// `enable_introspection` controls whether language-level wrappers (Node.js, Go)
@@ -198,6 +481,17 @@ impl OrchestratorConfig {
             enable_introspection,
             fair_sched: self.fair_sched,
             cycle_estimation: self.cycle_estimation,
+            integration_mode_override: self.integration_mode_override,
+            capture_output: self.capture_output,
+            valgrind_extra_args: self.valgrind_extra_args.clone(),
+            ignored_objects: self.ignored_objects.clone(),
+            inline_frames: self.inline_frames,
+            raw: self.raw,
+            clean_tmp_maps: self.clean_tmp_maps,
+            symbols_cache_dir: None,
+            benchmark_timeout: self.benchmark_timeout,
+            strip_paths: self.strip_paths,
+            cpu: self.cpu,
         }
     }
 }
@@ -221,16 +515,34 @@ impl OrchestratorConfig {
             enable_profiler: false,
             simulation_tool: SimulationTool::default(),
             profile_folder: None,
+            clear_profile_folder: false,
             skip_upload: false,
             skip_run: false,
             skip_setup: false,
             allow_empty: false,
             go_runner_version: None,
             show_full_output: false,
-            poll_results_options: PollResultsOptions::new(false, None),
+            poll_results_options: PollResultsOptions::new(false, None, false, Default::default()),
             extra_env: HashMap::new(),
             fair_sched: false,
             cycle_estimation: false,
+            integration_mode_override: None,
+            capture_output: false,
+            skip_unsupported_modes: false,
+            keep_profile_folder: false,
+            valgrind_extra_args: vec![],
+            ignored_objects: vec![],
+            compress_artifacts: false,
+            parallel_modes: false,
+            inline_frames: false,
+            raw: false,
+            clean_tmp_maps: false,
+            upload_concurrency: 2,
+            benchmark_timeout: None,
+            use_cache: false,
+            strip_paths: false,
+            cpu: None,
+            command_line_options: vec![],
         }
     }
 }
@@ -350,6 +662,256 @@ mod tests {
         assert_eq!(config.expected_run_parts_count(), 4);
     }
 
+    #[test]
+    fn test_validate_accepts_default_config() {
+        OrchestratorConfig::test().validate().unwrap();
+    }
+
+    #[test]
+    fn test_validate_rejects_perf_options_without_walltime() {
+        let config = OrchestratorConfig {
+            perf_unwinding_mode: Some(UnwindingMode::FramePointer),
+            ..OrchestratorConfig::test()
+        };
+        let err = config.validate().unwrap_err();
+        assert!(err.to_string().contains("--perf-unwinding-mode"));
+
+        let config = OrchestratorConfig {
+            walltime_profiler: Some(WalltimeProfiler::Perf),
+            ..OrchestratorConfig::test()
+        };
+        let err = config.validate().unwrap_err();
+        assert!(err.to_string().contains("--walltime-profiler"));
+
+        let config = OrchestratorConfig {
+            inline_frames: true,
+            ..OrchestratorConfig::test()
+        };
+        let err = config.validate().unwrap_err();
+        assert!(err.to_string().contains("--inline-frames"));
+
+        let config = OrchestratorConfig {
+            cpu: Some(0),
+            ..OrchestratorConfig::test()
+        };
+        let err = config.validate().unwrap_err();
+        assert!(err.to_string().contains("--cpu"));
+
+        // Accepted once walltime is one of the selected modes
+        let config = OrchestratorConfig {
+            modes: vec![RunnerMode::Simulation, RunnerMode::Walltime],
+            perf_unwinding_mode: Some(UnwindingMode::FramePointer),
+            walltime_profiler: Some(WalltimeProfiler::Perf),
+            inline_frames: true,
+            cpu: Some(0),
+            ..OrchestratorConfig::test()
+        };
+        config.validate().unwrap();
+    }
+
+    #[test]
+    fn test_ignore_inapplicable_options_keeps_command_line_options() {
+        let mut config = OrchestratorConfig {
+            perf_unwinding_mode: Some(UnwindingMode::FramePointer),
+            inline_frames: true,
+            ..OrchestratorConfig::test()
+        };
+        let messages = config.ignore_inapplicable_options();
+        assert_eq!(messages.len(), 2);
+        assert_eq!(messages[0].0, log::Level::Warn);
+        assert!(messages[0].1.contains("--perf-unwinding-mode"));
+        assert_eq!(messages[1].0, log::Level::Debug);
+        assert!(messages[1].1.contains("--inline-frames"));
+        assert_eq!(config.perf_unwinding_mode, None);
+        assert!(!config.inline_frames);
+        config.validate().unwrap();
+
+        let mut config = OrchestratorConfig {
+            perf_unwinding_mode: Some(UnwindingMode::FramePointer),
+            command_line_options: vec!["--perf-unwinding-mode"],
+            ..OrchestratorConfig::test()
+        };
+        assert!(config.ignore_inapplicable_options().is_empty());
+        let err = config.validate().unwrap_err();
+        assert!(err.to_string().contains("--perf-unwinding-mode"));
+
+        // Applicable options are kept, whatever their origin
+        let mut config = OrchestratorConfig {
+            modes: vec![RunnerMode::Walltime],
+            perf_unwinding_mode: Some(UnwindingMode::FramePointer),
+            fair_sched: true,
+            ..OrchestratorConfig::test()
+        };
+        let messages = config.ignore_inapplicable_options();
+        assert_eq!(messages.len(), 1);
+        assert!(messages[0].1.contains("--experimental-fair-sched"));
+        assert_eq!(
+            config.perf_unwinding_mode,
+            Some(UnwindingMode::FramePointer)
+        );
+    }
+
+    #[test]
+    fn test_validate_rejects_perf_unwinding_with_samply() {
+        let config = OrchestratorConfig {
+            modes: vec![RunnerMode::Walltime],
+            perf_unwinding_mode: Some(UnwindingMode::FramePointer),
+            walltime_profiler: Some(WalltimeProfiler::Samply),
+            ..OrchestratorConfig::test()
+        };
+        let err = config.validate().unwrap_err();
+        assert!(err.to_string().contains("samply"));
+    }
+
+    #[test]
+    fn test_validate_rejects_valgrind_options_without_simulation() {
+        let walltime = || OrchestratorConfig {
+            modes: vec![RunnerMode::Walltime],
+            ..OrchestratorConfig::test()
+        };
+
+        let config = OrchestratorConfig {
+            simulation_tool: SimulationTool::Tracegrind,
+            ..walltime()
+        };
+        let err = config.validate().unwrap_err();
+        assert!(err.to_string().contains("--simulation-tool"));
+
+        let config = OrchestratorConfig {
+            fair_sched: true,
+            ..walltime()
+        };
+        let err = config.validate().unwrap_err();
+        assert!(err.to_string().contains("--experimental-fair-sched"));
+
+        let config = OrchestratorConfig {
+            cycle_estimation: true,
+            ..walltime()
+        };
+        let err = config.validate().unwrap_err();
+        assert!(err.to_string().contains("--cycle-estimation"));
+    }
+
+    #[test]
+    fn test_validate_rejects_reserved_valgrind_args() {
+        let config = OrchestratorConfig {
+            valgrind_extra_args: vec!["--tool=memcheck".into()],
+            ..OrchestratorConfig::test()
+        };
+        assert!(config.validate().is_err());
+
+        let config = OrchestratorConfig {
+            valgrind_extra_args: vec!["--fair-sched=try".into()],
+            ..OrchestratorConfig::test()
+        };
+        config.validate().unwrap();
+    }
+
+    #[test]
+    fn test_validate_rejects_conflicting_parallel_modes() {
+        let config = OrchestratorConfig {
+            modes: vec![RunnerMode::Simulation, RunnerMode::Walltime],
+            parallel_modes: true,
+            ..OrchestratorConfig::test()
+        };
+        let err = config.validate().unwrap_err();
+        assert!(err.to_string().contains("can't run in parallel"));
+
+        let config = OrchestratorConfig {
+            parallel_modes: false,
+            ..config
+        };
+        config.validate().unwrap();
+    }
+
+    #[test]
+    fn test_validate_rejects_zero_upload_concurrency() {
+        let config = OrchestratorConfig {
+            upload_concurrency: 0,
+            ..OrchestratorConfig::test()
+        };
+        let err = config.validate().unwrap_err();
+        assert!(err.to_string().contains("--upload-concurrency"));
+    }
+
+    #[test]
+    fn test_validate_rejects_zero_benchmark_timeout() {
+        let config = OrchestratorConfig {
+            benchmark_timeout: Some(Duration::ZERO),
+            ..OrchestratorConfig::test()
+        };
+        let err = config.validate().unwrap_err();
+        assert!(err.to_string().contains("--benchmark-timeout"));
+    }
+
+    #[test]
+    fn test_validate_fail_on_regression() {
+        let mut config = OrchestratorConfig::test();
+        config.poll_results_options.fail_on_regression = true;
+        let err = config.validate().unwrap_err();
+        assert!(err.to_string().contains("--base"), "{err}");
+
+        config.poll_results_options.base_run_id = Some("base-run-id".to_string());
+        config.validate().unwrap();
+
+        // A no-op without upload
+        let mut config = OrchestratorConfig {
+            skip_upload: true,
+            ..OrchestratorConfig::test()
+        };
+        config.poll_results_options.fail_on_regression = true;
+        config.validate().unwrap();
+    }
+
+    #[test]
+    fn test_validate_rejects_an_invalid_integration_env() {
+        use crate::executor::shared::fifo::INTEGRATION_ENV;
+
+        temp_env::with_var(INTEGRATION_ENV, Some("my-harness"), || {
+            let err = OrchestratorConfig::test().validate().unwrap_err();
+            assert!(err.to_string().contains(INTEGRATION_ENV), "{err}");
+        });
+        temp_env::with_var(INTEGRATION_ENV, Some("my-harness:1.2.0"), || {
+            OrchestratorConfig::test().validate().unwrap();
+        });
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_validate_parallel_modes_never_include_walltime() {
+        let config = OrchestratorConfig {
+            modes: vec![RunnerMode::Walltime, RunnerMode::Memory],
+            parallel_modes: true,
+            ..OrchestratorConfig::test()
+        };
+        assert!(config.validate().is_err());
+
+        let config = OrchestratorConfig {
+            modes: vec![RunnerMode::Simulation, RunnerMode::Memory],
+            ..config
+        };
+        config.validate().unwrap();
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_validate_rejects_mongodb_with_memory() {
+        let config = OrchestratorConfig {
+            modes: vec![RunnerMode::Memory],
+            instruments: Instruments::test(),
+            ..OrchestratorConfig::test()
+        };
+        let err = config.validate().unwrap_err();
+        assert!(err.to_string().contains("mongodb"));
+
+        let config = OrchestratorConfig {
+            modes: vec![RunnerMode::Memory],
+            instruments: Instruments { mongodb: None },
+            ..OrchestratorConfig::test()
+        };
+        config.validate().unwrap();
+    }
+
     #[test]
     fn test_repository_override_from_arg() {
         let override_result =
@@ -1,5 +1,6 @@
 use super::ExecutorConfig;
 use std::path::PathBuf;
+use std::time::SystemTime;
 
 /// Per-mode execution context.
 ///
@@ -9,6 +10,8 @@ pub struct ExecutionContext {
     pub config: ExecutorConfig,
     /// Directory path where profiling data and results are stored
     pub profile_folder: PathBuf,
+    /// When the context was created, before the benchmarks of this run part started
+    pub created_at: SystemTime,
 }
 
 impl ExecutionContext {
@@ -16,6 +19,7 @@ impl ExecutionContext {
         ExecutionContext {
             config,
             profile_folder,
+            created_at: SystemTime::now(),
         }
     }
 }
@@ -81,6 +81,31 @@ impl CommandBuilder {
         self.cwd.as_deref()
     }
 
+    pub fn get_envs(&self) -> impl Iterator<Item = (&OsStr, &OsStr)> {
+        self.envs
+            .iter()
+            .map(|(k, v)| (k.as_os_str(), v.as_os_str()))
+    }
+
+    /// Wrap with `env KEY=VALUE...` so that the environment variables of the builder are passed
+    /// on the command line, for wrappers that may not forward all of them (e.g. sudo).
+    pub fn wrap_with_env_assignments(&mut self) -> &mut Self {
+        if self.envs.is_empty() {
+            return self;
+        }
+        let assignments: Vec<OsString> = self
+            .envs
+            .iter()
+            .map(|(key, value)| {
+                let mut assignment = key.clone();
+                assignment.push("=");
+                assignment.push(value);
+                assignment
+            })
+            .collect();
+        self.wrap("env", assignments)
+    }
+
     pub fn wrap<S, I, T>(&mut self, wrapper: S, wrapper_args: I) -> &mut Self
     where
         S: AsRef<OsStr>,
@@ -211,4 +236,104 @@ mod tests {
 
         assert_eq!(builder.as_command_line(), "env 'FOO=bar' ls -la");
     }
+
+    #[test]
+    fn test_env_assignments_wrap() {
+        let mut builder = CommandBuilder::new("ls");
+        builder
+            .arg("-la")
+            .envs([("FOO", "bar"), ("BAZ", "qux quux")])
+            .wrap_with_env_assignments()
+            .wrap("sudo", ["-n"]);
+
+        assert_eq!(
+            builder.as_command_line(),
+            "sudo -n env 'BAZ=qux quux' 'FOO=bar' ls -la"
+        );
+        // Still set on the spawned process too
+        assert_eq!(builder.get_envs().count(), 2);
+    }
+
+    #[test]
+    fn test_env_assignments_wrap_without_envs() {
+        let mut builder = CommandBuilder::new("ls");
+        builder.wrap_with_env_assignments();
+        assert_eq!(builder.as_command_line(), "ls");
+    }
+
+    #[test]
+    fn test_envs_survive_perf_and_sudo_wraps() {
+        let mut builder = CommandBuilder::new("bench");
+        builder.env("CODSPEED_BENCH_FILTER", "fib");
+
+        let mut perf = CommandBuilder::new("perf");
+        perf.args(["record", "--"]);
+        perf.env("PERF_WRAPPER", "1");
+        builder.wrap_with(perf);
+
+        // Same rewrap as the perf profiler, running the command through a shell pipe
+        let mut wrapped = CommandBuilder::new("bash");
+        wrapped.args(["-c", &format!("{} | cat", builder.as_command_line())]);
+        wrapped.envs(builder.get_envs());
+        wrapped
+            .wrap_with_env_assignments()
+            .wrap("sudo", ["--non-interactive", "--preserve-env"]);
+
+        let command = wrapped.build();
+        let envs: Vec<_> = command.get_envs().collect();
+        assert_eq!(
+            envs,
+            vec![
+                (OsStr::new("CODSPEED_BENCH_FILTER"), Some(OsStr::new("fib"))),
+                (OsStr::new("PERF_WRAPPER"), Some(OsStr::new("1"))),
+            ]
+        );
+        let args: Vec<_> = command.get_args().collect();
+        assert_eq!(
+            args,
+            vec![
+                "--non-interactive",
+                "--preserve-env",
+                "env",
+                "CODSPEED_BENCH_FILTER=fib",
+                "PERF_WRAPPER=1",
+                "bash",
+                "-c",
+                "perf record -- bench | cat",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_command_line_round_trips_through_the_shell() {
+        let args = [
+            "with space",
+            "it's quoted",
+            "$HOME",
+            "`echo injected`",
+            "a;b && c",
+            "",
+        ];
+        let mut builder = CommandBuilder::new("printf");
+        builder.arg("%s\\n").args(args);
+
+        let output = Command::new("bash")
+            .args(["-c", &builder.as_command_line()])
+            .output()
+            .unwrap();
+
+        assert!(output.status.success());
+        let printed = String::from_utf8(output.stdout).unwrap();
+        assert_eq!(printed.lines().collect::<Vec<_>>(), args);
+    }
+
+    #[test]
+    fn test_command_line_quotes_the_program() {
+        let mut builder = CommandBuilder::new("/opt/my benches/bench");
+        builder.arg("--filter=it's");
+        assert_eq!(
+            builder.as_command_line(),
+            "'/opt/my benches/bench' '--filter=it'\\''s'"
+        );
+    }
 }
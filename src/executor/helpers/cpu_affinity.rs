@@ -0,0 +1,101 @@
+//! Pinning of the walltime benchmarks to a CPU, with `--cpu`.
+//!
+//! The benchmark command runs under `taskset`, inside the profiler wrapper, so that every process
+//! of the benchmark inherits the affinity, set with `sched_setaffinity` before the command starts,
+//! while the profiler keeps running on the other CPUs.
+use crate::executor::helpers::command::CommandBuilder;
+use crate::prelude::*;
+
+/// The CPUs the runner may run on, which `taskset` can pin its children to. They can be fewer
+/// than the online ones, e.g. in a container restricted to a cpuset.
+#[cfg(target_os = "linux")]
+fn allowed_cpus() -> Result<Vec<usize>> {
+    use nix::sched::{CpuSet, sched_getaffinity};
+    use nix::unistd::Pid;
+
+    let cpu_set = sched_getaffinity(Pid::from_raw(0))?;
+    Ok((0..CpuSet::count())
+        .filter(|&cpu| cpu_set.is_set(cpu).unwrap_or(false))
+        .collect())
+}
+
+/// Check that the runner may run on `cpu`.
+#[cfg(target_os = "linux")]
+pub fn validate_cpu(cpu: usize) -> Result<()> {
+    let allowed_cpus = allowed_cpus().context("Failed to read the CPU affinity of the runner")?;
+    ensure!(
+        allowed_cpus.contains(&cpu),
+        "CPU {cpu} is not available to the runner, the available CPUs are {}",
+        allowed_cpus.iter().join(", ")
+    );
+    Ok(())
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn validate_cpu(_cpu: usize) -> Result<()> {
+    bail!("Pinning the benchmarks to a CPU is only supported on Linux")
+}
+
+/// Run the command with its affinity set to `cpu`.
+pub fn wrap_with_cpu_affinity(mut cmd_builder: CommandBuilder, cpu: usize) -> CommandBuilder {
+    debug!("Pinning the benchmark to CPU {cpu}");
+    cmd_builder.wrap("taskset", ["--cpu-list".to_string(), cpu.to_string()]);
+    cmd_builder
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_validate_cpu() {
+        let allowed_cpus = allowed_cpus().unwrap();
+        assert!(validate_cpu(allowed_cpus[0]).is_ok());
+
+        let unavailable_cpu = nix::sched::CpuSet::count();
+        assert_eq!(
+            validate_cpu(unavailable_cpu).unwrap_err().to_string(),
+            format!(
+                "CPU {unavailable_cpu} is not available to the runner, the available CPUs are {}",
+                allowed_cpus.iter().join(", ")
+            )
+        );
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    #[test]
+    fn test_validate_cpu() {
+        assert!(validate_cpu(0).is_err());
+    }
+
+    #[test]
+    fn test_the_affinity_is_set_to_the_requested_cpu() {
+        let mut cmd_builder = CommandBuilder::new("bash");
+        cmd_builder.args(["bench.sh"]);
+
+        let cmd_builder = wrap_with_cpu_affinity(cmd_builder, 3);
+        assert_eq!(
+            cmd_builder.as_command_line(),
+            "taskset --cpu-list 3 bash bench.sh"
+        );
+    }
+
+    /// `taskset` sets the affinity of the command, inherited by its children
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_the_command_runs_on_the_requested_cpu() {
+        let mut cmd_builder = CommandBuilder::new("sh");
+        cmd_builder.args(["-c", "grep Cpus_allowed_list /proc/self/status"]);
+
+        let cpu = allowed_cpus().unwrap()[0];
+        let output = wrap_with_cpu_affinity(cmd_builder, cpu)
+            .build()
+            .output()
+            .unwrap();
+        assert_eq!(
+            String::from_utf8_lossy(&output.stdout).trim(),
+            format!("Cpus_allowed_list:\t{cpu}")
+        );
+    }
+}
@@ -1,5 +1,6 @@
 use crate::executor::ExecutorConfig;
 use crate::executor::helpers::{introspected_golang, introspected_nodejs};
+use crate::local_logger::LogFilter;
 use crate::prelude::*;
 use crate::runner_mode::RunnerMode;
 use std::{collections::HashMap, env::consts::ARCH, path::Path};
@@ -8,7 +9,7 @@ pub fn get_base_injected_env(
     mode: RunnerMode,
     profile_folder: &Path,
     config: &ExecutorConfig,
-) -> HashMap<String, String> {
+) -> Result<HashMap<String, String>> {
     let runner_mode_internal_env_value = match mode {
         // While the runner now deprecates the usage of instrumentation with a message, we
         // internally still use instrumentation temporarily to give time to users to upgrade their
@@ -20,6 +21,7 @@ pub fn get_base_injected_env(
         RunnerMode::Walltime => "walltime",
         #[cfg(target_os = "linux")]
         RunnerMode::Memory => "memory",
+        RunnerMode::Auto => bail!("The auto mode must be resolved before running the benchmarks"),
     };
     let mut env = HashMap::from([
         ("PYTHONHASHSEED".into(), "0".into()),
@@ -66,7 +68,7 @@ pub fn get_base_injected_env(
 
     env.extend(config.extra_env.clone());
 
-    env
+    Ok(env)
 }
 
 /// Set the env variable to not warn users about Go's perf unwinding mode when running Go benchmarks
@@ -102,13 +104,6 @@ pub fn build_path_env(enable_introspection: bool) -> Result<String> {
 }
 
 pub fn is_codspeed_debug_enabled() -> bool {
-    std::env::var("CODSPEED_LOG")
-        .ok()
-        .and_then(|log_level| {
-            log_level
-                .parse::<log::LevelFilter>()
-                .map(|level| level >= log::LevelFilter::Debug)
-                .ok()
-        })
-        .unwrap_or_default()
+    let spec = std::env::var("CODSPEED_LOG").unwrap_or_default();
+    LogFilter::parse(&spec).default >= log::LevelFilter::Debug
 }
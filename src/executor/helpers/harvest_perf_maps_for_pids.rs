@@ -1,12 +1,16 @@
 use crate::prelude::*;
 use std::collections::HashSet;
+use std::os::unix::fs::MetadataExt;
 use std::path::{Path, PathBuf};
+use std::time::SystemTime;
 use tokio::fs;
 
+/// Copy the `/tmp/perf-<pid>.map` of the `pids` to the profile folder, returning the paths of the
+/// harvested files.
 pub async fn harvest_perf_maps_for_pids(
     profile_folder: &Path,
     pids: &HashSet<libc::pid_t>,
-) -> Result<()> {
+) -> Result<Vec<PathBuf>> {
     let perf_maps = pids
         .iter()
         .map(|pid| format!("perf-{pid}.map"))
@@ -20,6 +24,7 @@ pub async fn harvest_perf_maps_for_pids(
         .collect::<Vec<_>>();
     debug!("Found {} perf maps", perf_maps.len());
 
+    let mut harvested_paths = Vec::with_capacity(perf_maps.len());
     for (src_path, dst_path) in perf_maps {
         fs::copy(&src_path, &dst_path).await.map_err(|e| {
             anyhow!(
@@ -29,7 +34,73 @@ pub async fn harvest_perf_maps_for_pids(
                 e
             )
         })?;
+        harvested_paths.push(src_path);
     }
 
-    Ok(())
+    Ok(harvested_paths)
+}
+
+/// Remove the harvested perf maps and JIT dumps, so that they don't pile up in `/tmp` across
+/// runs.
+///
+/// A PID can be reused by another run on the same machine, so only the files modified since
+/// `since` and owned by the current user are removed, the others are left untouched.
+pub fn remove_harvested_tmp_files(paths: &[PathBuf], since: SystemTime) {
+    let current_uid = nix::unistd::Uid::effective().as_raw();
+    let mut removed_paths = vec![];
+    for path in paths {
+        let Ok(metadata) = std::fs::metadata(path) else {
+            continue;
+        };
+        let is_from_this_run = metadata.uid() == current_uid
+            && metadata.modified().is_ok_and(|modified| modified >= since);
+        if !is_from_this_run {
+            debug!("Keeping {}, it was not written by this run", path.display());
+            continue;
+        }
+
+        match std::fs::remove_file(path) {
+            Ok(()) => removed_paths.push(path.display().to_string()),
+            Err(e) => warn!("Failed to remove {}: {e}", path.display()),
+        }
+    }
+
+    if !removed_paths.is_empty() {
+        info!(
+            "Removed {} harvested files: {}",
+            removed_paths.len(),
+            removed_paths.join(", ")
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_remove_harvested_tmp_files_only_removes_files_of_this_run() {
+        let tmp = tempfile::tempdir().unwrap();
+        let since = SystemTime::now() - Duration::from_secs(60);
+
+        let harvested = tmp.path().join("perf-1.map");
+        std::fs::write(&harvested, "1000 10 main\n").unwrap();
+        let stale = tmp.path().join("perf-2.map");
+        std::fs::write(&stale, "1000 10 main\n").unwrap();
+        std::fs::File::options()
+            .write(true)
+            .open(&stale)
+            .unwrap()
+            .set_modified(since - Duration::from_secs(3600))
+            .unwrap();
+        let not_harvested = tmp.path().join("jit-3.dump");
+        std::fs::write(&not_harvested, "").unwrap();
+
+        remove_harvested_tmp_files(&[harvested.clone(), stale.clone()], since);
+
+        assert!(!harvested.exists());
+        assert!(stale.exists());
+        assert!(not_harvested.exists());
+    }
 }
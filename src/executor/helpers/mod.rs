@@ -2,6 +2,7 @@ pub mod apt;
 #[cfg(target_os = "linux")]
 pub mod capabilities;
 pub mod command;
+pub mod cpu_affinity;
 pub mod detect_executable;
 pub mod env;
 pub mod get_bench_command;
@@ -10,6 +11,7 @@ pub mod harvest_perf_maps_for_pids;
 pub mod homebrew;
 pub mod introspected_golang;
 pub mod introspected_nodejs;
+pub mod process_tree;
 pub mod profile_folder;
 pub mod run_command_with_log_pipe;
 pub mod run_with_env;
@@ -0,0 +1,141 @@
+use super::run_with_sudo::is_root_user;
+use crate::prelude::*;
+use libc::pid_t;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::process::{Command, Stdio};
+use std::time::{Duration, Instant};
+use sysinfo::{ProcessRefreshKind, ProcessStatus, RefreshKind, System};
+
+/// Time the processes have to exit after SIGTERM before they are killed with SIGKILL.
+const TERMINATION_GRACE_PERIOD: Duration = Duration::from_secs(5);
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// The processes spawned by `root`, directly or not, from the `(pid, parent pid)` pairs of the
+/// running processes.
+fn descendants(root: pid_t, processes: impl IntoIterator<Item = (pid_t, pid_t)>) -> Vec<pid_t> {
+    let mut children: HashMap<pid_t, Vec<pid_t>> = HashMap::new();
+    for (pid, parent) in processes {
+        children.entry(parent).or_default().push(pid);
+    }
+
+    let mut descendants = vec![];
+    let mut queue = VecDeque::from([root]);
+    while let Some(pid) = queue.pop_front() {
+        for &child in children.get(&pid).into_iter().flatten() {
+            if child != root && !descendants.contains(&child) {
+                descendants.push(child);
+                queue.push_back(child);
+            }
+        }
+    }
+    descendants
+}
+
+/// The `(pid, parent pid)` pairs of the live processes, zombies excluded.
+fn running_processes() -> Vec<(pid_t, pid_t)> {
+    let system = System::new_with_specifics(
+        RefreshKind::nothing().with_processes(ProcessRefreshKind::nothing()),
+    );
+    system
+        .processes()
+        .iter()
+        .filter(|(_, process)| process.status() != ProcessStatus::Zombie)
+        .filter_map(|(pid, process)| {
+            let parent = process.parent()?;
+            Some((pid.as_u32() as pid_t, parent.as_u32() as pid_t))
+        })
+        .collect()
+}
+
+/// Send `signal` (e.g. `TERM`) to `pids` with the `kill` command. The processes owned by root,
+/// such as perf or a benchmark run with sudo, are signalled through `sudo`.
+fn send_signal(pids: &[pid_t], signal: &str) {
+    let kill = |with_sudo: bool| {
+        let mut cmd = if with_sudo {
+            let mut cmd = Command::new("sudo");
+            cmd.args(["--non-interactive", "kill"]);
+            cmd
+        } else {
+            Command::new("kill")
+        };
+        cmd.args(["-s", signal])
+            .args(pids.iter().map(|pid| pid.to_string()))
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status()
+            .is_ok_and(|status| status.success())
+    };
+    // `kill` fails if any of the processes could not be signalled, e.g. because it already exited
+    if !kill(false) && !is_root_user() && !kill(true) {
+        debug!("Failed to send SIG{signal} to some of the processes {pids:?}");
+    }
+}
+
+/// Reap `root` if it exited, so that it is no longer listed as running.
+fn try_reap(root: pid_t) -> bool {
+    // Safety: only reaps `root`, whose `Child` handle is no longer waited on
+    let reaped = unsafe { libc::waitpid(root, std::ptr::null_mut(), libc::WNOHANG) };
+    reaped == root || reaped == -1
+}
+
+/// Terminate `root` and all the processes it spawned.
+///
+/// The descendants are collected before signalling, as they are reparented once their parent
+/// exits. They first receive SIGTERM, so that a profiler such as perf can finish writing its data,
+/// then SIGKILL if they are still running after [`TERMINATION_GRACE_PERIOD`].
+pub async fn kill_process_tree(root: pid_t) {
+    let mut tree = vec![root];
+    tree.extend(descendants(root, running_processes()));
+    debug!("Terminating the process tree {tree:?}");
+    send_signal(&tree, "TERM");
+
+    let deadline = Instant::now() + TERMINATION_GRACE_PERIOD;
+    let mut root_reaped = false;
+    loop {
+        root_reaped = root_reaped || try_reap(root);
+        let running: HashSet<pid_t> = running_processes()
+            .into_iter()
+            .map(|(pid, _)| pid)
+            .collect();
+        let remaining = tree
+            .iter()
+            .copied()
+            .filter(|pid| running.contains(pid) && !(*pid == root && root_reaped))
+            .collect_vec();
+        if remaining.is_empty() {
+            return;
+        }
+        if Instant::now() >= deadline {
+            warn!("Killing the processes {remaining:?} still running after SIGTERM");
+            send_signal(&remaining, "KILL");
+            break;
+        }
+        tokio::time::sleep(POLL_INTERVAL).await;
+    }
+
+    if !root_reaped {
+        // Safety: see `try_reap`, the root exits promptly after SIGKILL
+        unsafe { libc::waitpid(root, std::ptr::null_mut(), 0) };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_descendants() {
+        let processes = [
+            (1, 0),
+            (10, 1),
+            (11, 10),
+            (12, 10),
+            (13, 11),
+            (20, 1),
+            (21, 20),
+        ];
+
+        assert_eq!(descendants(10, processes), vec![11, 12, 13]);
+        assert_eq!(descendants(13, processes), Vec::<pid_t>::new());
+    }
+}
@@ -4,7 +4,13 @@ use rand::distr::Alphanumeric;
 use rand::distr::SampleString;
 use std::env;
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+
+/// Free space under the profile folder, in MB, below which a warning is printed before the run.
+/// The run is aborted below a tenth of it. Defaults to [`DEFAULT_MIN_FREE_MB`].
+pub const MIN_FREE_MB_ENV: &str = "CODSPEED_MIN_FREE_MB";
+
+const DEFAULT_MIN_FREE_MB: u64 = 500;
 
 pub fn create_profile_folder() -> Result<PathBuf> {
     let folder_name = format!(
@@ -24,6 +30,94 @@ pub fn create_profile_folder() -> Result<PathBuf> {
     Ok(folder_path)
 }
 
+/// Make sure `folder` holds no stale artifacts that a new run would mix with its own.
+///
+/// A non-empty folder is only emptied with `clear` (`--clear-profile-folder`), since it is given
+/// by the user and may not be a profile folder at all. Neither `--yes` nor a TTY confirmation
+/// is enough to delete it.
+pub fn clear_profile_folder(folder: &Path, clear: bool) -> Result<()> {
+    let Ok(mut entries) = fs::read_dir(folder) else {
+        // Missing folder: nothing to overwrite
+        return Ok(());
+    };
+    if entries.next().is_none() {
+        return Ok(());
+    }
+    ensure!(
+        clear,
+        "The profile folder {} is not empty. Pass --clear-profile-folder to delete its content \
+        before the run, or use an empty folder",
+        folder.display()
+    );
+
+    info!("Clearing the profile folder {}", folder.display());
+    for entry in fs::read_dir(folder)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            fs::remove_dir_all(&path)
+        } else {
+            fs::remove_file(&path)
+        }
+        .with_context(|| format!("Failed to remove {}", path.display()))?;
+    }
+
+    Ok(())
+}
+
+/// Check that there is enough free space under `folder` for the profiles, which can weigh
+/// gigabytes, rather than failing once the disk is full in the middle of the run.
+pub fn ensure_free_space(folder: &Path) -> Result<()> {
+    let min_free_mb = min_free_mb_from_env()?;
+    let free_mb = available_space_mb(folder)
+        .with_context(|| format!("Failed to get the free space under {}", folder.display()))?;
+    debug!("{free_mb} MB available under {}", folder.display());
+    if let Some(warning) = check_free_space(folder, free_mb, min_free_mb)? {
+        warn!("{warning}");
+    }
+    Ok(())
+}
+
+fn min_free_mb_from_env() -> Result<u64> {
+    let Ok(value) = env::var(MIN_FREE_MB_ENV) else {
+        return Ok(DEFAULT_MIN_FREE_MB);
+    };
+    value.trim().parse().with_context(|| {
+        format!("Invalid {MIN_FREE_MB_ENV} value {value:?}, expected a number of MB")
+    })
+}
+
+/// Space available to the current user on the filesystem of `folder`, in MB.
+fn available_space_mb(folder: &Path) -> Result<u64> {
+    // The user-provided folder is only created by the executors
+    let folder = std::path::absolute(folder)?;
+    let existing_ancestor = folder
+        .ancestors()
+        .find(|path| path.exists())
+        .unwrap_or(Path::new("/"));
+    let stat = nix::sys::statvfs::statvfs(existing_ancestor)?;
+    #[allow(clippy::unnecessary_cast)] // The field types differ across platforms
+    let available_bytes = stat.blocks_available() as u64 * stat.fragment_size() as u64;
+    Ok(available_bytes / (1024 * 1024))
+}
+
+/// Fail when `free_mb` is critically low, or return a warning when it's below `min_free_mb`.
+fn check_free_space(folder: &Path, free_mb: u64, min_free_mb: u64) -> Result<Option<String>> {
+    let advice = format!(
+        "Free some space or use another --profile-folder, the threshold is set with {MIN_FREE_MB_ENV}"
+    );
+    ensure!(
+        free_mb >= min_free_mb / 10,
+        "Only {free_mb} MB of disk space is available under {}, not enough for the profiles. {advice}",
+        folder.display()
+    );
+    Ok((free_mb < min_free_mb).then(|| {
+        format!(
+            "Only {free_mb} MB of disk space is available under {}, the profiles may fill it up. {advice}",
+            folder.display()
+        )
+    }))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -35,4 +129,46 @@ mod tests {
         assert!(folder_path.is_dir());
         Ok(())
     }
+
+    #[test]
+    fn test_check_free_space() {
+        let folder = Path::new("/tmp/profile");
+        assert_eq!(check_free_space(folder, 10_000, 500).unwrap(), None);
+
+        let warning = check_free_space(folder, 200, 500).unwrap().unwrap();
+        assert!(warning.starts_with("Only 200 MB of disk space is available under /tmp/profile"));
+
+        let error = check_free_space(folder, 20, 500).unwrap_err();
+        assert!(error.to_string().contains("not enough for the profiles"));
+
+        // A zero threshold disables the check
+        assert_eq!(check_free_space(folder, 0, 0).unwrap(), None);
+    }
+
+    #[test]
+    fn test_available_space_of_a_missing_folder() {
+        let tmp = tempfile::tempdir().unwrap();
+        assert!(available_space_mb(&tmp.path().join("not/created/yet")).is_ok());
+    }
+
+    #[test]
+    fn test_clear_profile_folder_requires_the_flag() {
+        let folder = tempfile::tempdir().unwrap();
+        fs::write(folder.path().join("stale.out"), "stale").unwrap();
+        fs::create_dir(folder.path().join("walltime-0")).unwrap();
+
+        let err = clear_profile_folder(folder.path(), false).unwrap_err();
+        assert!(err.to_string().contains("--clear-profile-folder"));
+        assert!(folder.path().join("stale.out").exists());
+
+        clear_profile_folder(folder.path(), true).unwrap();
+        assert_eq!(fs::read_dir(folder.path()).unwrap().count(), 0);
+    }
+
+    #[test]
+    fn test_clear_empty_or_missing_profile_folder() {
+        let folder = tempfile::tempdir().unwrap();
+        clear_profile_folder(folder.path(), false).unwrap();
+        clear_profile_folder(&folder.path().join("missing"), false).unwrap();
+    }
 }
@@ -1,17 +1,113 @@
+use super::process_tree::kill_process_tree;
 use crate::executor::EXECUTOR_TARGET;
 use crate::local_logger::rolling_buffer::ROLLING_BUFFER;
-use crate::local_logger::suspend_progress_bar;
+use crate::local_logger::{format_elapsed, suspend_progress_bar};
 use crate::prelude::*;
+use std::borrow::Cow;
+use std::fmt;
+use std::fs::File;
 use std::future::Future;
 use std::io::{Read, Write};
+use std::os::unix::process::ExitStatusExt;
+use std::path::Path;
 use std::process::Command;
 use std::process::ExitStatus;
 use std::thread;
+use std::time::Duration;
+
+/// File in the profile folder receiving the benchmark's stdout with `--capture-output`.
+pub const CAPTURED_STDOUT_FILE_NAME: &str = "benchmark.stdout.log";
+/// File in the profile folder receiving the benchmark's stderr with `--capture-output`.
+pub const CAPTURED_STDERR_FILE_NAME: &str = "benchmark.stderr.log";
+
+/// Prefix of the lines written by perf to the stderr of the command in its verbose mode. They are
+/// logged at the debug level rather than printed with the output of the benchmark.
+pub const PERF_OUTPUT_PREFIX: &str = "[perf] ";
+
+/// Log the lines of perf in `text` at the debug level, returning the other ones.
+pub fn log_perf_output(text: &str) -> Cow<'_, str> {
+    if !text.contains(PERF_OUTPUT_PREFIX) {
+        return Cow::Borrowed(text);
+    }
+    let mut other_lines = String::new();
+    for line in text.split_inclusive(['\n', '\r']) {
+        if line.starts_with(PERF_OUTPUT_PREFIX) {
+            debug!("{}", line.trim_end());
+        } else {
+            other_lines.push_str(line);
+        }
+    }
+    Cow::Owned(other_lines)
+}
+
+/// The benchmark command ran for longer than `--benchmark-timeout`, and was killed with all the
+/// processes it spawned.
+#[derive(Debug)]
+pub struct BenchmarkTimeoutError {
+    pub timeout: Duration,
+}
+
+impl fmt::Display for BenchmarkTimeoutError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "The benchmark command timed out after {} and was killed, \
+            increase --benchmark-timeout if it needs more time",
+            format_elapsed(self.timeout)
+        )
+    }
+}
+
+impl std::error::Error for BenchmarkTimeoutError {}
+
+/// Name a signal commonly terminating a benchmark, with its likely cause.
+fn describe_signal(signal: i32) -> String {
+    let (name, cause) = match signal {
+        libc::SIGKILL => ("SIGKILL", Some("likely OOM")),
+        libc::SIGSEGV => ("SIGSEGV", Some("segmentation fault")),
+        libc::SIGABRT => ("SIGABRT", Some("aborted")),
+        libc::SIGBUS => ("SIGBUS", Some("bus error")),
+        libc::SIGFPE => ("SIGFPE", Some("arithmetic error")),
+        libc::SIGILL => ("SIGILL", Some("illegal instruction")),
+        libc::SIGTERM => ("SIGTERM", None),
+        libc::SIGINT => ("SIGINT", None),
+        _ => return format!("signal {signal}"),
+    };
+    match cause {
+        Some(cause) => format!("{name} ({cause})"),
+        None => name.to_string(),
+    }
+}
+
+/// Describe an exit code, naming the signal when it looks like a shell reporting a child
+/// killed by a signal (`128 + signal`).
+pub fn describe_exit_code(code: i32) -> String {
+    match code {
+        129..=159 => format!(
+            "exit code {code}, probably killed by {}",
+            describe_signal(code - 128)
+        ),
+        _ => format!("exit code {code}"),
+    }
+}
+
+/// Describe how a process exited, naming the signal that killed it, if any.
+pub fn describe_exit_status(status: &ExitStatus) -> String {
+    match (status.signal(), status.code()) {
+        (Some(signal), _) => format!("killed by {}", describe_signal(signal)),
+        (None, Some(code)) => describe_exit_code(code),
+        (None, None) => status.to_string(),
+    }
+}
 
 /// Run a command and log its output to stdout and stderr
 ///
 /// # Arguments
 /// - `cmd`: The command to run.
+/// - `capture_folder`: If set, the raw stdout and stderr of the command are also written to
+///   [`CAPTURED_STDOUT_FILE_NAME`] and [`CAPTURED_STDERR_FILE_NAME`] in this folder.
+/// - `timeout`: If set, the command and all the processes it spawned are killed once it runs for
+///   longer, failing with a [`BenchmarkTimeoutError`].
 /// - `cb`: A callback function that takes the process and returns the exit status.
 ///
 /// # Returns
@@ -20,18 +116,29 @@ use std::thread;
 ///
 pub async fn run_command_with_log_pipe_and_callback<F, Fut>(
     mut cmd: Command,
+    capture_folder: Option<&Path>,
+    timeout: Option<Duration>,
     cb: F,
 ) -> Result<ExitStatus>
 where
     F: FnOnce(std::process::Child) -> Fut,
     Fut: Future<Output = anyhow::Result<ExitStatus>>,
 {
-    /// Write text to the rolling buffer if active, otherwise write raw bytes to the writer.
+    /// Write text to the rolling buffer if active, otherwise write raw bytes to the writer. The
+    /// lines of perf are logged instead.
     fn write_to_rolling_buffer_or_output(text: &str, raw_bytes: &[u8], writer: &mut impl Write) {
+        let text = log_perf_output(text);
+        let raw_bytes = match &text {
+            Cow::Borrowed(_) => raw_bytes,
+            Cow::Owned(other_lines) => other_lines.as_bytes(),
+        };
+        if raw_bytes.is_empty() {
+            return;
+        }
         if let Ok(mut guard) = ROLLING_BUFFER.lock() {
             if let Some(rb) = guard.as_mut() {
                 if rb.is_active() {
-                    rb.push_lines(text);
+                    rb.push_lines(&text);
                     return;
                 }
             }
@@ -42,6 +149,7 @@ where
     fn log_tee(
         mut reader: impl Read,
         mut writer: impl Write,
+        mut capture: Option<File>,
         log_prefix: Option<&str>,
     ) -> Result<()> {
         let prefix = log_prefix.unwrap_or("");
@@ -60,6 +168,10 @@ where
                 break;
             }
 
+            if let Some(capture) = capture.as_mut() {
+                capture.write_all(&buffer[..bytes_read])?;
+            }
+
             // Add the chunk to our line buffer
             line_buffer.extend_from_slice(&buffer[..bytes_read]);
 
@@ -80,6 +192,17 @@ where
         Ok(())
     }
 
+    let create_capture = |file_name: &str| -> Result<Option<File>> {
+        capture_folder
+            .map(|folder| {
+                let path = folder.join(file_name);
+                File::create(&path).with_context(|| format!("failed to create {}", path.display()))
+            })
+            .transpose()
+    };
+    let stdout_capture = create_capture(CAPTURED_STDOUT_FILE_NAME)?;
+    let stderr_capture = create_capture(CAPTURED_STDERR_FILE_NAME)?;
+
     let mut process = cmd
         .stdout(std::process::Stdio::piped())
         .stderr(std::process::Stdio::piped())
@@ -89,14 +212,26 @@ where
     let stderr = process.stderr.take().expect("unable to get stderr");
 
     let stdout_handle = thread::spawn(move || {
-        log_tee(stdout, std::io::stdout(), None).unwrap();
+        log_tee(stdout, std::io::stdout(), stdout_capture, None).unwrap();
     });
 
     let stderr_handle = thread::spawn(move || {
-        log_tee(stderr, std::io::stderr(), Some("[stderr]")).unwrap();
+        log_tee(stderr, std::io::stderr(), stderr_capture, Some("[stderr]")).unwrap();
     });
 
-    let result = cb(process).await;
+    let pid = process.id() as libc::pid_t;
+    let result = match timeout {
+        None => cb(process).await,
+        Some(timeout) => match tokio::time::timeout(timeout, cb(process)).await {
+            Ok(result) => result,
+            Err(_) => {
+                // The output pipes stay open, and the threads below blocked, as long as any of
+                // the spawned processes runs
+                kill_process_tree(pid).await;
+                Err(BenchmarkTimeoutError { timeout }.into())
+            }
+        },
+    };
 
     // Wait for threads to drain remaining output
     let _ = stdout_handle.join();
@@ -105,6 +240,98 @@ where
     result
 }
 
-pub async fn run_command_with_log_pipe(cmd: Command) -> Result<ExitStatus> {
-    run_command_with_log_pipe_and_callback(cmd, |mut child| async move { Ok(child.wait()?) }).await
+pub async fn run_command_with_log_pipe(
+    cmd: Command,
+    capture_folder: Option<&Path>,
+    timeout: Option<Duration>,
+) -> Result<ExitStatus> {
+    run_command_with_log_pipe_and_callback(cmd, capture_folder, timeout, |mut child| async move {
+        // Poll instead of blocking on `wait`, so that the timeout can elapse
+        loop {
+            if let Some(status) = child.try_wait()? {
+                return Ok(status);
+            }
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+    })
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_signal_termination_is_reported() {
+        let mut cmd = Command::new("sh");
+        cmd.args(["-c", "kill -SEGV $$"]);
+
+        let status = run_command_with_log_pipe(cmd, None, None).await.unwrap();
+
+        assert_eq!(status.signal(), Some(libc::SIGSEGV));
+        assert_eq!(
+            describe_exit_status(&status),
+            "killed by SIGSEGV (segmentation fault)"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_shell_reported_signal_is_named() {
+        let mut cmd = Command::new("sh");
+        cmd.args(["-c", "sh -c 'kill -KILL $$'"]);
+
+        let status = run_command_with_log_pipe(cmd, None, None).await.unwrap();
+
+        assert_eq!(status.code(), Some(137));
+        assert_eq!(
+            describe_exit_status(&status),
+            "exit code 137, probably killed by SIGKILL (likely OOM)"
+        );
+        assert_eq!(describe_exit_code(1), "exit code 1");
+    }
+
+    #[tokio::test]
+    async fn test_capture_output_to_folder() {
+        let capture_folder = tempfile::tempdir().unwrap();
+        let mut cmd = Command::new("sh");
+        cmd.args(["-c", "echo 'to stdout'; echo 'to stderr' >&2"]);
+
+        let status = run_command_with_log_pipe(cmd, Some(capture_folder.path()), None)
+            .await
+            .unwrap();
+        assert!(status.success());
+
+        let stdout =
+            std::fs::read_to_string(capture_folder.path().join(CAPTURED_STDOUT_FILE_NAME)).unwrap();
+        let stderr =
+            std::fs::read_to_string(capture_folder.path().join(CAPTURED_STDERR_FILE_NAME)).unwrap();
+        assert_eq!(stdout, "to stdout\n");
+        assert_eq!(stderr, "to stderr\n");
+    }
+
+    #[tokio::test]
+    async fn test_timeout_kills_the_process_tree() {
+        let pid_folder = tempfile::tempdir().unwrap();
+        let pid_file = pid_folder.path().join("background.pid");
+        let mut cmd = Command::new("sh");
+        cmd.arg("-c").arg(format!(
+            "sleep 30 & echo $! > {}; sleep 30",
+            pid_file.display()
+        ));
+
+        let err = run_command_with_log_pipe(cmd, None, Some(Duration::from_secs(1)))
+            .await
+            .unwrap_err();
+
+        let err = err.downcast::<BenchmarkTimeoutError>().unwrap();
+        assert_eq!(err.timeout, Duration::from_secs(1));
+        let background_pid: libc::pid_t = std::fs::read_to_string(&pid_file)
+            .unwrap()
+            .trim()
+            .parse()
+            .unwrap();
+        // Safety: signal 0 only checks that the process exists
+        let exists = unsafe { libc::kill(background_pid, 0) } == 0;
+        assert!(!exists, "the background sleep outlived the timeout");
+    }
 }
@@ -77,7 +77,9 @@ pub fn wrap_with_sudo(mut cmd_builder: CommandBuilder) -> Result<CommandBuilder>
     } else if is_sudo_available() {
         debug!("Wrapping with sudo: {}", cmd_builder.as_command_line());
         validate_sudo_access()?;
-        cmd_builder.wrap(
+        // sudo may still drop some variables (e.g. `PATH` or `LD_*`) despite `--preserve-env`,
+        // so the ones set on the command are passed explicitly
+        cmd_builder.wrap_with_env_assignments().wrap(
             "sudo",
             [
                 // Password prompt should not appear here since it has already been validated
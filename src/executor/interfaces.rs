@@ -30,6 +30,14 @@ impl ExecutorName {
         }
     }
 
+    /// The operating systems this executor runs on.
+    pub fn supported_platforms(&self) -> &'static str {
+        match self {
+            ExecutorName::Valgrind | ExecutorName::Memory => "Linux",
+            ExecutorName::WallTime => "Linux and macOS",
+        }
+    }
+
     /// Icon for this executor.
     pub fn icon(&self) -> Icon {
         match self {
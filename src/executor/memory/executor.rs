@@ -5,7 +5,9 @@ use crate::executor::ToolStatus;
 use crate::executor::helpers::command::CommandBuilder;
 use crate::executor::helpers::env::{build_path_env, get_base_injected_env};
 use crate::executor::helpers::get_bench_command::get_bench_command;
-use crate::executor::helpers::run_command_with_log_pipe::run_command_with_log_pipe_and_callback;
+use crate::executor::helpers::run_command_with_log_pipe::{
+    describe_exit_status, run_command_with_log_pipe_and_callback,
+};
 use crate::executor::helpers::run_with_env::wrap_with_env;
 use crate::executor::helpers::run_with_sudo::is_root_user;
 use crate::executor::shared::fifo::RunnerFifo;
@@ -16,6 +18,7 @@ use crate::runner_mode::RunnerMode;
 use crate::system::{SupportedOs, SystemInfo};
 use async_trait::async_trait;
 use ipc_channel::ipc;
+use memtrack::DiscoveryReport;
 use memtrack::MemtrackIpcClient;
 use memtrack::MemtrackIpcServer;
 use runner_shared::artifacts::{ArtifactExt, ExecutionTimestamps};
@@ -43,7 +46,7 @@ impl MemoryExecutor {
             RunnerMode::Memory,
             &execution_context.profile_folder,
             &execution_context.config,
-        );
+        )?;
 
         extra_env.insert(
             "PATH".into(),
@@ -166,11 +169,24 @@ impl Executor for MemoryExecutor {
             Ok(exit_status)
         };
 
-        let status = run_command_with_log_pipe_and_callback(cmd, on_process_started).await?;
+        let capture_folder = execution_context
+            .config
+            .capture_output
+            .then_some(execution_context.profile_folder.as_path());
+        let status = run_command_with_log_pipe_and_callback(
+            cmd,
+            capture_folder,
+            execution_context.config.benchmark_timeout,
+            on_process_started,
+        )
+        .await?;
         debug!("cmd exit status: {status:?}");
 
         if !status.success() {
-            bail!("failed to execute memory tracker process: {status}");
+            bail!(
+                "failed to execute memory tracker process: {}",
+                describe_exit_status(&status)
+            );
         }
 
         Ok(())
@@ -193,6 +209,14 @@ impl Executor for MemoryExecutor {
             })
             .any(|artifact| !artifact.uri_by_ts.is_empty());
 
+        // Explain where the allocators were found, which is the first thing to check when no
+        // allocations were recorded
+        match Self::load_discovery_report(&results_dir) {
+            Some(report) if !has_benchmarks => info!("Allocator discovery:\n{report}"),
+            Some(report) => debug!("Allocator discovery:\n{report}"),
+            None => debug!("No allocator discovery report found in {results_dir:?}"),
+        }
+
         if !has_benchmarks {
             if !execution_context.config.allow_empty {
                 bail!("No memory results found in profile folder: {results_dir:?}.");
@@ -206,6 +230,11 @@ impl Executor for MemoryExecutor {
 }
 
 impl MemoryExecutor {
+    fn load_discovery_report(results_dir: &Path) -> Option<DiscoveryReport> {
+        let file = std::fs::File::open(results_dir.join(DiscoveryReport::file_name(None))).ok()?;
+        DiscoveryReport::decode_from_reader(file).ok()
+    }
+
     async fn handle_fifo(
         mut runner_fifo: RunnerFifo,
         ipc: MemtrackIpcServer,
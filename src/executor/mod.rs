@@ -7,22 +7,27 @@ mod interfaces;
 #[cfg(target_os = "linux")]
 mod memory;
 pub mod orchestrator;
+mod result_cache;
 mod shared;
 #[cfg(test)]
-mod tests;
+pub(crate) mod tests;
 mod valgrind;
 mod wall_time;
 
+use crate::failure::{FailureClass, WithFailureClass};
 use crate::instruments::mongo_tracer::{MongoTracer, install_mongodb_tracer};
 use crate::local_logger::rolling_buffer::{activate_rolling_buffer, deactivate_rolling_buffer};
 use crate::prelude::*;
 use crate::runner_mode::RunnerMode;
 use crate::system::SystemInfo;
 use async_trait::async_trait;
-pub use config::{BenchmarkTarget, ExecutorConfig, WalltimeProfiler};
+pub use config::{BenchmarkTarget, ExecutorConfig, SimulationTool, WalltimeProfiler};
 pub use execution_context::ExecutionContext;
 pub use interfaces::ExecutorName;
 pub use orchestrator::Orchestrator;
+pub(crate) use wall_time::profiler::perf::PERF_PIPEDATA_FILE_NAME;
+pub(crate) use wall_time::profiler::perf::module_symbols::ModuleSymbols;
+pub(crate) use wall_time::profiler::perf::parse_perf_file::for_each_sample_callchain;
 
 #[cfg(target_os = "linux")]
 use memory::executor::MemoryExecutor;
@@ -39,6 +44,7 @@ impl Display for RunnerMode {
             RunnerMode::Walltime => write!(f, "walltime"),
             #[cfg(target_os = "linux")]
             RunnerMode::Memory => write!(f, "memory"),
+            RunnerMode::Auto => write!(f, "auto"),
         }
     }
 }
@@ -48,20 +54,24 @@ pub const EXECUTOR_TARGET: &str = "executor";
 pub fn get_executor_from_mode(
     mode: &RunnerMode,
     walltime_profiler: Option<WalltimeProfiler>,
-) -> Box<dyn Executor> {
-    match mode {
+    simulation_tool: SimulationTool,
+) -> Result<Box<dyn Executor>> {
+    Ok(match mode {
         #[allow(deprecated)]
-        RunnerMode::Instrumentation | RunnerMode::Simulation => Box::new(ValgrindExecutor),
+        RunnerMode::Instrumentation | RunnerMode::Simulation => {
+            Box::new(ValgrindExecutor::new(simulation_tool))
+        }
         RunnerMode::Walltime => Box::new(WallTimeExecutor::new(walltime_profiler)),
         #[cfg(target_os = "linux")]
         RunnerMode::Memory => Box::new(MemoryExecutor),
-    }
+        RunnerMode::Auto => bail!("The auto mode has no executor, it must be resolved first"),
+    })
 }
 
 pub fn get_all_executors() -> Vec<Box<dyn Executor>> {
     #[cfg_attr(not(target_os = "linux"), allow(unused_mut))]
     let mut executors: Vec<Box<dyn Executor>> = vec![
-        Box::new(ValgrindExecutor),
+        Box::new(ValgrindExecutor::new(SimulationTool::default())),
         Box::new(WallTimeExecutor::new(None)),
     ];
     #[cfg(target_os = "linux")]
@@ -160,19 +170,50 @@ pub async fn run_executor(
     setup_cache_dir: Option<&Path>,
     rolling_buffer_label: Option<&str>,
 ) -> Result<()> {
-    match executor.support_level(&orchestrator.system_info) {
+    setup_executor(
+        executor,
+        &orchestrator.system_info,
+        execution_context,
+        setup_cache_dir,
+    )
+    .await
+    .with_failure_class(FailureClass::Setup)?;
+
+    if !execution_context.config.skip_run {
+        run_and_teardown_executor(
+            executor,
+            orchestrator,
+            execution_context,
+            rolling_buffer_label,
+        )
+        .await
+        .with_failure_class(FailureClass::Run)?;
+    } else {
+        debug!("Skipping the run of the benchmarks");
+    };
+
+    Ok(())
+}
+
+/// Install the tools of the executor, unless the setup is skipped.
+pub async fn setup_executor(
+    executor: &mut dyn Executor,
+    system_info: &SystemInfo,
+    execution_context: &ExecutionContext,
+    setup_cache_dir: Option<&Path>,
+) -> Result<()> {
+    match executor.support_level(system_info) {
         ExecutorSupport::Unsupported => {
             bail!(
-                "The {} executor is not supported on {}",
+                "The {} executor is not supported on {}, it only runs on {}",
                 executor.name(),
-                orchestrator.system_info.os
+                system_info.os,
+                executor.name().supported_platforms()
             );
         }
         ExecutorSupport::RequiresManualInstallation | ExecutorSupport::FullySupported => {
             if !execution_context.config.skip_setup {
-                executor
-                    .setup(&orchestrator.system_info, setup_cache_dir)
-                    .await?;
+                executor.setup(system_info, setup_cache_dir).await?;
                 executor.grant_privileges()?;
             }
         }
@@ -187,40 +228,45 @@ pub async fn run_executor(
         debug!("Environment ready");
     }
 
-    if !execution_context.config.skip_run {
-        // TODO: refactor and move directly in the Instruments struct as a `start` method
-        let mongo_tracer =
-            if let Some(mongodb_config) = &execution_context.config.instruments.mongodb {
-                let mut mongo_tracer =
-                    MongoTracer::try_from(&execution_context.profile_folder, mongodb_config)?;
-                mongo_tracer.start().await?;
-                Some(mongo_tracer)
-            } else {
-                None
-            };
-
-        if let Some(label) = rolling_buffer_label {
-            activate_rolling_buffer(label);
-        }
-        let run_result = executor.run(execution_context, &mongo_tracer).await;
-        if rolling_buffer_label.is_some() {
-            deactivate_rolling_buffer();
-        }
-        run_result?;
-
-        // TODO: refactor and move directly in the Instruments struct as a `stop` method
-        if let Some(mut mongo_tracer) = mongo_tracer {
-            mongo_tracer.stop().await?;
-        }
-        debug!("Tearing down the executor");
-        executor.teardown(execution_context).await?;
+    Ok(())
+}
 
-        orchestrator
-            .logger
-            .persist_log_to_profile_folder(&execution_context.profile_folder)?;
+/// Run the benchmarks with the executor and collect their results.
+pub async fn run_and_teardown_executor(
+    executor: &mut dyn Executor,
+    orchestrator: &Orchestrator,
+    execution_context: &ExecutionContext,
+    rolling_buffer_label: Option<&str>,
+) -> Result<()> {
+    // TODO: refactor and move directly in the Instruments struct as a `start` method
+    let mongo_tracer = if let Some(mongodb_config) = &execution_context.config.instruments.mongodb {
+        let mut mongo_tracer =
+            MongoTracer::try_from(&execution_context.profile_folder, mongodb_config)?;
+        mongo_tracer.start().await?;
+        Some(mongo_tracer)
     } else {
-        debug!("Skipping the run of the benchmarks");
+        None
     };
 
+    if let Some(label) = rolling_buffer_label {
+        activate_rolling_buffer(label);
+    }
+    let run_result = executor.run(execution_context, &mongo_tracer).await;
+    if rolling_buffer_label.is_some() {
+        deactivate_rolling_buffer();
+    }
+    run_result?;
+
+    // TODO: refactor and move directly in the Instruments struct as a `stop` method
+    if let Some(mut mongo_tracer) = mongo_tracer {
+        mongo_tracer.stop().await?;
+    }
+    debug!("Tearing down the executor");
+    executor.teardown(execution_context).await?;
+
+    orchestrator
+        .logger
+        .persist_log_to_profile_folder(&execution_context.profile_folder)?;
+
     Ok(())
 }
@@ -1,18 +1,32 @@
-use super::{ExecutionContext, ExecutorName, get_executor_from_mode, run_executor};
+use super::{
+    ExecutionContext, Executor, ExecutorName, ExecutorSupport, SimulationTool, ToolInstallStatus,
+    ToolStatus, WalltimeProfiler, get_executor_from_mode, run_and_teardown_executor, run_executor,
+    setup_executor,
+};
 use crate::api_client::CodSpeedAPIClient;
 use crate::binary_installer::ensure_binary_installed;
 use crate::binary_pins::{self, PinnedBinary};
 use crate::cli::exec::multi_targets;
 use crate::cli::run::logger::Logger;
 use crate::executor::config::BenchmarkTarget;
-use crate::executor::config::OrchestratorConfig;
-use crate::executor::helpers::profile_folder::create_profile_folder;
+use crate::executor::config::{ExecutorConfig, OrchestratorConfig};
+use crate::executor::helpers::cpu_affinity::validate_cpu;
+use crate::executor::helpers::profile_folder::{
+    clear_profile_folder, create_profile_folder, ensure_free_space,
+};
+use crate::executor::helpers::run_with_sudo::can_elevate_without_prompt;
+use crate::executor::result_cache::{self, ResultCache};
+use crate::executor::wall_time::profiler::linux_sysctl::profiling_sysctls_are_set;
+use crate::executor::wall_time::profiler::perf::symbols_cache::SYMBOLS_CACHE_DIR_NAME;
+use crate::failure::{FailureClass, WithFailureClass};
+use crate::logger::{RUN_WARNINGS, warnings_summary};
 use crate::prelude::*;
 use crate::run_environment::{self, RunEnvironment, RunEnvironmentProvider};
-use crate::runner_mode::RunnerMode;
+use crate::runner_mode::{AutoModeEnvironment, RunnerMode};
 use crate::system::SystemInfo;
 use crate::upload::poll_results::poll_results;
 use crate::upload::{UploadResult, upload};
+use futures::{StreamExt, TryStreamExt};
 use serde_json::Value;
 use std::collections::BTreeMap;
 use std::path::{Path, PathBuf};
@@ -35,10 +49,32 @@ impl Orchestrator {
         self.provider.get_run_environment() == RunEnvironment::Local
     }
 
-    pub async fn new(config: OrchestratorConfig, api_client: &CodSpeedAPIClient) -> Result<Self> {
-        let provider = run_environment::get_provider(&config, api_client).await?;
+    pub async fn new(
+        mut config: OrchestratorConfig,
+        api_client: &CodSpeedAPIClient,
+    ) -> Result<Self> {
         let system_info = SystemInfo::new()?;
+        config.modes = resolve_modes(&config.modes, config.walltime_profiler, &system_info)?;
+        let ignored_options = config.ignore_inapplicable_options();
+        config.validate()?;
+        if let Some(cpu) = config.cpu {
+            validate_cpu(cpu)?;
+        }
+        // Filter before creating the provider, which announces the expected run parts count
+        if config.skip_unsupported_modes {
+            config.modes =
+                filter_supported_modes(&config.modes, config.walltime_profiler, &system_info)?;
+        }
+        let provider = run_environment::get_provider(&config, api_client).await?;
         let logger = Logger::new(provider.as_ref())?;
+        for (level, message) in ignored_options {
+            log::log!(level, "{message}");
+        }
+        if !config.skip_run {
+            for caveat in cpu_caveats(&config.modes, &system_info) {
+                warn!("{caveat}");
+            }
+        }
 
         #[allow(deprecated)]
         if config.modes.contains(&RunnerMode::Instrumentation) {
@@ -71,6 +107,12 @@ impl Orchestrator {
         setup_cache_dir: Option<&Path>,
         api_client: &mut CodSpeedAPIClient,
     ) -> Result<()> {
+        // Shared by every API request and upload of this run, so that a run reported
+        // by a user can be found in the backend logs
+        let correlation_id = uuid::Uuid::new_v4().to_string();
+        debug!("Correlation ID: {correlation_id}");
+        api_client.set_correlation_id(correlation_id.clone());
+
         // Build (command, label, uses_exec_harness) tuples while we still know the target type
         let mut command_labels: Vec<(String, String, bool)> = vec![];
 
@@ -87,7 +129,8 @@ impl Orchestrator {
                 EXEC_HARNESS_VERSION,
                 PinnedBinary::ExecHarnessInstaller,
             )
-            .await?;
+            .await
+            .with_failure_class(FailureClass::Setup)?;
 
             let pipe_cmd = multi_targets::build_exec_targets_pipe_command(&exec_targets)?;
             let label = match exec_targets.as_slice() {
@@ -113,23 +156,32 @@ impl Orchestrator {
         }
 
         // Flatten into (command, mode) run parts
-        let modes = &self.config.modes;
+        let modes = self
+            .config
+            .modes
+            .iter()
+            .map(|mode| {
+                Ok((
+                    mode,
+                    get_executor_from_mode(mode, None, self.config.simulation_tool)?.name(),
+                ))
+            })
+            .collect::<Result<Vec<_>>>()?;
         let run_parts: Vec<ExecutorTarget> = command_labels
             .iter()
             .flat_map(|(cmd, label, uses_exec_harness)| {
-                modes.iter().map(move |mode| {
-                    let executor_name = get_executor_from_mode(mode, None).name();
-                    ExecutorTarget {
+                modes
+                    .iter()
+                    .map(move |(mode, executor_name)| ExecutorTarget {
                         command: cmd.clone(),
-                        mode,
+                        mode: *mode,
                         label: format!(
                             "{} {} - {label}",
                             executor_name.icon(),
                             executor_name.label()
                         ),
                         uses_exec_harness: *uses_exec_harness,
-                    }
-                })
+                    })
             })
             .collect();
 
@@ -137,84 +189,216 @@ impl Orchestrator {
         let mut all_completed_runs = vec![];
 
         if !self.config.skip_run {
+            // With `--skip-run`, the folder holds the results of a previous run to upload
+            if let Some(folder) = &self.config.profile_folder {
+                clear_profile_folder(folder, self.config.clear_profile_folder)?;
+            }
             start_opened_group!("Running the benchmarks");
         }
 
-        for (run_part_index, part) in run_parts.into_iter().enumerate() {
-            let config = self
-                .config
-                .executor_config_for_command(part.command, !part.uses_exec_harness);
-            let mut executor = get_executor_from_mode(part.mode, self.config.walltime_profiler);
-            let profile_folder =
-                self.resolve_profile_folder(&executor.name(), run_part_index, total_parts)?;
-
-            let ctx = ExecutionContext::new(config, profile_folder);
-
-            let rolling_buffer_label =
-                (!self.config.show_full_output).then_some(part.label.as_str());
-
-            run_executor(
-                executor.as_mut(),
-                self,
-                &ctx,
-                setup_cache_dir,
-                rolling_buffer_label,
-            )
-            .await?;
+        let result_cache = match setup_cache_dir {
+            _ if !self.config.use_cache || self.config.skip_run => None,
+            Some(dir) => Some(ResultCache::new(dir)),
+            None => {
+                warn!("--use-cache requires a setup cache directory, running all the benchmarks");
+                None
+            }
+        };
 
-            all_completed_runs.push((ctx, executor.name()));
+        struct PreparedPart<'a> {
+            executor: Box<dyn Executor>,
+            ctx: ExecutionContext,
+            mode: &'a RunnerMode,
+            label: String,
+            cache_key: Option<String>,
+            restored_from_cache: bool,
         }
 
-        if !self.config.skip_run {
-            end_group!();
-        }
+        let mut profile_folders = vec![];
+        // Run in a block so that the profile folders are reported even when a part fails
+        let result: Result<()> = async {
+            let mut prepared_parts = vec![];
+            for (run_part_index, part) in run_parts.into_iter().enumerate() {
+                let config = ExecutorConfig {
+                    symbols_cache_dir: setup_cache_dir.map(|dir| dir.join(SYMBOLS_CACHE_DIR_NAME)),
+                    ..self
+                        .config
+                        .executor_config_for_command(part.command, !part.uses_exec_harness)
+                };
+                let cache_key = match &result_cache {
+                    Some(_) if part.uses_exec_harness => result_cache::cache_key(
+                        &exec_targets,
+                        part.mode,
+                        &config,
+                        self.config.walltime_profiler,
+                    ),
+                    _ => None,
+                };
+                let executor = get_executor_from_mode(
+                    part.mode,
+                    self.config.walltime_profiler,
+                    self.config.simulation_tool,
+                )?;
+                let profile_folder = resolve_profile_folder(
+                    self.config.profile_folder.as_deref(),
+                    &executor.name(),
+                    run_part_index,
+                    total_parts,
+                )?;
+
+                profile_folders.push((executor.name(), profile_folder.clone()));
+                let ctx = ExecutionContext::new(config, profile_folder);
+                prepared_parts.push(PreparedPart {
+                    executor,
+                    ctx,
+                    mode: part.mode,
+                    label: part.label,
+                    cache_key,
+                    restored_from_cache: false,
+                });
+            }
+            // The profile folders of a run share the same filesystem
+            if !self.config.skip_run {
+                if let Some((_, profile_folder)) = profile_folders.first() {
+                    ensure_free_space(profile_folder).with_failure_class(FailureClass::Setup)?;
+                }
+            }
 
-        self.upload_and_poll(all_completed_runs, api_client).await?;
+            // The run parts of a command are consecutive, one per mode
+            let parallel_group_size = if self.config.parallel_modes {
+                modes.len()
+            } else {
+                1
+            };
+            if let Some(cache) = &result_cache {
+                for part in &mut prepared_parts {
+                    let Some(key) = &part.cache_key else {
+                        continue;
+                    };
+                    match cache.restore(key, &part.ctx.profile_folder) {
+                        Ok(restored) => part.restored_from_cache = restored,
+                        Err(e) => warn!("{e:#}, running the benchmarks"),
+                    }
+                    if part.restored_from_cache {
+                        info!("Reusing the cached results of {}", part.label);
+                    }
+                }
+            }
 
-        Ok(())
-    }
+            for group in prepared_parts.chunks_mut(parallel_group_size) {
+                let mut group = group
+                    .iter_mut()
+                    .filter(|part| !part.restored_from_cache)
+                    .collect_vec();
+                match group.as_mut_slice() {
+                    [] => {}
+                    [part] => {
+                        let rolling_buffer_label =
+                            (!self.config.show_full_output).then_some(part.label.as_str());
+                        run_executor(
+                            part.executor.as_mut(),
+                            self,
+                            &part.ctx,
+                            setup_cache_dir,
+                            rolling_buffer_label,
+                        )
+                        .await?;
+                    }
+                    parts => {
+                        // The setups install the tools system-wide and may prompt for sudo, so
+                        // they run one after the other
+                        for part in parts.iter_mut() {
+                            setup_executor(
+                                part.executor.as_mut(),
+                                &self.system_info,
+                                &part.ctx,
+                                setup_cache_dir,
+                            )
+                            .await
+                            .with_failure_class(FailureClass::Setup)?;
+                        }
+                        if self.config.skip_run {
+                            debug!("Skipping the run of the benchmarks");
+                        } else {
+                            debug!("Running {} modes in parallel", parts.len());
+                            // The outputs of the executors would be interleaved in a rolling buffer
+                            futures::future::try_join_all(parts.iter_mut().map(|part| {
+                                run_and_teardown_executor(
+                                    part.executor.as_mut(),
+                                    self,
+                                    &part.ctx,
+                                    None,
+                                )
+                            }))
+                            .await
+                            .with_failure_class(FailureClass::Run)?;
+                        }
+                    }
+                }
 
-    /// Resolve the profile folder for a given run part.
-    ///
-    /// - Single run part + user-specified folder: use as-is
-    /// - Multiple run parts + user-specified folder: `<folder>/<executor>-<index>`
-    /// - No user-specified folder: create a random temp folder
-    fn resolve_profile_folder(
-        &self,
-        executor_name: &ExecutorName,
-        run_part_index: usize,
-        total_parts: usize,
-    ) -> Result<PathBuf> {
-        match (&self.config.profile_folder, total_parts) {
-            (Some(folder), 1) => Ok(folder.clone()),
-            (Some(folder), _) => {
-                let subfolder = folder.join(format!("{executor_name}-{run_part_index}"));
-                std::fs::create_dir_all(&subfolder).with_context(|| {
-                    format!(
-                        "Failed to create profile subfolder: {}",
-                        subfolder.display()
-                    )
-                })?;
-                Ok(subfolder)
+                if let Some(cache) = &result_cache {
+                    for part in &group {
+                        let Some(key) = &part.cache_key else {
+                            continue;
+                        };
+                        if let Err(e) = cache.store(
+                            key,
+                            part.mode,
+                            &part.ctx.config.command,
+                            &part.ctx.profile_folder,
+                        ) {
+                            warn!("Failed to cache the results of {}: {e:#}", part.label);
+                        }
+                    }
+                }
             }
-            (None, _) => create_profile_folder(),
+            all_completed_runs.extend(
+                prepared_parts
+                    .into_iter()
+                    .map(|part| (part.ctx, part.executor.name())),
+            );
+
+            if !self.config.skip_run {
+                end_group!();
+            }
+
+            self.upload_and_poll(all_completed_runs, api_client)
+                .await
+                .with_failure_class(FailureClass::Upload)
+        }
+        .await;
+
+        if self.config.keep_profile_folder {
+            report_profile_folders(&profile_folders);
+        }
+        // Summarize the warnings even when the run failed, they often explain the failure
+        if let Some(summary) = warnings_summary(&RUN_WARNINGS.take()) {
+            warn!("{summary}");
         }
+        result?;
+
+        if !self.config.skip_upload {
+            info!("Correlation ID: {correlation_id}");
+        }
+
+        Ok(())
     }
 
     /// Upload completed runs and poll results.
     async fn upload_and_poll(
         &self,
-        mut completed_runs: Vec<(ExecutionContext, ExecutorName)>,
-        api_client: &mut CodSpeedAPIClient,
+        completed_runs: Vec<(ExecutionContext, ExecutorName)>,
+        api_client: &CodSpeedAPIClient,
     ) -> Result<()> {
         let skip_upload = self.config.skip_upload;
 
         if !skip_upload {
             start_group!("Uploading results");
-            let last_upload_result = self.upload_all(&mut completed_runs, api_client).await?;
+            let last_upload_result = self.upload_all(&completed_runs, api_client).await?;
             end_group!();
 
-            if self.is_local() {
+            // The results are only shown locally, but `--fail-on-regression` needs them in CI too
+            if self.is_local() || self.config.poll_results_options.fail_on_regression {
                 poll_results(
                     api_client,
                     &last_upload_result,
@@ -224,6 +408,11 @@ impl Orchestrator {
             }
         } else {
             debug!("Skipping upload of performance data");
+            if self.config.poll_results_options.fail_on_regression {
+                warn!(
+                    "--fail-on-regression has no effect with --skip-upload, the results are not compared"
+                );
+            }
         }
 
         Ok(())
@@ -245,33 +434,57 @@ impl Orchestrator {
         suffix
     }
 
-    pub async fn upload_all(
+    async fn upload_part(
         &self,
-        completed_runs: &mut [(ExecutionContext, ExecutorName)],
-        api_client: &mut CodSpeedAPIClient,
+        mut api_client: CodSpeedAPIClient,
+        ctx: &ExecutionContext,
+        executor_name: &ExecutorName,
+        run_part_index: usize,
+        total_runs: usize,
     ) -> Result<UploadResult> {
-        let mut last_upload_result: Option<UploadResult> = None;
+        // OIDC tokens can expire quickly, so refresh just before each upload
+        self.provider.set_oidc_token(&mut api_client).await?;
 
+        if total_runs > 1 {
+            info!("Uploading results {}/{total_runs}", run_part_index + 1);
+        }
+        let run_part_suffix =
+            Self::build_run_part_suffix(executor_name, run_part_index, total_runs);
+        upload(
+            self,
+            &api_client,
+            ctx,
+            executor_name.clone(),
+            run_part_suffix,
+        )
+        .await
+    }
+
+    /// Upload the completed runs, `upload_concurrency` at a time.
+    pub async fn upload_all(
+        &self,
+        completed_runs: &[(ExecutionContext, ExecutorName)],
+        api_client: &CodSpeedAPIClient,
+    ) -> Result<UploadResult> {
         let total_runs = completed_runs.len();
-        for (run_part_index, (ctx, executor_name)) in completed_runs.iter_mut().enumerate() {
-            // OIDC tokens can expire quickly, so refresh just before each upload
-            self.provider.set_oidc_token(api_client).await?;
+        let uploads =
+            completed_runs
+                .iter()
+                .enumerate()
+                .map(|(run_part_index, (ctx, executor_name))| {
+                    // Each upload rotates the token of its own client
+                    self.upload_part(
+                        api_client.clone(),
+                        ctx,
+                        executor_name,
+                        run_part_index,
+                        total_runs,
+                    )
+                });
+        let last_upload_result = run_in_order(uploads, self.config.upload_concurrency)
+            .await?
+            .pop();
 
-            if total_runs > 1 {
-                info!("Uploading results {}/{total_runs}", run_part_index + 1);
-            }
-            let run_part_suffix =
-                Self::build_run_part_suffix(executor_name, run_part_index, total_runs);
-            let upload_result = upload(
-                self,
-                api_client,
-                ctx,
-                executor_name.clone(),
-                run_part_suffix,
-            )
-            .await?;
-            last_upload_result = Some(upload_result);
-        }
         info!("Performance data uploaded");
         if let Some(upload_result) = &last_upload_result {
             info!(
@@ -287,3 +500,379 @@ impl Orchestrator {
         last_upload_result.ok_or_else(|| anyhow::anyhow!("No completed runs to upload"))
     }
 }
+
+/// Run up to `concurrency` of the `tasks` at a time, returning their results in the order of the
+/// tasks rather than their completion order, so that the output stays deterministic.
+async fn run_in_order<T>(
+    tasks: impl IntoIterator<Item = impl Future<Output = Result<T>>>,
+    concurrency: usize,
+) -> Result<Vec<T>> {
+    futures::stream::iter(tasks)
+        .buffered(concurrency.max(1))
+        .try_collect()
+        .await
+}
+
+/// Resolve the profile folder for a given run part.
+///
+/// - Single run part + user-specified folder: use as-is
+/// - Multiple run parts + user-specified folder: `<folder>/<executor>-<index>`
+/// - No user-specified folder: create a random temp folder
+fn resolve_profile_folder(
+    user_folder: Option<&Path>,
+    executor_name: &ExecutorName,
+    run_part_index: usize,
+    total_parts: usize,
+) -> Result<PathBuf> {
+    match (user_folder, total_parts) {
+        (Some(folder), 1) => Ok(folder.to_path_buf()),
+        (Some(folder), _) => {
+            let subfolder = folder.join(format!("{executor_name}-{run_part_index}"));
+            std::fs::create_dir_all(&subfolder).with_context(|| {
+                format!(
+                    "Failed to create profile subfolder: {}",
+                    subfolder.display()
+                )
+            })?;
+            Ok(subfolder)
+        }
+        (None, _) => create_profile_folder(),
+    }
+}
+
+/// Log the absolute path of the profile folder of every run part.
+fn report_profile_folders(profile_folders: &[(ExecutorName, PathBuf)]) {
+    for (executor_name, folder) in profile_folders {
+        let folder = std::path::absolute(folder).unwrap_or_else(|_| folder.clone());
+        info!(
+            "Profile folder ({}): {}",
+            executor_name.label(),
+            folder.display()
+        );
+    }
+}
+
+/// Resolve the `auto` mode to the concrete mode suited to this machine.
+pub fn resolve_auto_mode(
+    walltime_profiler: Option<WalltimeProfiler>,
+    system_info: &SystemInfo,
+) -> Result<RunnerMode> {
+    let is_supported = |mode: RunnerMode| -> Result<bool> {
+        let executor = get_executor_from_mode(&mode, walltime_profiler, SimulationTool::default())?;
+        Ok(unsupported_reason(executor.as_ref(), system_info).is_none())
+    };
+    let environment = AutoModeEnvironment {
+        walltime_supported: is_supported(RunnerMode::Walltime)?,
+        simulation_supported: is_supported(RunnerMode::Simulation)?,
+        can_profile: profiling_sysctls_are_set() || can_elevate_without_prompt(),
+        is_ci: run_environment::is_ci(),
+    };
+    debug!("Resolving the auto mode for {environment:?}");
+
+    Ok(environment.resolve())
+}
+
+/// Replace the `auto` mode in the requested `modes` by the mode it resolves to on this machine.
+///
+/// Every entry point turning modes into executors goes through this first, since `auto` has no
+/// executor of its own.
+pub fn resolve_modes(
+    modes: &[RunnerMode],
+    walltime_profiler: Option<WalltimeProfiler>,
+    system_info: &SystemInfo,
+) -> Result<Vec<RunnerMode>> {
+    if !modes.contains(&RunnerMode::Auto) {
+        return Ok(modes.to_vec());
+    }
+
+    let resolved_mode = resolve_auto_mode(walltime_profiler, system_info)?;
+    info!("Using the {resolved_mode} mode, picked by the auto mode");
+    Ok(replace_auto_mode(modes, resolved_mode))
+}
+
+/// Replace `auto` by `resolved_mode` in `modes`, without duplicating a mode already requested.
+fn replace_auto_mode(modes: &[RunnerMode], resolved_mode: RunnerMode) -> Vec<RunnerMode> {
+    let mut replaced_modes: Vec<RunnerMode> = vec![];
+    for mode in modes {
+        let mode = if *mode == RunnerMode::Auto {
+            resolved_mode.clone()
+        } else {
+            mode.clone()
+        };
+        if !replaced_modes.contains(&mode) {
+            replaced_modes.push(mode);
+        }
+    }
+    replaced_modes
+}
+
+/// Keep the modes whose executor can run on this machine, warning about each skipped mode.
+///
+/// Fails if none of the modes can run.
+fn filter_supported_modes(
+    modes: &[RunnerMode],
+    walltime_profiler: Option<WalltimeProfiler>,
+    system_info: &SystemInfo,
+) -> Result<Vec<RunnerMode>> {
+    let mut supported_modes = vec![];
+    for mode in modes {
+        let executor = get_executor_from_mode(mode, walltime_profiler, SimulationTool::default())?;
+        match unsupported_reason(executor.as_ref(), system_info) {
+            Some(reason) => run_warning!("Skipping the {mode} mode: {reason}"),
+            None => supported_modes.push(mode.clone()),
+        }
+    }
+
+    if supported_modes.is_empty() {
+        bail!(
+            "None of the requested modes ({}) can run on this machine",
+            modes.iter().join(", ")
+        );
+    }
+
+    Ok(supported_modes)
+}
+
+/// Why the executor can't run on this machine, if it can't.
+fn unsupported_reason(executor: &dyn Executor, system_info: &SystemInfo) -> Option<String> {
+    match executor.support_level(system_info) {
+        ExecutorSupport::Unsupported => Some(format!(
+            "the {} executor is not supported on {}, it only runs on {}",
+            executor.name(),
+            system_info.os,
+            executor.name().supported_platforms()
+        )),
+        ExecutorSupport::RequiresManualInstallation => match executor.tool_status() {
+            Some(ToolStatus {
+                tool_name,
+                status: ToolInstallStatus::NotInstalled,
+            }) => Some(format!(
+                "{tool_name} is not installed and can't be installed automatically on {}",
+                system_info.os
+            )),
+            _ => None,
+        },
+        ExecutorSupport::FullySupported => None,
+    }
+}
+
+/// The CPU caveats of `modes`, once each: the modes sharing an executor share its caveat, e.g.
+/// simulation and instrumentation.
+fn cpu_caveats(modes: &[RunnerMode], system_info: &SystemInfo) -> Vec<String> {
+    modes
+        .iter()
+        .filter_map(|mode| cpu_caveat(mode, system_info))
+        .unique()
+        .collect()
+}
+
+/// Why the CPU of this machine may not be well-suited to `mode`, with an alternative.
+///
+/// Only a warning: the mode can still run, but some benchmarks may fail or be less accurate.
+fn cpu_caveat(mode: &RunnerMode, system_info: &SystemInfo) -> Option<String> {
+    let has_flag = |flag: &str| system_info.cpu_flags.iter().any(|f| f == flag);
+    let executor_name = get_executor_from_mode(mode, None, SimulationTool::default())
+        .ok()?
+        .name();
+
+    match (executor_name, system_info.arch.as_str()) {
+        // Valgrind can't decode these vector extensions, binaries using them crash under
+        // simulation with an illegal instruction
+        (ExecutorName::Valgrind, "x86_64") if has_flag("avx512f") => Some(format!(
+            "This {} CPU supports AVX-512, which valgrind can't simulate: benchmarks compiled \
+             for the host CPU (e.g. with `-C target-cpu=native` or `-march=native`) will crash \
+             under simulation. Target a baseline CPU, or use the walltime mode instead",
+            system_info.cpu_vendor_id
+        )),
+        (ExecutorName::Valgrind, "aarch64") if has_flag("sve") => Some(format!(
+            "This CPU supports SVE, which valgrind can't simulate: benchmarks compiled for the \
+             host CPU (e.g. with `-C target-cpu=native` or `-mcpu=native`) will crash under \
+             simulation. Target a baseline CPU, or use the walltime mode instead"
+        )),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::system::SupportedOs;
+
+    fn macos_system_info() -> SystemInfo {
+        SystemInfo {
+            os: SupportedOs::Macos {
+                version: "15.0".into(),
+            },
+            ..SystemInfo::test()
+        }
+    }
+
+    #[test]
+    fn test_replace_auto_mode_keeps_order_without_duplicates() {
+        assert_eq!(
+            replace_auto_mode(
+                &[RunnerMode::Auto, RunnerMode::Simulation],
+                RunnerMode::Walltime
+            ),
+            vec![RunnerMode::Walltime, RunnerMode::Simulation]
+        );
+        assert_eq!(
+            replace_auto_mode(
+                &[RunnerMode::Simulation, RunnerMode::Auto],
+                RunnerMode::Simulation
+            ),
+            vec![RunnerMode::Simulation]
+        );
+    }
+
+    #[test]
+    fn test_resolve_modes_keeps_concrete_modes() {
+        let modes = [RunnerMode::Simulation, RunnerMode::Walltime];
+        assert_eq!(
+            resolve_modes(&modes, None, &SystemInfo::test()).unwrap(),
+            modes
+        );
+    }
+
+    #[test]
+    fn test_auto_mode_has_no_executor() {
+        assert!(
+            get_executor_from_mode(&RunnerMode::Auto, None, SimulationTool::default()).is_err()
+        );
+    }
+
+    fn cpu_system_info(arch: &str, vendor_id: &str, flags: &[&str]) -> SystemInfo {
+        SystemInfo {
+            arch: arch.to_string(),
+            cpu_vendor_id: vendor_id.to_string(),
+            cpu_flags: flags.iter().map(|flag| flag.to_string()).collect(),
+            ..SystemInfo::test()
+        }
+    }
+
+    #[test]
+    fn test_cpu_caveat_intel_with_avx512() {
+        let system_info = cpu_system_info("x86_64", "GenuineIntel", &["avx2", "avx512f", "sse4_2"]);
+
+        let caveat = cpu_caveat(&RunnerMode::Simulation, &system_info).unwrap();
+        assert!(caveat.contains("GenuineIntel CPU supports AVX-512"));
+        assert!(caveat.contains("use the walltime mode instead"));
+        assert_eq!(cpu_caveat(&RunnerMode::Walltime, &system_info), None);
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn test_cpu_caveats_are_emitted_once_per_executor() {
+        let system_info = cpu_system_info("x86_64", "GenuineIntel", &["avx2", "avx512f", "sse4_2"]);
+
+        let caveats = cpu_caveats(
+            &[
+                RunnerMode::Simulation,
+                RunnerMode::Instrumentation,
+                RunnerMode::Walltime,
+            ],
+            &system_info,
+        );
+        assert_eq!(caveats.len(), 1);
+        assert!(cpu_caveats(&[RunnerMode::Walltime], &system_info).is_empty());
+    }
+
+    #[test]
+    fn test_cpu_caveat_amd_without_avx512() {
+        let system_info = cpu_system_info("x86_64", "AuthenticAMD", &["avx", "avx2", "sse4_2"]);
+
+        assert_eq!(cpu_caveat(&RunnerMode::Simulation, &system_info), None);
+        assert_eq!(cpu_caveat(&RunnerMode::Walltime, &system_info), None);
+    }
+
+    #[test]
+    fn test_cpu_caveat_arm_with_sve() {
+        let system_info = cpu_system_info("aarch64", "ARM", &["asimd", "fp", "sve"]);
+
+        let caveat = cpu_caveat(&RunnerMode::Simulation, &system_info).unwrap();
+        assert!(caveat.contains("supports SVE"));
+        assert_eq!(cpu_caveat(&RunnerMode::Walltime, &system_info), None);
+
+        let system_info = cpu_system_info("aarch64", "ARM", &["asimd", "fp"]);
+        assert_eq!(cpu_caveat(&RunnerMode::Simulation, &system_info), None);
+    }
+
+    #[test]
+    fn test_filter_supported_modes_skips_unsupported_mode() {
+        let modes = filter_supported_modes(
+            &[RunnerMode::Walltime, RunnerMode::Simulation],
+            None,
+            &macos_system_info(),
+        )
+        .unwrap();
+        assert_eq!(modes, vec![RunnerMode::Walltime]);
+    }
+
+    #[test]
+    fn test_filter_supported_modes_fails_without_viable_mode() {
+        let err = filter_supported_modes(&[RunnerMode::Simulation], None, &macos_system_info())
+            .unwrap_err();
+        assert!(err.to_string().contains("simulation"));
+    }
+
+    #[test]
+    fn test_unsupported_reason_points_to_the_supported_platforms() {
+        let executor =
+            get_executor_from_mode(&RunnerMode::Simulation, None, SimulationTool::default())
+                .unwrap();
+        let reason = unsupported_reason(executor.as_ref(), &macos_system_info()).unwrap();
+        assert_eq!(
+            reason,
+            "the valgrind executor is not supported on macos 15.0, it only runs on Linux"
+        );
+    }
+
+    #[test]
+    fn test_multi_mode_runs_use_predictable_subfolders() {
+        let folder = tempfile::tempdir().unwrap();
+        let simulation =
+            resolve_profile_folder(Some(folder.path()), &ExecutorName::Valgrind, 0, 2).unwrap();
+        let walltime =
+            resolve_profile_folder(Some(folder.path()), &ExecutorName::WallTime, 1, 2).unwrap();
+
+        assert_ne!(simulation, walltime);
+        assert_eq!(
+            simulation,
+            folder.path().join(format!("{}-0", ExecutorName::Valgrind))
+        );
+        assert_eq!(
+            walltime,
+            folder.path().join(format!("{}-1", ExecutorName::WallTime))
+        );
+        assert!(simulation.is_dir() && walltime.is_dir());
+
+        // A single run part uses the user's folder as-is
+        let single =
+            resolve_profile_folder(Some(folder.path()), &ExecutorName::WallTime, 0, 1).unwrap();
+        assert_eq!(single, folder.path());
+    }
+
+    #[tokio::test]
+    async fn test_run_in_order_keeps_the_order_of_the_tasks() {
+        let (sender, receiver) = futures::channel::oneshot::channel();
+        let completed = std::cell::RefCell::new(vec![]);
+
+        // The first upload only completes once the second one is issued
+        let first = async {
+            receiver.await?;
+            completed.borrow_mut().push("walltime");
+            Ok("walltime")
+        };
+        let second = async {
+            sender.send(()).unwrap();
+            completed.borrow_mut().push("memory");
+            Ok("memory")
+        };
+        let tasks: Vec<std::pin::Pin<Box<dyn Future<Output = Result<&str>>>>> =
+            vec![Box::pin(first), Box::pin(second)];
+
+        let results = run_in_order(tasks, 2).await.unwrap();
+        assert_eq!(results, vec!["walltime", "memory"]);
+        assert_eq!(*completed.borrow(), vec!["memory", "walltime"]);
+    }
+}
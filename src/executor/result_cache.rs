@@ -0,0 +1,589 @@
+//! Opt-in cache of the local results of the run parts, with `--use-cache`.
+//!
+//! The runner doesn't know which benchmarks a command runs, so the results are cached for a whole
+//! run part, keyed by a hash of its mode, command, options, the environment variables that can
+//! change the results and the content of the executables it runs and of the shared libraries
+//! they link. A reliable hash only exists for the exec targets of native executables: interpreters
+//! and shell commands read sources the runner can't track, so they always run.
+use super::config::WalltimeProfiler;
+use super::{BenchmarkTarget, ExecutorConfig};
+use crate::VERSION;
+use crate::prelude::*;
+use crate::runner_mode::RunnerMode;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+/// Directory of the cache, in the setup cache directory.
+pub const RESULT_CACHE_DIR_NAME: &str = "results-cache";
+const MANIFEST_FILE_NAME: &str = "manifest.json";
+
+/// Cached results older than this are evicted.
+const MAX_ENTRY_AGE: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+/// The oldest cached results are evicted beyond this total size.
+const MAX_CACHE_SIZE: u64 = 2 * 1024 * 1024 * 1024;
+
+/// Executables running code the runner can't hash.
+const INTERPRETERS: &[&str] = &[
+    "bash", "bun", "deno", "java", "node", "perl", "php", "python", "ruby", "sh", "uv",
+];
+
+/// Environment variables of the runner that can change the results of the benchmarks, which
+/// inherit them. A trailing `*` matches any suffix. The others are left out of the cache key, as
+/// many of them change from one shell or CI job to the other, e.g. `SHLVL` or `GITHUB_RUN_ID`.
+const RESULT_ENV_VARS: &[&str] = &[
+    "PATH",
+    "LD_*",
+    "DYLD_*",
+    "GLIBC_TUNABLES",
+    "MALLOC_*",
+    "RUSTFLAGS",
+    "RUST_MIN_STACK",
+    "RAYON_NUM_THREADS",
+    "TOKIO_WORKER_THREADS",
+    "OMP_*",
+    "GOGC",
+    "GOMAXPROCS",
+    "GODEBUG",
+    "NODE_OPTIONS",
+    "PYTHON*",
+    "JAVA_TOOL_OPTIONS",
+    "TZ",
+    "LANG",
+    "LC_*",
+];
+
+/// The cached run parts, by cache key.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Manifest {
+    entries: BTreeMap<String, ManifestEntry>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct ManifestEntry {
+    mode: RunnerMode,
+    command: String,
+    /// When the results were stored, in seconds since the Unix epoch
+    #[serde(default)]
+    stored_at: u64,
+    /// Size of the cached profile folder, in bytes
+    #[serde(default)]
+    size: u64,
+}
+
+/// `path` as seen from the working directory of the benchmarks, or from the current directory
+/// without one.
+fn in_working_directory(path: &Path, working_directory: Option<&Path>) -> PathBuf {
+    match working_directory {
+        Some(working_directory) => working_directory.join(path),
+        None => path.to_path_buf(),
+    }
+}
+
+/// Resolve `program` like a shell would, from the `PATH` unless it is a path.
+fn resolve_program(program: &str, working_directory: Option<&Path>) -> Option<PathBuf> {
+    if program.contains('/') {
+        return Some(in_working_directory(Path::new(program), working_directory));
+    }
+    std::env::split_paths(&std::env::var_os("PATH")?)
+        .map(|dir| dir.join(program))
+        .find(|path| path.is_file())
+}
+
+/// Whether `content` is an ELF or Mach-O executable.
+fn is_native_executable(content: &[u8]) -> bool {
+    const MAGICS: &[&[u8]] = &[
+        b"\x7fELF",
+        &[0xcf, 0xfa, 0xed, 0xfe],
+        &[0xce, 0xfa, 0xed, 0xfe],
+        &[0xca, 0xfe, 0xba, 0xbe],
+    ];
+    MAGICS.iter().any(|magic| content.starts_with(magic))
+}
+
+fn is_interpreter(path: &Path) -> bool {
+    let Some(name) = path.file_name().and_then(|name| name.to_str()) else {
+        return false;
+    };
+    INTERPRETERS.iter().any(|interpreter| {
+        name.strip_prefix(interpreter)
+            .is_some_and(|version| version.chars().all(|c| c.is_ascii_digit() || c == '.'))
+    })
+}
+
+/// The shared libraries `program` links, as resolved by `ldd`. `None` when they can't be resolved.
+#[cfg(target_os = "linux")]
+fn linked_libraries(program: &Path) -> Option<Vec<PathBuf>> {
+    let output = std::process::Command::new("ldd")
+        .arg(program)
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        // Static executables link no shared library
+        let is_static =
+            String::from_utf8_lossy(&output.stderr).contains("not a dynamic executable");
+        return is_static.then(Vec::new);
+    }
+
+    let mut libraries = vec![];
+    for line in String::from_utf8(output.stdout).ok()?.lines() {
+        if line.contains("not found") {
+            return None;
+        }
+        // `libc.so.6 => /lib/libc.so.6 (0x...)`, or `/lib64/ld-linux-x86-64.so.2 (0x...)`
+        let line = line.split_once(" => ").map_or(line, |(_, path)| path);
+        let path = line.split(" (").next().unwrap_or_default().trim();
+        // The vDSO has no file
+        if path.starts_with('/') {
+            libraries.push(PathBuf::from(path));
+        }
+    }
+    Some(libraries)
+}
+
+/// The linked libraries are only resolved on Linux, so the executables of the other systems are
+/// never cached.
+#[cfg(not(target_os = "linux"))]
+fn linked_libraries(_program: &Path) -> Option<Vec<PathBuf>> {
+    None
+}
+
+/// Hash the inputs of an exec target: its executable, the shared libraries it links and the
+/// files among its arguments, relative paths being resolved from `working_directory`. `None` when
+/// the executable is not a native one, as the code it runs can't be hashed.
+fn exec_target_hash(command: &[String], working_directory: Option<&Path>) -> Option<String> {
+    let (program, args) = command.split_first()?;
+    let program = resolve_program(program, working_directory)?;
+    if is_interpreter(&program) {
+        return None;
+    }
+    let content = std::fs::read(&program).ok()?;
+    if !is_native_executable(&content) {
+        return None;
+    }
+
+    let mut inputs = vec![format!(
+        "{}:{}",
+        program.display(),
+        sha256::digest(&*content)
+    )];
+    for library in linked_libraries(&program)? {
+        let content = std::fs::read(&library).ok()?;
+        inputs.push(format!(
+            "{}:{}",
+            library.display(),
+            sha256::digest(&*content)
+        ));
+    }
+    for arg in args {
+        let path = in_working_directory(Path::new(arg), working_directory);
+        if path.is_file() {
+            let content = std::fs::read(&path).ok()?;
+            inputs.push(format!("{arg}:{}", sha256::digest(&*content)));
+        }
+    }
+    Some(sha256::digest(inputs.join("\n")))
+}
+
+/// The options of the run part that change its results.
+fn config_inputs(config: &ExecutorConfig, walltime_profiler: Option<WalltimeProfiler>) -> String {
+    let ExecutorConfig {
+        working_directory,
+        instruments,
+        enable_profiler,
+        perf_unwinding_mode,
+        simulation_tool,
+        extra_env,
+        fair_sched,
+        cycle_estimation,
+        integration_mode_override,
+        valgrind_extra_args,
+        ignored_objects,
+        inline_frames,
+        raw,
+        strip_paths,
+        cpu,
+        ..
+    } = config;
+    let extra_env: BTreeMap<_, _> = extra_env.iter().collect();
+    format!(
+        "{working_directory:?} {instruments:?} {enable_profiler} {walltime_profiler:?} \
+         {perf_unwinding_mode:?} {simulation_tool:?} {extra_env:?} {fair_sched} {cycle_estimation} \
+         {integration_mode_override:?} {valgrind_extra_args:?} {ignored_objects:?} \
+         {inline_frames} {raw} {strip_paths} {cpu:?}"
+    )
+}
+
+fn is_result_env_var(name: &str) -> bool {
+    RESULT_ENV_VARS
+        .iter()
+        .any(|pattern| match pattern.strip_suffix('*') {
+            Some(prefix) => name.starts_with(prefix),
+            None => name == *pattern,
+        })
+}
+
+/// The environment variables of the runner that can change the results, see
+/// [`RESULT_ENV_VARS`]. The ones given with the options are part of [`config_inputs`].
+fn environment_inputs() -> String {
+    let env: BTreeMap<_, _> = std::env::vars_os()
+        .filter(|(name, _)| name.to_str().is_some_and(is_result_env_var))
+        .collect();
+    format!("{env:?}")
+}
+
+/// The cache key of the run part running `targets` in `mode` with `config`, or `None` when the
+/// inputs of a target can't be hashed reliably.
+pub fn cache_key(
+    targets: &[&BenchmarkTarget],
+    mode: &RunnerMode,
+    config: &ExecutorConfig,
+    walltime_profiler: Option<WalltimeProfiler>,
+) -> Option<String> {
+    if targets.is_empty() {
+        return None;
+    }
+    let mut inputs = vec![
+        VERSION.to_string(),
+        mode.to_string(),
+        config.command.clone(),
+        config_inputs(config, walltime_profiler),
+        environment_inputs(),
+    ];
+    for target in targets {
+        let BenchmarkTarget::Exec { command, .. } = target else {
+            return None;
+        };
+        inputs.push(exec_target_hash(
+            command,
+            config.working_directory.as_deref().map(Path::new),
+        )?);
+    }
+    Some(sha256::digest(inputs.join("\n")))
+}
+
+fn dir_size(dir: &Path) -> Result<u64> {
+    let mut size = 0;
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        let metadata = entry.metadata()?;
+        size += if metadata.is_dir() {
+            dir_size(&entry.path())?
+        } else {
+            metadata.len()
+        };
+    }
+    Ok(size)
+}
+
+fn copy_dir(from: &Path, to: &Path) -> Result<()> {
+    std::fs::create_dir_all(to)?;
+    for entry in std::fs::read_dir(from)? {
+        let entry = entry?;
+        let target = to.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            copy_dir(&entry.path(), &target)?;
+        } else {
+            std::fs::copy(entry.path(), &target)?;
+        }
+    }
+    Ok(())
+}
+
+/// The cached profile folders, with a manifest of the run parts they come from.
+pub struct ResultCache {
+    dir: PathBuf,
+}
+
+impl ResultCache {
+    pub fn new(setup_cache_dir: &Path) -> Self {
+        Self {
+            dir: setup_cache_dir.join(RESULT_CACHE_DIR_NAME),
+        }
+    }
+
+    fn manifest_path(&self) -> PathBuf {
+        self.dir.join(MANIFEST_FILE_NAME)
+    }
+
+    /// A missing or unreadable manifest is an empty cache.
+    fn manifest(&self) -> Manifest {
+        std::fs::read_to_string(self.manifest_path())
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default()
+    }
+
+    /// Copy the results cached for `key` to `profile_folder`. Returns whether they were cached.
+    pub fn restore(&self, key: &str, profile_folder: &Path) -> Result<bool> {
+        let entry_dir = self.dir.join(key);
+        if !self.manifest().entries.contains_key(key) || !entry_dir.is_dir() {
+            return Ok(false);
+        }
+        copy_dir(&entry_dir, profile_folder).with_context(|| {
+            format!(
+                "Failed to restore the cached results to {}",
+                profile_folder.display()
+            )
+        })?;
+        Ok(true)
+    }
+
+    /// Cache the results of `profile_folder` for `key`, evicting the results older than
+    /// [`MAX_ENTRY_AGE`] and then the oldest ones beyond [`MAX_CACHE_SIZE`].
+    pub fn store(
+        &self,
+        key: &str,
+        mode: &RunnerMode,
+        command: &str,
+        profile_folder: &Path,
+    ) -> Result<()> {
+        let entry_dir = self.dir.join(key);
+        if entry_dir.exists() {
+            std::fs::remove_dir_all(&entry_dir)?;
+        }
+        copy_dir(profile_folder, &entry_dir)?;
+
+        let now = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)?
+            .as_secs();
+        let mut manifest = self.manifest();
+        manifest.entries.insert(
+            key.to_string(),
+            ManifestEntry {
+                mode: mode.clone(),
+                command: command.to_string(),
+                stored_at: now,
+                size: dir_size(&entry_dir)?,
+            },
+        );
+        self.evict(&mut manifest, now, key)?;
+        std::fs::write(
+            self.manifest_path(),
+            serde_json::to_string_pretty(&manifest)?,
+        )?;
+        Ok(())
+    }
+
+    fn evict(&self, manifest: &mut Manifest, now: u64, stored_key: &str) -> Result<()> {
+        let mut entries = manifest
+            .entries
+            .iter()
+            .map(|(key, entry)| (entry.stored_at, entry.size, key.clone()))
+            .collect_vec();
+        // From the most recent results to the oldest ones
+        entries.sort_unstable_by(|a, b| b.cmp(a));
+
+        let mut total_size = 0;
+        for (stored_at, size, key) in entries {
+            total_size += size;
+            let expired = now.saturating_sub(stored_at) > MAX_ENTRY_AGE.as_secs();
+            // The results just stored are kept even when they exceed the size on their own
+            if expired || (total_size > MAX_CACHE_SIZE && key != stored_key) {
+                debug!("Evicting the cached results {key}");
+                manifest.entries.remove(&key);
+                let entry_dir = self.dir.join(&key);
+                if entry_dir.exists() {
+                    std::fs::remove_dir_all(&entry_dir)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn exec_target(command: &[&str]) -> BenchmarkTarget {
+        BenchmarkTarget::Exec {
+            command: command.iter().map(|arg| arg.to_string()).collect(),
+            name: None,
+            walltime_args: Default::default(),
+        }
+    }
+
+    fn config() -> ExecutorConfig {
+        ExecutorConfig {
+            command: "cmd".into(),
+            ..ExecutorConfig::test()
+        }
+    }
+
+    #[test]
+    fn test_cache_key_changes_with_the_inputs() {
+        let folder = tempfile::tempdir().unwrap();
+        let bench = folder.path().join("bench");
+        std::fs::write(&bench, b"\x7fELF version 1").unwrap();
+        let target = exec_target(&[bench.to_str().unwrap(), "--fast"]);
+        let key = |target: &BenchmarkTarget, config: &ExecutorConfig| {
+            cache_key(&[target], &RunnerMode::Walltime, config, None)
+        };
+
+        let first = key(&target, &config()).unwrap();
+        assert_eq!(key(&target, &config()).unwrap(), first);
+        assert_ne!(
+            cache_key(&[&target], &RunnerMode::Simulation, &config(), None).unwrap(),
+            first
+        );
+        assert_ne!(
+            cache_key(
+                &[&target],
+                &RunnerMode::Walltime,
+                &config(),
+                Some(WalltimeProfiler::Perf)
+            )
+            .unwrap(),
+            first
+        );
+        let pinned = ExecutorConfig {
+            cpu: Some(1),
+            ..config()
+        };
+        assert_ne!(key(&target, &pinned).unwrap(), first);
+        let with_env = ExecutorConfig {
+            extra_env: [("BENCH_SIZE".to_string(), "10".to_string())].into(),
+            ..config()
+        };
+        assert_ne!(key(&target, &with_env).unwrap(), first);
+        let from_env =
+            temp_env::with_var("MALLOC_ARENA_MAX", Some("2"), || key(&target, &config()));
+        assert_ne!(from_env.unwrap(), first);
+        let from_other_env = temp_env::with_var("SHLVL", Some("42"), || key(&target, &config()));
+        assert_eq!(from_other_env.unwrap(), first);
+
+        std::fs::write(&bench, b"\x7fELF version 2").unwrap();
+        assert_ne!(key(&target, &config()).unwrap(), first);
+    }
+
+    #[test]
+    fn test_relative_paths_are_resolved_from_the_working_directory() {
+        let folder = tempfile::tempdir().unwrap();
+        std::fs::write(folder.path().join("bench"), b"\x7fELF").unwrap();
+        let input = folder.path().join("input.txt");
+        std::fs::write(&input, "size=10").unwrap();
+        let target = exec_target(&["./bench", "input.txt"]);
+        let config = ExecutorConfig {
+            working_directory: Some(folder.path().to_string_lossy().into_owned()),
+            ..config()
+        };
+        let key = || cache_key(&[&target], &RunnerMode::Walltime, &config, None);
+
+        let first = key().unwrap();
+        std::fs::write(&input, "size=20").unwrap();
+        assert_ne!(key().unwrap(), first);
+    }
+
+    #[test]
+    fn test_unreliable_inputs_are_not_cached() {
+        let folder = tempfile::tempdir().unwrap();
+        let script = folder.path().join("bench.sh");
+        std::fs::write(&script, "#!/bin/sh\necho bench").unwrap();
+        let python = folder.path().join("python3.12");
+        std::fs::write(&python, b"\x7fELF").unwrap();
+
+        let mode = RunnerMode::Walltime;
+        let key = |target: &BenchmarkTarget| cache_key(&[target], &mode, &config(), None);
+        let script_target = exec_target(&[script.to_str().unwrap()]);
+        assert_eq!(key(&script_target), None);
+        let python_target = exec_target(&[python.to_str().unwrap(), "bench.py"]);
+        assert_eq!(key(&python_target), None);
+        let missing_target = exec_target(&["/nonexistent/bench"]);
+        assert_eq!(key(&missing_target), None);
+        let entrypoint = BenchmarkTarget::Entrypoint {
+            command: "cargo codspeed run".into(),
+            name: None,
+        };
+        assert_eq!(key(&entrypoint), None);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_linked_libraries() {
+        let libraries = linked_libraries(Path::new("/bin/sh")).unwrap();
+        assert!(
+            libraries
+                .iter()
+                .any(|library| library.to_string_lossy().contains("libc."))
+        );
+
+        let folder = tempfile::tempdir().unwrap();
+        let not_dynamic = folder.path().join("bench");
+        std::fs::write(&not_dynamic, b"\x7fELF").unwrap();
+        assert_eq!(linked_libraries(&not_dynamic), Some(vec![]));
+    }
+
+    #[test]
+    fn test_cache_hit_and_miss() {
+        let setup_cache_dir = tempfile::tempdir().unwrap();
+        let cache = ResultCache::new(setup_cache_dir.path());
+        let profile_folder = tempfile::tempdir().unwrap();
+        std::fs::create_dir(profile_folder.path().join("results")).unwrap();
+        std::fs::write(profile_folder.path().join("results/1.json"), "{}").unwrap();
+
+        let restored = tempfile::tempdir().unwrap();
+        assert!(!cache.restore("key", restored.path()).unwrap());
+
+        cache
+            .store("key", &RunnerMode::Walltime, "cmd", profile_folder.path())
+            .unwrap();
+        assert!(cache.restore("key", restored.path()).unwrap());
+        assert_eq!(
+            std::fs::read_to_string(restored.path().join("results/1.json")).unwrap(),
+            "{}"
+        );
+        assert!(!cache.restore("other-key", restored.path()).unwrap());
+    }
+
+    #[test]
+    fn test_old_and_oversized_results_are_evicted() {
+        let setup_cache_dir = tempfile::tempdir().unwrap();
+        let cache = ResultCache::new(setup_cache_dir.path());
+        let profile_folder = tempfile::tempdir().unwrap();
+        let mode = RunnerMode::Walltime;
+        cache
+            .store("old", &mode, "cmd", profile_folder.path())
+            .unwrap();
+        cache
+            .store("large", &mode, "cmd", profile_folder.path())
+            .unwrap();
+        cache
+            .store("recent", &mode, "cmd", profile_folder.path())
+            .unwrap();
+
+        let mut manifest = cache.manifest();
+        let now = manifest.entries["recent"].stored_at;
+        let entry = |stored_at, size| ManifestEntry {
+            mode: mode.clone(),
+            command: "cmd".into(),
+            stored_at,
+            size,
+        };
+        manifest
+            .entries
+            .insert("old".into(), entry(now - MAX_ENTRY_AGE.as_secs() - 1, 0));
+        manifest
+            .entries
+            .insert("large".into(), entry(now - 2, MAX_CACHE_SIZE));
+        manifest.entries.insert("recent".into(), entry(now - 1, 1));
+        cache.evict(&mut manifest, now, "recent").unwrap();
+
+        assert_eq!(manifest.entries.keys().collect_vec(), ["recent"]);
+        assert!(
+            !setup_cache_dir
+                .path()
+                .join(RESULT_CACHE_DIR_NAME)
+                .join("old")
+                .exists()
+        );
+        assert!(
+            !setup_cache_dir
+                .path()
+                .join(RESULT_CACHE_DIR_NAME)
+                .join("large")
+                .exists()
+        );
+    }
+}
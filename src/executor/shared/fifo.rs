@@ -1,13 +1,14 @@
 use crate::prelude::*;
 use anyhow::Context;
 use futures::StreamExt;
-use runner_shared::artifacts::ExecutionTimestamps;
+use runner_shared::artifacts::{ExecutionTimestamps, Region};
 use runner_shared::fifo::{Command as FifoCommand, MarkerType};
 use runner_shared::fifo::{RUNNER_ACK_FIFO, RUNNER_CTL_FIFO};
 use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
 use std::os::unix::fs::OpenOptionsExt;
 use std::path::{Path, PathBuf};
-use std::{collections::HashSet, time::Duration};
+use std::time::{Duration, Instant};
 use tokio::io::AsyncWriteExt;
 use tokio::net::unix::pid_t;
 use tokio::net::unix::pipe::Receiver as TokioPipeReader;
@@ -65,17 +66,114 @@ impl GenericFifo {
     }
 }
 
+/// Integration name of the raw runs, profiling a command without any integration.
+pub const RAW_INTEGRATION_NAME: &str = "codspeed-raw";
+
+/// Integration name of exec-harness, the harness running the benchmarks of `codspeed exec`.
+pub const EXEC_HARNESS_INTEGRATION_NAME: &str = "exec-harness";
+
+/// `name:version` of the integration used when none is set through the FIFO, e.g. by a wrapping
+/// tool or an integration not implementing the FIFO handshake yet.
+pub const INTEGRATION_ENV: &str = "CODSPEED_INTEGRATION";
+
+/// The integration set through [`INTEGRATION_ENV`], if any. Validated with the config, so that an
+/// invalid value doesn't waste a run.
+pub(crate) fn integration_from_env() -> anyhow::Result<Option<(String, String)>> {
+    let Ok(value) = std::env::var(INTEGRATION_ENV) else {
+        return Ok(None);
+    };
+    parse_integration(&value)
+        .with_context(|| format!("Invalid {INTEGRATION_ENV} value {value:?}"))
+        .map(Some)
+}
+
+/// Parse a `name:version` integration, e.g. `my-harness:1.2.0`.
+pub(crate) fn parse_integration(value: &str) -> anyhow::Result<(String, String)> {
+    let (name, version) = value
+        .split_once(':')
+        .context("Expected the `name:version` format")?;
+    let (name, version) = (name.trim(), version.trim());
+    ensure!(!name.is_empty(), "The integration name is empty");
+    ensure!(!version.is_empty(), "The integration version is empty");
+    Ok((name.to_string(), version.to_string()))
+}
+
 pub struct FifoBenchmarkData {
     /// Name and version of the integration
     pub integration: Option<(String, String)>,
     pub bench_pids: HashSet<pid_t>,
+    /// Profile every process of the run, for the benchmarks reported without their pids, e.g.
+    /// through a markers file
+    pub all_pids: bool,
 }
 
 impl FifoBenchmarkData {
+    /// The data an integration reported through the FIFO, validated. `integration` is a
+    /// `(name, version)` pair.
+    ///
+    /// The harness mode follows from the name: exec-harness
+    /// ([`EXEC_HARNESS_INTEGRATION_NAME`]) and the raw runs ([`RAW_INTEGRATION_NAME`]) profile
+    /// every process, other integrations only the `bench_pids` running their benchmarks, so they
+    /// need at least one.
+    pub fn new(
+        integration: (impl Into<String>, impl Into<String>),
+        bench_pids: impl IntoIterator<Item = pid_t>,
+    ) -> anyhow::Result<Self> {
+        let (name, version) = (integration.0.into(), integration.1.into());
+        ensure!(!name.trim().is_empty(), "The integration name is empty");
+        ensure!(
+            !version.trim().is_empty(),
+            "The integration version is empty"
+        );
+        let bench_pids: HashSet<pid_t> = bench_pids.into_iter().collect();
+        if let Some(pid) = bench_pids.iter().find(|pid| **pid <= 0) {
+            bail!("Invalid benchmark pid {pid}");
+        }
+
+        let data = Self {
+            integration: Some((name, version)),
+            bench_pids,
+            all_pids: false,
+        };
+        ensure!(
+            data.is_exec_harness() || data.is_raw() || !data.bench_pids.is_empty(),
+            "The integration {} reported no benchmark pid",
+            data.integration.as_ref().unwrap().0
+        );
+        Ok(data)
+    }
+
+    /// The reported data, validated with [`Self::new`]. Invalid data would waste the run, so it is
+    /// kept with a warning instead, without the invalid pids: every process is profiled when none
+    /// is left.
+    fn new_or_fallback(integration: (String, String), bench_pids: HashSet<pid_t>) -> Self {
+        match Self::new(integration.clone(), bench_pids.iter().copied()) {
+            Ok(data) => data,
+            Err(error) => {
+                run_warning!(
+                    "The integration reported invalid benchmark data, the profile may be incomplete: {error:#}"
+                );
+                let bench_pids: HashSet<pid_t> =
+                    bench_pids.into_iter().filter(|pid| *pid > 0).collect();
+                Self {
+                    integration: Some(integration),
+                    all_pids: bench_pids.is_empty(),
+                    bench_pids,
+                }
+            }
+        }
+    }
+
     pub fn is_exec_harness(&self) -> bool {
         self.integration
             .as_ref()
-            .is_some_and(|(name, _)| name == "exec-harness")
+            .is_some_and(|(name, _)| name == EXEC_HARNESS_INTEGRATION_NAME)
+    }
+
+    pub fn is_raw(&self) -> bool {
+        self.integration
+            .as_ref()
+            .is_some_and(|(name, _)| name == RAW_INTEGRATION_NAME)
     }
 }
 
@@ -163,30 +261,51 @@ impl RunnerFifo {
     pub async fn handle_fifo_messages(
         &mut self,
         child: &mut std::process::Child,
-        mut handle_cmd: impl AsyncFnMut(&FifoCommand) -> anyhow::Result<Option<FifoCommand>>,
+        handle_cmd: impl AsyncFnMut(&FifoCommand) -> anyhow::Result<Option<FifoCommand>>,
     ) -> anyhow::Result<(
         ExecutionTimestamps,
         FifoBenchmarkData,
         std::process::ExitStatus,
     )> {
-        let mut bench_order_by_timestamp = Vec::<(u64, String)>::new();
+        self.handle_fifo_messages_with(child, &mut CommandCallback(handle_cmd))
+            .await
+    }
+
+    /// Same as [`Self::handle_fifo_messages`], with a handler that is also called on a timer
+    /// while the child process runs, see [`FifoCommandHandler::on_tick`].
+    pub async fn handle_fifo_messages_with(
+        &mut self,
+        child: &mut std::process::Child,
+        handler: &mut impl FifoCommandHandler,
+    ) -> anyhow::Result<(
+        ExecutionTimestamps,
+        FifoBenchmarkData,
+        std::process::ExitStatus,
+    )> {
+        let mut bench_uris = BenchmarkUris::default();
         let mut bench_pids = HashSet::<pid_t>::new();
         let mut markers = Vec::<MarkerType>::new();
+        let mut regions = RegionTracker::default();
 
         let mut integration = None;
+        let fallback_integration = integration_from_env()?;
 
         // Must match the clock used by the benchmarked process so timestamps
         // from both sides are comparable.
         let get_current_time = instrument_hooks_bindings::InstrumentHooks::current_timestamp;
 
         let mut benchmark_started = false;
+        let mut last_tick = Instant::now();
 
         // Outer loop: continues until health check fails
         loop {
-            // Inner loop: process commands until timeout/error
+            // Inner loop: process commands until timeout/error, or until the next tick is due
             loop {
+                if last_tick.elapsed() >= TICK_INTERVAL {
+                    break;
+                }
                 let result: Result<_, Elapsed> =
-                    tokio::time::timeout(Duration::from_secs(1), self.recv_cmd()).await;
+                    tokio::time::timeout(TICK_INTERVAL, self.recv_cmd()).await;
                 let cmd = match result {
                     Ok(Ok(cmd)) => cmd,
                     Ok(Err(e)) => {
@@ -198,7 +317,7 @@ impl RunnerFifo {
                 trace!("Received command: {cmd:?}");
 
                 // Try executor-specific handler first
-                if let Some(response) = handle_cmd(&cmd).await? {
+                if let Some(response) = handler.handle_cmd(&cmd).await? {
                     self.send_cmd(response).await?;
                     continue;
                 }
@@ -206,7 +325,7 @@ impl RunnerFifo {
                 // Fall through to shared implementation for standard commands
                 match &cmd {
                     FifoCommand::CurrentBenchmark { pid, uri } => {
-                        bench_order_by_timestamp.push((get_current_time(), uri.to_string()));
+                        bench_uris.record(get_current_time(), uri);
                         bench_pids.insert(*pid);
                         self.send_cmd(FifoCommand::Ack).await?;
                     }
@@ -236,6 +355,14 @@ impl RunnerFifo {
                         markers.push(*marker);
                         self.send_cmd(FifoCommand::Ack).await?;
                     }
+                    FifoCommand::RegionStart(name) => {
+                        regions.start(name, get_current_time());
+                        self.send_cmd(FifoCommand::Ack).await?;
+                    }
+                    FifoCommand::RegionEnd(name) => {
+                        regions.end(name, get_current_time());
+                        self.send_cmd(FifoCommand::Ack).await?;
+                    }
                     FifoCommand::SetVersion(protocol_version) => {
                         match protocol_version.cmp(&runner_shared::fifo::CURRENT_PROTOCOL_VERSION) {
                             Ordering::Less => {
@@ -266,6 +393,9 @@ impl RunnerFifo {
                 }
             }
 
+            handler.on_tick().await?;
+            last_tick = Instant::now();
+
             // Check if the process has exited using try_wait (non-blocking)
             match child.try_wait() {
                 Ok(None) => {} // Still running, continue loop
@@ -273,11 +403,23 @@ impl RunnerFifo {
                     debug!(
                         "Process terminated with status: {exit_status}, stopping the command handler"
                     );
-                    let marker_result =
-                        ExecutionTimestamps::new(&bench_order_by_timestamp, &markers);
-                    let fifo_data = FifoBenchmarkData {
-                        integration,
-                        bench_pids,
+                    let marker_result = ExecutionTimestamps {
+                        regions: regions.finish(),
+                        ..ExecutionTimestamps::new(&bench_uris.finish(), &markers)
+                    };
+                    if integration.is_none() && fallback_integration.is_some() {
+                        debug!("No integration reported, using the one set by {INTEGRATION_ENV}");
+                    }
+                    // Without a reported benchmark, the run is empty and is reported as such later
+                    let fifo_data = match integration.or(fallback_integration) {
+                        Some(integration) if !bench_pids.is_empty() => {
+                            FifoBenchmarkData::new_or_fallback(integration, bench_pids)
+                        }
+                        integration => FifoBenchmarkData {
+                            integration,
+                            bench_pids,
+                            all_pids: false,
+                        },
                     };
                     return Ok((marker_result, fifo_data, exit_status));
                 }
@@ -287,6 +429,314 @@ impl RunnerFifo {
     }
 }
 
+/// Interval between two calls of [`FifoCommandHandler::on_tick`], and between two checks of the
+/// exit of the child process.
+const TICK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Executor-specific handling of the FIFO commands, see [`RunnerFifo::handle_fifo_messages`].
+pub trait FifoCommandHandler {
+    /// Handle `cmd`, returning the response to send, or `None` to fall through to the shared
+    /// implementation.
+    async fn handle_cmd(&mut self, cmd: &FifoCommand) -> anyhow::Result<Option<FifoCommand>>;
+
+    /// Called about every second while the child process runs, whether commands are received
+    /// or not.
+    async fn on_tick(&mut self) -> anyhow::Result<()> {
+        Ok(())
+    }
+}
+
+/// A [`FifoCommandHandler`] made of a callback handling the commands only.
+struct CommandCallback<F>(F);
+
+impl<F> FifoCommandHandler for CommandCallback<F>
+where
+    F: AsyncFnMut(&FifoCommand) -> anyhow::Result<Option<FifoCommand>>,
+{
+    async fn handle_cmd(&mut self, cmd: &FifoCommand) -> anyhow::Result<Option<FifoCommand>> {
+        (self.0)(cmd).await
+    }
+}
+
+/// Benchmark URIs longer than this are integration bugs, e.g. a whole parameter value dumped in
+/// the benchmark name.
+const MAX_BENCHMARK_URI_LENGTH: usize = 1024;
+
+/// Stands for the empty benchmark URIs, so their samples stay delimited.
+const EMPTY_BENCHMARK_URI_PLACEHOLDER: &str = "<unnamed benchmark>";
+
+/// Normalizes the benchmark URIs reported by the integration with `CurrentBenchmark`, keeping
+/// track of the anomalies to report them once at the end of the run.
+#[derive(Default)]
+struct BenchmarkUris {
+    uri_by_ts: Vec<(u64, String)>,
+    seen_uris: HashSet<String>,
+    empty_count: usize,
+    overlong_uris: Vec<String>,
+    duplicate_uris: Vec<String>,
+}
+
+impl BenchmarkUris {
+    /// Record the trimmed `uri`. An empty one is replaced by
+    /// [`EMPTY_BENCHMARK_URI_PLACEHOLDER`] and one longer than [`MAX_BENCHMARK_URI_LENGTH`] is
+    /// truncated, so that every timestamp keeps its entry.
+    fn record(&mut self, timestamp: u64, uri: &str) {
+        let mut uri = uri.trim();
+        if uri.is_empty() {
+            self.empty_count += 1;
+            self.uri_by_ts
+                .push((timestamp, EMPTY_BENCHMARK_URI_PLACEHOLDER.to_string()));
+            return;
+        }
+        if let Some((truncated_len, _)) = uri.char_indices().nth(MAX_BENCHMARK_URI_LENGTH) {
+            self.overlong_uris.push(uri.to_string());
+            uri = &uri[..truncated_len];
+        }
+        if !self.seen_uris.insert(uri.to_string()) && !self.duplicate_uris.iter().any(|d| d == uri)
+        {
+            self.duplicate_uris.push(uri.to_string());
+        }
+        self.uri_by_ts.push((timestamp, uri.to_string()));
+    }
+
+    /// The summary of the anomalies of the recorded URIs, if any.
+    fn anomalies(&self) -> Option<String> {
+        let mut anomalies = vec![];
+        if self.empty_count > 0 {
+            anomalies.push(format!(
+                "{} empty URIs were replaced by {EMPTY_BENCHMARK_URI_PLACEHOLDER}",
+                self.empty_count
+            ));
+        }
+        if !self.overlong_uris.is_empty() {
+            anomalies.push(format!(
+                "{} URIs longer than {MAX_BENCHMARK_URI_LENGTH} characters were truncated: {}",
+                self.overlong_uris.len(),
+                self.overlong_uris
+                    .iter()
+                    .map(|uri| format!("{}...", uri.chars().take(64).collect::<String>()))
+                    .join(", ")
+            ));
+        }
+        if !self.duplicate_uris.is_empty() {
+            anomalies.push(format!(
+                "{} URIs were reported more than once: {}",
+                self.duplicate_uris.len(),
+                self.duplicate_uris.join(", ")
+            ));
+        }
+        (!anomalies.is_empty()).then(|| anomalies.join("; "))
+    }
+
+    fn finish(self) -> Vec<(u64, String)> {
+        if let Some(anomalies) = self.anomalies() {
+            run_warning!(
+                "The integration reported invalid benchmark URIs, this is likely a bug of the integration: {anomalies}"
+            );
+        }
+        self.uri_by_ts
+    }
+}
+
+/// Pairs the `RegionStart` and `RegionEnd` commands into [`Region`]s.
+#[derive(Default)]
+struct RegionTracker {
+    /// Start timestamps of the regions not ended yet, by name. Regions with the same name can
+    /// be nested, the innermost one is ended first.
+    open_regions: HashMap<String, Vec<u64>>,
+    regions: Vec<Region>,
+}
+
+impl RegionTracker {
+    fn start(&mut self, name: &str, timestamp: u64) {
+        self.open_regions
+            .entry(name.to_string())
+            .or_default()
+            .push(timestamp);
+    }
+
+    fn end(&mut self, name: &str, timestamp: u64) {
+        let Some(start) = self
+            .open_regions
+            .get_mut(name)
+            .and_then(|starts| starts.pop())
+        else {
+            warn!("Received RegionEnd for region `{name}` before RegionStart, ignoring");
+            return;
+        };
+        self.regions.push(Region {
+            name: name.to_string(),
+            start,
+            end: timestamp,
+        });
+    }
+
+    /// The ended regions, sorted by start timestamp. Regions never ended are dropped.
+    fn finish(mut self) -> Vec<Region> {
+        let unended = self
+            .open_regions
+            .iter()
+            .filter(|(_, starts)| !starts.is_empty())
+            .map(|(name, _)| name.as_str())
+            .sorted()
+            .collect::<Vec<_>>();
+        if !unended.is_empty() {
+            warn!(
+                "Regions never ended by the integration, ignoring them: {}",
+                unended.join(", ")
+            );
+        }
+
+        self.regions.sort_by_key(|region| region.start);
+        self.regions
+    }
+}
+
+#[cfg(test)]
+mod timestamps_tests {
+    use super::*;
+    use runner_shared::artifacts::ArtifactExt;
+
+    #[test]
+    fn test_benchmark_data_constructor() {
+        let data = FifoBenchmarkData::new(("codspeed-rust", "4.0.0"), [42, 43]).unwrap();
+        assert_eq!(
+            data.integration,
+            Some(("codspeed-rust".to_string(), "4.0.0".to_string()))
+        );
+        assert_eq!(data.bench_pids, HashSet::from([42, 43]));
+        assert!(!data.is_exec_harness());
+
+        let harness = FifoBenchmarkData::new((EXEC_HARNESS_INTEGRATION_NAME, "1.0.0"), []).unwrap();
+        assert!(harness.is_exec_harness());
+        let raw = FifoBenchmarkData::new((RAW_INTEGRATION_NAME, "4.0.0"), []).unwrap();
+        assert!(raw.is_raw());
+
+        for (integration, pids) in [
+            (("", "1.0.0"), vec![42]),
+            (("codspeed-rust", " "), vec![42]),
+            (("codspeed-rust", "4.0.0"), vec![-1]),
+            (("codspeed-rust", "4.0.0"), vec![]),
+        ] {
+            assert!(FifoBenchmarkData::new(integration, pids).is_err());
+        }
+    }
+
+    #[test]
+    fn test_invalid_benchmark_data_falls_back() {
+        let integration = ("codspeed-rust".to_string(), "4.0.0".to_string());
+
+        let data = FifoBenchmarkData::new_or_fallback(integration.clone(), HashSet::from([42, -1]));
+        assert_eq!(data.integration.as_ref(), Some(&integration));
+        assert_eq!(data.bench_pids, HashSet::from([42]));
+        assert!(!data.all_pids);
+
+        // Without any valid pid left, every process is profiled
+        let data = FifoBenchmarkData::new_or_fallback(integration, HashSet::from([0]));
+        assert!(data.bench_pids.is_empty());
+        assert!(data.all_pids);
+    }
+
+    #[test]
+    fn test_regions_are_saved_in_the_execution_timestamps() {
+        let mut regions = RegionTracker::default();
+        regions.start("parse", 100);
+        regions.start("tokenize", 110);
+        regions.end("tokenize", 150);
+        regions.end("parse", 200);
+        // Ignored: ended before being started, and never ended
+        regions.end("setup", 210);
+        regions.start("teardown", 220);
+
+        let timestamps = ExecutionTimestamps {
+            regions: regions.finish(),
+            ..ExecutionTimestamps::new(&[(50, "bench::parse".to_string())], &[])
+        };
+        let folder = tempfile::tempdir().unwrap();
+        timestamps.save_to(folder.path()).unwrap();
+
+        let file =
+            std::fs::File::open(folder.path().join(ExecutionTimestamps::file_name(None))).unwrap();
+        let saved = ExecutionTimestamps::decode_from_reader(file).unwrap();
+        assert_eq!(
+            saved.regions,
+            vec![
+                Region {
+                    name: "parse".to_string(),
+                    start: 100,
+                    end: 200,
+                },
+                Region {
+                    name: "tokenize".to_string(),
+                    start: 110,
+                    end: 150,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_benchmark_uris_are_trimmed() {
+        let mut uris = BenchmarkUris::default();
+        uris.record(10, "  bench.py::test_a\n");
+        uris.record(20, "bench.py::test_b");
+
+        assert_eq!(uris.anomalies(), None);
+        assert_eq!(
+            uris.finish(),
+            vec![
+                (10, "bench.py::test_a".to_string()),
+                (20, "bench.py::test_b".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_invalid_benchmark_uris_are_reported() {
+        let overlong_uri = format!("bench.py::test_{}", "x".repeat(MAX_BENCHMARK_URI_LENGTH));
+        let mut uris = BenchmarkUris::default();
+        uris.record(10, "bench.py::test_a");
+        uris.record(20, "   ");
+        uris.record(30, &overlong_uri);
+        uris.record(40, "bench.py::test_a ");
+        uris.record(50, "bench.py::test_a");
+        uris.record(60, "");
+
+        assert_eq!(
+            uris.anomalies().unwrap(),
+            format!(
+                "2 empty URIs were replaced by {EMPTY_BENCHMARK_URI_PLACEHOLDER}; \
+                 1 URIs longer than {MAX_BENCHMARK_URI_LENGTH} characters were truncated: {}...; \
+                 1 URIs were reported more than once: bench.py::test_a",
+                &overlong_uri[..64]
+            )
+        );
+        // Every timestamp keeps its entry, and duplicates are kept, the backend deduplicates them
+        assert_eq!(
+            uris.finish(),
+            vec![
+                (10, "bench.py::test_a".to_string()),
+                (20, EMPTY_BENCHMARK_URI_PLACEHOLDER.to_string()),
+                (30, overlong_uri[..MAX_BENCHMARK_URI_LENGTH].to_string()),
+                (40, "bench.py::test_a".to_string()),
+                (50, "bench.py::test_a".to_string()),
+                (60, EMPTY_BENCHMARK_URI_PLACEHOLDER.to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_timestamps_without_regions_are_unchanged() {
+        let timestamps = ExecutionTimestamps::new(&[(50, "bench::parse".to_string())], &[]);
+        let mut encoded = Vec::new();
+        timestamps.encode_to_writer(&mut encoded).unwrap();
+
+        let decoded = ExecutionTimestamps::decode_from_reader(encoded.as_slice()).unwrap();
+        assert!(decoded.regions.is_empty());
+        assert_eq!(decoded.uri_by_ts, timestamps.uri_by_ts);
+    }
+}
+
 #[cfg(all(test, target_os = "linux"))]
 mod tests {
     use super::*;
@@ -323,4 +773,40 @@ mod tests {
             "recv_cmd should be cancel-safe: expected Ok(Ok(Ack)), got: {second:?}"
         );
     }
+
+    #[tokio::test]
+    async fn integration_falls_back_to_the_env() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let mut fifo = RunnerFifo::open(
+            &temp_dir.path().join("ctl_fifo"),
+            &temp_dir.path().join("ack_fifo"),
+        )
+        .unwrap();
+        // Exits without reporting any integration through the FIFO
+        let mut child = std::process::Command::new("true").spawn().unwrap();
+
+        let (_, fifo_data, _) =
+            temp_env::async_with_vars([(INTEGRATION_ENV, Some("my-harness:1.2.0"))], async {
+                fifo.handle_fifo_messages(&mut child, async |_: &FifoCommand| Ok(None))
+                    .await
+                    .unwrap()
+            })
+            .await;
+
+        assert_eq!(
+            fifo_data.integration,
+            Some(("my-harness".to_string(), "1.2.0".to_string()))
+        );
+    }
+
+    #[test]
+    fn parse_integration_requires_name_and_version() {
+        assert_eq!(
+            parse_integration("my-harness:1.2.0").unwrap(),
+            ("my-harness".to_string(), "1.2.0".to_string())
+        );
+        for invalid in ["my-harness", ":1.2.0", "my-harness:", ""] {
+            assert!(parse_integration(invalid).is_err(), "{invalid:?}");
+        }
+    }
 }
@@ -0,0 +1,320 @@
+//! Benchmark markers written to a file, for the integrations that can't implement the FIFO
+//! protocol.
+//!
+//! The file lists the benchmarks, in the order they ran, with the timestamps of the clock of
+//! `instrument-hooks` (`CLOCK_MONOTONIC`, in nanoseconds), and the `name:version` of the
+//! integration. The integration can also be set with `CODSPEED_INTEGRATION`, and the file can be
+//! a bare array of the benchmarks:
+//!
+//! ```json
+//! {
+//!   "integration": "my-harness:1.2.0",
+//!   "benchmarks": [
+//!     { "uri": "bench.py::test_parse", "start_ts": 1000, "stop_ts": 2000 },
+//!     { "uri": "bench.py::test_render", "start_ts": 2500, "stop_ts": 4000 }
+//!   ]
+//! }
+//! ```
+//!
+//! Without the FIFO, nothing enables the perf events around the benchmarks, so perf records the
+//! whole run when `CODSPEED_MARKERS_FILE` is set, and the samples of every process are kept. A
+//! `markers.json` in the profile folder is read too, but the runner can't tell before the run
+//! that the integration will write one: such integrations must set `CODSPEED_MARKERS_FILE`.
+use crate::executor::shared::fifo::{FifoBenchmarkData, INTEGRATION_ENV, parse_integration};
+use crate::prelude::*;
+use runner_shared::artifacts::ExecutionTimestamps;
+use runner_shared::fifo::MarkerType;
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+
+/// Name of the markers file looked up in the profile folder.
+pub const MARKERS_FILE_NAME: &str = "markers.json";
+/// Path of the markers file, to write it outside of the profile folder.
+pub const MARKERS_FILE_ENV: &str = "CODSPEED_MARKERS_FILE";
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct FileMarker {
+    uri: String,
+    start_ts: u64,
+    stop_ts: u64,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum MarkersFile {
+    WithIntegration {
+        integration: String,
+        benchmarks: Vec<FileMarker>,
+    },
+    Benchmarks(Vec<FileMarker>),
+}
+
+/// Whether `$CODSPEED_MARKERS_FILE` is set, in which case the integration doesn't use the FIFO
+/// and perf must record the whole run.
+pub fn markers_file_is_set() -> bool {
+    std::env::var_os(MARKERS_FILE_ENV).is_some()
+}
+
+/// Fill `timestamps` with the markers file when the integration reported no benchmark through the
+/// FIFO. The regions reported through the FIFO are kept.
+///
+/// The benchmarks of a markers file come without their pids, so every process is profiled. The
+/// integration is the one of the file, or else the one reported through the FIFO or set with
+/// [`INTEGRATION_ENV`].
+pub fn merge_markers_file(
+    fifo_data: FifoBenchmarkData,
+    timestamps: ExecutionTimestamps,
+    profile_folder: &Path,
+) -> Result<(FifoBenchmarkData, ExecutionTimestamps)> {
+    if !timestamps.uri_by_ts.is_empty() || !timestamps.markers.is_empty() {
+        return Ok((fifo_data, timestamps));
+    }
+    let Some((integration, from_file)) = load_markers_file(profile_folder)? else {
+        return Ok((fifo_data, timestamps));
+    };
+    let integration = integration.or(fifo_data.integration).with_context(|| {
+        format!(
+            "The markers file sets no integration, add an `integration` entry to it or set {INTEGRATION_ENV}"
+        )
+    })?;
+
+    let fifo_data = FifoBenchmarkData {
+        integration: Some(integration),
+        bench_pids: fifo_data.bench_pids,
+        all_pids: true,
+    };
+    let timestamps = ExecutionTimestamps {
+        regions: timestamps.regions,
+        ..from_file
+    };
+    Ok((fifo_data, timestamps))
+}
+
+/// Load the file at `$CODSPEED_MARKERS_FILE`, or else the `markers.json` of the profile folder if
+/// it exists, with the integration it sets if any.
+fn load_markers_file(
+    profile_folder: &Path,
+) -> Result<Option<(Option<(String, String)>, ExecutionTimestamps)>> {
+    let path = match std::env::var_os(MARKERS_FILE_ENV) {
+        Some(path) => PathBuf::from(path),
+        None => {
+            let path = profile_folder.join(MARKERS_FILE_NAME);
+            if !path.exists() {
+                return Ok(None);
+            }
+            path
+        }
+    };
+    debug!("Reading the benchmark markers from {}", path.display());
+
+    let content = std::fs::read_to_string(&path)
+        .with_context(|| format!("Failed to read the markers file {}", path.display()))?;
+    let (integration, markers) = match serde_json::from_str::<MarkersFile>(&content)
+        .with_context(|| format!("Invalid markers file {}", path.display()))?
+    {
+        MarkersFile::WithIntegration {
+            integration,
+            benchmarks,
+        } => {
+            let integration = parse_integration(&integration).with_context(|| {
+                format!(
+                    "Invalid integration {integration:?} in the markers file {}",
+                    path.display()
+                )
+            })?;
+            (Some(integration), benchmarks)
+        }
+        MarkersFile::Benchmarks(benchmarks) => (None, benchmarks),
+    };
+    let timestamps = timestamps_from_markers(&markers)
+        .with_context(|| format!("Invalid markers file {}", path.display()))?;
+    Ok(Some((integration, timestamps)))
+}
+
+/// Each benchmark becomes a sample, which must start after the previous one stopped.
+fn timestamps_from_markers(markers: &[FileMarker]) -> Result<ExecutionTimestamps> {
+    let mut uri_by_ts = Vec::with_capacity(markers.len());
+    let mut sample_markers = Vec::with_capacity(markers.len() * 2);
+    let mut previous: Option<&FileMarker> = None;
+
+    for marker in markers {
+        ensure!(
+            marker.start_ts < marker.stop_ts,
+            "Benchmark {} stops at {} before it starts at {}",
+            marker.uri,
+            marker.stop_ts,
+            marker.start_ts
+        );
+        if let Some(previous) = previous {
+            ensure!(
+                previous.stop_ts <= marker.start_ts,
+                "Benchmark {} starts at {} before the previous one, {}, stops at {}",
+                marker.uri,
+                marker.start_ts,
+                previous.uri,
+                previous.stop_ts
+            );
+        }
+        uri_by_ts.push((marker.start_ts, marker.uri.clone()));
+        sample_markers.push(MarkerType::SampleStart(marker.start_ts));
+        sample_markers.push(MarkerType::SampleEnd(marker.stop_ts));
+        previous = Some(marker);
+    }
+
+    Ok(ExecutionTimestamps::new(&uri_by_ts, &sample_markers))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use runner_shared::artifacts::Region;
+    use std::collections::HashSet;
+
+    fn reported_fifo_data(integration: Option<(&str, &str)>) -> FifoBenchmarkData {
+        FifoBenchmarkData {
+            integration: integration.map(|(name, version)| (name.into(), version.into())),
+            bench_pids: HashSet::new(),
+            all_pids: false,
+        }
+    }
+
+    const MARKERS: &str = r#"[
+        { "uri": "bench.py::test_parse", "start_ts": 1000, "stop_ts": 2000 },
+        { "uri": "bench.py::test_render", "start_ts": 2500, "stop_ts": 4000 }
+    ]"#;
+
+    #[test]
+    fn test_markers_file_fills_the_missing_fifo_markers() {
+        let folder = tempfile::tempdir().unwrap();
+        std::fs::write(folder.path().join(MARKERS_FILE_NAME), MARKERS).unwrap();
+        let region = Region {
+            name: "setup".to_string(),
+            start: 1100,
+            end: 1200,
+        };
+        let from_fifo = ExecutionTimestamps {
+            regions: vec![region.clone()],
+            ..ExecutionTimestamps::new(&[], &[])
+        };
+
+        let (fifo_data, timestamps) = temp_env::with_var_unset(MARKERS_FILE_ENV, || {
+            merge_markers_file(
+                reported_fifo_data(Some(("my-harness", "1.2.0"))),
+                from_fifo,
+                folder.path(),
+            )
+            .unwrap()
+        });
+
+        assert!(fifo_data.all_pids);
+        assert_eq!(
+            fifo_data.integration,
+            Some(("my-harness".to_string(), "1.2.0".to_string()))
+        );
+        assert_eq!(
+            timestamps.uri_by_ts,
+            vec![
+                (1000, "bench.py::test_parse".to_string()),
+                (2500, "bench.py::test_render".to_string()),
+            ]
+        );
+        assert_eq!(
+            timestamps.markers,
+            vec![
+                MarkerType::SampleStart(1000),
+                MarkerType::SampleEnd(2000),
+                MarkerType::SampleStart(2500),
+                MarkerType::SampleEnd(4000),
+            ]
+        );
+        assert_eq!(timestamps.regions, vec![region]);
+    }
+
+    #[test]
+    fn test_markers_file_path_from_env() {
+        let file = tempfile::NamedTempFile::new().unwrap();
+        std::fs::write(file.path(), MARKERS).unwrap();
+        let folder = tempfile::tempdir().unwrap();
+
+        let (_, timestamps) = temp_env::with_var(MARKERS_FILE_ENV, Some(file.path()), || {
+            merge_markers_file(
+                reported_fifo_data(Some(("my-harness", "1.2.0"))),
+                ExecutionTimestamps::new(&[], &[]),
+                folder.path(),
+            )
+            .unwrap()
+        });
+
+        assert_eq!(timestamps.uri_by_ts.len(), 2);
+    }
+
+    #[test]
+    fn test_markers_file_sets_the_integration() {
+        let folder = tempfile::tempdir().unwrap();
+        let markers =
+            format!(r#"{{ "integration": "my-harness:1.2.0", "benchmarks": {MARKERS} }}"#);
+        std::fs::write(folder.path().join(MARKERS_FILE_NAME), markers).unwrap();
+
+        let (fifo_data, timestamps) = temp_env::with_var_unset(MARKERS_FILE_ENV, || {
+            merge_markers_file(
+                reported_fifo_data(None),
+                ExecutionTimestamps::new(&[], &[]),
+                folder.path(),
+            )
+            .unwrap()
+        });
+        assert_eq!(
+            fifo_data.integration,
+            Some(("my-harness".to_string(), "1.2.0".to_string()))
+        );
+        assert_eq!(timestamps.uri_by_ts.len(), 2);
+
+        // Without any integration, the run can't be reported
+        std::fs::write(folder.path().join(MARKERS_FILE_NAME), MARKERS).unwrap();
+        let err = temp_env::with_var_unset(MARKERS_FILE_ENV, || {
+            merge_markers_file(
+                reported_fifo_data(None),
+                ExecutionTimestamps::new(&[], &[]),
+                folder.path(),
+            )
+            .unwrap_err()
+        });
+        assert!(err.to_string().contains("sets no integration"));
+    }
+
+    #[test]
+    fn test_fifo_markers_take_precedence() {
+        let folder = tempfile::tempdir().unwrap();
+        std::fs::write(folder.path().join(MARKERS_FILE_NAME), MARKERS).unwrap();
+        let from_fifo = ExecutionTimestamps::new(
+            &[(10, "bench::fifo".to_string())],
+            &[MarkerType::SampleStart(10), MarkerType::SampleEnd(20)],
+        );
+
+        let (fifo_data, timestamps) = temp_env::with_var_unset(MARKERS_FILE_ENV, || {
+            merge_markers_file(reported_fifo_data(None), from_fifo, folder.path()).unwrap()
+        });
+
+        assert!(!fifo_data.all_pids);
+        assert_eq!(timestamps.uri_by_ts, vec![(10, "bench::fifo".to_string())]);
+    }
+
+    #[test]
+    fn test_non_monotonic_markers_are_rejected() {
+        let overlapping = r#"[
+            { "uri": "bench::a", "start_ts": 1000, "stop_ts": 2000 },
+            { "uri": "bench::b", "start_ts": 1500, "stop_ts": 3000 }
+        ]"#;
+        let markers: Vec<FileMarker> = serde_json::from_str(overlapping).unwrap();
+        let err = timestamps_from_markers(&markers).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Benchmark bench::b starts at 1500 before the previous one, bench::a, stops at 2000"
+        );
+
+        let reversed = r#"[{ "uri": "bench::a", "start_ts": 2000, "stop_ts": 1000 }]"#;
+        let markers: Vec<FileMarker> = serde_json::from_str(reversed).unwrap();
+        assert!(timestamps_from_markers(&markers).is_err());
+    }
+}
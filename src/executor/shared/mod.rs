@@ -1 +1,2 @@
 pub mod fifo;
+pub mod markers_file;
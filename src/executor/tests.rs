@@ -148,6 +148,7 @@ fi
 #[cfg(target_os = "linux")]
 mod valgrind {
     use super::helpers::*;
+    use crate::executor::SimulationTool;
     use crate::executor::valgrind::executor::ValgrindExecutor;
 
     async fn get_valgrind_executor() -> (SemaphorePermit<'static>, ValgrindExecutor) {
@@ -155,14 +156,14 @@ mod valgrind {
 
         VALGRIND_SETUP
             .get_or_init(|| async {
-                let executor = ValgrindExecutor;
+                let executor = ValgrindExecutor::new(SimulationTool::Callgrind);
                 let system_info = SystemInfo::new().unwrap();
                 executor.setup(&system_info, None).await.unwrap();
             })
             .await;
         let _lock = acquire_bpf_instrumentation_lock().await;
 
-        (_lock, ValgrindExecutor)
+        (_lock, ValgrindExecutor::new(SimulationTool::Callgrind))
     }
 
     fn valgrind_config(command: &str) -> ExecutorConfig {
@@ -206,13 +207,23 @@ mod valgrind {
 }
 
 #[test_with::env(GITHUB_ACTIONS)]
-mod walltime {
+pub(crate) mod walltime {
     use super::helpers::*;
     use crate::executor::wall_time::executor::WallTimeExecutor;
 
+    static WALLTIME_SEMAPHORE: OnceCell<Semaphore> = OnceCell::const_new();
+
+    /// We can't execute multiple walltime executors in parallel because perf isn't thread-safe
+    /// (yet). The tests running perf, also outside of this module, hold this permit.
+    pub(crate) async fn acquire_walltime_lock() -> SemaphorePermit<'static> {
+        let semaphore = WALLTIME_SEMAPHORE
+            .get_or_init(|| async { Semaphore::new(1) })
+            .await;
+        semaphore.acquire().await.unwrap()
+    }
+
     async fn get_walltime_executor() -> (SemaphorePermit<'static>, WallTimeExecutor) {
         static WALLTIME_INIT: OnceCell<()> = OnceCell::const_new();
-        static WALLTIME_SEMAPHORE: OnceCell<Semaphore> = OnceCell::const_new();
 
         WALLTIME_INIT
             .get_or_init(|| async {
@@ -222,12 +233,7 @@ mod walltime {
             })
             .await;
 
-        // We can't execute multiple walltime executors in parallel because perf isn't thread-safe (yet). We have to
-        // use a semaphore to limit concurrent access.
-        let semaphore = WALLTIME_SEMAPHORE
-            .get_or_init(|| async { Semaphore::new(1) })
-            .await;
-        let permit = semaphore.acquire().await.unwrap();
+        let permit = acquire_walltime_lock().await;
 
         (permit, WallTimeExecutor::new(None))
     }
@@ -312,6 +318,37 @@ fi
         .await;
     }
 
+    // Ensure that raw runs profile the command without any integration
+    #[test_log::test(tokio::test)]
+    async fn test_walltime_executor_raw() {
+        use runner_shared::metadata::WalltimeMetadata;
+
+        let (_permit, mut executor) = get_walltime_executor().await;
+
+        let config = ExecutorConfig {
+            raw: true,
+            ..walltime_config(
+                "sh -c 'i=0; while [ $i -lt 100000 ]; do i=$((i+1)); done'",
+                true,
+            )
+        };
+        // Unset GITHUB_ACTIONS to force LocalProvider which supports repository_override
+        temp_env::async_with_vars(&[("GITHUB_ACTIONS", None::<&str>)], async {
+            let (execution_context, _temp_dir) = create_test_setup(config).await;
+            executor.run(&execution_context, &None).await.unwrap();
+            executor.teardown(&execution_context).await.unwrap();
+
+            let profile_folder = &execution_context.profile_folder;
+            let perf_file = profile_folder.join(crate::executor::PERF_PIPEDATA_FILE_NAME);
+            assert!(std::fs::metadata(perf_file).unwrap().len() > 0);
+
+            let metadata = WalltimeMetadata::load_from(profile_folder).unwrap();
+            assert_eq!(metadata.integration.0, "codspeed-raw");
+            assert_eq!(metadata.uri_by_ts.len(), 1);
+        })
+        .await;
+    }
+
     // Ensure that commands that fail actually fail
     #[rstest::rstest]
     #[test_log::test(tokio::test)]
@@ -377,6 +414,76 @@ fi
         })
         .await;
     }
+
+    /// A benchmark spinning for a few hundred milliseconds, long enough to be sampled
+    #[cfg(target_os = "macos")]
+    const BUSY_LOOP_SOURCE: &str = r#"
+int main(void) {
+    for (volatile unsigned long i = 0; i < 200000000; i++) {}
+    return 0;
+}
+"#;
+
+    // Ensure that samply records the benchmark markers and samples on macOS
+    #[cfg(target_os = "macos")]
+    #[test_log::test(tokio::test)]
+    async fn test_samply_profile_has_markers_and_samples() {
+        use exec_harness::walltime::WalltimeExecutionArgs;
+        use runner_shared::metadata::WalltimeMetadata;
+        use std::process::Command;
+
+        let (_permit, mut executor) = get_walltime_executor().await;
+
+        // System binaries are signed in a way that prevents profiling them, benchmark a binary
+        // built locally instead
+        let build_dir = TempDir::new().unwrap();
+        let source = build_dir.path().join("busy_loop.c");
+        std::fs::write(&source, BUSY_LOOP_SOURCE).unwrap();
+        let binary = build_dir.path().join("busy_loop");
+        let status = Command::new("cc")
+            .args(["-O0", "-o"])
+            .arg(&binary)
+            .arg(&source)
+            .status()
+            .unwrap();
+        assert!(status.success(), "failed to compile the benchmark");
+
+        let walltime_args = WalltimeExecutionArgs {
+            warmup_time: Some("0s".to_string()),
+            max_time: None,
+            min_time: None,
+            max_rounds: Some(3),
+            min_rounds: None,
+        };
+        let wrapped_command =
+            wrap_with_exec_harness(&walltime_args, &[binary.to_string_lossy().into_owned()]);
+
+        // Unset GITHUB_ACTIONS to force LocalProvider which supports repository_override
+        temp_env::async_with_vars(&[("GITHUB_ACTIONS", None::<&str>)], async {
+            let config = walltime_config(&wrapped_command, true);
+            let (execution_context, _temp_dir) = create_test_setup(config).await;
+            executor.run(&execution_context, &None).await.unwrap();
+            executor.teardown(&execution_context).await.unwrap();
+
+            let profile_folder = &execution_context.profile_folder;
+            let metadata = WalltimeMetadata::load_from(profile_folder).unwrap();
+            assert!(!metadata.markers.is_empty(), "no benchmark markers");
+
+            let profile_file =
+                std::fs::File::open(profile_folder.join("samply-profile.json.gz")).unwrap();
+            let profile: serde_json::Value =
+                serde_json::from_reader(flate2::read::GzDecoder::new(profile_file)).unwrap();
+            let sample_count: usize = profile["threads"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .filter_map(|thread| thread["samples"]["stack"].as_array())
+                .map(Vec::len)
+                .sum();
+            assert!(sample_count > 0, "no samples in the samply profile");
+        })
+        .await;
+    }
 }
 
 #[cfg(target_os = "linux")]
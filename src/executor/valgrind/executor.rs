@@ -3,17 +3,29 @@ use std::path::Path;
 
 use crate::executor::Executor;
 use crate::executor::ToolStatus;
+use crate::executor::config::SimulationTool;
+use crate::executor::helpers::harvest_perf_maps_for_pids::remove_harvested_tmp_files;
 use crate::executor::{ExecutionContext, ExecutorName, ExecutorSupport};
 use crate::instruments::mongo_tracer::MongoTracer;
 use crate::prelude::*;
 use crate::system::{SupportedOs, SystemInfo};
 
+use super::helpers::profile_files::validate_profile_files;
+use super::setup::ensure_valgrind_is_usable;
 use super::setup::get_valgrind_status;
 use super::setup::install_valgrind;
 use super::setup::is_codspeed_valgrind_installation_supported;
 use super::{helpers::perf_maps::harvest_perf_maps, helpers::venv_compat, measure};
 
-pub struct ValgrindExecutor;
+pub struct ValgrindExecutor {
+    simulation_tool: SimulationTool,
+}
+
+impl ValgrindExecutor {
+    pub fn new(simulation_tool: SimulationTool) -> Self {
+        Self { simulation_tool }
+    }
+}
 
 #[async_trait(?Send)]
 impl Executor for ValgrindExecutor {
@@ -40,6 +52,8 @@ impl Executor for ValgrindExecutor {
 
     async fn setup(&self, system_info: &SystemInfo, setup_cache_dir: Option<&Path>) -> Result<()> {
         install_valgrind(system_info, setup_cache_dir).await?;
+        // Fail here rather than when spawning valgrind if the installation is still unusable
+        ensure_valgrind_is_usable(system_info, self.simulation_tool)?;
 
         if let Err(error) = venv_compat::symlink_libpython(None) {
             warn!("Failed to symlink libpython");
@@ -66,7 +80,14 @@ impl Executor for ValgrindExecutor {
     }
 
     async fn teardown(&self, execution_context: &ExecutionContext) -> Result<()> {
-        harvest_perf_maps(&execution_context.profile_folder).await?;
+        validate_profile_files(
+            &execution_context.profile_folder,
+            execution_context.config.simulation_tool,
+        )?;
+        let perf_maps = harvest_perf_maps(&execution_context.profile_folder).await?;
+        if execution_context.config.clean_tmp_maps {
+            remove_harvested_tmp_files(&perf_maps, execution_context.created_at);
+        }
 
         // No matter the command in input, at this point valgrind will have been run and have produced output files.
         //
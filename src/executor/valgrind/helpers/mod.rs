@@ -1,4 +1,5 @@
 pub mod ignored_objects_path;
 pub mod perf_maps;
+pub mod profile_files;
 pub mod python;
 pub mod venv_compat;
@@ -2,7 +2,7 @@ use crate::executor::helpers::harvest_perf_maps_for_pids::harvest_perf_maps_for_
 use crate::prelude::*;
 use std::collections::HashSet;
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 /// Extracts a PID from a profile output file path.
 ///
@@ -15,7 +15,9 @@ fn extract_pid_from_profile_file(path: &Path) -> Option<libc::pid_t> {
     }
 }
 
-pub async fn harvest_perf_maps(profile_folder: &Path) -> Result<()> {
+/// Harvest the perf maps of the processes profiled by valgrind, returning the paths of the
+/// harvested files.
+pub async fn harvest_perf_maps(profile_folder: &Path) -> Result<Vec<PathBuf>> {
     let pids = fs::read_dir(profile_folder)?
         .filter_map(|entry| entry.ok())
         .map(|entry| entry.path())
@@ -0,0 +1,139 @@
+use crate::executor::config::SimulationTool;
+use crate::prelude::*;
+use std::fs::{self, File};
+use std::io::{BufRead, BufReader, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+/// Size of the end of a callgrind file read to find its closing `totals:` line.
+const CALLGRIND_TAIL_SIZE: u64 = 4096;
+
+fn profile_file_extension(tool: SimulationTool) -> &'static str {
+    match tool {
+        SimulationTool::Callgrind => "out",
+        SimulationTool::Tracegrind => "tgtrace",
+    }
+}
+
+/// Check that every profile file valgrind wrote in `profile_folder` is complete.
+///
+/// A valgrind process killed mid-run (e.g. by the OOM killer) leaves an empty or
+/// truncated file behind, which would otherwise be uploaded as a run without results.
+pub fn validate_profile_files(profile_folder: &Path, tool: SimulationTool) -> Result<()> {
+    let extension = profile_file_extension(tool);
+    let files: Vec<PathBuf> = fs::read_dir(profile_folder)?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        // Profile files are named `<pid>.<extension>`
+        .filter(|path| {
+            path.extension().is_some_and(|ext| ext == extension)
+                && path
+                    .file_stem()
+                    .and_then(|stem| stem.to_str())
+                    .is_some_and(|stem| stem.parse::<libc::pid_t>().is_ok())
+        })
+        .collect();
+
+    for file in &files {
+        validate_profile_file(file, tool).with_context(|| {
+            format!(
+                "Valgrind produced an incomplete profile file {}. \
+                The benchmark process may have been killed, e.g. by running out of memory",
+                file.display()
+            )
+        })?;
+    }
+    debug!("Validated {} profile file(s)", files.len());
+
+    Ok(())
+}
+
+fn validate_profile_file(path: &Path, tool: SimulationTool) -> Result<()> {
+    let len = fs::metadata(path)?.len();
+    ensure!(len > 0, "the file is empty");
+
+    match tool {
+        SimulationTool::Callgrind => validate_callgrind_file(path, len),
+        // The tracegrind format has no header we can check beyond its presence
+        SimulationTool::Tracegrind => Ok(()),
+    }
+}
+
+/// Callgrind files start with a format header and end each part with a `totals:` line.
+fn validate_callgrind_file(path: &Path, len: u64) -> Result<()> {
+    let mut file = File::open(path)?;
+
+    let mut first_line = String::new();
+    BufReader::new(&mut file).read_line(&mut first_line)?;
+    let first_line = first_line.trim();
+    ensure!(
+        first_line.starts_with("# callgrind format") || first_line.starts_with("version:"),
+        "missing callgrind header, found {first_line:?}"
+    );
+
+    file.seek(SeekFrom::Start(len.saturating_sub(CALLGRIND_TAIL_SIZE)))?;
+    let mut tail = Vec::new();
+    file.read_to_end(&mut tail)?;
+    let tail = String::from_utf8_lossy(&tail);
+    let last_line = tail.lines().rev().find(|line| !line.trim().is_empty());
+    ensure!(
+        last_line.is_some_and(|line| line.starts_with("totals:")),
+        "the file is truncated, its last line is not `totals:`"
+    );
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const COMPLETE_CALLGRIND: &str = "# callgrind format\n\
+        version: 1\n\
+        creator: callgrind-3.25.1.codspeed\n\
+        pid: 1234\n\
+        cmd: python bench.py\n\
+        part: 1\n\
+        \n\
+        positions: line\n\
+        events: Ir\n\
+        summary: 42\n\
+        \n\
+        fl=(1) bench.py\n\
+        fn=(1) main\n\
+        1 42\n\
+        \n\
+        totals: 42\n";
+
+    #[test]
+    fn test_complete_callgrind_file_is_valid() {
+        let folder = tempfile::tempdir().unwrap();
+        fs::write(folder.path().join("1234.out"), COMPLETE_CALLGRIND).unwrap();
+        // Files of other tools and logs are ignored
+        fs::write(folder.path().join("valgrind.1234.log"), "").unwrap();
+
+        validate_profile_files(folder.path(), SimulationTool::Callgrind).unwrap();
+    }
+
+    #[test]
+    fn test_truncated_callgrind_file_is_rejected() {
+        let folder = tempfile::tempdir().unwrap();
+        let truncated = &COMPLETE_CALLGRIND[..COMPLETE_CALLGRIND.find("1 42").unwrap()];
+        fs::write(folder.path().join("1234.out"), truncated).unwrap();
+
+        let err = validate_profile_files(folder.path(), SimulationTool::Callgrind).unwrap_err();
+        assert!(err.to_string().contains("1234.out"));
+        assert!(format!("{err:#}").contains("truncated"));
+    }
+
+    #[test]
+    fn test_empty_profile_files_are_rejected() {
+        let folder = tempfile::tempdir().unwrap();
+        fs::write(folder.path().join("1234.tgtrace"), "").unwrap();
+
+        let err = validate_profile_files(folder.path(), SimulationTool::Tracegrind).unwrap_err();
+        assert!(format!("{err:#}").contains("empty"));
+
+        fs::write(folder.path().join("1234.tgtrace"), [0u8, 1, 2]).unwrap();
+        validate_profile_files(folder.path(), SimulationTool::Tracegrind).unwrap();
+    }
+}
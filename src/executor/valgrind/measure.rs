@@ -3,7 +3,9 @@ use crate::executor::RunnerMode;
 use crate::executor::config::SimulationTool;
 use crate::executor::helpers::env::{build_path_env, get_base_injected_env};
 use crate::executor::helpers::get_bench_command::get_bench_command;
-use crate::executor::helpers::run_command_with_log_pipe::run_command_with_log_pipe;
+use crate::executor::helpers::run_command_with_log_pipe::{
+    BenchmarkTimeoutError, describe_exit_code, describe_exit_status, run_command_with_log_pipe,
+};
 use crate::executor::valgrind::helpers::ignored_objects_path::get_objects_path_to_ignore;
 use crate::executor::valgrind::helpers::python::is_free_threaded_python;
 use crate::instruments::mongo_tracer::MongoTracer;
@@ -17,6 +19,37 @@ use std::path::Path;
 use std::{env::consts::ARCH, process::Command};
 use tempfile::TempPath;
 
+/// Valgrind options that CodSpeed relies on, which `--valgrind-arg` can't override.
+const RESERVED_VALGRIND_OPTIONS: &[&str] = &[
+    "--tool",
+    "--callgrind-out-file",
+    "--tracegrind-out-file",
+    "--log-file",
+    "--log-fd",
+    "--log-socket",
+    "--xml",
+    "--trace-children",
+    "--instr-atstart",
+];
+
+/// Reject the user-provided valgrind arguments that would break the profile collection.
+pub fn validate_valgrind_extra_args(args: &[String]) -> Result<()> {
+    for arg in args {
+        ensure!(
+            arg.starts_with('-'),
+            "Invalid valgrind argument `{arg}`: only options starting with `-` are supported"
+        );
+        let option = arg
+            .split_once('=')
+            .map_or(arg.as_str(), |(option, _)| option);
+        ensure!(
+            !RESERVED_VALGRIND_OPTIONS.contains(&option),
+            "The valgrind option `{option}` is managed by CodSpeed and can't be overridden"
+        );
+    }
+    Ok(())
+}
+
 /// Builds the Valgrind argument list for the given simulation tool.
 fn get_valgrind_args(tool: &SimulationTool, config: &ExecutorConfig) -> Vec<String> {
     let mut args: Vec<String> = [
@@ -61,6 +94,9 @@ fn get_valgrind_args(tool: &SimulationTool, config: &ExecutorConfig) -> Vec<Stri
         args.push("--fair-sched=yes".to_string());
     }
 
+    // Last, so that they take precedence over the defaults above
+    args.extend(config.valgrind_extra_args.iter().cloned());
+
     args
 }
 
@@ -130,7 +166,7 @@ pub async fn measure(
         RunnerMode::Simulation,
         profile_folder,
         config,
-    ));
+    )?);
 
     // Only set PYTHONMALLOC=malloc for non-free-threaded Python builds.
     // Free-threaded Python (with GIL disabled) manages memory differently and
@@ -190,9 +226,18 @@ pub async fn measure(
     }
 
     debug!("cmd: {cmd:?}");
-    let status = run_command_with_log_pipe(cmd)
-        .await
-        .map_err(|e| anyhow!("failed to execute the benchmark process. {e}"))?;
+    let status = run_command_with_log_pipe(
+        cmd,
+        config.capture_output.then_some(profile_folder),
+        config.benchmark_timeout,
+    )
+    .await
+    .map_err(|e| {
+        if e.is::<BenchmarkTimeoutError>() {
+            return e;
+        }
+        anyhow!("failed to execute the benchmark process. {e}")
+    })?;
     debug!(
         "Valgrind exit code = {:?}, Valgrind signal = {:?}",
         status.code(),
@@ -202,7 +247,10 @@ pub async fn measure(
     // Check the valgrind exit code
     if !status.success() {
         dump_valgrind_logs(profile_folder);
-        bail!("failed to execute valgrind");
+        bail!(
+            "failed to execute valgrind: {}",
+            describe_exit_status(&status)
+        );
     }
 
     // Check the exit code which was written to the file by the wrapper script.
@@ -214,7 +262,10 @@ pub async fn measure(
     };
     debug!("Program exit code = {cmd_status}");
     if cmd_status != 0 {
-        bail!("failed to execute the benchmark process, exit code: {cmd_status}");
+        bail!(
+            "failed to execute the benchmark process: {}",
+            describe_exit_code(cmd_status as i32)
+        );
     }
 
     Ok(())
@@ -240,6 +291,33 @@ mod tests {
         (script_status, out_status)
     }
 
+    #[test]
+    fn test_valgrind_extra_args_are_forwarded() {
+        let config = ExecutorConfig {
+            valgrind_extra_args: vec!["--D1=65536,8,64".into()],
+            ..ExecutorConfig::test()
+        };
+        validate_valgrind_extra_args(&config.valgrind_extra_args).unwrap();
+
+        let args = get_valgrind_args(&SimulationTool::Callgrind, &config);
+        assert_eq!(args.last().map(String::as_str), Some("--D1=65536,8,64"));
+    }
+
+    #[test]
+    fn test_valgrind_extra_args_reject_reserved_options() {
+        for arg in [
+            "--tool=memcheck",
+            "--callgrind-out-file=/tmp/out",
+            "--trace-children=no",
+        ] {
+            let err = validate_valgrind_extra_args(&[arg.to_string()]).unwrap_err();
+            assert!(err.to_string().contains("managed by CodSpeed"), "{arg}");
+        }
+        // Similar names are not reserved
+        validate_valgrind_extra_args(&["--trace-children-skip=*python*".to_string()]).unwrap();
+        assert!(validate_valgrind_extra_args(&["ls".to_string()]).is_err());
+    }
+
     #[test]
     fn test_valgrind_skips_rustup_wrapped_proxy() {
         let config = ExecutorConfig::test();
@@ -2,3 +2,5 @@ pub mod executor;
 pub mod helpers;
 mod measure;
 mod setup;
+
+pub use measure::validate_valgrind_extra_args;
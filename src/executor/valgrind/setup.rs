@@ -3,6 +3,7 @@ use crate::binary_pins::{
     VALGRIND_CODSPEED_VERSION_STRING, ValgrindTarget,
 };
 use crate::cli::run::helpers::download_pinned_file;
+use crate::executor::config::SimulationTool;
 use crate::executor::helpers::apt;
 use crate::executor::{ToolInstallStatus, ToolStatus};
 use crate::prelude::*;
@@ -217,6 +218,65 @@ pub async fn install_valgrind(
     .await
 }
 
+/// How to get a compatible valgrind on this system, appended to the setup errors.
+fn valgrind_install_hint(system_info: &SystemInfo) -> String {
+    let expected_version = VALGRIND_CODSPEED_VERSION_STRING.as_str();
+    if is_codspeed_valgrind_installation_supported(system_info) {
+        return format!(
+            "Run `codspeed setup` to install the CodSpeed build of valgrind ({expected_version})"
+        );
+    }
+
+    match &system_info.os {
+        SupportedOs::Linux(_) => format!(
+            "CodSpeed does not provide a valgrind package for {} ({}), build valgrind \
+             {expected_version} or higher from https://github.com/CodSpeedHQ/valgrind-codspeed \
+             and make sure it is in the PATH",
+            system_info.os, system_info.arch
+        ),
+        SupportedOs::Macos { .. } => {
+            "valgrind is not supported on macOS, use the walltime mode instead".to_string()
+        }
+    }
+}
+
+fn simulation_tool_name(tool: SimulationTool) -> &'static str {
+    match tool {
+        SimulationTool::Callgrind => "callgrind",
+        SimulationTool::Tracegrind => "tracegrind",
+    }
+}
+
+/// Check that the valgrind found in the `PATH` is a compatible CodSpeed build providing `tool`,
+/// so that the run fails early with installation instructions rather than when spawning it.
+pub fn ensure_valgrind_is_usable(system_info: &SystemInfo, tool: SimulationTool) -> Result<()> {
+    match get_valgrind_status().status {
+        ToolInstallStatus::Installed { .. } => {}
+        ToolInstallStatus::NotInstalled => bail!(
+            "valgrind is not installed. {}",
+            valgrind_install_hint(system_info)
+        ),
+        ToolInstallStatus::IncorrectVersion { version, message } => bail!(
+            "Incompatible valgrind installation: {version} ({message}). {}",
+            valgrind_install_hint(system_info)
+        ),
+    }
+
+    let tool_name = simulation_tool_name(tool);
+    let has_tool = Command::new("valgrind")
+        .arg(format!("--tool={tool_name}"))
+        .arg("--help")
+        .output()
+        .is_ok_and(|output| output.status.success());
+    ensure!(
+        has_tool,
+        "The installed valgrind does not provide the {tool_name} tool. {}",
+        valgrind_install_hint(system_info)
+    );
+
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use insta::assert_snapshot;
@@ -402,6 +462,39 @@ mod tests {
         assert!(matches!(status, ToolInstallStatus::IncorrectVersion { .. }));
     }
 
+    #[test]
+    fn test_ensure_valgrind_is_usable_without_valgrind_in_path() {
+        let system_info = SystemInfo {
+            os: SupportedOs::Linux(LinuxDistribution::Ubuntu {
+                version: "24.04".into(),
+            }),
+            arch: "x86_64".to_string(),
+            ..SystemInfo::test()
+        };
+
+        let error = temp_env::with_var("PATH", Some(""), || {
+            ensure_valgrind_is_usable(&system_info, SimulationTool::Callgrind).unwrap_err()
+        });
+        assert_snapshot!(
+            error.to_string(),
+            @"valgrind is not installed. Run `codspeed setup` to install the CodSpeed build of valgrind (3.26.0.codspeed4)"
+        );
+    }
+
+    #[test]
+    fn test_valgrind_install_hint_unsupported_distro() {
+        let system_info = SystemInfo {
+            os: SupportedOs::Linux(LinuxDistribution::Ubuntu {
+                version: "20.04".into(),
+            }),
+            arch: "x86_64".to_string(),
+            ..SystemInfo::test()
+        };
+        let hint = valgrind_install_hint(&system_info);
+        assert!(hint.contains("ubuntu 20.04 (x86_64)"));
+        assert!(hint.contains("https://github.com/CodSpeedHQ/valgrind-codspeed"));
+    }
+
     #[test]
     fn test_parse_valgrind_codspeed_version_invalid_format() {
         assert_eq!(
@@ -3,29 +3,39 @@ use super::isolation::{requires_isolation, wrap_isolation_scope};
 use super::profiler::Profiler;
 use super::profiler::perf::PerfProfiler;
 use super::profiler::samply::SamplyProfiler;
+use crate::VERSION;
 use crate::executor::Executor;
 use crate::executor::ExecutorConfig;
 use crate::executor::ToolStatus;
 use crate::executor::config::WalltimeProfiler;
 use crate::executor::helpers::command::CommandBuilder;
+use crate::executor::helpers::cpu_affinity::wrap_with_cpu_affinity;
 use crate::executor::helpers::env::{build_path_env, get_base_injected_env};
 use crate::executor::helpers::get_bench_command::get_bench_command;
+use crate::executor::helpers::run_command_with_log_pipe::BenchmarkTimeoutError;
+use crate::executor::helpers::run_command_with_log_pipe::describe_exit_status;
 use crate::executor::helpers::run_command_with_log_pipe::run_command_with_log_pipe;
 use crate::executor::helpers::run_command_with_log_pipe::run_command_with_log_pipe_and_callback;
 use crate::executor::helpers::run_with_env::wrap_with_env;
 use crate::executor::helpers::run_with_sudo::wrap_with_sudo;
 use crate::executor::shared::fifo::FifoBenchmarkData;
+use crate::executor::shared::fifo::FifoCommandHandler;
+use crate::executor::shared::fifo::RAW_INTEGRATION_NAME;
 use crate::executor::shared::fifo::RunnerFifo;
+use crate::executor::shared::markers_file::merge_markers_file;
 use crate::executor::{ExecutionContext, ExecutorName, ExecutorSupport};
 use crate::instruments::mongo_tracer::MongoTracer;
 use crate::prelude::*;
 use crate::runner_mode::RunnerMode;
 use crate::system::{SupportedOs, SystemInfo};
 use async_trait::async_trait;
+use instrument_hooks_bindings::InstrumentHooks;
 use runner_shared::artifacts::ExecutionTimestamps;
 use runner_shared::fifo::Command as FifoCommand;
 use runner_shared::fifo::IntegrationMode;
+use runner_shared::fifo::MarkerType;
 use std::cell::OnceCell;
+use std::collections::HashSet;
 use std::fs::canonicalize;
 use std::io::Write;
 use std::path::Path;
@@ -89,6 +99,10 @@ pub struct WallTimeExecutor {
     benchmark_state: OnceCell<(FifoBenchmarkData, ExecutionTimestamps)>,
 }
 
+/// Pick the walltime profiler: perf on Linux, samply on macOS, unless overridden.
+///
+/// On macOS, samply collects the call stacks through the system sampling APIs, with the same
+/// markers and execution timestamps as perf on Linux.
 fn select_profiler(profiler_override: Option<WalltimeProfiler>) -> Option<Box<dyn Profiler>> {
     match profiler_override {
         Some(WalltimeProfiler::Perf) => Some(Box::new(PerfProfiler::new())),
@@ -125,7 +139,7 @@ impl WallTimeExecutor {
             RunnerMode::Walltime,
             &execution_context.profile_folder,
             &execution_context.config,
-        );
+        )?;
         extra_env.insert("PATH".into(), path_value);
 
         // We have to write the benchmark command to a script, to ensure proper formatting
@@ -218,17 +232,38 @@ impl Executor for WallTimeExecutor {
                 } else {
                     cmd_builder
                 };
+                let cmd_builder = match execution_context.config.cpu {
+                    Some(cpu) => wrap_with_cpu_affinity(cmd_builder, cpu),
+                    None => cmd_builder,
+                };
                 let cmd = cmd_builder.build();
                 debug!("cmd: {cmd:?}");
-                run_command_with_log_pipe(cmd).await
+                let capture_folder = execution_context
+                    .config
+                    .capture_output
+                    .then_some(execution_context.profile_folder.as_path());
+                run_command_with_log_pipe(
+                    cmd,
+                    capture_folder,
+                    execution_context.config.benchmark_timeout,
+                )
+                .await
             }
         };
 
-        let status = status.map_err(|e| anyhow!("failed to execute the benchmark process. {e}"))?;
+        let status = status.map_err(|e| {
+            if e.is::<BenchmarkTimeoutError>() {
+                return e;
+            }
+            anyhow!("failed to execute the benchmark process. {e}")
+        })?;
         debug!("cmd exit status: {status:?}");
 
         if !status.success() {
-            bail!("failed to execute the benchmark process: {status}");
+            bail!(
+                "failed to execute the benchmark process: {}",
+                describe_exit_status(&status)
+            );
         }
 
         Ok(())
@@ -245,10 +280,13 @@ impl Executor for WallTimeExecutor {
                 .await?;
         }
 
-        validate_walltime_results(
-            &execution_context.profile_folder,
-            execution_context.config.allow_empty,
-        )?;
+        // Raw runs produce a profile but no benchmark results
+        if !execution_context.config.raw {
+            validate_walltime_results(
+                &execution_context.profile_folder,
+                execution_context.config.allow_empty,
+            )?;
+        }
 
         Ok(())
     }
@@ -265,6 +303,11 @@ async fn run_with_profiler(
     isolate: bool,
     benchmark_state: &OnceCell<(FifoBenchmarkData, ExecutionTimestamps)>,
 ) -> Result<std::process::ExitStatus> {
+    // Innermost, so that the profiler doesn't compete with the benchmark for its CPU
+    let cmd_builder = match config.cpu {
+        Some(cpu) => wrap_with_cpu_affinity(cmd_builder, cpu),
+        None => cmd_builder,
+    };
     let wrapped = profiler
         .wrap_command(cmd_builder, config, profile_folder, isolate)
         .await?;
@@ -273,36 +316,97 @@ async fn run_with_profiler(
 
     let mut runner_fifo = RunnerFifo::new()?;
 
-    run_command_with_log_pipe_and_callback(cmd, async move |mut child| {
-        let on_cmd = async |c: &FifoCommand| match c {
+    // Note: in perf's pipe mode, perf redirects the benchmark's stdout to
+    // stderr, so the captured output ends up in the stderr file.
+    let capture_folder = config.capture_output.then_some(profile_folder);
+    run_command_with_log_pipe_and_callback(
+        cmd,
+        capture_folder,
+        config.benchmark_timeout,
+        async move |mut child| {
+            let mut handler = ProfilerFifoHandler {
+                profiler: &mut *profiler,
+                config,
+            };
+
+            let start = InstrumentHooks::current_timestamp();
+            let (timestamps, fifo_data, exit_status) = runner_fifo
+                .handle_fifo_messages_with(&mut child, &mut handler)
+                .await?;
+
+            let state = if config.raw {
+                raw_benchmark_state(&config.command, start, InstrumentHooks::current_timestamp())
+            } else {
+                merge_markers_file(fifo_data, timestamps, profile_folder)?
+            };
+            let _ = benchmark_state.set(state);
+
+            Ok(exit_status)
+        },
+    )
+    .await
+}
+
+/// Dispatches the FIFO commands of the integration, and the ticks of the FIFO loop, to the hooks
+/// of the profiler.
+struct ProfilerFifoHandler<'a> {
+    profiler: &'a mut dyn Profiler,
+    config: &'a ExecutorConfig,
+}
+
+impl FifoCommandHandler for ProfilerFifoHandler<'_> {
+    async fn handle_cmd(&mut self, cmd: &FifoCommand) -> anyhow::Result<Option<FifoCommand>> {
+        match cmd {
             FifoCommand::StartProfiler => {
-                profiler.on_start_profiler().await?;
+                self.profiler.on_start_profiler().await?;
                 Ok(None)
             }
             FifoCommand::StopProfiler => {
-                profiler.on_stop_profiler().await?;
+                self.profiler.on_stop_profiler().await?;
                 Ok(None)
             }
             #[allow(deprecated)]
-            FifoCommand::PingProfiler => Ok(Some(if profiler.on_ping().await? {
+            FifoCommand::PingProfiler => Ok(Some(if self.profiler.on_ping().await? {
                 FifoCommand::Ack
             } else {
                 FifoCommand::Err
             })),
             FifoCommand::GetIntegrationMode => Ok(Some(FifoCommand::IntegrationModeResponse(
-                IntegrationMode::Walltime,
+                reported_integration_mode(self.config),
             ))),
             _ => Ok(None),
-        };
+        }
+    }
 
-        let (timestamps, fifo_data, exit_status) =
-            runner_fifo.handle_fifo_messages(&mut child, on_cmd).await?;
+    async fn on_tick(&mut self) -> anyhow::Result<()> {
+        self.profiler.on_tick().await
+    }
+}
 
-        let _ = benchmark_state.set((fifo_data, timestamps));
+/// The benchmark state of a raw run: without an integration to report the benchmarks, the whole
+/// execution of `command` is a single benchmark, whose samples come from all the processes.
+fn raw_benchmark_state(
+    command: &str,
+    start: u64,
+    end: u64,
+) -> (FifoBenchmarkData, ExecutionTimestamps) {
+    let fifo_data = FifoBenchmarkData {
+        integration: Some((RAW_INTEGRATION_NAME.to_string(), VERSION.to_string())),
+        bench_pids: HashSet::new(),
+        all_pids: true,
+    };
+    let timestamps = ExecutionTimestamps::new(
+        &[(start, command.to_string())],
+        &[MarkerType::SampleStart(start), MarkerType::SampleEnd(end)],
+    );
+    (fifo_data, timestamps)
+}
 
-        Ok(exit_status)
-    })
-    .await
+/// The integration mode reported to the integration, unless overridden with `--integration-mode`.
+fn reported_integration_mode(config: &ExecutorConfig) -> IntegrationMode {
+    config
+        .integration_mode_override
+        .unwrap_or(IntegrationMode::Walltime)
 }
 
 #[cfg(test)]
@@ -351,4 +455,30 @@ mod tests {
         tmp_dst.read_to_string(&mut result).unwrap();
         assert_eq!(result, "pre\npost\n");
     }
+
+    #[test]
+    #[cfg(target_os = "macos")]
+    fn test_macos_has_a_default_walltime_profiler() {
+        assert!(select_profiler(None).is_some());
+    }
+
+    #[test]
+    fn test_reported_integration_mode_defaults_to_walltime() {
+        assert_eq!(
+            reported_integration_mode(&ExecutorConfig::test()),
+            IntegrationMode::Walltime
+        );
+    }
+
+    #[test]
+    fn test_reported_integration_mode_uses_override() {
+        let config = ExecutorConfig {
+            integration_mode_override: Some(IntegrationMode::Simulation),
+            ..ExecutorConfig::test()
+        };
+        assert_eq!(
+            reported_integration_mode(&config),
+            IntegrationMode::Simulation
+        );
+    }
 }
@@ -2,21 +2,43 @@ use crate::prelude::*;
 
 #[cfg(target_os = "linux")]
 use crate::executor::helpers::run_with_sudo::run_with_sudo;
+#[cfg(target_os = "linux")]
+use crate::prompt::{WithoutTty, confirm};
 #[cfg(any(test, target_os = "linux"))]
 use anyhow::Context;
 #[cfg(target_os = "linux")]
 use std::process::Command;
 
+/// The sysctls required for profiling, with their expected values.
+#[cfg(target_os = "linux")]
+const PROFILING_SYSCTLS: [(&str, i64); 2] = [
+    ("kernel.kptr_restrict", 0),
+    ("kernel.perf_event_paranoid", -1),
+];
+
 pub fn ensure_linux_profiling_sysctls() -> Result<()> {
     #[cfg(target_os = "linux")]
-    {
-        ensure_sysctl("kernel.kptr_restrict", 0)?;
-        ensure_sysctl("kernel.perf_event_paranoid", -1)?;
+    for (name, target_value) in PROFILING_SYSCTLS {
+        ensure_sysctl(name, target_value)?;
     }
 
     Ok(())
 }
 
+/// Whether the sysctls required for profiling already have the expected values.
+pub fn profiling_sysctls_are_set() -> bool {
+    #[cfg(target_os = "linux")]
+    {
+        PROFILING_SYSCTLS
+            .into_iter()
+            .all(|(name, target_value)| sysctl_read(name).is_ok_and(|value| value == target_value))
+    }
+    #[cfg(not(target_os = "linux"))]
+    {
+        true
+    }
+}
+
 #[cfg(target_os = "linux")]
 fn ensure_sysctl(name: &str, target_value: i64) -> Result<()> {
     if sysctl_read(name)? == target_value {
@@ -24,6 +46,11 @@ fn ensure_sysctl(name: &str, target_value: i64) -> Result<()> {
     }
 
     let assignment = format!("{name}={target_value}");
+    // Non-interactive (CI): keep applying the setting as before
+    confirm(
+        &format!("Profiling requires setting `{assignment}` with sudo. Continue?"),
+        WithoutTty::Proceed,
+    )?;
     run_with_sudo("sysctl", ["-w", assignment.as_str()])
 }
 
@@ -4,11 +4,11 @@
 //! (perf, samply, instruments, ...) and produces a unified set of artifacts
 //! in the profile folder.
 
-mod linux_sysctl;
+pub(crate) mod linux_sysctl;
 pub mod perf;
 pub mod samply;
 
-const WALLTIME_METADATA_CURRENT_VERSION: u64 = 1;
+use runner_shared::metadata::WALLTIME_METADATA_CURRENT_VERSION;
 
 /// Sampling rate (Hz) shared by all walltime profilers. A prime number to
 /// avoid synchronization with periodic tasks.
@@ -69,6 +69,12 @@ pub trait Profiler {
         Ok(())
     }
 
+    /// Called about every second while the benchmark runs, including the runs without an
+    /// integration, e.g. to watch the size of the profiler output.
+    async fn on_tick(&mut self) -> anyhow::Result<()> {
+        Ok(())
+    }
+
     /// Health-check ping from the benchmarked process. Returning `false`
     /// indicates the profiler is unhealthy and the harness should report an
     /// error to the integration.
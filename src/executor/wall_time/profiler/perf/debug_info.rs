@@ -5,7 +5,7 @@ use crate::prelude::*;
 use addr2line::{fallible_iterator::FallibleIterator, gimli};
 use object::{Object, ObjectSection};
 use rayon::prelude::*;
-use runner_shared::debug_info::{DebugInfo, ModuleDebugInfo};
+use runner_shared::debug_info::{DebugInfo, InlineFrame, InlineRange, ModuleDebugInfo};
 use std::collections::HashMap;
 use std::path::Path;
 use std::path::PathBuf;
@@ -18,6 +18,18 @@ pub trait ModuleDebugInfoExt {
         symbols: &ModuleSymbols,
         load_bias: u64,
     ) -> anyhow::Result<Self>
+    where
+        Self: Sized,
+    {
+        Self::from_symbols_with_inline_frames(path, symbols, load_bias, false)
+    }
+
+    fn from_symbols_with_inline_frames<P: AsRef<Path>>(
+        path: P,
+        symbols: &ModuleSymbols,
+        load_bias: u64,
+        inline_frames: bool,
+    ) -> anyhow::Result<Self>
     where
         Self: Sized;
 
@@ -48,10 +60,13 @@ impl ModuleDebugInfoExt for ModuleDebugInfo {
     ///
     /// If the binary has no DWARF sections, tries to find a separate debug file
     /// via `.gnu_debuglink` (e.g. installed by `libc6-dbg`).
-    fn from_symbols<P: AsRef<Path>>(
+    ///
+    /// With `inline_frames`, also records the chains of inlined frames covering the symbols.
+    fn from_symbols_with_inline_frames<P: AsRef<Path>>(
         path: P,
         symbols: &ModuleSymbols,
         load_bias: u64,
+        inline_frames: bool,
     ) -> anyhow::Result<Self> {
         let content = std::fs::read(path.as_ref())?;
         let object = object::File::parse(&*content)?;
@@ -121,15 +136,75 @@ impl ModuleDebugInfoExt for ModuleDebugInfo {
             anyhow::bail!("No debug info could be extracted from module");
         };
 
+        let inline_ranges = if inline_frames {
+            collect_inline_ranges(&ctx, symbols)
+        } else {
+            vec![]
+        };
+
         Ok(ModuleDebugInfo {
             object_path: path.as_ref().to_string_lossy().to_string(),
             load_bias,
             addr_bounds: (min_addr, max_addr),
             debug_infos,
+            inline_ranges,
         })
     }
 }
 
+/// Collect the address ranges of the symbols that are covered by inlined functions, with the
+/// chain of frames at each of them. Consecutive ranges with the same chain are merged.
+fn collect_inline_ranges(
+    ctx: &addr2line::Context<EndianRcSlice>,
+    symbols: &ModuleSymbols,
+) -> Vec<InlineRange> {
+    let mut inline_ranges: Vec<InlineRange> = Vec::new();
+    for symbol in symbols.symbols().iter().sorted_by_key(|s| s.addr) {
+        let Ok(locations) = ctx.find_location_range(symbol.addr, symbol.addr + symbol.size) else {
+            continue;
+        };
+        for (start, len, _) in locations {
+            let Some(frames) = inline_frames_at(ctx, start) else {
+                continue;
+            };
+            let end = start + len;
+            match inline_ranges.last_mut() {
+                Some(last) if last.end == start && last.frames == frames => last.end = end,
+                _ => inline_ranges.push(InlineRange { start, end, frames }),
+            }
+        }
+    }
+    inline_ranges
+}
+
+/// The chain of frames at `addr`, from the innermost inlined function to the outermost caller.
+/// Returns `None` when no function is inlined at this address.
+fn inline_frames_at(
+    ctx: &addr2line::Context<EndianRcSlice>,
+    addr: u64,
+) -> Option<Vec<InlineFrame>> {
+    let frames: Vec<InlineFrame> = ctx
+        .find_frames(addr)
+        .skip_all_loads()
+        .ok()?
+        .map(|frame| {
+            let name = frame
+                .function
+                .as_ref()
+                .and_then(|function| function.raw_name().ok())
+                .map(|name| name.into_owned())
+                .unwrap_or_default();
+            let (file, line) = frame
+                .location
+                .map(|location| (location.file.unwrap_or_default().to_string(), location.line))
+                .unwrap_or_default();
+            Ok(InlineFrame { name, file, line })
+        })
+        .collect()
+        .ok()?;
+    (frames.len() > 1).then_some(frames)
+}
+
 fn is_libc_filename(file_name: &str) -> bool {
     file_name.starts_with("libc.so") || file_name.starts_with("libc-")
 }
@@ -142,7 +217,7 @@ fn warn_missing_libc_debug_info(path: &Path) {
         return;
     }
 
-    warn!(
+    run_warning!(
         "Debug info for {} not found. Install libc6-dbg (Debian/Ubuntu) or \
          glibc-debuginfo (Fedora/RHEL) to fix missing symbols in the flamegraph",
         path.display()
@@ -153,12 +228,18 @@ fn warn_missing_libc_debug_info(path: &Path) {
 /// Returns a map of path -> ModuleDebugInfo with `load_bias: 0` (load bias is per-pid).
 pub fn debug_info_by_path(
     loaded_modules_by_path: &HashMap<PathBuf, LoadedModule>,
+    inline_frames: bool,
 ) -> HashMap<PathBuf, ModuleDebugInfo> {
     loaded_modules_by_path
         .par_iter()
         .filter_map(|(path, loaded_module)| {
             let module_symbols = loaded_module.module_symbols.as_ref()?;
-            match ModuleDebugInfo::from_symbols(path, module_symbols, 0) {
+            match ModuleDebugInfo::from_symbols_with_inline_frames(
+                path,
+                module_symbols,
+                0,
+                inline_frames,
+            ) {
                 Ok(module_debug_info) => Some((path.clone(), module_debug_info)),
                 Err(error) => {
                     trace!("Failed to load debug info for module {path:?}: {error}");
@@ -255,6 +336,40 @@ mod tests {
         insta::assert_debug_snapshot!(module_debug_info.debug_infos);
     }
 
+    #[test]
+    fn test_the_algorithms_inline_frames() {
+        const MODULE_PATH: &str = "testdata/perf_map/the_algorithms.bin";
+
+        let module_symbols = ModuleSymbols::from_elf(MODULE_PATH).unwrap();
+        let without_inline_frames =
+            ModuleDebugInfo::from_symbols(MODULE_PATH, &module_symbols, 0).unwrap();
+        assert!(without_inline_frames.inline_ranges.is_empty());
+
+        let module_debug_info =
+            ModuleDebugInfo::from_symbols_with_inline_frames(MODULE_PATH, &module_symbols, 0, true)
+                .unwrap();
+        assert_eq!(
+            module_debug_info.debug_infos,
+            without_inline_frames.debug_infos
+        );
+        assert!(
+            module_debug_info
+                .inline_ranges
+                .is_sorted_by_key(|range| range.start)
+        );
+        for range in &module_debug_info.inline_ranges {
+            assert!(range.start < range.end);
+            assert!(range.frames.len() > 1);
+        }
+        // Release Rust binaries inline most of the standard library, e.g. iterator adapters
+        assert!(
+            module_debug_info
+                .inline_ranges
+                .iter()
+                .any(|range| { range.frames[0].file.contains("library/core/src/") })
+        );
+    }
+
     #[rstest::rstest]
     #[case::cpp(
         "testdata/perf_map/cpp_my_benchmark_stripped.bin",
@@ -1,10 +1,24 @@
 //! Based on this: https://github.com/mstange/samply/blob/4a5afec57b7c68b37ecde12b5a258de523e89463/samply/src/linux_shared/svma_file_range.rs#L8
 
 use anyhow::Context;
-use log::trace;
-use object::Object;
+use log::{debug, trace};
 use object::ObjectSegment;
+use object::{BinaryFormat, Object, ObjectKind, SegmentFlags};
+use std::collections::HashSet;
+use std::ops::Range;
 use std::path::{Path, PathBuf};
+use std::sync::{LazyLock, Mutex};
+
+/// Colon-separated directories searched for separate debug files, replacing the default ones
+/// (like GDB's `debug-file-directory`).
+pub const DEBUG_FILE_DIRS_ENV: &str = "CODSPEED_DEBUG_FILE_DIRS";
+
+const DEFAULT_DEBUG_FILE_DIRS: &[&str] = &["/usr/lib/debug", "/run/current-system/sw/lib/debug"];
+
+/// Binaries without a separate debug file in the searched directories. Both the symbols and
+/// the debug info of a module look for its debug file, this avoids stating the same paths twice.
+static MISSING_DEBUG_FILES: LazyLock<Mutex<HashSet<(PathBuf, Vec<PathBuf>)>>> =
+    LazyLock::new(Default::default);
 
 // A file range in an object file, such as a segment or a section,
 // for which we know the corresponding Stated Virtual Memory Address (SVMA).
@@ -12,17 +26,38 @@ struct SvmaFileRange {
     pub svma: u64,
     pub file_offset: u64,
     pub size: u64,
+    pub is_executable: bool,
 }
 
 impl SvmaFileRange {
     pub fn from_segment<'data, S: ObjectSegment<'data>>(segment: S) -> Self {
         let svma = segment.address();
         let (file_offset, size) = segment.file_range();
+        let is_executable = match segment.flags() {
+            SegmentFlags::Elf { p_flags } => p_flags & object::elf::PF_X != 0,
+            _ => false,
+        };
         SvmaFileRange {
             svma,
             file_offset,
             size,
+            is_executable,
+        }
+    }
+
+    /// Whether the segment is the one mapped from `page_offset`: the kernel maps a segment from
+    /// its file offset rounded down to a page.
+    pub fn contains_page_offset(&self, page_offset: u64, page_size: u64) -> bool {
+        self.page_range(page_size).contains(&page_offset)
+    }
+
+    /// The page offsets from which the segment can be mapped, empty for segments without file
+    /// content.
+    pub fn page_range(&self, page_size: u64) -> Range<u64> {
+        if self.size == 0 {
+            return 0..0;
         }
+        self.file_offset - self.file_offset % page_size..self.file_offset + self.size
     }
 
     pub fn encompasses_file_range(&self, runtime_file_offset: u64, mapping_size: u64) -> bool {
@@ -40,6 +75,26 @@ impl SvmaFileRange {
     }
 }
 
+fn page_size() -> u64 {
+    // Safety: sysconf has no preconditions
+    match unsafe { libc::sysconf(libc::_SC_PAGESIZE) } {
+        size if size > 0 => size as u64,
+        _ => 0x1000,
+    }
+}
+
+/// The page offsets of `object` from which its executable segments are mapped, see
+/// [`SvmaFileRange::contains_page_offset`].
+pub fn executable_segment_page_ranges(object: &object::File) -> Vec<Range<u64>> {
+    let page_size = page_size();
+    object
+        .segments()
+        .map(SvmaFileRange::from_segment)
+        .filter(|segment| segment.is_executable && segment.size > 0)
+        .map(|segment| segment.page_range(page_size))
+        .collect()
+}
+
 pub fn compute_load_bias(
     runtime_start_addr: u64,
     runtime_end_addr: u64,
@@ -82,25 +137,35 @@ pub fn compute_load_bias(
     //   LOAD           0x0000000000002000 0x0000000000402000 0x0000000000402000        <-- we'll match this
     //                  0x000000000010ceb1 0x000000000010ceb1  R E    0x1000
     // ```
+    //
+    // A module can have several executable segments (e.g. with `--emit-relocs` or a split
+    // `.text`), each mapped by its own MMAP2 record. The page offset of a mapping identifies its
+    // segment, the overlap checks below are only a fallback for unusual mappings.
     let mapping_size = runtime_end_addr - runtime_start_addr;
-    let load_segment = object
-        .segments()
-        .map(SvmaFileRange::from_segment)
+    let segments: Vec<SvmaFileRange> = object.segments().map(SvmaFileRange::from_segment).collect();
+    let page_size = page_size();
+    let load_segment = segments
+        .iter()
         .find(|segment| {
-            // When the kernel loads an ELF file, it maps entire pages (usually 4KB aligned),
-            // not just the exact segment boundaries. Here's what happens:
-            //
-            // **ELF File Structure**:
-            // - LOAD segment 1: file offset 0x0      - 0x4d26a  (data/code)
-            // - LOAD segment 2: file offset 0x4d26c  - 0x13c4b6 (executable code)
-            //
-            // **Kernel Memory Mapping**: The kernel rounds down to page boundaries when mapping:
-            // - Maps pages starting at offset 0x0     (covers segment 1)
-            // - Maps pages starting at offset 0x4d000 (page-aligned, covers segment 2)
-            //
-            // (the example values are based on the `test_rust_divan_symbols` test)
-            segment.encompasses_file_range(runtime_file_offset, mapping_size)
-                || segment.is_encompassed_by_file_range(runtime_file_offset, mapping_size)
+            segment.is_executable && segment.contains_page_offset(runtime_file_offset, page_size)
+        })
+        .or_else(|| {
+            segments.iter().find(|segment| {
+                // When the kernel loads an ELF file, it maps entire pages (usually 4KB aligned),
+                // not just the exact segment boundaries. Here's what happens:
+                //
+                // **ELF File Structure**:
+                // - LOAD segment 1: file offset 0x0      - 0x4d26a  (data/code)
+                // - LOAD segment 2: file offset 0x4d26c  - 0x13c4b6 (executable code)
+                //
+                // **Kernel Memory Mapping**: The kernel rounds down to page boundaries when mapping:
+                // - Maps pages starting at offset 0x0     (covers segment 1)
+                // - Maps pages starting at offset 0x4d000 (page-aligned, covers segment 2)
+                //
+                // (the example values are based on the `test_rust_divan_symbols` test)
+                segment.encompasses_file_range(runtime_file_offset, mapping_size)
+                    || segment.is_encompassed_by_file_range(runtime_file_offset, mapping_size)
+            })
         })
         .context(format!(
             "Could not find segment or section overlapping the file offset range 0x{:x}..0x{:x}",
@@ -124,10 +189,32 @@ pub fn compute_load_bias(
     //  - `runtime_start_addr`: The actual base address of this segment in memory (from `/proc/maps`).
     //  - `load_segment.address()`: The preferred virtual address (`p_vaddr`) from the ELF file itself.
     //
-    // This single calculation correctly handles both PIE/shared-objects and non-PIE executables:
-    //  - For PIE/.so files:   `0x7f... (random) - 0x... (small) = <large_bias>`
-    //  - For non-PIE files: `0x402000 (fixed) - 0x402000 (fixed) = 0`
-    Ok(runtime_start_addr.wrapping_sub(load_segment.svma))
+    //  - For PIE/.so files (`ET_DYN`): `0x7f... (random) - 0x... (small) = <large_bias>`
+    //  - For non-PIE files (`ET_EXEC`): `0x402000 (fixed) - 0x402000 (fixed) = 0`
+    //
+    // A non-PIE executable can't be relocated, so its bias is always 0. Computing it anyway is
+    // fragile: matching the mapping with the wrong segment yields a bogus (even "negative") bias
+    // and every symbol of the main executable ends up shifted.
+    let load_bias = runtime_start_addr.wrapping_sub(load_segment.svma);
+    if object.format() == BinaryFormat::Elf && object.kind() == ObjectKind::Executable {
+        debug!("ELF type EXEC (non-PIE): fixed load address, load bias 0");
+        if load_bias != 0 {
+            debug!("Ignoring the load bias {load_bias:#x} computed for a non-PIE executable");
+        }
+        return Ok(0);
+    }
+
+    debug!(
+        "ELF type {:?} (PIE or shared object): load bias {load_bias:#x}",
+        object.kind()
+    );
+    if runtime_start_addr < load_segment.svma {
+        debug!(
+            "Negative load bias: the segment at {:#x} is mapped below its address {:#x}",
+            runtime_start_addr, load_segment.svma
+        );
+    }
+    Ok(load_bias)
 }
 
 /// The "relative address base" is the base address which [`LookupAddress::Relative`]
@@ -206,13 +293,30 @@ pub fn compute_base_avma(base_svma: u64, load_bias: u64) -> u64 {
 /// practice. On NixOS, `environment.enableDebugInfo = true` populates the
 /// same layout under `/run/current-system/sw/lib/debug`.
 ///
+/// The directories searched can be replaced through [`DEBUG_FILE_DIRS_ENV`]. Failed lookups
+/// are cached for the lifetime of the process.
+///
 /// [Separate Debug Files]: https://sourceware.org/gdb/current/onlinedocs/gdb.html/Separate-Debug-Files.html
 pub fn find_debug_file(object: &object::File, binary_path: &Path) -> Option<PathBuf> {
-    ["/usr/lib/debug", "/run/current-system/sw/lib/debug"]
+    let debug_dirs = match std::env::var_os(DEBUG_FILE_DIRS_ENV) {
+        Some(dirs) => std::env::split_paths(&dirs).collect(),
+        None => DEFAULT_DEBUG_FILE_DIRS.iter().map(PathBuf::from).collect(),
+    };
+    let cache_key = (binary_path.to_path_buf(), debug_dirs);
+    if MISSING_DEBUG_FILES.lock().unwrap().contains(&cache_key) {
+        trace!("No debug file for {} (cached)", binary_path.display());
+        return None;
+    }
+
+    let debug_file = cache_key
+        .1
         .iter()
-        .map(Path::new)
         .filter(|dir| dir.exists())
-        .find_map(|dir| find_debug_file_in(object, binary_path, dir))
+        .find_map(|dir| find_debug_file_in(object, binary_path, dir));
+    if debug_file.is_none() {
+        MISSING_DEBUG_FILES.lock().unwrap().insert(cache_key);
+    }
+    debug_file
 }
 
 fn find_debug_file_in(
@@ -330,6 +434,78 @@ mod tests {
     const LIBC_PATH: &str = "testdata/perf_map/libc.so.6";
     const LIBC_DEBUG_PATH: &str = "testdata/perf_map/libc.so.6.debug";
 
+    /// The bias of the executable segment of a `testdata/load_bias` fixture (mapped from the
+    /// file offset 0x1000), given the address it is mapped at.
+    fn load_bias_of(fixture: &str, runtime_start_addr: u64, runtime_offset: u64) -> u64 {
+        let content = std::fs::read(format!("testdata/load_bias/{fixture}")).unwrap();
+        let object = object::File::parse(&*content).unwrap();
+        compute_load_bias(
+            runtime_start_addr,
+            runtime_start_addr + 0x1000,
+            runtime_offset,
+            &object,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_load_bias_of_non_pie_executable() {
+        // `ET_EXEC`, mapped at the address of its segment
+        assert_eq!(load_bias_of("non_pie.bin", 0x401000, 0x1000), 0);
+        // Matching the mapping with the first segment would yield a bias of 0x1000
+        assert_eq!(load_bias_of("non_pie.bin", 0x401000, 0x0), 0);
+    }
+
+    // `two_text_segments.bin` has two executable segments, mapped by one record each:
+    // `.text` at the file offset 0x1000 and `.text_far` at 0x3000, linked at 0x200000
+    const TWO_TEXT_SEGMENTS_BASE: u64 = 0x5555_5555_4000;
+
+    #[test]
+    fn test_load_bias_of_first_text_segment() {
+        assert_eq!(
+            load_bias_of(
+                "two_text_segments.bin",
+                TWO_TEXT_SEGMENTS_BASE + 0x1000,
+                0x1000
+            ),
+            TWO_TEXT_SEGMENTS_BASE
+        );
+    }
+
+    #[test]
+    fn test_load_bias_of_second_text_segment() {
+        assert_eq!(
+            load_bias_of(
+                "two_text_segments.bin",
+                TWO_TEXT_SEGMENTS_BASE + 0x200000,
+                0x3000
+            ),
+            TWO_TEXT_SEGMENTS_BASE
+        );
+    }
+
+    #[test]
+    fn test_executable_segment_page_ranges() {
+        let content = std::fs::read("testdata/load_bias/pie.bin").unwrap();
+        let object = object::File::parse(&*content).unwrap();
+        let ranges = executable_segment_page_ranges(&object);
+        let maps_executable_segment =
+            |page_offset| ranges.iter().any(|range| range.contains(&page_offset));
+        // The segments at 0x0 and 0x2000 are read-only, the one at 0x1000 is `.text`
+        assert!(!maps_executable_segment(0x0));
+        assert!(maps_executable_segment(0x1000));
+        assert!(!maps_executable_segment(0x2000));
+    }
+
+    #[test]
+    fn test_load_bias_of_pie_executable() {
+        // `ET_DYN`, with its segments at small addresses relocated by ASLR
+        assert_eq!(
+            load_bias_of("pie.bin", 0x5555_5555_5000, 0x1000),
+            0x5555_5555_4000
+        );
+    }
+
     #[test]
     fn test_find_debug_file_by_build_id() {
         // Ubuntu's `libc6-dbg` installs its debug file under
@@ -353,6 +529,58 @@ mod tests {
         assert_eq!(result, Some(debug_file_path));
     }
 
+    /// Stage the libc fixture in a tempdir, with its debug file in a `.build-id` layout under a
+    /// separate debug root.
+    fn setup_build_id_debug_root() -> (tempfile::TempDir, PathBuf, PathBuf, PathBuf) {
+        let content = std::fs::read(LIBC_PATH).unwrap();
+        let object = object::File::parse(&*content).unwrap();
+        let build_id = object.build_id().unwrap().unwrap();
+        let hex: String = build_id.iter().map(|b| format!("{b:02x}")).collect();
+
+        let tmp = tempfile::tempdir().unwrap();
+        let binary_path = tmp.path().join("lib").join("libc.so.6");
+        std::fs::create_dir_all(binary_path.parent().unwrap()).unwrap();
+        std::fs::copy(LIBC_PATH, &binary_path).unwrap();
+
+        let debug_root = tmp.path().join("debug");
+        let debug_file_path = debug_root
+            .join(".build-id")
+            .join(&hex[..2])
+            .join(format!("{}.debug", &hex[2..]));
+        (tmp, binary_path, debug_root, debug_file_path)
+    }
+
+    #[test]
+    fn test_find_debug_file_in_debug_root_from_env() {
+        let (_tmp, binary_path, debug_root, debug_file_path) = setup_build_id_debug_root();
+        std::fs::create_dir_all(debug_file_path.parent().unwrap()).unwrap();
+        std::fs::copy(LIBC_DEBUG_PATH, &debug_file_path).unwrap();
+        let content = std::fs::read(&binary_path).unwrap();
+        let object = object::File::parse(&*content).unwrap();
+
+        let result = temp_env::with_var(DEBUG_FILE_DIRS_ENV, Some(&debug_root), || {
+            find_debug_file(&object, &binary_path)
+        });
+        assert_eq!(result, Some(debug_file_path));
+    }
+
+    #[test]
+    fn test_find_debug_file_caches_missing_debug_files() {
+        let (_tmp, binary_path, debug_root, debug_file_path) = setup_build_id_debug_root();
+        std::fs::create_dir_all(&debug_root).unwrap();
+        let content = std::fs::read(&binary_path).unwrap();
+        let object = object::File::parse(&*content).unwrap();
+
+        temp_env::with_var(DEBUG_FILE_DIRS_ENV, Some(&debug_root), || {
+            assert_eq!(find_debug_file(&object, &binary_path), None);
+
+            // Not looked up again for the same binary and debug directories
+            std::fs::create_dir_all(debug_file_path.parent().unwrap()).unwrap();
+            std::fs::copy(LIBC_DEBUG_PATH, &debug_file_path).unwrap();
+            assert_eq!(find_debug_file(&object, &binary_path), None);
+        });
+    }
+
     #[test]
     fn test_find_debug_file_by_debuglink() {
         let (_dir, binary, debug_file) =
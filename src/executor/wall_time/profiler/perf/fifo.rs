@@ -1,8 +1,15 @@
 use std::ops::Deref;
+use std::time::Duration;
 
+use anyhow::Context;
 use tokio::io::{AsyncReadExt, AsyncWriteExt};
 
 use crate::executor::shared::fifo::GenericFifo;
+
+/// How long perf has to acknowledge a ping before it's considered unresponsive. It's generous
+/// since perf may still be starting, e.g. under `sudo`.
+const PING_TIMEOUT: Duration = Duration::from_secs(30);
+
 pub struct PerfFifo {
     fifo: GenericFifo,
 }
@@ -31,9 +38,13 @@ impl PerfFifo {
         Ok(())
     }
 
+    /// Ping perf once. The ack read is cancelled on timeout, so the FIFO can't be reused after
+    /// an error: a late ack would be taken for the one of the next command.
     pub async fn ping(&mut self) -> anyhow::Result<()> {
         self.fifo.ctl_sender().write_all(b"ping\n\0").await?;
-        self.wait_for_ack().await;
+        tokio::time::timeout(PING_TIMEOUT, self.wait_for_ack())
+            .await
+            .context("perf did not acknowledge the ping")?;
 
         Ok(())
     }
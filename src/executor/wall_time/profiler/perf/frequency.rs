@@ -0,0 +1,147 @@
+use crate::executor::wall_time::profiler::SAMPLING_RATE_HZ;
+use crate::prelude::*;
+use std::time::Duration;
+
+/// Sampling frequency of perf: a number of Hz, or `auto` to derive it from
+/// [`PERF_EXPECTED_DURATION_MS_ENV`], which must then be set. Defaults to [`SAMPLING_RATE_HZ`].
+pub const PERF_FREQ_ENV: &str = "CODSPEED_PERF_FREQ";
+
+/// Expected duration of a benchmark in milliseconds, required by `CODSPEED_PERF_FREQ=auto`.
+pub const PERF_EXPECTED_DURATION_MS_ENV: &str = "CODSPEED_PERF_EXPECTED_DURATION_MS";
+
+/// Number of samples per benchmark targeted by `CODSPEED_PERF_FREQ=auto`.
+const AUTO_TARGET_SAMPLES: u64 = 1000;
+
+/// Bounds of the frequencies picked automatically. Both are primes, so that a prime is always
+/// found between them.
+const MIN_FREQUENCY_HZ: u32 = 97;
+const MAX_FREQUENCY_HZ: u32 = 9973;
+
+/// Pick a sampling frequency taking about `target_samples` samples over `expected_duration`.
+///
+/// Returns the prime closest to the ideal frequency, clamped to
+/// [`MIN_FREQUENCY_HZ`]..=[`MAX_FREQUENCY_HZ`]. Prime frequencies avoid synchronizing with
+/// periodic tasks.
+pub fn choose_frequency(expected_duration: Duration, target_samples: u64) -> u32 {
+    let ideal_frequency = target_samples as f64 / expected_duration.as_secs_f64();
+    let frequency = ideal_frequency
+        .clamp(MIN_FREQUENCY_HZ as f64, MAX_FREQUENCY_HZ as f64)
+        .round() as u32;
+
+    (0..)
+        .flat_map(|offset| [frequency - offset, frequency + offset])
+        .find(|candidate| {
+            (MIN_FREQUENCY_HZ..=MAX_FREQUENCY_HZ).contains(candidate) && is_prime(*candidate)
+        })
+        .expect("the frequency bounds are primes")
+}
+
+fn is_prime(n: u32) -> bool {
+    n >= 2 && (2..).take_while(|d| d * d <= n).all(|d| n % d != 0)
+}
+
+/// Resolve the sampling frequency from [`PERF_FREQ_ENV`].
+pub fn frequency_from_env() -> Result<u32> {
+    let Ok(value) = std::env::var(PERF_FREQ_ENV) else {
+        return Ok(SAMPLING_RATE_HZ);
+    };
+    if value.trim() != "auto" {
+        let frequency = value.trim().parse::<u32>().with_context(|| {
+            format!("Invalid {PERF_FREQ_ENV} value {value:?}, expected a frequency in Hz or `auto`")
+        })?;
+        ensure!(frequency > 0, "{PERF_FREQ_ENV} must be greater than 0");
+        return Ok(frequency);
+    }
+
+    let expected_duration_ms = std::env::var(PERF_EXPECTED_DURATION_MS_ENV).map_err(|_| {
+        anyhow!(
+            "{PERF_FREQ_ENV}=auto requires {PERF_EXPECTED_DURATION_MS_ENV}, \
+            the expected duration of a benchmark in milliseconds"
+        )
+    })?;
+    let expected_duration_ms = expected_duration_ms
+        .trim()
+        .parse::<u64>()
+        .ok()
+        .filter(|ms| *ms > 0)
+        .with_context(|| {
+            format!(
+                "Invalid {PERF_EXPECTED_DURATION_MS_ENV} value {expected_duration_ms:?}, \
+                expected a positive number of milliseconds"
+            )
+        })?;
+    let frequency = choose_frequency(
+        Duration::from_millis(expected_duration_ms),
+        AUTO_TARGET_SAMPLES,
+    );
+    debug!(
+        "Picked a sampling frequency of {frequency} Hz for benchmarks of {expected_duration_ms}ms"
+    );
+    Ok(frequency)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_prime() {
+        let primes = (0..30).filter(|n| is_prime(*n)).collect::<Vec<_>>();
+        assert_eq!(primes, vec![2, 3, 5, 7, 11, 13, 17, 19, 23, 29]);
+        assert!(is_prime(MIN_FREQUENCY_HZ));
+        assert!(is_prime(MAX_FREQUENCY_HZ));
+        assert!(is_prime(SAMPLING_RATE_HZ));
+    }
+
+    #[test]
+    fn test_choose_frequency_picks_the_closest_prime() {
+        // 1000 Hz is ideal: 997 is closer than 1009
+        assert_eq!(choose_frequency(Duration::from_secs(1), 1000), 997);
+        // 2000 Hz is ideal: 1999 is prime
+        assert_eq!(choose_frequency(Duration::from_millis(500), 1000), 1999);
+    }
+
+    #[test]
+    fn test_choose_frequency_clamps_to_bounds() {
+        assert_eq!(
+            choose_frequency(Duration::from_millis(1), 1000),
+            MAX_FREQUENCY_HZ
+        );
+        assert_eq!(
+            choose_frequency(Duration::from_secs(3600), 1000),
+            MIN_FREQUENCY_HZ
+        );
+        assert_eq!(choose_frequency(Duration::ZERO, 1000), MAX_FREQUENCY_HZ);
+    }
+
+    #[test]
+    fn test_frequency_from_env() {
+        temp_env::with_vars(
+            [
+                (PERF_FREQ_ENV, None::<&str>),
+                (PERF_EXPECTED_DURATION_MS_ENV, None),
+            ],
+            || assert_eq!(frequency_from_env().unwrap(), SAMPLING_RATE_HZ),
+        );
+        temp_env::with_var(PERF_FREQ_ENV, Some("499"), || {
+            assert_eq!(frequency_from_env().unwrap(), 499)
+        });
+        temp_env::with_var(PERF_FREQ_ENV, Some("fast"), || {
+            assert!(frequency_from_env().is_err())
+        });
+        temp_env::with_vars(
+            [
+                (PERF_FREQ_ENV, Some("auto")),
+                (PERF_EXPECTED_DURATION_MS_ENV, Some("500")),
+            ],
+            || assert_eq!(frequency_from_env().unwrap(), 1999),
+        );
+        temp_env::with_vars(
+            [
+                (PERF_FREQ_ENV, Some("auto")),
+                (PERF_EXPECTED_DURATION_MS_ENV, None),
+            ],
+            || assert!(frequency_from_env().is_err()),
+        );
+    }
+}
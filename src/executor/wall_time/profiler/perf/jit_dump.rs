@@ -5,6 +5,7 @@ use runner_shared::unwind_data::{ProcessUnwindData, UnwindData};
 use std::{
     collections::{HashMap, HashSet},
     path::{Path, PathBuf},
+    time::SystemTime,
 };
 
 struct JitDump {
@@ -106,6 +107,38 @@ impl JitDump {
     }
 }
 
+/// PIDs of the `jit-<pid>.dump` files of `dir` modified since `since` that are not in
+/// `tracked_pids`, sorted.
+fn untracked_jit_dump_pids(
+    dir: &Path,
+    tracked_pids: &HashSet<libc::pid_t>,
+    since: SystemTime,
+) -> Vec<libc::pid_t> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return vec![];
+    };
+
+    entries
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| {
+            entry
+                .metadata()
+                .and_then(|metadata| metadata.modified())
+                .is_ok_and(|modified| modified >= since)
+        })
+        .filter_map(|entry| {
+            let file_name = entry.file_name();
+            let pid = file_name
+                .to_str()?
+                .strip_prefix("jit-")?
+                .strip_suffix(".dump")?;
+            pid.parse::<libc::pid_t>().ok()
+        })
+        .filter(|pid| !tracked_pids.contains(pid))
+        .sorted()
+        .collect()
+}
+
 /// Converts all the `jit-<pid>.dump` into a perf-<pid>.map with symbols, and collects the unwind data
 ///
 /// # Symbols
@@ -117,9 +150,22 @@ impl JitDump {
 pub async fn save_symbols_and_harvest_unwind_data_for_pids(
     profile_folder: &Path,
     pids: &HashSet<libc::pid_t>,
+    started_at: SystemTime,
 ) -> Result<HashMap<i32, Vec<(UnwindData, ProcessUnwindData)>>> {
     let mut jit_unwind_data_by_path = HashMap::new();
 
+    // The dumps of other processes are not harvested, but the ones written during the run hint at
+    // a benchmark process that the integration did not report
+    let untracked_pids = untracked_jit_dump_pids(Path::new("/tmp"), pids, started_at);
+    if !untracked_pids.is_empty() {
+        warn!(
+            "Found JIT dumps in /tmp for processes that were not tracked (PIDs: {}), their JIT \
+             symbols are not harvested. If they ran benchmarks, the integration may not have \
+             reported their PIDs",
+            untracked_pids.iter().join(", ")
+        );
+    }
+
     for pid in pids {
         let name = format!("jit-{pid}.dump");
         let path = PathBuf::from("/tmp").join(&name);
@@ -153,3 +199,40 @@ pub async fn save_symbols_and_harvest_unwind_data_for_pids(
 
     Ok(jit_unwind_data_by_path)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_untracked_jit_dump_pids() {
+        let started_at = SystemTime::now() - Duration::from_secs(60);
+        let tmp = tempfile::tempdir().unwrap();
+        for file_name in [
+            "jit-100.dump",
+            "jit-200.dump",
+            "jit-42.dump",
+            "jit-7.dump",
+            "perf-300.map",
+            "jit-abc.dump",
+        ] {
+            std::fs::write(tmp.path().join(file_name), "").unwrap();
+        }
+        // Left over by a previous run
+        std::fs::File::options()
+            .write(true)
+            .open(tmp.path().join("jit-7.dump"))
+            .and_then(|file| file.set_modified(started_at - Duration::from_secs(3600)))
+            .unwrap();
+
+        let tracked_pids = HashSet::from([100]);
+        assert_eq!(
+            untracked_jit_dump_pids(tmp.path(), &tracked_pids, started_at),
+            vec![42, 200]
+        );
+
+        let tracked_pids = HashSet::from([42, 100, 200]);
+        assert!(untracked_jit_dump_pids(tmp.path(), &tracked_pids, started_at).is_empty());
+    }
+}
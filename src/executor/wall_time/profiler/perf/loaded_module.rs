@@ -14,6 +14,8 @@ pub struct LoadedModule {
     pub module_symbols: Option<ModuleSymbols>,
     /// Unwind data extracted from the mapped ELF file
     pub unwind_data: Option<UnwindData>,
+    /// Build-id of the ELF file when it was mapped, if perf recorded it
+    pub build_id: Option<Vec<u8>>,
     /// Per-process mounting information
     pub process_loaded_modules: HashMap<pid_t, ProcessLoadedModule>,
 }
@@ -23,8 +25,9 @@ pub struct ProcessLoadedModule {
     /// Load bias used to adjust declared elf addresses to their actual runtime addresses
     /// The bias is the difference between where the segment *actually* is in memory versus where the ELF file *preferred* it to be
     pub symbols_load_bias: Option<u64>,
-    /// Unwind data specific to the process mounting, derived from both load bias and the actual unwind data
-    pub process_unwind_data: Option<ProcessUnwindData>,
+    /// Unwind data specific to the process mounting, derived from both load bias and the actual unwind data.
+    /// One per executable segment, so that the gaps between the segments are not covered
+    pub process_unwind_data: Vec<ProcessUnwindData>,
 }
 
 impl LoadedModule {
@@ -7,44 +7,65 @@ use crate::executor::helpers::command::CommandBuilder;
 use crate::executor::helpers::detect_executable::command_has_executable;
 use crate::executor::helpers::env::is_codspeed_debug_enabled;
 use crate::executor::helpers::env::suppress_go_perf_unwinding_warning;
-use crate::executor::helpers::harvest_perf_maps_for_pids::harvest_perf_maps_for_pids;
+use crate::executor::helpers::harvest_perf_maps_for_pids::{
+    harvest_perf_maps_for_pids, remove_harvested_tmp_files,
+};
+use crate::executor::helpers::run_command_with_log_pipe::PERF_OUTPUT_PREFIX;
 use crate::executor::helpers::run_with_sudo::wrap_with_sudo;
 use crate::executor::shared::fifo::FifoBenchmarkData;
+use crate::executor::shared::markers_file;
 use crate::executor::wall_time::profiler::NO_BENCHMARKS_DETECTED_WARNING;
 use crate::executor::wall_time::profiler::Profiler;
 use crate::executor::wall_time::profiler::SAMPLING_RATE_HZ;
 use crate::executor::wall_time::profiler::WALLTIME_METADATA_CURRENT_VERSION;
 use crate::executor::wall_time::profiler::linux_sysctl::ensure_linux_profiling_sysctls;
-use crate::executor::wall_time::profiler::perf::perf_executable::get_working_perf_executable;
+use crate::executor::wall_time::profiler::perf::perf_executable::{
+    PERF_BIN_ENV, get_working_perf_executable, is_perf_executable_forced,
+};
 use crate::prelude::*;
-use crate::system::SystemInfo;
+use crate::system::{SystemInfo, capture_environment};
 use anyhow::Context;
 use async_trait::async_trait;
 use fifo::PerfFifo;
+use output_cap::PerfOutputCap;
 use parse_perf_file::MemmapRecordsOutput;
-use perf_executable::get_compression_flags;
-use perf_executable::get_event_flags;
+use perf_executable::PerfCapabilities;
 use runner_shared::artifacts::ArtifactExt;
 use runner_shared::artifacts::ExecutionTimestamps;
 use runner_shared::metadata::WalltimeMetadata;
 use std::path::Path;
 use std::path::PathBuf;
+use std::time::SystemTime;
+use symbols_cache::SymbolsCache;
 
 mod debug_info;
 mod elf_helper;
+mod frequency;
 mod jit_dump;
 mod loaded_module;
-mod module_symbols;
+pub(crate) mod module_symbols;
 mod naming;
-mod parse_perf_file;
+mod output_cap;
+pub(crate) mod parse_perf_file;
+mod sample_count;
 mod save_artifacts;
 pub(crate) mod setup;
+pub(crate) mod symbols_cache;
 mod unwind_data;
+mod warmup;
 
 pub mod fifo;
 pub mod perf_executable;
 
-const PERF_PIPEDATA_FILE_NAME: &str = "perf.pipedata";
+pub(crate) const PERF_PIPEDATA_FILE_NAME: &str = "perf.pipedata";
+
+/// Set to surface the output of perf itself in the debug logs, e.g. the number of samples it
+/// collected and its warnings.
+const PERF_VERBOSE_ENV: &str = "CODSPEED_PERF_VERBOSE";
+
+/// Run by the benchmark command in the verbose mode, to get back the original stderr that was
+/// saved on fd 3, perf's own stderr being prefixed.
+const RESTORE_STDERR_SCRIPT: &str = r#"exec 2>&3 3>&-; exec "$@""#;
 
 pub struct PerfProfiler {
     /// Set by [`Profiler::wrap_command`]; used by the FIFO hooks to control event
@@ -54,6 +75,38 @@ pub struct PerfProfiler {
     /// Path to the file that the wrapped command pipes `perf record`'s
     /// stdout into. Set by [`Profiler::wrap_command`]; consumed by [`Profiler::finalize`].
     perf_file_path: Option<PathBuf>,
+
+    /// User-provided globs of the modules to ignore. Set by [`Profiler::wrap_command`];
+    /// consumed by [`Profiler::finalize`].
+    ignored_objects: Vec<glob::Pattern>,
+
+    /// Whether to record the chains of inlined frames in the debug info. Set by
+    /// [`Profiler::wrap_command`]; consumed by [`Profiler::finalize`].
+    inline_frames: bool,
+
+    /// Whether to strip the absolute paths of the modules from the metadata. Set by
+    /// [`Profiler::wrap_command`]; consumed by [`Profiler::finalize`].
+    strip_paths: bool,
+
+    /// Frequency perf samples at, set by [`Profiler::wrap_command`]; used by
+    /// [`Profiler::finalize`] to report the benchmarks with too few samples.
+    sampling_frequency: u32,
+
+    /// Size guard on the perf output, set by [`Profiler::wrap_command`] when
+    /// [`output_cap::PERF_MAX_BYTES_ENV`] is set; checked by the FIFO hooks and on each tick.
+    output_cap: Option<PerfOutputCap>,
+
+    /// When the benchmark was started, set by [`Profiler::wrap_command`]; used by
+    /// [`Profiler::finalize`] to tell the files of `/tmp` written by this run.
+    started_at: Option<SystemTime>,
+
+    /// Whether the harvested perf maps and JIT dumps must be removed from `/tmp`, set by
+    /// [`Profiler::wrap_command`].
+    clean_tmp_maps: bool,
+
+    /// Cache of the symbols of the system libraries, set by [`Profiler::wrap_command`] when a
+    /// setup cache dir is provided; used by [`Profiler::finalize`].
+    symbols_cache: Option<SymbolsCache>,
 }
 
 impl PerfProfiler {
@@ -61,6 +114,14 @@ impl PerfProfiler {
         Self {
             perf_fifo: None,
             perf_file_path: None,
+            ignored_objects: vec![],
+            inline_frames: false,
+            strip_paths: false,
+            sampling_frequency: SAMPLING_RATE_HZ,
+            output_cap: None,
+            started_at: None,
+            clean_tmp_maps: false,
+            symbols_cache: None,
         }
     }
 
@@ -127,30 +188,43 @@ impl Profiler for PerfProfiler {
             UnwindingMode::Dwarf => &format!("dwarf,{}", stack_size.unwrap_or(8192)),
         };
         debug!("Using call graph mode: {cg_mode:?}");
+        let sampling_frequency = frequency::frequency_from_env()?;
 
         let working_perf_executable =
             get_working_perf_executable().context("Failed to find a working perf executable")?;
+        let perf_verbose = std::env::var(PERF_VERBOSE_ENV).is_ok();
         let mut perf_wrapper_builder = CommandBuilder::new(&working_perf_executable);
         perf_wrapper_builder.arg("record");
-        if !is_codspeed_debug_enabled() {
+        if !is_codspeed_debug_enabled() && !perf_verbose {
             perf_wrapper_builder.arg("--quiet");
         }
-        // Add compression if available
-        if let Some(compression_flags) = get_compression_flags(&working_perf_executable)? {
-            perf_wrapper_builder.arg(compression_flags);
-            // Add events flag if all required events are available
-            if let Some(events_flag) = get_event_flags(&working_perf_executable)? {
-                perf_wrapper_builder.arg(events_flag);
+        let capabilities = PerfCapabilities::probe(&working_perf_executable)?;
+        info!("{capabilities}");
+        if is_perf_executable_forced() {
+            if capabilities.compression_flags.is_none() {
+                warn!(
+                    "The perf executable set in {PERF_BIN_ENV} has no zstd compression, recording without it nor the extra events"
+                );
+            } else if capabilities.event_flags.is_none() {
+                warn!(
+                    "The perf executable set in {PERF_BIN_ENV} lacks some of the required events, recording without them"
+                );
             }
         }
-
+        perf_wrapper_builder.args(capabilities.compression_flags);
+        perf_wrapper_builder.args(capabilities.event_flags);
+        perf_wrapper_builder.args(capabilities.buildid_mmap_flag);
+
+        // Without the FIFO, raw runs and the integrations writing a markers file can't enable the
+        // events around their benchmarks
+        let records_whole_run = config.raw || markers_file::markers_file_is_set();
+        perf_wrapper_builder.args(warmup::delay_flag(records_whole_run)?);
         perf_wrapper_builder.args([
             "--timestamp",
             // Required for matching the markers and URIs to the samples.
             "-k",
             "CLOCK_MONOTONIC",
-            &format!("--freq={SAMPLING_RATE_HZ}"),
-            "--delay=-1",
+            &format!("--freq={sampling_frequency}"),
             "-g",
             "--user-callchains",
             &format!("--call-graph={cg_mode}"),
@@ -164,24 +238,36 @@ impl Profiler for PerfProfiler {
             "--",
         ]);
 
+        if perf_verbose {
+            cmd_builder.wrap("bash", ["-c", RESTORE_STDERR_SCRIPT, "bash"]);
+        }
         cmd_builder.wrap_with(perf_wrapper_builder);
 
-        let raw_command = format!(
-            "set -o pipefail && {} | cat > {}",
+        let raw_command = pipe_to_file_command(
             &cmd_builder.as_command_line(),
-            perf_file_path.to_string_lossy()
+            &perf_file_path,
+            perf_verbose,
         );
 
         let mut wrapped_builder = CommandBuilder::new("bash");
         wrapped_builder.args(["-c", &raw_command]);
 
-        // IMPORTANT: Preserve the working directory from the original command
+        // IMPORTANT: Preserve the working directory and environment from the original command
         if let Some(cwd) = cmd_builder.get_current_dir() {
             wrapped_builder.current_dir(cwd);
         }
+        wrapped_builder.envs(cmd_builder.get_envs());
 
         self.perf_fifo = Some(perf_fifo);
+        self.output_cap = PerfOutputCap::from_env(&perf_file_path)?;
         self.perf_file_path = Some(perf_file_path);
+        self.ignored_objects = parse_ignored_objects(&config.ignored_objects)?;
+        self.inline_frames = config.inline_frames;
+        self.strip_paths = config.strip_paths;
+        self.sampling_frequency = sampling_frequency;
+        self.started_at = Some(SystemTime::now());
+        self.clean_tmp_maps = config.clean_tmp_maps;
+        self.symbols_cache = config.symbols_cache_dir.clone().map(SymbolsCache::new);
 
         // Isolated runs reparent the benchmark out of perf's subtree, so perf
         // must record system-wide under sudo. Unisolated runs record perf's own
@@ -196,15 +282,41 @@ impl Profiler for PerfProfiler {
     }
 
     async fn on_start_profiler(&mut self) -> anyhow::Result<()> {
+        // Keep the events disabled once the output is capped
+        if self.output_cap.as_mut().is_some_and(|cap| cap.check()) {
+            return Ok(());
+        }
         self.perf_fifo_mut()?.start_events().await
     }
 
     async fn on_stop_profiler(&mut self) -> anyhow::Result<()> {
-        self.perf_fifo_mut()?.stop_events().await
+        self.perf_fifo_mut()?.stop_events().await?;
+        if let Some(cap) = self.output_cap.as_mut() {
+            cap.check();
+        }
+        Ok(())
+    }
+
+    async fn on_tick(&mut self) -> anyhow::Result<()> {
+        // Once reached, the events were already stopped or never enabled again
+        let Some(cap) = self.output_cap.as_mut().filter(|cap| !cap.is_reached()) else {
+            return Ok(());
+        };
+        // Also covers the runs recording without benchmark boundaries, e.g. `--raw`, where the
+        // start and stop hooks are never called
+        if cap.check() {
+            self.perf_fifo_mut()?.stop_events().await?;
+        }
+        Ok(())
     }
 
     async fn on_ping(&mut self) -> anyhow::Result<bool> {
-        Ok(self.perf_fifo_mut()?.ping().await.is_ok())
+        // Without a working control FIFO, the events are never enabled and the whole run would
+        // produce no data, so abort it right away
+        self.perf_fifo_mut()?.ping().await.context(
+            "perf control FIFO not responding; perf may have failed to start — check permissions",
+        )?;
+        Ok(true)
     }
 
     async fn finalize(
@@ -219,17 +331,29 @@ impl Profiler for PerfProfiler {
             .perf_file_path
             .as_ref()
             .context("PerfProfiler::wrap_command must be called before finalize")?;
+        let started_at = self
+            .started_at
+            .context("PerfProfiler::wrap_command must be called before finalize")?;
 
         let bench_data = BenchmarkData {
             fifo_data,
             marker_result: timestamps,
+            ignored_objects: &self.ignored_objects,
+            inline_frames: self.inline_frames,
+            strip_paths: self.strip_paths,
+            started_at,
+            clean_tmp_maps: self.clean_tmp_maps,
+            symbols_cache: self.symbols_cache.as_ref(),
+            forced_pid_filter: parse_perf_file::PidFilter::forced_from_env()?,
+            fork_tracking: parse_perf_file::fork_tracking_from_env(),
+            sampling_frequency: self.sampling_frequency,
         };
 
         // Append perf maps, unwind info and other metadata
         if let Err(BenchmarkDataSaveError::MissingIntegration) =
             bench_data.save_to(profile_folder, perf_file_path).await
         {
-            warn!("{NO_BENCHMARKS_DETECTED_WARNING}");
+            run_warning!("{NO_BENCHMARKS_DETECTED_WARNING}");
             return Ok(());
         }
 
@@ -238,9 +362,56 @@ impl Profiler for PerfProfiler {
     }
 }
 
+/// Shell command running `command_line` with its stdout written to `output_path`, failing if
+/// the command fails. The paths are quoted, the command line must already be.
+///
+/// With `prefix_stderr`, each line of the stderr of the command is prefixed with
+/// [`PERF_OUTPUT_PREFIX`] as it is written, for the log pipe to log it at the debug level, and
+/// the original stderr is available on fd 3, see [`RESTORE_STDERR_SCRIPT`]. The stdout carrying
+/// the data is untouched.
+fn pipe_to_file_command(command_line: &str, output_path: &Path, prefix_stderr: bool) -> String {
+    let output_path = shell_words::quote(&output_path.to_string_lossy()).into_owned();
+    if !prefix_stderr {
+        return format!("set -o pipefail && {command_line} | cat > {output_path}");
+    }
+    // The stderr of the command goes to the prefixing loop and its stdout to fd 4, the pipe to
+    // the data file
+    let prefix_lines = format!(
+        r#"while IFS= read -r line || [ -n "$line" ]; do printf '{PERF_OUTPUT_PREFIX}%s\n' "$line"; done"#
+    );
+    format!(
+        "set -o pipefail && {{ {{ {command_line} 2>&1 >&4 4>&- | {prefix_lines} >&3; }} 4>&1 | cat > {output_path}; }} 3>&2"
+    )
+}
+
+/// Compile the `--ignore-object` globs.
+fn parse_ignored_objects(globs: &[String]) -> anyhow::Result<Vec<glob::Pattern>> {
+    globs
+        .iter()
+        .map(|g| {
+            glob::Pattern::new(g).with_context(|| format!("Invalid --ignore-object glob `{g}`"))
+        })
+        .collect()
+}
+
 struct BenchmarkData<'a> {
     fifo_data: &'a FifoBenchmarkData,
     marker_result: &'a ExecutionTimestamps,
+    ignored_objects: &'a [glob::Pattern],
+    inline_frames: bool,
+    strip_paths: bool,
+    /// When the benchmark was started, the files of `/tmp` modified before are from other runs
+    started_at: SystemTime,
+    /// Whether the harvested files of `/tmp` modified since `started_at` are removed
+    clean_tmp_maps: bool,
+    symbols_cache: Option<&'a SymbolsCache>,
+    /// Set through [`parse_perf_file::FORCE_PIDS_ENV`], replaces the bench pids filter
+    forced_pid_filter: Option<parse_perf_file::PidFilter>,
+    /// Unset through [`parse_perf_file::NO_FORK_TRACKING_ENV`], to parse the records of the
+    /// tracked pids only
+    fork_tracking: bool,
+    /// Frequency perf sampled at, see [`frequency::frequency_from_env`]
+    sampling_frequency: u32,
 }
 
 #[derive(Debug)]
@@ -257,68 +428,322 @@ impl BenchmarkData<'_> {
         path: &Path,
         perf_file_path: &Path,
     ) -> Result<(), BenchmarkDataSaveError> {
-        self.marker_result.save_to(path).unwrap();
-
-        let pid_filter = if self.fifo_data.is_exec_harness() {
+        let tolerance_ns = sample_count::marker_tolerance_from_env().unwrap_or_else(|error| {
+            warn!(
+                "{error:#}, using the default tolerance of {}ns",
+                sample_count::DEFAULT_MARKER_TOLERANCE_NS
+            );
+            sample_count::DEFAULT_MARKER_TOLERANCE_NS
+        });
+        let marker_result = sample_count::widen_sample_markers(self.marker_result, tolerance_ns);
+        marker_result.save_to(path).unwrap();
+
+        let pid_filter = if let Some(forced_pid_filter) = &self.forced_pid_filter {
+            info!(
+                "{} is set, parsing the perf records of the forced PIDs",
+                parse_perf_file::FORCE_PIDS_ENV
+            );
+            forced_pid_filter.clone()
+        } else if self.fifo_data.all_pids
+            || self.fifo_data.is_exec_harness()
+            || self.fifo_data.is_raw()
+        {
             parse_perf_file::PidFilter::All
         } else {
             parse_perf_file::PidFilter::TrackedPids(self.fifo_data.bench_pids.clone())
         };
+        let pid_filter = if self.fork_tracking {
+            pid_filter
+        } else {
+            info!(
+                "{} is set, the processes forked by the benchmarks are not profiled",
+                parse_perf_file::NO_FORK_TRACKING_ENV
+            );
+            pid_filter.without_fork_tracking()
+        };
 
         debug!("Pid filter for perf file parsing: {pid_filter:?}");
         debug!("Reading perf data from file for mmap extraction");
+        let memmap_records =
+            parse_perf_file::parse_for_memmap2(perf_file_path, pid_filter, self.symbols_cache)
+                .map_err(|e| {
+                    error!("Failed to parse perf file: {e:#}");
+                    BenchmarkDataSaveError::FailedToParsePerfFile
+                })?;
+        if let Some(warning) = memmap_records.empty_profile_warning() {
+            run_warning!("{warning}");
+        }
+        sample_count::warn_low_sample_benchmarks(
+            &marker_result,
+            &memmap_records.sample_timestamps,
+            self.sampling_frequency,
+        );
         let MemmapRecordsOutput {
             loaded_modules_by_path,
             tracked_pids,
-        } = parse_perf_file::parse_for_memmap2(perf_file_path, pid_filter).map_err(|e| {
-            error!("Failed to parse perf file: {e}");
-            BenchmarkDataSaveError::FailedToParsePerfFile
-        })?;
+            ..
+        } = memmap_records;
 
         // Harvest the perf maps generated by python. This will copy the perf
         // maps from /tmp to the profile folder. We have to write our own perf
         // maps to these files AFTERWARDS, otherwise it'll be overwritten!
         debug!("Harvesting perf maps and jit dumps for pids: {tracked_pids:?}");
-        harvest_perf_maps_for_pids(path, &tracked_pids)
+        let perf_maps = harvest_perf_maps_for_pids(path, &tracked_pids)
             .await
             .map_err(|e| {
                 error!("Failed to harvest perf maps: {e}");
                 BenchmarkDataSaveError::FailedToHarvestPerfMaps
             })?;
-        let jit_unwind_data_by_pid =
-            jit_dump::save_symbols_and_harvest_unwind_data_for_pids(path, &tracked_pids)
-                .await
-                .map_err(|e| {
-                    error!("Failed to harvest jit dumps: {e}");
-                    BenchmarkDataSaveError::FailedToHarvestJitDumps
-                })?;
+        let jit_unwind_data_by_pid = jit_dump::save_symbols_and_harvest_unwind_data_for_pids(
+            path,
+            &tracked_pids,
+            self.started_at,
+        )
+        .await
+        .map_err(|e| {
+            error!("Failed to harvest jit dumps: {e}");
+            BenchmarkDataSaveError::FailedToHarvestJitDumps
+        })?;
+        if self.clean_tmp_maps {
+            let jit_dumps = jit_unwind_data_by_pid
+                .keys()
+                .map(|pid| PathBuf::from("/tmp").join(format!("jit-{pid}.dump")));
+            remove_harvested_tmp_files(
+                &perf_maps.into_iter().chain(jit_dumps).collect_vec(),
+                self.started_at,
+            );
+        }
 
-        let artifacts =
-            save_artifacts::save_artifacts(path, &loaded_modules_by_path, &jit_unwind_data_by_pid);
+        let artifacts = save_artifacts::save_artifacts(
+            path,
+            &loaded_modules_by_path,
+            &jit_unwind_data_by_pid,
+            self.ignored_objects,
+            self.inline_frames,
+        );
 
         debug!("Saving metadata");
         #[allow(deprecated)]
-        let metadata = WalltimeMetadata {
+        let mut metadata = WalltimeMetadata {
             version: WALLTIME_METADATA_CURRENT_VERSION,
             integration: self
                 .fifo_data
                 .integration
                 .clone()
                 .ok_or(BenchmarkDataSaveError::MissingIntegration)?,
-            uri_by_ts: self.marker_result.uri_by_ts.clone(),
+            uri_by_ts: marker_result.uri_by_ts.clone(),
+            ignored_modules: save_artifacts::flatten_ignored_modules(
+                &artifacts.ignored_modules_by_pid,
+            ),
             ignored_modules_by_pid: artifacts.ignored_modules_by_pid,
-            markers: self.marker_result.markers.clone(),
+            markers: marker_result.markers.clone(),
             debug_info: artifacts.debug_info,
             mapped_process_debug_info_by_pid: artifacts.mapped_process_debug_info_by_pid,
             mapped_process_unwind_data_by_pid: artifacts.mapped_process_unwind_data_by_pid,
             mapped_process_module_symbols: artifacts.symbol_pid_mappings_by_pid,
+            module_symbols_line_count: artifacts.symbols_line_count_by_key,
             path_key_to_path: artifacts.key_to_path,
+            environment: capture_environment(),
             // Deprecated fields below are no longer used
             debug_info_by_pid: Default::default(),
-            ignored_modules: Default::default(),
         };
+        if self.strip_paths {
+            metadata.strip_paths();
+        }
         metadata.save_to(path).unwrap();
 
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::executor::helpers::run_command_with_log_pipe::log_perf_output;
+    use crate::executor::shared::fifo::EXEC_HARNESS_INTEGRATION_NAME;
+
+    #[test]
+    fn test_pipe_to_file_command_quotes_the_output_path() {
+        let folder = tempfile::tempdir().unwrap();
+        let output_path = folder.path().join("it's a $HOME.pipedata");
+        let mut builder = CommandBuilder::new("printf");
+        builder.args(["%s", "perf data"]);
+
+        let status = std::process::Command::new("bash")
+            .args([
+                "-c",
+                &pipe_to_file_command(&builder.as_command_line(), &output_path, false),
+            ])
+            .status()
+            .unwrap();
+
+        assert!(status.success());
+        assert_eq!(std::fs::read_to_string(&output_path).unwrap(), "perf data");
+    }
+
+    #[test]
+    fn test_verbose_perf_stderr_is_routed_to_the_logs() {
+        let folder = tempfile::tempdir().unwrap();
+        let output_path = folder.path().join(PERF_PIPEDATA_FILE_NAME);
+
+        // A fake perf writing its data to stdout and its messages to stderr, then running the
+        // benchmark which writes to stderr too
+        let mut builder = CommandBuilder::new("bash");
+        builder.args(["-c", "echo error >&2"]);
+        builder.wrap("bash", ["-c", RESTORE_STDERR_SCRIPT, "bash"]);
+        builder.wrap(
+            "bash",
+            [
+                "-c",
+                r#"printf 'perf data'; echo '[ perf record: Captured 42 samples ]' >&2; "$@""#,
+                "perf",
+            ],
+        );
+
+        let output = std::process::Command::new("bash")
+            .args([
+                "-c",
+                &pipe_to_file_command(&builder.as_command_line(), &output_path, true),
+            ])
+            .output()
+            .unwrap();
+
+        assert!(output.status.success());
+        assert_eq!(std::fs::read_to_string(&output_path).unwrap(), "perf data");
+        // The output of perf is prefixed for the log pipe, the benchmark keeps its own stderr
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert_eq!(
+            stderr.lines().sorted().collect_vec(),
+            vec!["[perf] [ perf record: Captured 42 samples ]", "error"]
+        );
+        assert_eq!(
+            log_perf_output(&stderr),
+            "error\n",
+            "the lines of perf are logged rather than printed"
+        );
+    }
+
+    /// A perf pipe stream without any event: the header, then the `PERF_RECORD_HEADER_ATTR`
+    /// record of a software event, with a `perf_event_attr` of `PERF_ATTR_SIZE_VER0` bytes.
+    fn empty_perf_pipedata() -> Vec<u8> {
+        const PERF_RECORD_HEADER_ATTR: u32 = 64;
+        const PERF_ATTR_SIZE_VER0: u32 = 64;
+        let mut data = b"PERFILE2".to_vec();
+        data.extend_from_slice(&16u64.to_le_bytes());
+        data.extend_from_slice(&PERF_RECORD_HEADER_ATTR.to_le_bytes());
+        data.extend_from_slice(&0u16.to_le_bytes());
+        data.extend_from_slice(&(8 + PERF_ATTR_SIZE_VER0 as u16).to_le_bytes());
+        data.extend_from_slice(&1u32.to_le_bytes()); // PERF_TYPE_SOFTWARE
+        data.extend_from_slice(&PERF_ATTR_SIZE_VER0.to_le_bytes());
+        data.extend_from_slice(&0u64.to_le_bytes()); // PERF_COUNT_SW_CPU_CLOCK
+        data.extend_from_slice(&997u64.to_le_bytes()); // sample_freq
+        data.extend_from_slice(&0x6u64.to_le_bytes()); // PERF_SAMPLE_TID | PERF_SAMPLE_TIME
+        data.extend_from_slice(&[0; 32]); // read_format, flags, wakeup_events, bp_type, config1
+        data
+    }
+
+    #[tokio::test]
+    async fn test_benchmark_data_round_trips_through_save_to() {
+        let profile_folder = tempfile::tempdir().unwrap();
+        let perf_file_path = profile_folder.path().join(PERF_PIPEDATA_FILE_NAME);
+        std::fs::write(&perf_file_path, empty_perf_pipedata()).unwrap();
+
+        let fifo_data =
+            FifoBenchmarkData::new((EXEC_HARNESS_INTEGRATION_NAME, "1.0.0"), []).unwrap();
+        let marker_result = ExecutionTimestamps::new(&[(100, "bench::fib".to_string())], &[]);
+        BenchmarkData {
+            fifo_data: &fifo_data,
+            marker_result: &marker_result,
+            ignored_objects: &[],
+            inline_frames: false,
+            strip_paths: false,
+            started_at: SystemTime::now(),
+            clean_tmp_maps: false,
+            symbols_cache: None,
+            forced_pid_filter: None,
+            fork_tracking: true,
+            sampling_frequency: SAMPLING_RATE_HZ,
+        }
+        .save_to(profile_folder.path(), &perf_file_path)
+        .await
+        .unwrap();
+
+        let metadata = WalltimeMetadata::load_from(profile_folder.path()).unwrap();
+        assert_eq!(
+            metadata.integration,
+            ("exec-harness".to_string(), "1.0.0".to_string())
+        );
+        assert_eq!(metadata.uri_by_ts, marker_result.uri_by_ts);
+    }
+
+    #[tokio::test]
+    async fn test_markers_file_run_is_saved_with_its_integration() {
+        use crate::executor::shared::markers_file::{
+            MARKERS_FILE_ENV, MARKERS_FILE_NAME, merge_markers_file,
+        };
+
+        let profile_folder = tempfile::tempdir().unwrap();
+        let perf_file_path = profile_folder.path().join(PERF_PIPEDATA_FILE_NAME);
+        std::fs::write(&perf_file_path, empty_perf_pipedata()).unwrap();
+        std::fs::write(
+            profile_folder.path().join(MARKERS_FILE_NAME),
+            r#"{
+                "integration": "my-harness:1.2.0",
+                "benchmarks": [{ "uri": "bench::parse", "start_ts": 1000, "stop_ts": 2000 }]
+            }"#,
+        )
+        .unwrap();
+
+        // Nothing was reported through the FIFO
+        let reported = FifoBenchmarkData {
+            integration: None,
+            bench_pids: Default::default(),
+            all_pids: false,
+        };
+        let (fifo_data, marker_result) = temp_env::with_var_unset(MARKERS_FILE_ENV, || {
+            merge_markers_file(
+                reported,
+                ExecutionTimestamps::new(&[], &[]),
+                profile_folder.path(),
+            )
+            .unwrap()
+        });
+        BenchmarkData {
+            fifo_data: &fifo_data,
+            marker_result: &marker_result,
+            ignored_objects: &[],
+            inline_frames: false,
+            strip_paths: false,
+            started_at: SystemTime::now(),
+            clean_tmp_maps: false,
+            symbols_cache: None,
+            forced_pid_filter: None,
+            fork_tracking: true,
+            sampling_frequency: SAMPLING_RATE_HZ,
+        }
+        .save_to(profile_folder.path(), &perf_file_path)
+        .await
+        .unwrap();
+
+        let metadata = WalltimeMetadata::load_from(profile_folder.path()).unwrap();
+        assert_eq!(
+            metadata.integration,
+            ("my-harness".to_string(), "1.2.0".to_string())
+        );
+        assert_eq!(metadata.uri_by_ts, vec![(1000, "bench::parse".to_string())]);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_ping_aborts_when_perf_does_not_respond() {
+        // Nothing reads the control FIFO, as when perf failed to start
+        let mut profiler = PerfProfiler::new();
+        profiler.perf_fifo = Some(PerfFifo::new().unwrap());
+
+        let error = profiler.on_ping().await.unwrap_err();
+        assert!(
+            error
+                .to_string()
+                .starts_with("perf control FIFO not responding")
+        );
+    }
+}
@@ -1,12 +1,15 @@
 use super::elf_helper;
-use log::trace;
+use log::{trace, warn};
 use object::{Object, ObjectSymbol, ObjectSymbolTable};
 use runner_shared::module_symbols::SYMBOLS_MAP_SUFFIX;
 use std::{
-    collections::HashSet,
+    borrow::Cow,
+    collections::{HashMap, HashSet},
     fmt::Debug,
-    io::{BufWriter, Write},
-    path::Path,
+    io::{BufRead, BufReader, BufWriter, Write},
+    ops::Range,
+    path::{Path, PathBuf},
+    sync::OnceLock,
 };
 
 #[derive(Hash, PartialEq, Eq, Clone)]
@@ -26,6 +29,116 @@ impl Debug for Symbol {
     }
 }
 
+impl Symbol {
+    /// The name of the symbol, demangled if it is a mangled Rust or C++ name.
+    ///
+    /// Only meant for display: the symbols files keep the raw names, which the backend expects.
+    pub fn demangled_name(&self) -> Cow<'_, str> {
+        demangle(&self.name)
+    }
+}
+
+/// Demangle a Rust (legacy or v0) or C++ symbol name, or return it unchanged if it isn't mangled.
+///
+/// The Rust names are tried first, since the legacy Rust mangling is also valid C++ mangling.
+/// Their hash suffix is dropped.
+pub fn demangle(name: &str) -> Cow<'_, str> {
+    if let Ok(demangled) = rustc_demangle::try_demangle(name) {
+        return Cow::Owned(format!("{demangled:#}"));
+    }
+    if name.starts_with("_Z") {
+        if let Ok(symbol) = cpp_demangle::Symbol::new(name) {
+            return Cow::Owned(symbol.to_string());
+        }
+    }
+    Cow::Borrowed(name)
+}
+
+/// Maximum size in bytes given to a zero-sized symbol when extending it to the next symbol.
+pub const MAX_SYMBOL_EXTENSION_ENV: &str = "CODSPEED_MAX_SYMBOL_EXTENSION";
+const DEFAULT_MAX_SYMBOL_EXTENSION: u64 = 64 * 1024;
+
+/// The extension cap, read once from the environment so that an invalid value is only reported
+/// once.
+pub(super) fn max_symbol_extension() -> u64 {
+    static MAX_SYMBOL_EXTENSION: OnceLock<u64> = OnceLock::new();
+    *MAX_SYMBOL_EXTENSION.get_or_init(max_symbol_extension_from_env)
+}
+
+/// Read the extension cap from the environment, falling back to the default on invalid values.
+fn max_symbol_extension_from_env() -> u64 {
+    let Ok(value) = std::env::var(MAX_SYMBOL_EXTENSION_ENV) else {
+        return DEFAULT_MAX_SYMBOL_EXTENSION;
+    };
+    match value.trim().parse::<u64>() {
+        Ok(max) if max > 0 => max,
+        _ => {
+            warn!(
+                "Invalid {MAX_SYMBOL_EXTENSION_ENV} value {value:?}, expected a positive number of bytes. Using the default of {DEFAULT_MAX_SYMBOL_EXTENSION} bytes"
+            );
+            DEFAULT_MAX_SYMBOL_EXTENSION
+        }
+    }
+}
+
+/// Update zero-sized symbols to cover the range until the next symbol, as perf does:
+/// https://github.com/torvalds/linux/blob/e538109ac71d801d26776af5f3c54f548296c29c/tools/perf/util/symbol.c#L256
+/// A common source for these is inline assembly functions.
+///
+/// Unlike perf, the extension is capped to `max_extension` bytes, so that a small stub followed
+/// by a large gap isn't credited the samples of the whole gap. `symbols` must be sorted by
+/// address.
+fn extend_zero_sized_symbols(symbols: &mut [Symbol], max_extension: u64) {
+    for i in 0..symbols.len() {
+        if symbols[i].size == 0 {
+            let size = if i + 1 < symbols.len() {
+                // Set size to the distance to the next symbol
+                symbols[i + 1].addr.saturating_sub(symbols[i].addr)
+            } else {
+                // Last symbol: round up to next 4KB page boundary and add 4KiB
+                // This matches perf's behavior: roundup(curr->start, 4096) + 4096
+                const PAGE_SIZE: u64 = 4096;
+                let addr = symbols[i].addr;
+                let end_addr = addr.next_multiple_of(PAGE_SIZE) + PAGE_SIZE;
+                end_addr.saturating_sub(addr)
+            };
+            symbols[i].size = size.min(max_extension);
+        }
+    }
+}
+
+/// A symbol of an object file, with its binding.
+struct ElfSymbol {
+    symbol: Symbol,
+    is_weak: bool,
+}
+
+/// Keep a single symbol per `(addr, size)`: the same function is often in both the `.symtab`
+/// and `.dynsym` tables, or has weak aliases. Strong symbols are preferred over weak ones, then
+/// the last one is kept, which is the one [`ModuleSymbols::find`] resolves to among duplicates.
+///
+/// The order of the kept symbols is preserved.
+fn dedup_symbols(symbols: Vec<ElfSymbol>) -> Vec<Symbol> {
+    let mut kept_by_key: HashMap<(u64, u64), usize> = HashMap::new();
+    for (index, elf_symbol) in symbols.iter().enumerate() {
+        let key = (elf_symbol.symbol.addr, elf_symbol.symbol.size);
+        match kept_by_key.get(&key) {
+            Some(&kept) if elf_symbol.is_weak && !symbols[kept].is_weak => {}
+            _ => {
+                kept_by_key.insert(key, index);
+            }
+        }
+    }
+
+    let kept: HashSet<usize> = kept_by_key.into_values().collect();
+    symbols
+        .into_iter()
+        .enumerate()
+        .filter(|(index, _)| kept.contains(index))
+        .map(|(_, elf_symbol)| elf_symbol.symbol)
+        .collect()
+}
+
 #[derive(Debug, Clone)]
 /// Symbols for a module, extracted from an ELF file.
 /// The addresses are raw ELF addresses, meaning they represent where the symbols request to be loaded in memory.
@@ -45,6 +158,16 @@ impl ModuleSymbols {
         &self.symbols
     }
 
+    /// Find the symbol covering the raw ELF address `addr`.
+    ///
+    /// Relies on the symbols being sorted by address, as produced by [`Self::from_elf`] and
+    /// [`Self::from_keyed_file`].
+    pub fn find(&self, addr: u64) -> Option<&Symbol> {
+        let index = self.symbols.partition_point(|symbol| symbol.addr <= addr);
+        let symbol = self.symbols.get(index.checked_sub(1)?)?;
+        (addr < symbol.addr + symbol.size).then_some(symbol)
+    }
+
     /// Returns `(min_addr, max_addr_end)` covering all symbols, or `None` if empty.
     pub fn addr_bounds(&self) -> Option<(u64, u64)> {
         let first = self.symbols.first()?;
@@ -58,25 +181,19 @@ impl ModuleSymbols {
     }
 
     /// Extract raw symbols from an object file's `.symtab` and `.dynsym` tables.
-    fn extract_symbols_from_object(object: &object::File) -> Vec<Symbol> {
+    fn extract_symbols_from_object(object: &object::File) -> Vec<ElfSymbol> {
         let mut symbols = Vec::new();
 
-        if let Some(symbol_table) = object.symbol_table() {
+        let tables = [object.symbol_table(), object.dynamic_symbol_table()];
+        for symbol_table in tables.into_iter().flatten() {
             symbols.extend(symbol_table.symbols().filter_map(|symbol| {
-                Some(Symbol {
-                    addr: symbol.address(),
-                    size: symbol.size(),
-                    name: symbol.name().ok()?.to_string(),
-                })
-            }));
-        }
-
-        if let Some(symbol_table) = object.dynamic_symbol_table() {
-            symbols.extend(symbol_table.symbols().filter_map(|symbol| {
-                Some(Symbol {
-                    addr: symbol.address(),
-                    size: symbol.size(),
-                    name: symbol.name().ok()?.to_string(),
+                Some(ElfSymbol {
+                    symbol: Symbol {
+                        addr: symbol.address(),
+                        size: symbol.size(),
+                        name: symbol.name().ok()?.to_string(),
+                    },
+                    is_weak: symbol.is_weak(),
                 })
             }));
         }
@@ -109,12 +226,14 @@ impl ModuleSymbols {
             });
 
             if let Some(debug_symbols) = debug_symbols {
-                let existing: HashSet<(u64, String)> =
-                    symbols.iter().map(|s| (s.addr, s.name.clone())).collect();
+                let existing: HashSet<(u64, String)> = symbols
+                    .iter()
+                    .map(|s| (s.symbol.addr, s.symbol.name.clone()))
+                    .collect();
                 symbols.extend(
                     debug_symbols
                         .into_iter()
-                        .filter(|s| !existing.contains(&(s.addr, s.name.clone()))),
+                        .filter(|s| !existing.contains(&(s.symbol.addr, s.symbol.name.clone()))),
                 );
             }
         }
@@ -122,7 +241,7 @@ impl ModuleSymbols {
         // Filter out
         //  - ARM ELF "mapping symbols" (https://github.com/torvalds/linux/blob/9448598b22c50c8a5bb77a9103e2d49f134c9578/tools/perf/util/symbol-elf.c#L1591C1-L1598C4)
         //  - symbols that have en empty name
-        symbols.retain(|symbol| {
+        symbols.retain(|ElfSymbol { symbol, .. }| {
             if symbol.name.is_empty() {
                 return false;
             }
@@ -138,26 +257,10 @@ impl ModuleSymbols {
             true
         });
 
-        // Update zero-sized symbols to cover the range until the next symbol
-        // This is what perf does
-        // https://github.com/torvalds/linux/blob/e538109ac71d801d26776af5f3c54f548296c29c/tools/perf/util/symbol.c#L256
-        // A common source for these is inline assembly functions.
-        symbols.sort_by_key(|symbol| symbol.addr);
-        for i in 0..symbols.len() {
-            if symbols[i].size == 0 {
-                if i + 1 < symbols.len() {
-                    // Set size to the distance to the next symbol
-                    symbols[i].size = symbols[i + 1].addr.saturating_sub(symbols[i].addr);
-                } else {
-                    // Last symbol: round up to next 4KB page boundary and add 4KiB
-                    // This matches perf's behavior: roundup(curr->start, 4096) + 4096
-                    const PAGE_SIZE: u64 = 4096;
-                    let addr = symbols[i].addr;
-                    let end_addr = addr.next_multiple_of(PAGE_SIZE) + PAGE_SIZE;
-                    symbols[i].size = end_addr.saturating_sub(addr);
-                }
-            }
-        }
+        symbols.sort_by_key(|s| s.symbol.addr);
+        let mut symbols = dedup_symbols(symbols);
+
+        extend_zero_sized_symbols(&mut symbols, max_symbol_extension());
 
         // Filter out any symbols are still zero-sized
         symbols.retain(|symbol| symbol.size > 0);
@@ -169,6 +272,16 @@ impl ModuleSymbols {
         Ok(Self { symbols })
     }
 
+    /// The page offsets from which the module at `path` maps its executable segments, to
+    /// recognize the mappings whose protection is unknown.
+    pub fn executable_segment_page_ranges<P: AsRef<Path>>(
+        path: P,
+    ) -> anyhow::Result<Vec<Range<u64>>> {
+        let content = std::fs::read(path.as_ref())?;
+        let object = object::File::parse(&*content)?;
+        Ok(elf_helper::executable_segment_page_ranges(&object))
+    }
+
     /// Compute the load_bias for this module given runtime addresses.
     /// This reads the ELF file again to find the matching PT_LOAD segment.
     pub fn compute_load_bias<P: AsRef<Path>>(
@@ -203,21 +316,148 @@ impl ModuleSymbols {
                 symbol.addr, symbol.size, symbol.name
             )?;
         }
+        // Flush explicitly: errors are silently ignored when the writer is dropped
+        writer.flush()?;
 
         Ok(())
     }
 
+    /// Load the symbols saved for `key` by [`Self::save_to_keyed_file`], sorted by address.
+    pub fn from_keyed_file<P: AsRef<Path>>(folder: P, key: &str) -> anyhow::Result<Self> {
+        let path = keyed_file_path(folder, key);
+        let file = std::fs::File::open(&path)?;
+        let mut symbols = Vec::new();
+        for (index, line) in BufReader::new(file).lines().enumerate() {
+            let line = line?;
+            let symbol = parse_symbol_line(&line).ok_or_else(|| {
+                anyhow::anyhow!(
+                    "malformed line {} of {}: {line:?}",
+                    index + 1,
+                    path.display()
+                )
+            })?;
+            symbols.push(symbol);
+        }
+        symbols.sort_by_key(|symbol| symbol.addr);
+
+        Ok(Self { symbols })
+    }
+
     /// Save symbols (at raw ELF addresses, no bias) to a keyed file.
     pub fn save_to_keyed_file<P: AsRef<Path>>(&self, folder: P, key: &str) -> anyhow::Result<()> {
-        let path = folder.as_ref().join(format!("{key}.{SYMBOLS_MAP_SUFFIX}"));
-        self.append_to_file(path)
+        self.append_to_file(keyed_file_path(folder, key))
     }
 }
 
+/// Path of the symbols file saved for `key` by [`ModuleSymbols::save_to_keyed_file`].
+pub fn keyed_file_path<P: AsRef<Path>>(folder: P, key: &str) -> PathBuf {
+    folder.as_ref().join(format!("{key}.{SYMBOLS_MAP_SUFFIX}"))
+}
+
+/// Parse a `<hex addr> <hex size> <name>` line of a symbols file.
+fn parse_symbol_line(line: &str) -> Option<Symbol> {
+    let mut parts = line.splitn(3, ' ');
+    let addr = u64::from_str_radix(parts.next()?, 16).ok()?;
+    let size = u64::from_str_radix(parts.next()?, 16).ok()?;
+    let name = parts.next().filter(|name| !name.is_empty())?;
+    Some(Symbol {
+        addr,
+        size,
+        name: name.to_string(),
+    })
+}
+
+/// Read back a symbols file and check that it holds `expected_lines` well-formed
+/// `<hex addr> <hex size> <name>` lines, returning the line count.
+pub fn validate_symbols_file<P: AsRef<Path>>(
+    path: P,
+    expected_lines: usize,
+) -> anyhow::Result<usize> {
+    let file = std::fs::File::open(path.as_ref())?;
+    let mut line_count = 0;
+    for (index, line) in BufReader::new(file).lines().enumerate() {
+        let line = line?;
+        anyhow::ensure!(
+            parse_symbol_line(&line).is_some(),
+            "malformed line {}: {line:?}",
+            index + 1
+        );
+        line_count += 1;
+    }
+    anyhow::ensure!(
+        line_count == expected_lines,
+        "expected {expected_lines} lines but found {line_count}, the file may be truncated"
+    );
+    Ok(line_count)
+}
+
 #[cfg(all(test, target_os = "linux"))]
 mod tests {
     use super::*;
 
+    fn sample_symbols() -> ModuleSymbols {
+        ModuleSymbols::new(vec![
+            Symbol {
+                addr: 0x1000,
+                size: 0x10,
+                name: "main".into(),
+            },
+            Symbol {
+                addr: 0x1010,
+                size: 0x20,
+                name: "operator new(unsigned long)".into(),
+            },
+        ])
+    }
+
+    #[test]
+    fn test_saved_symbols_file_is_valid() {
+        let folder = tempfile::tempdir().unwrap();
+        sample_symbols()
+            .save_to_keyed_file(folder.path(), "0-bench")
+            .unwrap();
+
+        let line_count =
+            validate_symbols_file(keyed_file_path(folder.path(), "0-bench"), 2).unwrap();
+        assert_eq!(line_count, 2);
+    }
+
+    #[test]
+    fn test_find_symbol_in_keyed_file() {
+        let folder = tempfile::tempdir().unwrap();
+        sample_symbols()
+            .save_to_keyed_file(folder.path(), "0-bench")
+            .unwrap();
+        let module_symbols = ModuleSymbols::from_keyed_file(folder.path(), "0-bench").unwrap();
+
+        assert_eq!(module_symbols.find(0x1000).unwrap().name, "main");
+        assert_eq!(
+            module_symbols.find(0x102f).unwrap().name,
+            "operator new(unsigned long)"
+        );
+        assert!(module_symbols.find(0xfff).is_none());
+        assert!(module_symbols.find(0x1030).is_none());
+    }
+
+    #[test]
+    fn test_corrupted_symbols_file_is_rejected() {
+        let folder = tempfile::tempdir().unwrap();
+        let path = keyed_file_path(folder.path(), "0-bench");
+
+        std::fs::write(&path, "1000 10 main\n10zz 20 other\n").unwrap();
+        let err = validate_symbols_file(&path, 2).unwrap_err();
+        assert!(err.to_string().contains("malformed line 2"));
+
+        // Truncated in the middle of the second line
+        std::fs::write(&path, "1000 10 main\n1010").unwrap();
+        assert!(validate_symbols_file(&path, 2).is_err());
+
+        // Truncated at a line boundary
+        std::fs::write(&path, "1000 10 main\n").unwrap();
+        let err = validate_symbols_file(&path, 2).unwrap_err();
+        assert!(err.to_string().contains("truncated"));
+    }
+
     #[test]
     fn test_golang_symbols() {
         let module_symbols = ModuleSymbols::from_elf("testdata/perf_map/go_fib.bin").unwrap();
@@ -305,4 +545,89 @@ mod tests {
             "internal libc symbol `_int_malloc` should be merged in from the debug file"
         );
     }
+
+    #[test]
+    fn test_duplicate_symbols_are_deduplicated() {
+        // `strong_fn` and its weak alias `weak_alias` are both in the `.symtab` and `.dynsym`
+        // tables, at the same address with the same size
+        let module_symbols = ModuleSymbols::from_elf("testdata/module_symbols/aliases.so").unwrap();
+
+        let names_at = |addr: u64| {
+            module_symbols
+                .symbols()
+                .iter()
+                .filter(|symbol| symbol.addr == addr)
+                .map(|symbol| symbol.name.as_str())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(names_at(0x1000), vec!["strong_fn"]);
+        assert_eq!(names_at(0x1005), vec!["other_fn"]);
+        assert_eq!(module_symbols.find(0x1002).unwrap().name, "strong_fn");
+    }
+
+    #[test]
+    fn test_zero_sized_symbol_extension_is_capped() {
+        let symbol = |addr: u64, size: u64, name: &str| Symbol {
+            addr,
+            size,
+            name: name.into(),
+        };
+        let mut symbols = vec![
+            symbol(0x1000, 0, "small_gap"),
+            // Followed by a 2MiB gap
+            symbol(0x1010, 0, "large_gap"),
+            symbol(0x200000, 0x10, "sized"),
+            symbol(0x300000, 0, "last"),
+        ];
+
+        extend_zero_sized_symbols(&mut symbols, 0x10000);
+
+        let sizes = symbols.iter().map(|symbol| symbol.size).collect::<Vec<_>>();
+        assert_eq!(sizes, vec![0x10, 0x10000, 0x10, 0x1000]);
+    }
+
+    #[test]
+    fn test_max_symbol_extension_from_env() {
+        temp_env::with_var_unset(MAX_SYMBOL_EXTENSION_ENV, || {
+            assert_eq!(
+                max_symbol_extension_from_env(),
+                DEFAULT_MAX_SYMBOL_EXTENSION
+            );
+        });
+        temp_env::with_var(MAX_SYMBOL_EXTENSION_ENV, Some("4096"), || {
+            assert_eq!(max_symbol_extension_from_env(), 4096);
+        });
+        for invalid in ["0", "64KiB"] {
+            temp_env::with_var(MAX_SYMBOL_EXTENSION_ENV, Some(invalid), || {
+                assert_eq!(
+                    max_symbol_extension_from_env(),
+                    DEFAULT_MAX_SYMBOL_EXTENSION
+                );
+            });
+        }
+    }
+
+    #[test]
+    fn test_demangle_rust_names() {
+        assert_eq!(
+            demangle("_ZN4core3fmt5write17h5d5b5e6b0e6d4d0aE"),
+            "core::fmt::write"
+        );
+        assert_eq!(
+            demangle("_RNvCs15kBYyAo9fc_7mycrate7example"),
+            "mycrate::example"
+        );
+    }
+
+    #[test]
+    fn test_demangle_cpp_names() {
+        assert_eq!(demangle("_Znwm"), "operator new(unsigned long)");
+        assert_eq!(demangle("_ZN5bench3fibEi"), "bench::fib(int)");
+    }
+
+    #[test]
+    fn test_unmangled_names_are_unchanged() {
+        assert!(matches!(demangle("main"), Cow::Borrowed("main")));
+        assert!(matches!(demangle("_Zinvalid"), Cow::Borrowed("_Zinvalid")));
+    }
 }
@@ -0,0 +1,104 @@
+use crate::prelude::*;
+use std::path::{Path, PathBuf};
+
+/// Maximum size in bytes of the perf output, to avoid filling the disk on long benchmarks.
+pub const PERF_MAX_BYTES_ENV: &str = "CODSPEED_PERF_MAX_BYTES";
+
+/// Size guard on the perf output file.
+///
+/// perf writes its output through a shell pipe, so the size is checked from the FIFO hooks and on
+/// a timer while perf runs: once the cap is reached, the events are disabled and the data
+/// collected so far is kept intact for parsing.
+#[derive(Debug)]
+pub struct PerfOutputCap {
+    perf_file_path: PathBuf,
+    max_bytes: u64,
+    reached: bool,
+}
+
+impl PerfOutputCap {
+    pub fn new(perf_file_path: &Path, max_bytes: u64) -> Self {
+        Self {
+            perf_file_path: perf_file_path.to_path_buf(),
+            max_bytes,
+            reached: false,
+        }
+    }
+
+    /// Build the cap configured through [`PERF_MAX_BYTES_ENV`], if set.
+    pub fn from_env(perf_file_path: &Path) -> Result<Option<Self>> {
+        let Ok(value) = std::env::var(PERF_MAX_BYTES_ENV) else {
+            return Ok(None);
+        };
+        let max_bytes = value.trim().parse::<u64>().with_context(|| {
+            format!("Invalid {PERF_MAX_BYTES_ENV} value {value:?}, expected a number of bytes")
+        })?;
+        Ok(Some(Self::new(perf_file_path, max_bytes)))
+    }
+
+    /// Whether the cap was reached by a previous [`Self::check`].
+    pub fn is_reached(&self) -> bool {
+        self.reached
+    }
+
+    /// Check the size of the perf output, warning the first time it reaches the cap.
+    /// Returns true once the cap has been reached.
+    pub fn check(&mut self) -> bool {
+        if self.reached {
+            return true;
+        }
+
+        // The file is created by the shell pipeline, it may not exist yet
+        let size = std::fs::metadata(&self.perf_file_path)
+            .map(|metadata| metadata.len())
+            .unwrap_or(0);
+        if size >= self.max_bytes {
+            self.reached = true;
+            warn!(
+                "The perf output reached {size} bytes, over the {} bytes allowed by {PERF_MAX_BYTES_ENV}. \
+                Profiling was stopped, the rest of the run will not be profiled.",
+                self.max_bytes
+            );
+        }
+        self.reached
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cap_is_reached_once_the_output_grows_past_the_limit() {
+        let folder = tempfile::tempdir().unwrap();
+        let perf_file_path = folder.path().join("perf.pipedata");
+        let mut cap = PerfOutputCap::new(&perf_file_path, 16);
+
+        assert!(!cap.check());
+        std::fs::write(&perf_file_path, [0u8; 10]).unwrap();
+        assert!(!cap.check());
+        assert!(!cap.is_reached());
+        std::fs::write(&perf_file_path, [0u8; 32]).unwrap();
+        assert!(cap.check());
+        assert!(cap.is_reached());
+
+        // Stays reached, even if the file were to shrink
+        std::fs::write(&perf_file_path, []).unwrap();
+        assert!(cap.check());
+    }
+
+    #[test]
+    fn test_cap_from_env() {
+        let perf_file_path = Path::new("/tmp/perf.pipedata");
+        temp_env::with_var(PERF_MAX_BYTES_ENV, Some("1024"), || {
+            let cap = PerfOutputCap::from_env(perf_file_path).unwrap().unwrap();
+            assert_eq!(cap.max_bytes, 1024);
+        });
+        temp_env::with_var(PERF_MAX_BYTES_ENV, None::<&str>, || {
+            assert!(PerfOutputCap::from_env(perf_file_path).unwrap().is_none());
+        });
+        temp_env::with_var(PERF_MAX_BYTES_ENV, Some("1GB"), || {
+            assert!(PerfOutputCap::from_env(perf_file_path).is_err());
+        });
+    }
+}
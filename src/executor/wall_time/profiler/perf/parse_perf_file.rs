@@ -1,14 +1,19 @@
 use super::loaded_module::{LoadedModule, ProcessLoadedModule};
 use super::module_symbols::ModuleSymbols;
-use super::unwind_data::unwind_data_from_elf;
+use super::symbols_cache::{SymbolsCache, load_module_symbols};
+use super::unwind_data::{BuildIdMismatch, unwind_data_from_elf};
 use crate::prelude::*;
 use libc::pid_t;
 use linux_perf_data::PerfFileReader;
 use linux_perf_data::PerfFileRecord;
 use linux_perf_data::linux_perf_event_reader::EventRecord;
+use linux_perf_data::linux_perf_event_reader::Mmap2FileId;
+use linux_perf_data::linux_perf_event_reader::Mmap2Record;
 use linux_perf_data::linux_perf_event_reader::RecordType;
+use runner_shared::unwind_data::ProcessUnwindData;
 use std::collections::HashMap;
 use std::collections::HashSet;
+use std::ops::Range;
 use std::path::Path;
 use std::path::PathBuf;
 
@@ -16,6 +21,31 @@ pub struct MemmapRecordsOutput {
     /// Module symbols and the computed load bias for each pid that maps the ELF path.
     pub loaded_modules_by_path: HashMap<PathBuf, LoadedModule>,
     pub tracked_pids: HashSet<pid_t>,
+    /// Sorted timestamps of the samples recorded for the tracked pids.
+    pub sample_timestamps: Vec<u64>,
+}
+
+impl MemmapRecordsOutput {
+    /// Explain why the profile will be empty when no executable module was found for the
+    /// tracked pids, e.g. because no MMAP2 record matched them.
+    pub fn empty_profile_warning(&self) -> Option<String> {
+        let reason = if self.tracked_pids.is_empty() {
+            "no benchmark process was tracked"
+        } else if self.loaded_modules_by_path.is_empty() {
+            "no executable module was mapped by the benchmark processes"
+        } else {
+            return None;
+        };
+
+        Some(format!(
+            "The perf data was recorded, but {reason} (tracked pids: {:?}). \
+            The uploaded profile will not contain any symbols. Likely causes are:\n\
+            - benchmarks too short to be sampled\n\
+            - a perf unwinding mode unsupported by the benchmarks (see --perf-unwinding-mode)\n\
+            - perf lacking the permissions to record the benchmark processes",
+            self.tracked_pids.iter().sorted().collect::<Vec<_>>()
+        ))
+    }
 }
 
 /// Parse the perf file at `perf_file_path` and look for MMAP2 records for the given `pids`.
@@ -25,8 +55,13 @@ pub struct MemmapRecordsOutput {
 pub fn parse_for_memmap2<P: AsRef<Path>>(
     perf_file_path: P,
     mut pid_filter: PidFilter,
+    symbols_cache: Option<&SymbolsCache>,
 ) -> Result<MemmapRecordsOutput> {
     let mut loaded_modules_by_path = HashMap::<PathBuf, LoadedModule>::new();
+    let mut untracked_forks = UntrackedForks::new();
+    let mut sample_timestamps = Vec::new();
+    let mut mmap_protection = MmapProtection::default();
+    let mut executable_segments = ExecutableSegments::default();
 
     // 1MiB buffer
     let reader = std::io::BufReader::with_capacity(
@@ -37,10 +72,26 @@ pub fn parse_for_memmap2<P: AsRef<Path>>(
     let PerfFileReader {
         mut perf_file,
         mut record_iter,
-    } = PerfFileReader::parse_pipe(reader)?;
+    } = PerfFileReader::parse_pipe(reader).context("Failed to read the perf data header")?;
 
     // This loop relies on the events being in chronological order, which is guaranteed by the perf file format.
-    while let Some(record) = record_iter.next_record(&mut perf_file)? {
+    let mut records_read = 0;
+    loop {
+        // A malformed record can't be skipped: its size is what locates the next one in the stream
+        let record = match record_iter.next_record(&mut perf_file) {
+            Ok(Some(record)) => record,
+            Ok(None) => break,
+            Err(source) => {
+                debug!("Failed to read perf record after {records_read} valid records: {source}");
+                return Err(ParseError::InvalidRecord {
+                    records_read,
+                    source,
+                }
+                .into());
+            }
+        };
+        records_read += 1;
+
         let PerfFileRecord::EventRecord { record, .. } = record else {
             continue;
         };
@@ -58,23 +109,34 @@ pub fn parse_for_memmap2<P: AsRef<Path>>(
                     continue;
                 };
 
-                // Thread creation also emits FORK, which share parent's address space, so nothing to do
+                // Thread creation (clone() with CLONE_THREAD) also emits FORK, with the same pid
+                // and a new tid. Threads share the parent's address space and pid, and the filter
+                // is on pids, so nothing to do
                 if fork_record.ppid == fork_record.pid {
                     continue;
                 }
 
-                if pid_filter.add_child_if_parent_tracked(fork_record.ppid, fork_record.pid) {
+                // posix_spawn() and vfork() also emit FORK records, so launchers forking the
+                // actual workload are tracked transitively like regular forks
+                if pid_filter.add_child_if_parent_tracked(
+                    fork_record.ppid,
+                    fork_record.pid,
+                    &mut untracked_forks,
+                ) {
                     trace!(
                         "Fork: Tracking child PID {} from parent PID {}",
                         fork_record.pid, fork_record.ppid
                     );
                 }
 
-                inherit_parent_mappings(
-                    &mut loaded_modules_by_path,
-                    fork_record.ppid,
-                    fork_record.pid,
-                );
+                // The untracked children don't inherit the mappings, e.g. without fork tracking
+                if pid_filter.should_include(fork_record.pid) {
+                    inherit_parent_mappings(
+                        &mut loaded_modules_by_path,
+                        fork_record.ppid,
+                        fork_record.pid,
+                    );
+                }
             }
             RecordType::COMM => {
                 // An execve() replaces the entire address space: the mappings inherited from the
@@ -115,7 +177,25 @@ pub fn parse_for_memmap2<P: AsRef<Path>>(
                     continue;
                 }
 
-                process_mmap2_record(mmap2_record, &mut loaded_modules_by_path);
+                process_mmap2_record(
+                    mmap2_record,
+                    &mut mmap_protection,
+                    &mut executable_segments,
+                    &mut loaded_modules_by_path,
+                    symbols_cache,
+                );
+            }
+            RecordType::SAMPLE => {
+                // Only the timestamp is needed, to count the samples of each benchmark
+                let Ok(common_data) = record.common_data() else {
+                    continue;
+                };
+                let (Some(pid), Some(timestamp)) = (common_data.pid, common_data.timestamp) else {
+                    continue;
+                };
+                if pid_filter.should_include(pid) {
+                    sample_timestamps.push(timestamp);
+                }
             }
             _ => continue,
         }
@@ -127,44 +207,248 @@ pub fn parse_for_memmap2<P: AsRef<Path>>(
             .iter()
             .flat_map(|(_, loaded)| loaded.pids())
             .collect(),
-        PidFilter::TrackedPids(tracked) => tracked,
+        PidFilter::TrackedPids(tracked) | PidFilter::ExactPids(tracked) => tracked,
     };
 
+    sample_timestamps.sort_unstable();
+
     Ok(MemmapRecordsOutput {
         loaded_modules_by_path,
         tracked_pids,
+        sample_timestamps,
     })
 }
 
-/// PID filter for parsing perf records
+/// Markers separating the contexts of a sample callchain, see `enum perf_callchain_context` in
+/// `linux/perf_event.h`. Every value above [`PERF_CONTEXT_MAX`] is a marker, not an address.
+const PERF_CONTEXT_USER: u64 = -512i64 as u64;
+const PERF_CONTEXT_MAX: u64 = -4095i64 as u64;
+
+/// Call `on_sample` with the pid and the user space frames of every sample of the perf file at
+/// `perf_file_path`, from the innermost frame to the outermost one.
+///
+/// The callchains are the ones recorded by perf, which are only complete with the frame pointer
+/// unwinding: the profiles recorded with the DWARF unwinding, whose stacks are unwound later from
+/// the copied stack memory, are rejected.
+pub fn for_each_sample_callchain<P: AsRef<Path>>(
+    perf_file_path: P,
+    mut on_sample: impl FnMut(pid_t, &[u64]),
+) -> Result<()> {
+    let reader = std::io::BufReader::with_capacity(
+        1024 * 1024,
+        std::fs::File::open(perf_file_path.as_ref())?,
+    );
+    let PerfFileReader {
+        mut perf_file,
+        mut record_iter,
+    } = PerfFileReader::parse_pipe(reader).context("Failed to read the perf data header")?;
+
+    let mut records_read = 0;
+    let mut frames = Vec::new();
+    loop {
+        let record = match record_iter.next_record(&mut perf_file) {
+            Ok(Some(record)) => record,
+            Ok(None) => break,
+            Err(source) => {
+                return Err(ParseError::InvalidRecord {
+                    records_read,
+                    source,
+                }
+                .into());
+            }
+        };
+        records_read += 1;
+
+        let PerfFileRecord::EventRecord { record, .. } = record else {
+            continue;
+        };
+        if record.record_type != RecordType::SAMPLE {
+            continue;
+        }
+        let Ok(EventRecord::Sample(sample)) = record.parse() else {
+            continue;
+        };
+        ensure!(
+            sample.user_stack.is_none(),
+            "The profile was recorded with the DWARF unwinding, whose stacks can't be exported: \
+             record it again with `--perf-unwinding-mode fp`"
+        );
+        let Some(pid) = sample.pid else {
+            continue;
+        };
+
+        frames.clear();
+        match &sample.callchain {
+            Some(callchain) => {
+                let addresses = (0..callchain.len()).filter_map(|index| callchain.get(index));
+                frames.extend(user_frames(addresses));
+            }
+            None => frames.extend(sample.ip),
+        }
+        if !frames.is_empty() {
+            on_sample(pid, &frames);
+        }
+    }
+
+    Ok(())
+}
+
+/// Keep the user space addresses of a callchain, dropping the context markers and the kernel
+/// frames.
+fn user_frames(callchain: impl Iterator<Item = u64>) -> impl Iterator<Item = u64> {
+    let mut in_user_context = true;
+    callchain.filter(move |&address| {
+        if address >= PERF_CONTEXT_MAX {
+            in_user_context = address == PERF_CONTEXT_USER;
+            return false;
+        }
+        in_user_context
+    })
+}
+
+/// The perf data could not be parsed.
 #[derive(Debug)]
+pub enum ParseError {
+    /// A record is malformed or cut short, usually because perf was killed (e.g. by the OOM
+    /// killer) while writing it.
+    InvalidRecord {
+        /// Number of records read successfully before the invalid one
+        records_read: usize,
+        source: linux_perf_data::Error,
+    },
+}
+
+impl std::fmt::Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ParseError::InvalidRecord {
+                records_read,
+                source,
+            } => write!(
+                f,
+                "Invalid perf record after {records_read} valid records ({source}). \
+                The perf data is likely truncated, check whether perf ran out of memory or disk space"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ParseError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ParseError::InvalidRecord { source, .. } => Some(source),
+        }
+    }
+}
+
+/// Children forked by parents that were not tracked (yet), by parent PID.
+///
+/// Records from different CPUs are only ordered within a round, so the FORK record of a grandchild
+/// can be read before the one making its parent a tracked child.
+type UntrackedForks = HashMap<pid_t, Vec<pid_t>>;
+
+/// Comma-separated PIDs whose perf records are parsed (along with their children) instead of the
+/// bench PIDs reported by the integration, to diagnose integrations under-reporting them.
+pub const FORCE_PIDS_ENV: &str = "CODSPEED_FORCE_PIDS";
+
+/// Set to parse the perf records of the bench PIDs only, without the processes they fork, e.g.
+/// when a benchmark starts a long-lived daemon whose modules would bloat the artifacts.
+pub const NO_FORK_TRACKING_ENV: &str = "CODSPEED_NO_FORK_TRACKING";
+
+/// Whether the children of the tracked PIDs are tracked, unless disabled with
+/// [`NO_FORK_TRACKING_ENV`].
+pub fn fork_tracking_from_env() -> bool {
+    std::env::var_os(NO_FORK_TRACKING_ENV).is_none()
+}
+
+/// PID filter for parsing perf records
+#[derive(Debug, Clone, PartialEq)]
 pub enum PidFilter {
     /// Parse records for all PIDs
     All,
     /// Parse records only for specific PIDs and their children
     TrackedPids(HashSet<pid_t>),
+    /// Parse records only for specific PIDs, see [`NO_FORK_TRACKING_ENV`]
+    ExactPids(HashSet<pid_t>),
 }
 
 impl PidFilter {
+    /// The same filter, without tracking the children of the PIDs.
+    pub fn without_fork_tracking(self) -> Self {
+        match self {
+            PidFilter::TrackedPids(pids) => PidFilter::ExactPids(pids),
+            filter => filter,
+        }
+    }
+
+    /// Build the filter forced through [`FORCE_PIDS_ENV`], if set.
+    pub fn forced_from_env() -> Result<Option<Self>> {
+        let Ok(value) = std::env::var(FORCE_PIDS_ENV) else {
+            return Ok(None);
+        };
+        Self::from_pid_list(&value)
+            .with_context(|| format!("Invalid {FORCE_PIDS_ENV} value {value:?}"))
+            .map(Some)
+    }
+
+    /// Parse a comma-separated list of PIDs, e.g. `123,456`, into a [`PidFilter::TrackedPids`].
+    fn from_pid_list(value: &str) -> Result<Self> {
+        let pids = value
+            .split(',')
+            .map(|pid| {
+                let pid = pid.trim();
+                pid.parse::<pid_t>()
+                    .with_context(|| format!("`{pid}` is not a PID"))
+            })
+            .collect::<Result<HashSet<_>>>()?;
+        Ok(PidFilter::TrackedPids(pids))
+    }
+
     /// Check if a PID should be included in parsing
     fn should_include(&self, pid: pid_t) -> bool {
         match self {
             PidFilter::All => true,
-            PidFilter::TrackedPids(tracked_pids) => tracked_pids.contains(&pid),
+            PidFilter::TrackedPids(tracked_pids) | PidFilter::ExactPids(tracked_pids) => {
+                tracked_pids.contains(&pid)
+            }
         }
     }
 
-    /// Add a child PID to the filter if we're tracking its parent
+    /// Add a child PID to the filter if we're tracking its parent, along with the descendants
+    /// it already forked. Otherwise, remember the fork in `untracked_forks` in case the parent
+    /// becomes tracked later.
     /// Returns true if the child was added
-    fn add_child_if_parent_tracked(&mut self, parent_pid: pid_t, child_pid: pid_t) -> bool {
+    fn add_child_if_parent_tracked(
+        &mut self,
+        parent_pid: pid_t,
+        child_pid: pid_t,
+        untracked_forks: &mut UntrackedForks,
+    ) -> bool {
         match self {
-            PidFilter::All => false, // Already tracking all PIDs
+            PidFilter::All => false,          // Already tracking all PIDs
+            PidFilter::ExactPids(_) => false, // Children are never tracked
             PidFilter::TrackedPids(tracked_pids) => {
-                if tracked_pids.contains(&parent_pid) {
-                    tracked_pids.insert(child_pid)
-                } else {
-                    false
+                if !tracked_pids.contains(&parent_pid) {
+                    untracked_forks
+                        .entry(parent_pid)
+                        .or_default()
+                        .push(child_pid);
+                    return false;
+                }
+                if !tracked_pids.insert(child_pid) {
+                    return false;
                 }
+
+                let mut newly_tracked = vec![child_pid];
+                while let Some(pid) = newly_tracked.pop() {
+                    for descendant in untracked_forks.remove(&pid).unwrap_or_default() {
+                        if tracked_pids.insert(descendant) {
+                            trace!("Fork: Tracking PID {descendant} forked earlier by PID {pid}");
+                            newly_tracked.push(descendant);
+                        }
+                    }
+                }
+                true
             }
         }
     }
@@ -202,19 +486,86 @@ fn inherit_parent_mappings(
 }
 
 /// Drop every mapping recorded for `pid` across all modules.
+///
+/// The pid itself stays in the [`PidFilter`]: an exec keeps the pid, so the MMAP2 records of the
+/// new binary are still collected.
 fn purge_process_mappings(loaded_modules_by_path: &mut HashMap<PathBuf, LoadedModule>, pid: pid_t) {
     for loaded_module in loaded_modules_by_path.values_mut() {
         loaded_module.process_loaded_modules.remove(&pid);
     }
 }
 
+/// Whether the kernel fills the protection of the MMAP2 records. Some older kernels leave it
+/// zeroed, so that every mapping would look non-executable.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+enum MmapProtection {
+    /// No record seen yet.
+    #[default]
+    Unknown,
+    /// The records have a protection: a zero one is a `PROT_NONE` mapping.
+    Populated,
+    /// The records seen so far have no protection: the executable mappings are recognized from
+    /// the segments of the mapped ELF files instead.
+    Missing,
+}
+
+impl MmapProtection {
+    /// Update the state with the protection of a record.
+    fn observe(&mut self, protection: u32) {
+        match (*self, protection) {
+            (MmapProtection::Populated, _) => {}
+            (_, 0) => {
+                if *self == MmapProtection::Unknown {
+                    debug!(
+                        "The MMAP2 records have no protection, inferring the executable mappings from the ELF segments"
+                    );
+                    *self = MmapProtection::Missing;
+                }
+            }
+            _ => *self = MmapProtection::Populated,
+        }
+    }
+}
+
+/// The executable segments of the mapped files, read once per file when the MMAP2 records have
+/// no protection.
+#[derive(Debug, Default)]
+struct ExecutableSegments {
+    /// `None` for the files whose segments could not be read.
+    page_ranges_by_path: HashMap<PathBuf, Option<Vec<Range<u64>>>>,
+}
+
+impl ExecutableSegments {
+    /// Whether the mapping of `path` from `page_offset` is one of its executable segments.
+    fn is_mapped_from(&mut self, path: &Path, page_offset: u64) -> bool {
+        if !self.page_ranges_by_path.contains_key(path) {
+            let page_ranges = ModuleSymbols::executable_segment_page_ranges(path)
+                .inspect_err(|e| trace!("Failed to read the segments of {path:?}: {e}"))
+                .ok();
+            self.page_ranges_by_path
+                .insert(path.to_path_buf(), page_ranges);
+        }
+
+        self.page_ranges_by_path[path]
+            .iter()
+            .flatten()
+            .any(|range| range.contains(&page_offset))
+    }
+}
+
 /// Process a single MMAP2 record and add it to the symbols and unwind data maps
 fn process_mmap2_record(
-    record: linux_perf_data::linux_perf_event_reader::Mmap2Record,
+    record: Mmap2Record,
+    mmap_protection: &mut MmapProtection,
+    executable_segments: &mut ExecutableSegments,
     loaded_modules_by_path: &mut HashMap<PathBuf, LoadedModule>,
+    symbols_cache: Option<&SymbolsCache>,
 ) {
+    mmap_protection.observe(record.protection);
     // Check PROT_EXEC early to avoid string allocation for non-executable mappings
-    if record.protection as i32 & libc::PROT_EXEC == 0 {
+    if *mmap_protection == MmapProtection::Populated
+        && record.protection as i32 & libc::PROT_EXEC == 0
+    {
         return;
     }
 
@@ -235,6 +586,12 @@ fn process_mmap2_record(
     let record_path = PathBuf::from(&record_path_string);
     let end_addr = record.address + record.length;
 
+    if *mmap_protection == MmapProtection::Missing
+        && !executable_segments.is_mapped_from(&record_path, record.page_offset)
+    {
+        return;
+    }
+
     trace!(
         "Mapping: Pid {}: {:016x}-{:016x} {:08x} {:?} (Prot {:?})",
         record.pid,
@@ -262,6 +619,11 @@ fn process_mmap2_record(
         .entry(record_path.clone())
         .or_default();
 
+    let is_new_build_id = match &record.file_id {
+        Mmap2FileId::BuildId(build_id) => record_build_id(loaded_module, build_id),
+        _ => false,
+    };
+
     let process_loaded_module = loaded_module
         .process_loaded_modules
         .entry(record.pid)
@@ -269,7 +631,7 @@ fn process_mmap2_record(
 
     // Extract module symbols if it's no module symbol from path
     if loaded_module.module_symbols.is_none() {
-        match ModuleSymbols::from_elf(&record_path) {
+        match load_module_symbols(&record_path, symbols_cache) {
             Ok(symbols) => loaded_module.module_symbols = Some(symbols),
             Err(error) => {
                 debug!("Failed to load symbols for module {record_path_string}: {error}");
@@ -277,30 +639,87 @@ fn process_mmap2_record(
         }
     }
 
-    // Store load bias for this process mounting
-    process_loaded_module.symbols_load_bias = Some(load_bias);
+    // Store load bias for this process mounting. A module with several executable segments is
+    // mapped by one record per segment, all with the load bias of the module: a different bias
+    // means that the module was unmapped and mapped again elsewhere.
+    let previous_load_bias = process_loaded_module.symbols_load_bias.replace(load_bias);
+    let is_other_segment = previous_load_bias == Some(load_bias);
+    if let Some(previous_load_bias) = previous_load_bias.filter(|bias| *bias != load_bias) {
+        debug!(
+            "Pid {}: {record_path_string} mapped again, load bias {previous_load_bias:#x} -> {load_bias:#x}",
+            record.pid
+        );
+    }
 
     // Extract unwind_data
     match unwind_data_from_elf(
         record_path_string.as_bytes(),
         record.address,
         end_addr,
-        None,
+        loaded_module.build_id.as_deref(),
         load_bias,
     ) {
         Ok((unwind_data, process_unwind_data)) => {
             loaded_module.unwind_data = Some(unwind_data);
-            process_loaded_module.process_unwind_data = Some(process_unwind_data);
-        }
-        Err(error) => {
-            debug!("Failed to load unwind data for module {record_path_string}: {error}");
+            add_segment_unwind_data(
+                &mut process_loaded_module.process_unwind_data,
+                process_unwind_data,
+                is_other_segment,
+            );
         }
+        Err(error) => match error.downcast_ref::<BuildIdMismatch>() {
+            // Warn once per module, on its first mapping
+            Some(mismatch) if is_new_build_id => {
+                run_warning!("{}", build_id_mismatch_warning(mismatch))
+            }
+            _ => debug!("Failed to load unwind data for module {record_path_string}: {error}"),
+        },
     };
 }
 
+/// Add the unwind data of a `new` executable segment of a module to the ones of the process, so
+/// that the frames of every segment are unwound without covering the gaps between them. A
+/// mapping that is not another segment of the same module replaces the previous ones.
+fn add_segment_unwind_data(
+    segments: &mut Vec<ProcessUnwindData>,
+    new: ProcessUnwindData,
+    is_other_segment: bool,
+) {
+    if !is_other_segment {
+        segments.clear();
+    }
+    if !segments
+        .iter()
+        .any(|segment| segment.avma_range == new.avma_range)
+    {
+        segments.push(new);
+    }
+}
+
+/// Store the build-id perf recorded for a module, the first time it is mapped. Returns whether it
+/// was stored, the file on disk is then checked against it when its unwind data is loaded.
+fn record_build_id(loaded_module: &mut LoadedModule, build_id: &[u8]) -> bool {
+    if loaded_module.build_id.is_some() || build_id.is_empty() {
+        return false;
+    }
+    loaded_module.build_id = Some(build_id.to_vec());
+    true
+}
+
+/// Explain why the symbols of a module may be wrong when its file is no longer the profiled one,
+/// e.g. because the binary was rebuilt while the benchmarks ran.
+fn build_id_mismatch_warning(mismatch: &BuildIdMismatch) -> String {
+    format!(
+        "{} has the build-id {}, but {} was profiled: the binary changed since the benchmarks \
+        ran, its symbols may be wrong",
+        mismatch.path, mismatch.file_build_id, mismatch.expected_build_id
+    )
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use debugid::CodeId;
 
     fn make_module_with_parent(ppid: pid_t, load_bias: u64) -> LoadedModule {
         let mut m = LoadedModule::default();
@@ -308,12 +727,220 @@ mod tests {
             ppid,
             ProcessLoadedModule {
                 symbols_load_bias: Some(load_bias),
-                process_unwind_data: None,
+                process_unwind_data: vec![],
             },
         );
         m
     }
 
+    /// Header of a perf pipe stream: the `PERFILE2` magic and the header size.
+    fn perf_pipe_header() -> Vec<u8> {
+        let mut data = b"PERFILE2".to_vec();
+        data.extend_from_slice(&16u64.to_le_bytes());
+        data
+    }
+
+    #[test]
+    fn parse_for_memmap2_rejects_truncated_perf_data_without_panicking() {
+        let folder = tempfile::tempdir().unwrap();
+        let perf_file_path = folder.path().join("perf.pipedata");
+
+        // A record header whose size is smaller than the header itself
+        let mut invalid_size = perf_pipe_header();
+        invalid_size.extend_from_slice(&10u32.to_le_bytes()); // PERF_RECORD_MMAP2
+        invalid_size.extend_from_slice(&0u16.to_le_bytes());
+        invalid_size.extend_from_slice(&4u16.to_le_bytes());
+        // A record header cut in the middle
+        let mut truncated = perf_pipe_header();
+        truncated.extend_from_slice(&[10, 0, 0]);
+
+        for data in [invalid_size, truncated, b"PERFILE2".to_vec()] {
+            std::fs::write(&perf_file_path, data).unwrap();
+            let result = parse_for_memmap2(&perf_file_path, PidFilter::All, None);
+            assert!(result.is_err());
+        }
+    }
+
+    #[test]
+    fn user_frames_drop_the_kernel_frames_and_the_markers() {
+        const PERF_CONTEXT_KERNEL: u64 = -128i64 as u64;
+        let callchain = [
+            PERF_CONTEXT_KERNEL,
+            0xffff_ffff_8100_0000,
+            0xffff_ffff_8100_1000,
+            PERF_CONTEXT_USER,
+            0x5555_5555_6000,
+            0x5555_5555_5000,
+        ];
+        assert_eq!(
+            user_frames(callchain.into_iter()).collect::<Vec<_>>(),
+            vec![0x5555_5555_6000, 0x5555_5555_5000]
+        );
+        // Callchains without markers are all user space
+        assert_eq!(
+            user_frames([0x1000, 0x2000].into_iter()).collect::<Vec<_>>(),
+            vec![0x1000, 0x2000]
+        );
+    }
+
+    #[test]
+    fn invalid_record_error_explains_truncation() {
+        let error = ParseError::InvalidRecord {
+            records_read: 42,
+            source: linux_perf_data::Error::InvalidPerfEventSize,
+        };
+        let message = error.to_string();
+        assert!(message.contains("after 42 valid records"));
+        assert!(message.contains("truncated"));
+    }
+
+    #[test]
+    fn empty_profile_warning_when_no_pid_matches() {
+        // Output of parsing a perf file whose MMAP2 records all belong to other pids
+        let output = MemmapRecordsOutput {
+            loaded_modules_by_path: HashMap::new(),
+            tracked_pids: HashSet::from([42]),
+            sample_timestamps: vec![],
+        };
+        let warning = output.empty_profile_warning().unwrap();
+        assert!(warning.contains("no executable module was mapped"));
+        assert!(warning.contains("[42]"));
+
+        let output = MemmapRecordsOutput {
+            loaded_modules_by_path: HashMap::new(),
+            tracked_pids: HashSet::new(),
+            sample_timestamps: vec![],
+        };
+        assert!(
+            output
+                .empty_profile_warning()
+                .unwrap()
+                .contains("no benchmark process was tracked")
+        );
+
+        let output = MemmapRecordsOutput {
+            loaded_modules_by_path: HashMap::from([(
+                PathBuf::from("/tmp/bench"),
+                make_module_with_parent(42, 0),
+            )]),
+            tracked_pids: HashSet::from([42]),
+            sample_timestamps: vec![],
+        };
+        assert_eq!(output.empty_profile_warning(), None);
+    }
+
+    #[test]
+    fn pid_filter_from_forced_pids_env() {
+        temp_env::with_var(FORCE_PIDS_ENV, Some("123, 456"), || {
+            assert_eq!(
+                PidFilter::forced_from_env().unwrap(),
+                Some(PidFilter::TrackedPids(HashSet::from([123, 456])))
+            );
+        });
+        temp_env::with_var(FORCE_PIDS_ENV, None::<&str>, || {
+            assert_eq!(PidFilter::forced_from_env().unwrap(), None);
+        });
+        for invalid in ["", "123,abc", "123,,456"] {
+            temp_env::with_var(FORCE_PIDS_ENV, Some(invalid), || {
+                let err = PidFilter::forced_from_env().unwrap_err();
+                assert!(err.to_string().contains(FORCE_PIDS_ENV));
+            });
+        }
+    }
+
+    #[test]
+    fn pid_filter_tracks_three_level_process_tree() {
+        // bench (100) -> launcher (200, e.g. a shell exec'ing a runner) -> workload (300)
+        let mut pid_filter = PidFilter::TrackedPids(HashSet::from([100]));
+        let mut untracked_forks = UntrackedForks::new();
+
+        assert!(pid_filter.add_child_if_parent_tracked(100, 200, &mut untracked_forks));
+        assert!(pid_filter.add_child_if_parent_tracked(200, 300, &mut untracked_forks));
+        // Unrelated processes stay untracked
+        assert!(!pid_filter.add_child_if_parent_tracked(1, 400, &mut untracked_forks));
+
+        assert!(pid_filter.should_include(300));
+        assert!(!pid_filter.should_include(400));
+    }
+
+    #[test]
+    fn pid_filter_without_fork_tracking_keeps_only_the_seed_pids() {
+        // bench (100) -> launcher (200) -> daemon (300), and a second bench pid (500)
+        let mut pid_filter =
+            PidFilter::TrackedPids(HashSet::from([100, 500])).without_fork_tracking();
+        let mut untracked_forks = UntrackedForks::new();
+
+        assert!(!pid_filter.add_child_if_parent_tracked(100, 200, &mut untracked_forks));
+        assert!(!pid_filter.add_child_if_parent_tracked(200, 300, &mut untracked_forks));
+
+        assert_eq!(pid_filter, PidFilter::ExactPids(HashSet::from([100, 500])));
+        assert!(pid_filter.should_include(100));
+        assert!(pid_filter.should_include(500));
+        assert!(!pid_filter.should_include(200));
+        assert!(!pid_filter.should_include(300));
+        assert_eq!(PidFilter::All.without_fork_tracking(), PidFilter::All);
+    }
+
+    #[test]
+    fn fork_tracking_is_enabled_by_default() {
+        temp_env::with_var(NO_FORK_TRACKING_ENV, None::<&str>, || {
+            assert!(fork_tracking_from_env());
+        });
+        temp_env::with_var(NO_FORK_TRACKING_ENV, Some("1"), || {
+            assert!(!fork_tracking_from_env());
+        });
+    }
+
+    #[test]
+    fn pid_filter_tracks_descendants_forked_before_their_parent_is_tracked() {
+        let mut pid_filter = PidFilter::TrackedPids(HashSet::from([100]));
+        let mut untracked_forks = UntrackedForks::new();
+
+        // The workload's FORK record is read before the launcher's
+        assert!(!pid_filter.add_child_if_parent_tracked(200, 300, &mut untracked_forks));
+        assert!(!pid_filter.add_child_if_parent_tracked(300, 301, &mut untracked_forks));
+        assert!(!pid_filter.should_include(300));
+
+        assert!(pid_filter.add_child_if_parent_tracked(100, 200, &mut untracked_forks));
+
+        assert!(pid_filter.should_include(200));
+        assert!(pid_filter.should_include(300));
+        assert!(pid_filter.should_include(301));
+        assert!(untracked_forks.is_empty());
+    }
+
+    #[test]
+    fn pid_filter_keeps_tracking_forked_child_across_exec() {
+        // Tracked bash (100) forks 200, which then execs a different binary. Replays the
+        // FORK -> COMM(exec) -> MMAP2 handling of `parse_for_memmap2`.
+        let mut pid_filter = PidFilter::TrackedPids(HashSet::from([100]));
+        let mut untracked_forks = UntrackedForks::new();
+        let mut modules: HashMap<PathBuf, LoadedModule> = HashMap::new();
+        modules.insert(
+            PathBuf::from("/usr/bin/bash"),
+            make_module_with_parent(100, 0xaaaaaaaa0000),
+        );
+
+        assert!(pid_filter.add_child_if_parent_tracked(100, 200, &mut untracked_forks));
+        inherit_parent_mappings(&mut modules, 100, 200);
+
+        assert!(pid_filter.should_include(200));
+        purge_process_mappings(&mut modules, 200);
+
+        // The post-exec MMAP2 records of the child are still included
+        assert!(pid_filter.should_include(200));
+        modules.insert(
+            PathBuf::from("/cpp/build/fractal_main"),
+            make_module_with_parent(200, 0xbbbbbbbb0000),
+        );
+
+        let bash = &modules[&PathBuf::from("/usr/bin/bash")];
+        assert!(bash.process_loaded_modules.contains_key(&100));
+        assert!(!bash.process_loaded_modules.contains_key(&200));
+        let fractal = &modules[&PathBuf::from("/cpp/build/fractal_main")];
+        assert!(fractal.process_loaded_modules.contains_key(&200));
+    }
+
     #[test]
     fn inherit_parent_mappings_copies_parent_entry_to_child() {
         let mut modules: HashMap<PathBuf, LoadedModule> = HashMap::new();
@@ -338,7 +965,7 @@ mod tests {
             200,
             ProcessLoadedModule {
                 symbols_load_bias: Some(0xcafe),
-                process_unwind_data: None,
+                process_unwind_data: vec![],
             },
         );
         modules.insert(PathBuf::from("/lib/libpython.so"), m);
@@ -364,7 +991,7 @@ mod tests {
             200,
             ProcessLoadedModule {
                 symbols_load_bias: Some(0xaaaaaaaa0000),
-                process_unwind_data: None,
+                process_unwind_data: vec![],
             },
         );
         modules.insert(PathBuf::from("/usr/bin/bash"), bash);
@@ -398,4 +1025,154 @@ mod tests {
             Some(0xaaaaaaaa0000)
         );
     }
+
+    #[test]
+    fn build_id_is_recorded_and_checked() {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("testdata/module_symbols/aliases.so");
+        let path_bytes = path.to_str().unwrap().as_bytes();
+        // `readelf -n aliases.so`
+        let build_id = [
+            0x19, 0xa4, 0xd6, 0x9f, 0x16, 0xf4, 0xaf, 0xe0, 0xb4, 0xe5, 0x85, 0xb6, 0x76, 0xd8,
+            0x48, 0x24, 0x0b, 0xde, 0xdb, 0x73,
+        ];
+
+        let mut module = LoadedModule::default();
+        assert!(record_build_id(&mut module, &build_id));
+        assert_eq!(module.build_id.as_deref(), Some(&build_id[..]));
+        assert!(unwind_data_from_elf(path_bytes, 0x1000, 0x2000, Some(&build_id), 0).is_ok());
+
+        let mut rebuilt = build_id;
+        rebuilt[0] ^= 0xff;
+        let error =
+            unwind_data_from_elf(path_bytes, 0x1000, 0x2000, Some(&rebuilt), 0).unwrap_err();
+        let warning = build_id_mismatch_warning(error.downcast_ref().unwrap());
+        assert!(warning.contains("has the build-id 19a4d69f16f4afe0b4e585b676d848240bdedb73"));
+        assert!(warning.contains("but e6a4d69f16f4afe0b4e585b676d848240bdedb73 was profiled"));
+
+        // The build-id of the first mapping is kept
+        assert!(!record_build_id(&mut module, &rebuilt));
+        assert_eq!(module.build_id.as_deref(), Some(&build_id[..]));
+    }
+
+    #[test]
+    fn build_id_mmap_records_are_parsed_and_checked() {
+        // Perf streams recorded with `--buildid-mmap`, mapping the module at a relative path
+        let fixture = |name: &str| {
+            Path::new(env!("CARGO_MANIFEST_DIR"))
+                .join("testdata/buildid_mmap")
+                .join(name)
+        };
+        let module_path = PathBuf::from("testdata/module_symbols/aliases.so");
+
+        let output = parse_for_memmap2(fixture("aliases.pipedata"), PidFilter::All, None).unwrap();
+        let module = &output.loaded_modules_by_path[&module_path];
+        assert_eq!(
+            CodeId::from_binary(module.build_id.as_deref().unwrap()).to_string(),
+            "19a4d69f16f4afe0b4e585b676d848240bdedb73"
+        );
+        assert!(module.unwind_data.is_some());
+        assert_eq!(
+            module.process_loaded_modules[&1000].symbols_load_bias,
+            Some(0x7f00_0000_0000)
+        );
+
+        // The file on disk is not the profiled one: its unwind data is dropped
+        let output =
+            parse_for_memmap2(fixture("aliases_rebuilt.pipedata"), PidFilter::All, None).unwrap();
+        let module = &output.loaded_modules_by_path[&module_path];
+        assert_eq!(
+            CodeId::from_binary(module.build_id.as_deref().unwrap()).to_string(),
+            "e6a4d69f16f4afe0b4e585b676d848240bdedb73"
+        );
+        assert!(module.unwind_data.is_none());
+    }
+
+    /// An MMAP2 record of the module at `path`, as recorded on the kernels that
+    /// leave the protection zeroed.
+    fn mmap2_record_without_protection(
+        path: &[u8],
+        address: u64,
+        page_offset: u64,
+    ) -> Mmap2Record<'_> {
+        Mmap2Record {
+            pid: 1234,
+            tid: 1234,
+            address,
+            length: 0x1000,
+            page_offset,
+            file_id: Mmap2FileId::BuildId(vec![]),
+            protection: 0,
+            flags: libc::MAP_PRIVATE as u32,
+            path: linux_perf_data::linux_perf_event_reader::RawData::Single(path),
+            cpu_mode: linux_perf_data::linux_perf_event_reader::CpuMode::User,
+        }
+    }
+
+    #[test]
+    fn mappings_without_protection_use_the_elf_segments() {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("testdata/load_bias/pie.bin");
+        let path_bytes = path.to_str().unwrap().as_bytes();
+        let base = 0x5555_5555_4000;
+
+        let mut mmap_protection = MmapProtection::default();
+        let mut executable_segments = ExecutableSegments::default();
+        let mut loaded_modules_by_path = HashMap::new();
+        // The read-only segment is skipped, the `.text` one is kept
+        for page_offset in [0x0, 0x1000, 0x2000] {
+            process_mmap2_record(
+                mmap2_record_without_protection(path_bytes, base + page_offset, page_offset),
+                &mut mmap_protection,
+                &mut executable_segments,
+                &mut loaded_modules_by_path,
+                None,
+            );
+        }
+        assert_eq!(mmap_protection, MmapProtection::Missing);
+        // The segments are read once for the three mappings
+        assert_eq!(executable_segments.page_ranges_by_path.len(), 1);
+
+        let module = &loaded_modules_by_path[&path];
+        assert!(module.module_symbols.is_some());
+        assert_eq!(
+            module.process_loaded_modules[&1234].symbols_load_bias,
+            Some(base)
+        );
+
+        // Once a record has a protection, a zero one is a `PROT_NONE` mapping
+        let mut mmap_protection = MmapProtection::Populated;
+        let mut loaded_modules_by_path = HashMap::new();
+        process_mmap2_record(
+            mmap2_record_without_protection(path_bytes, base + 0x1000, 0x1000),
+            &mut mmap_protection,
+            &mut ExecutableSegments::default(),
+            &mut loaded_modules_by_path,
+            None,
+        );
+        assert!(loaded_modules_by_path.is_empty());
+    }
+
+    #[test]
+    fn unwind_data_covers_every_executable_segment() {
+        let segment = |avma_range: std::ops::Range<u64>| ProcessUnwindData {
+            timestamp: None,
+            avma_range,
+            base_avma: 0x5555_5555_4000,
+        };
+        let text = segment(0x5555_5555_5000..0x5555_5555_6000);
+        let text_far = segment(0x5555_5575_4000..0x5555_5575_5000);
+
+        let mut segments = vec![];
+        add_segment_unwind_data(&mut segments, text.clone(), false);
+        add_segment_unwind_data(&mut segments, text_far.clone(), true);
+        // A segment mapped again is not duplicated
+        add_segment_unwind_data(&mut segments, text_far.clone(), true);
+        let ranges = segments.iter().map(|s| s.avma_range.clone()).collect_vec();
+        assert_eq!(ranges, vec![text.avma_range.clone(), text_far.avma_range]);
+
+        // The module mapped again elsewhere replaces the segments
+        let remapped = segment(0x7f00_0000_1000..0x7f00_0000_2000);
+        add_segment_unwind_data(&mut segments, remapped.clone(), false);
+        assert_eq!(segments.len(), 1);
+        assert_eq!(segments[0].avma_range, remapped.avma_range);
+    }
 }
@@ -1,16 +1,65 @@
 use runner_shared::perf_event::PerfEvent;
 
 use crate::prelude::*;
+use std::os::unix::fs::PermissionsExt;
 use std::path::Path;
 
-use std::{ffi::OsString, process::Command};
+use std::{
+    ffi::OsString,
+    process::{Command, Stdio},
+};
 
 const FIND_PERF_CMD: &str =
     "find /usr/lib -executable -path \"/usr/lib/linux-tools-*/perf\" | sort | tail -n1";
 
+/// Path to a perf executable to use instead of the discovered one, e.g. a custom build.
+pub const PERF_BIN_ENV: &str = "CODSPEED_PERF_BIN";
+
+/// Whether the perf executable is forced with [`PERF_BIN_ENV`].
+pub fn is_perf_executable_forced() -> bool {
+    std::env::var_os(PERF_BIN_ENV).is_some()
+}
+
+/// The perf executable forced with [`PERF_BIN_ENV`], if it is executable and reports a version.
+fn get_forced_perf_executable(path: &OsString) -> Result<OsString> {
+    let metadata = std::fs::metadata(path).context("The file is not accessible")?;
+    ensure!(
+        metadata.is_file() && metadata.permissions().mode() & 0o111 != 0,
+        "The file is not executable"
+    );
+    let output = Command::new(path)
+        .arg("--version")
+        .output()
+        .context("Failed to run it")?;
+    ensure!(
+        output.status.success(),
+        "`--version` failed: {}",
+        String::from_utf8_lossy(&output.stderr).trim()
+    );
+    debug!(
+        "Using the perf executable from {PERF_BIN_ENV}: {}",
+        String::from_utf8_lossy(&output.stdout).trim()
+    );
+    Ok(path.clone())
+}
+
 /// Attempts to find the path to the `perf` executable that is installed and working.
+/// The executable set in [`PERF_BIN_ENV`] takes precedence over the discovered one.
 /// Returns None if `perf` is not installed or not functioning correctly.
 pub fn get_working_perf_executable() -> Option<OsString> {
+    if let Some(path) = std::env::var_os(PERF_BIN_ENV) {
+        return match get_forced_perf_executable(&path) {
+            Ok(path) => Some(path),
+            Err(error) => {
+                warn!(
+                    "{PERF_BIN_ENV} is set to {}, which is not a working perf executable: {error:#}",
+                    path.to_string_lossy()
+                );
+                None
+            }
+        };
+    }
+
     let is_installed = Command::new("which")
         .arg("perf")
         .output()
@@ -145,3 +194,170 @@ pub fn get_compression_flags<S: AsRef<Path>>(perf_executable: S) -> Result<Optio
         Ok(None)
     }
 }
+
+/// Detects if perf can record the build-id of the mapped files in the MMAP2 records, to tell when
+/// a binary changed since it was profiled. Returns the flag to pass to perf record if it can.
+///
+/// The flag also needs kernel support (5.12+), which a perf executable newer than the kernel
+/// lists without having it, e.g. in a container or with [`PERF_BIN_ENV`]. It is therefore only
+/// used if a dry recording with it succeeds.
+pub fn get_buildid_mmap_flag<S: AsRef<Path>>(perf_executable: S) -> Result<Option<String>> {
+    // `perf record -h` prints the usage on stderr and exits with an error
+    let output = Command::new(perf_executable.as_ref())
+        .args(["record", "-h"])
+        .output()
+        .context("Failed to run perf record -h")?;
+    let usage = [output.stdout, output.stderr].concat();
+    if !String::from_utf8_lossy(&usage).contains("--buildid-mmap") {
+        debug!("perf does not support --buildid-mmap, the build-ids won't be checked");
+        return Ok(None);
+    }
+
+    let output = Command::new(perf_executable.as_ref())
+        .args([
+            "record",
+            "--quiet",
+            "--buildid-mmap",
+            "-o",
+            "-",
+            "--",
+            "true",
+        ])
+        .stdout(Stdio::null())
+        .output()
+        .context("Failed to run perf record --buildid-mmap")?;
+    if output.status.success() {
+        debug!("perf supports --buildid-mmap");
+        Ok(Some("--buildid-mmap".to_string()))
+    } else {
+        debug!(
+            "perf lists --buildid-mmap but failed to record with it, the build-ids won't be checked: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+        Ok(None)
+    }
+}
+
+/// The optional perf record flags supported by a perf executable.
+#[derive(Debug, Default, PartialEq)]
+pub struct PerfCapabilities {
+    /// The zstd compression flag, see [`get_compression_flags`].
+    pub compression_flags: Option<String>,
+    /// The flag recording the extra events, see [`get_event_flags`]. Only probed when
+    /// compression is available, as the events make the data much larger.
+    pub event_flags: Option<String>,
+    /// The flag recording the build-ids in the mappings, see [`get_buildid_mmap_flag`].
+    pub buildid_mmap_flag: Option<String>,
+}
+
+impl PerfCapabilities {
+    pub fn probe(perf_executable: &OsString) -> Result<Self> {
+        let compression_flags = get_compression_flags(perf_executable)?;
+        let event_flags = if compression_flags.is_some() {
+            get_event_flags(perf_executable)?
+        } else {
+            None
+        };
+        Ok(Self {
+            compression_flags,
+            event_flags,
+            buildid_mmap_flag: get_buildid_mmap_flag(perf_executable)?,
+        })
+    }
+}
+
+impl std::fmt::Display for PerfCapabilities {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let on_off = |flags: &Option<String>| if flags.is_some() { "on" } else { "off" };
+        write!(
+            f,
+            "perf: compression={}, extra-events={}, buildid-mmap={}",
+            on_off(&self.compression_flags),
+            on_off(&self.event_flags),
+            on_off(&self.buildid_mmap_flag)
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_capabilities_summary() {
+        let capabilities = PerfCapabilities {
+            compression_flags: Some("--compression-level=3".into()),
+            event_flags: Some("-e {cycles}".into()),
+            buildid_mmap_flag: Some("--buildid-mmap".into()),
+        };
+        assert_eq!(
+            capabilities.to_string(),
+            "perf: compression=on, extra-events=on, buildid-mmap=on"
+        );
+
+        let capabilities = PerfCapabilities {
+            event_flags: None,
+            ..capabilities
+        };
+        assert_eq!(
+            capabilities.to_string(),
+            "perf: compression=on, extra-events=off, buildid-mmap=on"
+        );
+        assert_eq!(
+            PerfCapabilities::default().to_string(),
+            "perf: compression=off, extra-events=off, buildid-mmap=off"
+        );
+    }
+
+    #[test]
+    fn test_forced_perf_executable_is_selected() {
+        let folder = tempfile::tempdir().unwrap();
+        let perf = folder.path().join("perf");
+        std::fs::write(&perf, "#!/bin/sh\necho perf version 6.99.custom\n").unwrap();
+        std::fs::set_permissions(&perf, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+        temp_env::with_var(PERF_BIN_ENV, Some(&perf), || {
+            assert!(is_perf_executable_forced());
+            assert_eq!(get_working_perf_executable(), Some(perf.clone().into()));
+        });
+    }
+
+    #[test]
+    fn test_forced_perf_executable_must_work() {
+        let folder = tempfile::tempdir().unwrap();
+        let not_executable = folder.path().join("perf");
+        std::fs::write(&not_executable, "#!/bin/sh\necho perf version 6.99\n").unwrap();
+        let failing = folder.path().join("failing-perf");
+        std::fs::write(&failing, "#!/bin/sh\nexit 1\n").unwrap();
+        std::fs::set_permissions(&failing, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+        for path in [not_executable, failing, folder.path().join("missing")] {
+            temp_env::with_var(PERF_BIN_ENV, Some(&path), || {
+                assert_eq!(get_working_perf_executable(), None);
+            });
+        }
+    }
+
+    #[test]
+    fn test_buildid_mmap_needs_kernel_support() {
+        let folder = tempfile::tempdir().unwrap();
+        let perf = folder.path().join("perf");
+        // A perf listing the flag, on a kernel without build-ids in the mmap events
+        let script = |record_status: u8| {
+            format!(
+                "#!/bin/sh\n\
+                if [ \"$2\" = -h ]; then echo '    --buildid-mmap  Record build-id in map events' >&2; exit 129; fi\n\
+                exit {record_status}\n"
+            )
+        };
+        std::fs::write(&perf, script(1)).unwrap();
+        std::fs::set_permissions(&perf, std::fs::Permissions::from_mode(0o755)).unwrap();
+        assert_eq!(get_buildid_mmap_flag(&perf).unwrap(), None);
+
+        std::fs::write(&perf, script(0)).unwrap();
+        assert_eq!(
+            get_buildid_mmap_flag(&perf).unwrap().as_deref(),
+            Some("--buildid-mmap")
+        );
+    }
+}
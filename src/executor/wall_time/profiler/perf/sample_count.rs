@@ -0,0 +1,289 @@
+use crate::prelude::*;
+use runner_shared::artifacts::ExecutionTimestamps;
+use runner_shared::fifo::MarkerType;
+use std::collections::HashMap;
+
+/// Below this number of samples, the profile of a benchmark is mostly noise.
+pub const MIN_SAMPLES_PER_BENCHMARK: usize = 10;
+
+/// Tolerance in nanoseconds when matching the samples to the sampling windows of the markers.
+pub const MARKER_TOLERANCE_NS_ENV: &str = "CODSPEED_PERF_MARKER_TOLERANCE_NS";
+
+/// Default of [`MARKER_TOLERANCE_NS_ENV`]: well below the sampling period, so that only the
+/// samples taken right at a boundary move to the window.
+pub const DEFAULT_MARKER_TOLERANCE_NS: u64 = 10_000;
+
+/// Resolve the marker tolerance from [`MARKER_TOLERANCE_NS_ENV`].
+pub fn marker_tolerance_from_env() -> Result<u64> {
+    let Ok(value) = std::env::var(MARKER_TOLERANCE_NS_ENV) else {
+        return Ok(DEFAULT_MARKER_TOLERANCE_NS);
+    };
+    value.trim().parse::<u64>().with_context(|| {
+        format!(
+            "Invalid {MARKER_TOLERANCE_NS_ENV} value {value:?}, expected a number of nanoseconds"
+        )
+    })
+}
+
+/// Widen the sampling `windows` by `tolerance_ns` on each side, for the samples taken by perf
+/// slightly before or after the marker because of the skew between the clocks of the processes.
+/// A window never grows past the middle of the gap to its neighbors, so that a sample is
+/// matched to a single window, nor past the sorted `barriers`, so that it stays between the same
+/// benchmark URIs.
+fn widen_windows(windows: &[(u64, u64)], tolerance_ns: u64, barriers: &[u64]) -> Vec<(u64, u64)> {
+    windows
+        .iter()
+        .enumerate()
+        .map(|(i, &(start, end))| {
+            let mut widened_start = start.saturating_sub(tolerance_ns);
+            if let Some(&(_, previous_end)) = i.checked_sub(1).and_then(|i| windows.get(i)) {
+                if previous_end < start {
+                    widened_start =
+                        widened_start.max(previous_end + (start - previous_end) / 2 + 1);
+                } else {
+                    widened_start = start;
+                }
+            }
+            if let Some(barrier) = barriers.iter().rev().find(|&&ts| ts <= start) {
+                widened_start = widened_start.max(*barrier);
+            }
+            let mut widened_end = end.saturating_add(tolerance_ns);
+            if let Some(&(next_start, _)) = windows.get(i + 1) {
+                if end < next_start {
+                    widened_end = widened_end.min(end + (next_start - end) / 2);
+                } else {
+                    widened_end = end;
+                }
+            }
+            if let Some(barrier) = barriers.iter().find(|&&ts| ts >= end) {
+                widened_end = widened_end.min(*barrier);
+            }
+            (widened_start, widened_end)
+        })
+        .collect()
+}
+
+/// The `SampleStart`/`SampleEnd` pairs of the markers, sorted.
+fn sampling_windows(markers: &[MarkerType]) -> Vec<(u64, u64)> {
+    let mut windows = Vec::new();
+    let mut window_start = None;
+    for marker in markers {
+        match *marker {
+            MarkerType::SampleStart(start) => window_start = Some(start),
+            MarkerType::SampleEnd(end) => {
+                if let Some(start) = window_start.take() {
+                    windows.push((start, end));
+                }
+            }
+            _ => {}
+        }
+    }
+    windows.sort_unstable();
+    windows
+}
+
+/// The execution timestamps with their sampling windows widened by `tolerance_ns` (see
+/// [`widen_windows`]), so that the samples taken right at a boundary are attributed to the
+/// benchmark when the profile is processed too.
+pub fn widen_sample_markers(
+    execution_timestamps: &ExecutionTimestamps,
+    tolerance_ns: u64,
+) -> ExecutionTimestamps {
+    let windows = sampling_windows(&execution_timestamps.markers);
+    let barriers = execution_timestamps
+        .uri_by_ts
+        .iter()
+        .map(|(ts, _)| *ts)
+        .sorted()
+        .collect::<Vec<_>>();
+    let widened_by_window = windows
+        .iter()
+        .copied()
+        .zip(widen_windows(&windows, tolerance_ns, &barriers))
+        .collect::<HashMap<_, _>>();
+
+    let mut markers = execution_timestamps.markers.clone();
+    let mut window_start = None;
+    for i in 0..markers.len() {
+        match markers[i] {
+            MarkerType::SampleStart(start) => window_start = Some((i, start)),
+            MarkerType::SampleEnd(end) => {
+                if let Some((start_index, start)) = window_start.take() {
+                    let (widened_start, widened_end) = widened_by_window[&(start, end)];
+                    markers[start_index] = MarkerType::SampleStart(widened_start);
+                    markers[i] = MarkerType::SampleEnd(widened_end);
+                }
+            }
+            _ => {}
+        }
+    }
+    ExecutionTimestamps {
+        markers,
+        ..execution_timestamps.clone()
+    }
+}
+
+/// Count the samples taken during the sampling windows of each benchmark.
+///
+/// A benchmark URI is reported once the benchmark ran, so its windows are the
+/// `SampleStart`/`SampleEnd` pairs between the previous URI and its own. Benchmarks without
+/// any window are left out, since their samples can't be told apart.
+pub fn count_samples_by_uri<'a>(
+    execution_timestamps: &'a ExecutionTimestamps,
+    sorted_sample_timestamps: &[u64],
+) -> Vec<(&'a str, usize)> {
+    let windows = sampling_windows(&execution_timestamps.markers);
+
+    let samples_between = |start: u64, end: u64| {
+        sorted_sample_timestamps
+            .partition_point(|&ts| ts <= end)
+            .saturating_sub(sorted_sample_timestamps.partition_point(|&ts| ts < start))
+    };
+
+    let mut previous_uri_ts = 0;
+    execution_timestamps
+        .uri_by_ts
+        .iter()
+        .sorted_by_key(|(ts, _)| *ts)
+        .filter_map(|(uri_ts, uri)| {
+            let benchmark_windows = windows
+                .iter()
+                .filter(|(start, end)| *start >= previous_uri_ts && *end <= *uri_ts)
+                .collect::<Vec<_>>();
+            previous_uri_ts = *uri_ts;
+            if benchmark_windows.is_empty() {
+                return None;
+            }
+
+            let sample_count = benchmark_windows
+                .into_iter()
+                .map(|(start, end)| samples_between(*start, *end))
+                .sum();
+            Some((uri.as_str(), sample_count))
+        })
+        .collect()
+}
+
+/// Warn about the benchmarks that got fewer than [`MIN_SAMPLES_PER_BENCHMARK`] samples, when
+/// sampling at `sampling_frequency` Hz.
+pub fn warn_low_sample_benchmarks(
+    execution_timestamps: &ExecutionTimestamps,
+    sorted_sample_timestamps: &[u64],
+    sampling_frequency: u32,
+) {
+    for (uri, sample_count) in count_samples_by_uri(execution_timestamps, sorted_sample_timestamps)
+    {
+        if sample_count < MIN_SAMPLES_PER_BENCHMARK {
+            run_warning!(
+                "Benchmark {uri} only got {sample_count} samples (at least {MIN_SAMPLES_PER_BENCHMARK} expected), \
+                its profile will not be meaningful. Increase the work done per iteration so that it runs \
+                for well over the {sampling_frequency} Hz sampling period."
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_count_samples_by_uri_flags_short_benchmark() {
+        let execution_timestamps = ExecutionTimestamps::new(
+            &[
+                (2_000, "bench::long".to_string()),
+                (3_000, "bench::short".to_string()),
+            ],
+            &[
+                MarkerType::SampleStart(1_000),
+                MarkerType::SampleEnd(1_900),
+                MarkerType::SampleStart(2_100),
+                MarkerType::SampleEnd(2_200),
+            ],
+        );
+        // 20 samples in the first window, 2 in the second and a few outside of any window
+        let sample_timestamps = (0..20)
+            .map(|i| 1_000 + i * 40)
+            .chain([1_950, 2_150, 2_200, 2_500])
+            .sorted()
+            .collect::<Vec<_>>();
+
+        let counts = count_samples_by_uri(&execution_timestamps, &sample_timestamps);
+
+        assert_eq!(counts, vec![("bench::long", 20), ("bench::short", 2)]);
+        let low_sample_uris = counts
+            .iter()
+            .filter(|(_, count)| *count < MIN_SAMPLES_PER_BENCHMARK)
+            .map(|(uri, _)| *uri)
+            .collect::<Vec<_>>();
+        assert_eq!(low_sample_uris, vec!["bench::short"]);
+    }
+
+    #[test]
+    fn test_count_samples_by_uri_skips_benchmarks_without_markers() {
+        let execution_timestamps =
+            ExecutionTimestamps::new(&[(2_000, "bench::no_markers".to_string())], &[]);
+
+        assert!(count_samples_by_uri(&execution_timestamps, &[1_500]).is_empty());
+    }
+
+    #[test]
+    fn test_samples_at_the_boundaries_are_matched_within_the_tolerance() {
+        let execution_timestamps = ExecutionTimestamps::new(
+            &[
+                (1_910, "bench::first".to_string()),
+                (3_000, "bench::second".to_string()),
+            ],
+            &[
+                MarkerType::SampleStart(1_000),
+                MarkerType::SampleEnd(1_900),
+                MarkerType::SampleStart(1_920),
+                MarkerType::SampleEnd(2_900),
+            ],
+        );
+        // Samples taken slightly before the start and after the end of the windows, two in the
+        // 20ns gap between them and one far outside
+        let sample_timestamps = [995, 1_500, 1_905, 1_915, 2_500, 2_908, 2_950];
+
+        assert_eq!(
+            count_samples_by_uri(&execution_timestamps, &sample_timestamps),
+            vec![("bench::first", 1), ("bench::second", 1)]
+        );
+        // The samples of the gap go to the closest window, each counted once
+        let widened = widen_sample_markers(&execution_timestamps, 10);
+        assert_eq!(
+            widened.markers,
+            vec![
+                MarkerType::SampleStart(990),
+                MarkerType::SampleEnd(1_910),
+                MarkerType::SampleStart(1_911),
+                MarkerType::SampleEnd(2_910),
+            ]
+        );
+        assert_eq!(
+            count_samples_by_uri(&widened, &sample_timestamps),
+            vec![("bench::first", 3), ("bench::second", 3)]
+        );
+        // The windows don't grow past the URIs of the benchmarks
+        let widened = widen_sample_markers(&execution_timestamps, 1_000);
+        assert_eq!(widened.markers[0], MarkerType::SampleStart(0));
+        assert_eq!(widened.markers[3], MarkerType::SampleEnd(3_000));
+        assert_eq!(widened.uri_by_ts, execution_timestamps.uri_by_ts);
+    }
+
+    #[test]
+    fn test_marker_tolerance_from_env() {
+        temp_env::with_var(MARKER_TOLERANCE_NS_ENV, None::<&str>, || {
+            assert_eq!(
+                marker_tolerance_from_env().unwrap(),
+                DEFAULT_MARKER_TOLERANCE_NS
+            );
+        });
+        temp_env::with_var(MARKER_TOLERANCE_NS_ENV, Some("0"), || {
+            assert_eq!(marker_tolerance_from_env().unwrap(), 0);
+        });
+        temp_env::with_var(MARKER_TOLERANCE_NS_ENV, Some("1ms"), || {
+            assert!(marker_tolerance_from_env().is_err());
+        });
+    }
+}
@@ -1,5 +1,6 @@
 use super::debug_info::debug_info_by_path;
 use super::loaded_module::LoadedModule;
+use super::module_symbols;
 use crate::executor::valgrind::helpers::ignored_objects_path::get_objects_path_to_ignore;
 use crate::executor::wall_time::profiler::perf::naming;
 use crate::prelude::*;
@@ -9,10 +10,13 @@ use runner_shared::debug_info::{MappedProcessDebugInfo, ModuleDebugInfo};
 use runner_shared::module_symbols::MappedProcessModuleSymbols;
 use runner_shared::unwind_data::{MappedProcessUnwindData, ProcessUnwindData, UnwindData};
 use std::collections::HashMap;
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::ops::Range;
 use std::path::{Path, PathBuf};
 
 pub struct SavedArtifacts {
     pub symbol_pid_mappings_by_pid: HashMap<pid_t, Vec<MappedProcessModuleSymbols>>,
+    pub symbols_line_count_by_key: HashMap<String, u64>,
     pub debug_info: HashMap<String, ModuleDebugInfo>,
     pub mapped_process_debug_info_by_pid: HashMap<pid_t, Vec<MappedProcessDebugInfo>>,
     pub mapped_process_unwind_data_by_pid: HashMap<pid_t, Vec<MappedProcessUnwindData>>,
@@ -25,16 +29,18 @@ pub fn save_artifacts(
     profile_folder: &Path,
     loaded_modules_by_path: &HashMap<PathBuf, LoadedModule>,
     jit_unwind_data_by_pid: &HashMap<pid_t, Vec<(UnwindData, ProcessUnwindData)>>,
+    ignored_objects: &[glob::Pattern],
+    inline_frames: bool,
 ) -> SavedArtifacts {
     let mut path_to_key = HashMap::<PathBuf, String>::new();
 
     register_paths(&mut path_to_key, loaded_modules_by_path);
 
-    let symbol_pid_mappings_by_pid =
+    let (symbol_pid_mappings_by_pid, symbols_line_count_by_key) =
         save_symbols(profile_folder, loaded_modules_by_path, &path_to_key);
 
     let (debug_info, mapped_process_debug_info_by_pid) =
-        save_debug_info(loaded_modules_by_path, &mut path_to_key);
+        save_debug_info(loaded_modules_by_path, &mut path_to_key, inline_frames);
 
     let mapped_process_unwind_data_by_pid = save_unwind_data(
         profile_folder,
@@ -43,7 +49,7 @@ pub fn save_artifacts(
         &mut path_to_key,
     );
 
-    let ignored_modules_by_pid = collect_ignored_modules(loaded_modules_by_path);
+    let ignored_modules_by_pid = collect_ignored_modules(loaded_modules_by_path, ignored_objects);
 
     let key_to_path = path_to_key
         .into_iter()
@@ -52,6 +58,7 @@ pub fn save_artifacts(
 
     SavedArtifacts {
         symbol_pid_mappings_by_pid,
+        symbols_line_count_by_key,
         debug_info,
         mapped_process_debug_info_by_pid,
         mapped_process_unwind_data_by_pid,
@@ -86,19 +93,41 @@ fn save_symbols(
     profile_folder: &Path,
     loaded_modules_by_path: &HashMap<PathBuf, LoadedModule>,
     path_to_key: &HashMap<PathBuf, String>,
-) -> HashMap<pid_t, Vec<MappedProcessModuleSymbols>> {
+) -> (
+    HashMap<pid_t, Vec<MappedProcessModuleSymbols>>,
+    HashMap<String, u64>,
+) {
     let symbols_count = loaded_modules_by_path
         .values()
         .filter(|m| m.module_symbols.is_some())
         .count();
     debug!("Saving symbols ({symbols_count} unique entries)");
 
-    loaded_modules_by_path.par_iter().for_each(|(path, m)| {
-        if let Some(ref symbols) = m.module_symbols {
+    let symbols_line_count_by_key: HashMap<String, u64> = loaded_modules_by_path
+        .par_iter()
+        .filter_map(|(path, m)| {
+            let symbols = m.module_symbols.as_ref()?;
             let key = &path_to_key[path];
-            symbols.save_to_keyed_file(profile_folder, key).unwrap();
-        }
-    });
+            let line_count = symbols
+                .save_to_keyed_file(profile_folder, key)
+                .and_then(|_| {
+                    module_symbols::validate_symbols_file(
+                        module_symbols::keyed_file_path(profile_folder, key),
+                        symbols.symbols().len(),
+                    )
+                });
+            match line_count {
+                Ok(line_count) => Some((key.clone(), line_count as u64)),
+                Err(e) => {
+                    run_warning!(
+                        "Excluding the symbols of {} from the profile: {e:#}",
+                        path.display()
+                    );
+                    None
+                }
+            }
+        })
+        .collect();
 
     let mut mappings_by_pid: HashMap<pid_t, Vec<MappedProcessModuleSymbols>> = HashMap::new();
     for (path, loaded_module) in loaded_modules_by_path {
@@ -106,14 +135,40 @@ fn save_symbols(
             continue;
         }
         let key = &path_to_key[path];
+        // Skip the modules whose symbols file could not be written correctly
+        if !symbols_line_count_by_key.contains_key(key) {
+            continue;
+        }
+        let addr_bounds = loaded_module
+            .module_symbols
+            .as_ref()
+            .and_then(|ms| ms.addr_bounds());
         for (&pid, pm) in &loaded_module.process_loaded_modules {
             if let Some(load_bias) = pm.symbols_load_bias {
+                let avma_ranges = pm
+                    .process_unwind_data
+                    .iter()
+                    .map(|pud| pud.avma_range.clone())
+                    .collect_vec();
+                // The range spanning the executable segments of the module
+                let mapped_range = avma_ranges
+                    .iter()
+                    .cloned()
+                    .reduce(|a, b| a.start.min(b.start)..a.end.max(b.end));
+                if let (Some(addr_bounds), Some(mapped_range)) = (addr_bounds, mapped_range) {
+                    if let Some(warning) =
+                        symbols_mapping_warning(path, pid, addr_bounds, load_bias, &mapped_range)
+                    {
+                        run_warning!("{warning}");
+                    }
+                }
                 mappings_by_pid
                     .entry(pid)
                     .or_default()
                     .push(MappedProcessModuleSymbols {
                         perf_map_key: key.clone(),
                         load_bias,
+                        avma_ranges,
                     });
             }
         }
@@ -121,39 +176,106 @@ fn save_symbols(
     for mappings in mappings_by_pid.values_mut() {
         mappings.sort_by(|a, b| a.perf_map_key.cmp(&b.perf_map_key));
     }
-    mappings_by_pid
+    (mappings_by_pid, symbols_line_count_by_key)
+}
+
+/// Warn when the symbols of the module at `path`, rebased with the load bias of `pid`, don't
+/// overlap the executable range mapped by that process: the load bias is wrong, and so would be
+/// the symbolication. The symbols may extend past the mapping, e.g. with the data symbols, so only
+/// disjoint ranges are reported.
+fn symbols_mapping_warning(
+    path: &Path,
+    pid: pid_t,
+    (start, end): (u64, u64),
+    load_bias: u64,
+    avma_range: &Range<u64>,
+) -> Option<String> {
+    let (start, end) = (start.wrapping_add(load_bias), end.wrapping_add(load_bias));
+    if start < avma_range.end && avma_range.start < end {
+        return None;
+    }
+    Some(format!(
+        "The symbols of {} in pid {pid} are at {start:#x}-{end:#x} with the load bias {load_bias:#x}, \
+        outside of its mapping at {:#x}-{:#x}, its functions may be misattributed",
+        path.display(),
+        avma_range.start,
+        avma_range.end
+    ))
 }
 
 /// Compute debug info from symbols and build per-pid debug info mappings.
 fn save_debug_info(
     loaded_modules_by_path: &HashMap<PathBuf, LoadedModule>,
     path_to_key: &mut HashMap<PathBuf, String>,
+    inline_frames: bool,
 ) -> (
     HashMap<String, ModuleDebugInfo>,
     HashMap<pid_t, Vec<MappedProcessDebugInfo>>,
 ) {
     debug!("Saving debug_info");
 
-    let debug_info_by_elf_path = debug_info_by_path(loaded_modules_by_path);
+    let debug_info_by_elf_path = debug_info_by_path(loaded_modules_by_path, inline_frames);
+    dedup_debug_info(debug_info_by_elf_path, loaded_modules_by_path, path_to_key)
+}
 
-    for path in debug_info_by_elf_path.keys() {
-        get_or_insert_key(path_to_key, path);
-    }
+/// Hash the content of a [`ModuleDebugInfo`], leaving out the path it was read from.
+fn debug_info_content_hash(module_debug_info: &ModuleDebugInfo) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    module_debug_info.addr_bounds.hash(&mut hasher);
+    module_debug_info.load_bias.hash(&mut hasher);
+    module_debug_info.debug_infos.hash(&mut hasher);
+    module_debug_info.inline_ranges.hash(&mut hasher);
+    hasher.finish()
+}
 
-    let debug_info: HashMap<String, ModuleDebugInfo> = debug_info_by_elf_path
+/// Key the debug info by content, so that identical debug info loaded from several paths
+/// (copied libraries, multi-arch layouts) is stored once, under the key of its first path.
+fn dedup_debug_info(
+    debug_info_by_elf_path: HashMap<PathBuf, ModuleDebugInfo>,
+    loaded_modules_by_path: &HashMap<PathBuf, LoadedModule>,
+    path_to_key: &mut HashMap<PathBuf, String>,
+) -> (
+    HashMap<String, ModuleDebugInfo>,
+    HashMap<pid_t, Vec<MappedProcessDebugInfo>>,
+) {
+    let mut debug_info: HashMap<String, ModuleDebugInfo> = HashMap::new();
+    let mut keys_by_hash: HashMap<u64, Vec<String>> = HashMap::new();
+    let mut debug_info_key_by_path: HashMap<PathBuf, String> = HashMap::new();
+    // Sort the paths so that the shared key doesn't depend on the map iteration order
+    for (path, module_debug_info) in debug_info_by_elf_path
         .into_iter()
-        .filter_map(|(path, info)| {
-            let key = path_to_key.get(&path)?.clone();
-            Some((key, info))
-        })
-        .collect();
+        .sorted_by(|(a, _), (b, _)| a.cmp(b))
+    {
+        let path_key = get_or_insert_key(path_to_key, &path);
+        let same_hash_keys = keys_by_hash
+            .entry(debug_info_content_hash(&module_debug_info))
+            .or_default();
+        let shared_key = same_hash_keys.iter().find(|key| {
+            let existing = &debug_info[key.as_str()];
+            existing.addr_bounds == module_debug_info.addr_bounds
+                && existing.load_bias == module_debug_info.load_bias
+                && existing.debug_infos == module_debug_info.debug_infos
+                && existing.inline_ranges == module_debug_info.inline_ranges
+        });
+        let key = match shared_key {
+            Some(key) => key.clone(),
+            None => {
+                same_hash_keys.push(path_key.clone());
+                debug_info.insert(path_key.clone(), module_debug_info);
+                path_key
+            }
+        };
+        debug_info_key_by_path.insert(path, key);
+    }
+    debug!(
+        "Saved {} unique debug_info entries for {} modules",
+        debug_info.len(),
+        debug_info_key_by_path.len()
+    );
 
     let mut mappings_by_pid: HashMap<pid_t, Vec<MappedProcessDebugInfo>> = HashMap::new();
     for (path, loaded_module) in loaded_modules_by_path {
-        if loaded_module.module_symbols.is_none() {
-            continue;
-        }
-        let Some(key) = path_to_key.get(path) else {
+        let Some(key) = debug_info_key_by_path.get(path) else {
             continue;
         };
         for (&pid, pm) in &loaded_module.process_loaded_modules {
@@ -203,7 +325,7 @@ fn save_unwind_data(
         }
         let key = &path_to_key[path];
         for (&pid, pm) in &loaded_module.process_loaded_modules {
-            if let Some(ref pud) = pm.process_unwind_data {
+            for pud in &pm.process_unwind_data {
                 mappings_by_pid
                     .entry(pid)
                     .or_default()
@@ -246,21 +368,51 @@ fn save_unwind_data(
     mappings_by_pid
 }
 
-/// Collect ignored modules by finding known-ignored and python modules in the mounted modules.
+/// Flatten the per-pid ignored modules into the deprecated `ignored_modules` list, still
+/// written for the parsers predating `ignored_modules_by_pid`.
+pub fn flatten_ignored_modules(
+    ignored_modules_by_pid: &HashMap<pid_t, Vec<(String, u64, u64)>>,
+) -> Vec<(String, u64, u64)> {
+    ignored_modules_by_pid
+        .values()
+        .flatten()
+        .cloned()
+        .sorted()
+        .dedup()
+        .collect()
+}
+
+/// Collect ignored modules by finding known-ignored, python and user-ignored (`ignored_objects`
+/// globs, matched against the full path) modules in the mounted modules.
 /// Returns per-pid entries with runtime address ranges derived from symbol bounds + load bias.
 fn collect_ignored_modules(
     loaded_modules_by_path: &HashMap<PathBuf, LoadedModule>,
+    ignored_objects: &[glob::Pattern],
 ) -> HashMap<pid_t, Vec<(String, u64, u64)>> {
-    let mut by_pid: HashMap<pid_t, Vec<(String, u64, u64)>> = HashMap::new();
+    collect_ignored_modules_with_paths(
+        loaded_modules_by_path,
+        &get_objects_path_to_ignore(),
+        ignored_objects,
+    )
+}
 
-    let ignore_paths = get_objects_path_to_ignore();
+/// [`collect_ignored_modules`] with explicit known-ignored paths.
+fn collect_ignored_modules_with_paths(
+    loaded_modules_by_path: &HashMap<PathBuf, LoadedModule>,
+    ignore_paths: &[String],
+    ignored_objects: &[glob::Pattern],
+) -> HashMap<pid_t, Vec<(String, u64, u64)>> {
+    let mut by_pid: HashMap<pid_t, Vec<(String, u64, u64)>> = HashMap::new();
 
     for (path, loaded_module) in loaded_modules_by_path {
         let path_str = path.to_string_lossy();
 
         let is_ignored = ignore_paths
             .iter()
-            .any(|ip| path_str.as_ref() == ip.as_str());
+            .any(|ip| path_str.as_ref() == ip.as_str())
+            || ignored_objects
+                .iter()
+                .any(|pattern| pattern.matches(&path_str));
         let is_python = path
             .file_name()
             .map(|name| name.to_string_lossy().starts_with("python"))
@@ -292,3 +444,199 @@ fn collect_ignored_modules(
 
     by_pid
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::executor::wall_time::profiler::perf::loaded_module::ProcessLoadedModule;
+    use crate::executor::wall_time::profiler::perf::module_symbols::{ModuleSymbols, Symbol};
+    use runner_shared::debug_info::DebugInfo;
+
+    fn loaded_module(pid: pid_t, load_bias: u64) -> LoadedModule {
+        LoadedModule {
+            module_symbols: Some(ModuleSymbols::new(vec![
+                Symbol {
+                    addr: 0x1000,
+                    size: 0x100,
+                    name: "first".into(),
+                },
+                Symbol {
+                    addr: 0x2000,
+                    size: 0x200,
+                    name: "last".into(),
+                },
+            ])),
+            unwind_data: None,
+            build_id: None,
+            process_loaded_modules: HashMap::from([(
+                pid,
+                ProcessLoadedModule {
+                    symbols_load_bias: Some(load_bias),
+                    process_unwind_data: vec![],
+                },
+            )]),
+        }
+    }
+
+    #[test]
+    fn test_identical_debug_info_is_stored_once() {
+        let module_debug_info = |object_path: &str| ModuleDebugInfo {
+            object_path: object_path.to_string(),
+            addr_bounds: (0x1000, 0x2200),
+            load_bias: 0,
+            debug_infos: vec![DebugInfo {
+                addr: 0x1000,
+                size: 0x100,
+                name: "first".into(),
+                file: "src/lib.rs".into(),
+                line: Some(12),
+            }],
+            inline_ranges: vec![],
+        };
+        let (copy_path, lib_path) = (
+            PathBuf::from("/opt/app/lib/libbench.so"),
+            PathBuf::from("/usr/lib/libbench.so"),
+        );
+        let loaded_modules_by_path = HashMap::from([
+            (copy_path.clone(), loaded_module(1, 0x7f00_0000)),
+            (lib_path.clone(), loaded_module(2, 0x5500_0000)),
+        ]);
+        let debug_info_by_elf_path = HashMap::from([
+            (
+                copy_path.clone(),
+                module_debug_info("/opt/app/lib/libbench.so"),
+            ),
+            (lib_path.clone(), module_debug_info("/usr/lib/libbench.so")),
+        ]);
+        let mut path_to_key = HashMap::new();
+        register_paths(&mut path_to_key, &loaded_modules_by_path);
+
+        let (debug_info, mappings_by_pid) = dedup_debug_info(
+            debug_info_by_elf_path,
+            &loaded_modules_by_path,
+            &mut path_to_key,
+        );
+
+        let shared_key = &path_to_key[&copy_path];
+        assert_eq!(debug_info.keys().collect::<Vec<_>>(), vec![shared_key]);
+        for (pid, load_bias) in [(1, 0x7f00_0000), (2, 0x5500_0000)] {
+            let mappings = &mappings_by_pid[&pid];
+            assert_eq!(mappings.len(), 1);
+            assert_eq!(&mappings[0].debug_info_key, shared_key);
+            assert_eq!(mappings[0].load_bias, load_bias);
+        }
+    }
+
+    #[test]
+    fn test_user_globs_add_modules_to_ignored_list() {
+        let loaded_modules_by_path = HashMap::from([
+            (
+                PathBuf::from("/opt/vendor/lib/libasan.so.8"),
+                loaded_module(42, 0x7f00_0000),
+            ),
+            (
+                PathBuf::from("/usr/lib/libbench.so"),
+                loaded_module(42, 0x5500_0000),
+            ),
+        ]);
+
+        let without_globs = collect_ignored_modules_with_paths(&loaded_modules_by_path, &[], &[]);
+        assert!(without_globs.is_empty());
+
+        let globs = [glob::Pattern::new("*/libasan.so*").unwrap()];
+        let ignored = collect_ignored_modules_with_paths(&loaded_modules_by_path, &[], &globs);
+        assert_eq!(
+            ignored[&42],
+            vec![(
+                "/opt/vendor/lib/libasan.so.8".to_string(),
+                0x7f00_1000,
+                0x7f00_2200
+            )]
+        );
+    }
+
+    #[test]
+    fn test_python_and_ignored_paths_are_collected() {
+        let loaded_modules_by_path = HashMap::from([
+            (
+                PathBuf::from("/usr/bin/python3.12"),
+                loaded_module(7, 0x1_0000),
+            ),
+            (
+                PathBuf::from("/usr/lib/libnode.so.115"),
+                loaded_module(7, 0x2_0000),
+            ),
+            (
+                PathBuf::from("/usr/lib/libbench.so"),
+                loaded_module(7, 0x3_0000),
+            ),
+        ]);
+        let ignore_paths = ["/usr/lib/libnode.so.115".to_string()];
+
+        let mut ignored =
+            collect_ignored_modules_with_paths(&loaded_modules_by_path, &ignore_paths, &[]);
+        let mut modules = ignored.remove(&7).unwrap();
+        modules.sort();
+        assert!(ignored.is_empty());
+        assert_eq!(
+            modules,
+            vec![
+                ("/usr/bin/python3.12".to_string(), 0x1_1000, 0x1_2200),
+                ("/usr/lib/libnode.so.115".to_string(), 0x2_1000, 0x2_2200),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_ignored_module_ranges_are_kept_per_pid() {
+        let mut module = loaded_module(1, 0x7f00_0000);
+        module.process_loaded_modules.insert(
+            2,
+            ProcessLoadedModule {
+                symbols_load_bias: Some(0x7e00_0000),
+                process_unwind_data: vec![],
+            },
+        );
+        let loaded_modules_by_path =
+            HashMap::from([(PathBuf::from("/usr/bin/python3.12"), module)]);
+
+        let ignored = collect_ignored_modules_with_paths(&loaded_modules_by_path, &[], &[]);
+        let path = "/usr/bin/python3.12".to_string();
+        assert_eq!(ignored[&1], vec![(path.clone(), 0x7f00_1000, 0x7f00_2200)]);
+        assert_eq!(ignored[&2], vec![(path.clone(), 0x7e00_1000, 0x7e00_2200)]);
+
+        assert_eq!(
+            flatten_ignored_modules(&ignored),
+            vec![
+                (path.clone(), 0x7e00_1000, 0x7e00_2200),
+                (path, 0x7f00_1000, 0x7f00_2200),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_symbols_outside_of_the_mapping_are_reported() {
+        let path = Path::new("/usr/lib/libbench.so");
+        // The symbols span 0x1000-0x2200, see `loaded_module`
+        let bounds = (0x1000, 0x2200);
+
+        assert_eq!(
+            symbols_mapping_warning(path, 42, bounds, 0x7f00_0000, &(0x7f00_1000..0x7f00_2000)),
+            None
+        );
+        // Data symbols past the executable mapping are expected
+        assert_eq!(
+            symbols_mapping_warning(path, 42, bounds, 0x7f00_0000, &(0x7f00_1000..0x7f00_1800)),
+            None
+        );
+        assert_eq!(
+            symbols_mapping_warning(path, 42, bounds, 0x5500_0000, &(0x7f00_1000..0x7f00_2000))
+                .as_deref(),
+            Some(
+                "The symbols of /usr/lib/libbench.so in pid 42 are at 0x55001000-0x55002200 with \
+                the load bias 0x55000000, outside of its mapping at 0x7f001000-0x7f002000, its \
+                functions may be misattributed"
+            )
+        );
+    }
+}
@@ -0,0 +1,239 @@
+use super::elf_helper;
+use super::module_symbols::{ModuleSymbols, keyed_file_path, max_symbol_extension};
+use crate::VERSION;
+use crate::prelude::*;
+use object::Object;
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+/// Name of the symbols cache folder, under the setup cache dir.
+pub const SYMBOLS_CACHE_DIR_NAME: &str = "symbols";
+
+/// Only the symbols of the system libraries are cached: they rarely change across runs, unlike
+/// the benchmark binaries which would fill the cache with a new entry on every build.
+const SYSTEM_LIBRARY_PREFIXES: &[&str] = &["/usr/lib", "/usr/lib64", "/lib", "/lib64"];
+
+/// Entries unused for this long are evicted, e.g. the ones of a previous runner version or of an
+/// updated library.
+const MAX_ENTRY_AGE: Duration = Duration::from_secs(30 * 24 * 60 * 60);
+
+/// On-disk cache of the symbols extracted from the ELF files, persisted across runs.
+///
+/// Entries are keyed by the build-id of the ELF file, or by its path, modification time and size
+/// when it has none, so that a rebuilt or updated library misses the cache. The runner version
+/// and the extension cap of the zero-sized symbols are part of the key too, as they change the
+/// extracted symbols.
+pub struct SymbolsCache {
+    dir: PathBuf,
+}
+
+impl SymbolsCache {
+    pub fn new(dir: PathBuf) -> Self {
+        let cache = Self { dir };
+        if let Err(e) = cache.evict_unused_entries() {
+            debug!("Failed to evict the unused cached symbols: {e}");
+        }
+        cache
+    }
+
+    /// Remove the entries not used for [`MAX_ENTRY_AGE`], as told by their modification time,
+    /// which is updated on every hit.
+    fn evict_unused_entries(&self) -> Result<()> {
+        if !self.dir.exists() {
+            return Ok(());
+        }
+        let now = SystemTime::now();
+        for entry in std::fs::read_dir(&self.dir)? {
+            let entry = entry?;
+            let last_used = entry.metadata()?.modified()?;
+            if now.duration_since(last_used).unwrap_or_default() > MAX_ENTRY_AGE {
+                trace!("Evicting the cached symbols {}", entry.path().display());
+                std::fs::remove_file(entry.path())?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Load the symbols of the ELF file at `path` from the cache, extracting and caching them on
+    /// a miss.
+    pub fn module_symbols(&self, path: &Path) -> Result<ModuleSymbols> {
+        let key = cache_key(path)?;
+        let entry_path = keyed_file_path(&self.dir, &key);
+        if entry_path.exists() {
+            match ModuleSymbols::from_keyed_file(&self.dir, &key) {
+                Ok(symbols) => {
+                    trace!("Loaded the symbols of {} from the cache", path.display());
+                    mark_as_used(&entry_path);
+                    return Ok(symbols);
+                }
+                Err(e) => debug!("Ignoring the cached symbols of {}: {e}", path.display()),
+            }
+        }
+
+        let symbols = ModuleSymbols::from_elf(path)?;
+        if let Err(e) = self.save(&key, &symbols) {
+            debug!("Failed to cache the symbols of {}: {e}", path.display());
+        }
+        Ok(symbols)
+    }
+
+    /// Save the symbols through a temporary file, so that a concurrent run never reads a
+    /// partially written entry.
+    fn save(&self, key: &str, symbols: &ModuleSymbols) -> Result<()> {
+        std::fs::create_dir_all(&self.dir)?;
+        let file = tempfile::NamedTempFile::new_in(&self.dir)?;
+        symbols.append_to_file(file.path())?;
+        file.persist(keyed_file_path(&self.dir, key))?;
+        Ok(())
+    }
+}
+
+/// Bump the modification time of a cache entry, so that it isn't evicted while it's used.
+fn mark_as_used(entry_path: &Path) {
+    let result = std::fs::File::options()
+        .write(true)
+        .open(entry_path)
+        .and_then(|file| file.set_modified(SystemTime::now()));
+    if let Err(e) = result {
+        debug!("Failed to mark {} as used: {e}", entry_path.display());
+    }
+}
+
+/// Load the symbols of the ELF file at `path`, through the cache for the system libraries.
+pub fn load_module_symbols(path: &Path, cache: Option<&SymbolsCache>) -> Result<ModuleSymbols> {
+    match cache {
+        Some(cache) if is_system_library(path) => cache.module_symbols(path),
+        _ => ModuleSymbols::from_elf(path),
+    }
+}
+
+fn is_system_library(path: &Path) -> bool {
+    SYSTEM_LIBRARY_PREFIXES
+        .iter()
+        .any(|prefix| path.starts_with(prefix))
+}
+
+/// Cache key of the ELF file at `path`. The symbols of a stripped library depend on whether its
+/// debug file is installed, so that is part of the key too.
+fn cache_key(path: &Path) -> Result<String> {
+    let content = std::fs::read(path)?;
+    let object = object::File::parse(&*content)?;
+
+    let file_key: String = match object.build_id().ok().flatten() {
+        Some(build_id) if !build_id.is_empty() => {
+            build_id.iter().map(|b| format!("{b:02x}")).collect()
+        }
+        _ => {
+            let metadata = std::fs::metadata(path)?;
+            let mut hasher = DefaultHasher::new();
+            (path, metadata.modified()?, metadata.len()).hash(&mut hasher);
+            format!("{:016x}", hasher.finish())
+        }
+    };
+
+    let key = format!("{VERSION}-{:x}-{file_key}", max_symbol_extension());
+    if elf_helper::find_debug_file(&object, path).is_some() {
+        Ok(format!("{key}-debug"))
+    } else {
+        Ok(key)
+    }
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use super::*;
+    use crate::executor::wall_time::profiler::perf::module_symbols::Symbol;
+
+    const LIBC_PATH: &str = "testdata/perf_map/libc.so.6";
+
+    /// Replace the cached symbols of `path` by a single fake one, to tell cache hits apart.
+    fn poison_cache_entry(cache: &SymbolsCache, path: &Path) {
+        let key = cache_key(path).unwrap();
+        std::fs::remove_file(keyed_file_path(&cache.dir, &key)).unwrap();
+        ModuleSymbols::new(vec![Symbol {
+            addr: 0x1000,
+            size: 0x10,
+            name: "cached".into(),
+        }])
+        .save_to_keyed_file(&cache.dir, &key)
+        .unwrap();
+    }
+
+    fn symbol_names(symbols: &ModuleSymbols) -> Vec<&str> {
+        symbols
+            .symbols()
+            .iter()
+            .map(|symbol| symbol.name.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn test_symbols_cache_hit() {
+        let tmp = tempfile::tempdir().unwrap();
+        let cache = SymbolsCache::new(tmp.path().join(SYMBOLS_CACHE_DIR_NAME));
+        let libc_path = Path::new(LIBC_PATH);
+
+        let extracted = cache.module_symbols(libc_path).unwrap();
+        assert_eq!(
+            extracted.symbols(),
+            ModuleSymbols::from_elf(libc_path).unwrap().symbols()
+        );
+
+        poison_cache_entry(&cache, libc_path);
+        let cached = cache.module_symbols(libc_path).unwrap();
+        assert_eq!(symbol_names(&cached), vec!["cached"]);
+    }
+
+    #[test]
+    fn test_symbols_cache_misses_on_build_id_change() {
+        let tmp = tempfile::tempdir().unwrap();
+        let cache = SymbolsCache::new(tmp.path().join(SYMBOLS_CACHE_DIR_NAME));
+        let libc_path = tmp.path().join("libc.so.6");
+        std::fs::copy(LIBC_PATH, &libc_path).unwrap();
+
+        cache.module_symbols(&libc_path).unwrap();
+        poison_cache_entry(&cache, &libc_path);
+
+        // Flip a byte of the build-id, as if the library was updated
+        let mut content = std::fs::read(&libc_path).unwrap();
+        let build_id = object::File::parse(&*content)
+            .unwrap()
+            .build_id()
+            .unwrap()
+            .unwrap()
+            .to_vec();
+        let offset = content
+            .windows(build_id.len())
+            .position(|window| window == build_id)
+            .unwrap();
+        content[offset] ^= 0xff;
+        std::fs::write(&libc_path, content).unwrap();
+
+        let symbols = cache.module_symbols(&libc_path).unwrap();
+        assert_ne!(symbol_names(&symbols), vec!["cached"]);
+    }
+
+    #[test]
+    fn test_unused_entries_are_evicted() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path().join(SYMBOLS_CACHE_DIR_NAME);
+        let cache = SymbolsCache::new(dir.clone());
+        let libc_path = Path::new(LIBC_PATH);
+        cache.module_symbols(libc_path).unwrap();
+        let entry_path = keyed_file_path(&dir, &cache_key(libc_path).unwrap());
+
+        SymbolsCache::new(dir.clone());
+        assert!(entry_path.exists());
+
+        let last_used = SystemTime::now() - MAX_ENTRY_AGE - Duration::from_secs(60);
+        std::fs::File::options()
+            .write(true)
+            .open(&entry_path)
+            .unwrap()
+            .set_modified(last_used)
+            .unwrap();
+        SymbolsCache::new(dir);
+        assert!(!entry_path.exists());
+    }
+}
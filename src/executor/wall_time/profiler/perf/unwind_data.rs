@@ -9,6 +9,26 @@ use runner_shared::unwind_data::ProcessUnwindData;
 use runner_shared::unwind_data::UnwindData;
 use std::ops::Range;
 
+/// The file is not the one perf profiled, e.g. because it was rebuilt while the benchmarks ran.
+#[derive(Debug)]
+pub struct BuildIdMismatch {
+    pub path: String,
+    pub file_build_id: CodeId,
+    pub expected_build_id: CodeId,
+}
+
+impl std::fmt::Display for BuildIdMismatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "File {:?} has non-matching build ID {} (expected {})",
+            self.path, self.file_build_id, self.expected_build_id
+        )
+    }
+}
+
+impl std::error::Error for BuildIdMismatch {}
+
 // Based on: https://github.com/mstange/linux-perf-stuff/blob/22ca6531b90c10dd2a4519351c843b8d7958a451/src/main.rs#L747-L893
 pub fn unwind_data_from_elf(
     path_slice: &[u8],
@@ -31,11 +51,12 @@ pub fn unwind_data_from_elf(
     match (build_id, file.build_id()) {
         (Some(build_id), Ok(Some(file_build_id))) => {
             if build_id != file_build_id {
-                let file_build_id = CodeId::from_binary(file_build_id);
-                let expected_build_id = CodeId::from_binary(build_id);
-                bail!(
-                    "File {path:?} has non-matching build ID {file_build_id} (expected {expected_build_id})"
-                );
+                return Err(BuildIdMismatch {
+                    path,
+                    file_build_id: CodeId::from_binary(file_build_id),
+                    expected_build_id: CodeId::from_binary(build_id),
+                }
+                .into());
             }
         }
         (Some(_), Err(_)) | (Some(_), Ok(None)) => {
@@ -66,12 +87,18 @@ pub fn unwind_data_from_elf(
     // binaries (e.g. Valgrind's statically-linked tools) are linked without
     // `ld --eh-frame-hdr` and don't carry it. The parser rebuilds the index
     // from `.eh_frame` in that case.
+    let eh_frame_data = eh_frame_data.context("Failed to find eh_frame data")?;
+    // A `.eh_frame` holding at most its zero terminator has no entry to unwind with, e.g. when
+    // built without unwind tables
+    if eh_frame_data.iter().all(|byte| *byte == 0) {
+        bail!("File {path:?} has an empty eh_frame section");
+    }
     let unwind_data = UnwindData {
         path: path.clone(),
         base_svma,
         eh_frame_hdr: eh_frame_hdr_data,
         eh_frame_hdr_svma: eh_frame_hdr.as_ref().map(svma_range),
-        eh_frame: eh_frame_data.context("Failed to find eh_frame data")?,
+        eh_frame: eh_frame_data,
         eh_frame_svma: eh_frame
             .as_ref()
             .map(svma_range)
@@ -245,6 +272,36 @@ mod tests {
         assert!(!unwind_data.eh_frame.is_empty());
     }
 
+    #[test]
+    fn test_shared_object_without_eh_frame_hdr_unwind_data() {
+        // A self-contained shared object linked without `.eh_frame_hdr`, see
+        // `testdata/unwind_data/build.sh`. Its `.text` segment is mapped from the file offset
+        // 0x1000.
+        let module_path = "testdata/unwind_data/no_eh_frame_hdr.so";
+        let load_bias = 0x7fff_f7f0_0000;
+        assert_load_bias(
+            load_bias + 0x1000,
+            load_bias + 0x2000,
+            0x1000,
+            module_path,
+            load_bias,
+        );
+
+        let (unwind_data, process_unwind_data) = unwind_data_from_elf(
+            module_path.as_bytes(),
+            load_bias + 0x1000,
+            load_bias + 0x2000,
+            None,
+            load_bias,
+        )
+        .unwrap();
+        assert!(unwind_data.eh_frame_hdr.is_none());
+        assert!(unwind_data.eh_frame_hdr_svma.is_none());
+        assert_eq!(unwind_data.eh_frame.len(), 0x94);
+        assert_eq!(unwind_data.eh_frame_svma, 0x2000..0x2094);
+        assert_eq!(process_unwind_data.base_avma, load_bias);
+    }
+
     #[test]
     fn test_ruff_unwind_data() {
         // gdb testdata/perf_map/ty_walltime -ex "break main" -ex "run" -ex "info proc mappings" -ex "continue" -ex "quit" -batch
@@ -0,0 +1,51 @@
+use crate::prelude::*;
+
+/// Time in milliseconds perf waits before recording a whole run, to skip its startup.
+pub const PERF_WARMUP_MS_ENV: &str = "CODSPEED_PERF_WARMUP_MS";
+
+/// The `--delay` flag of perf record.
+///
+/// The integrations enable the events around the benchmarks through the FIFO, so perf starts
+/// with the events disabled. Without the FIFO (raw runs, markers files), the whole execution is
+/// recorded, after the warmup set in [`PERF_WARMUP_MS_ENV`] if any.
+pub fn delay_flag(record_whole_run: bool) -> Result<Option<String>> {
+    let warmup_ms = std::env::var(PERF_WARMUP_MS_ENV).ok();
+    if !record_whole_run {
+        if warmup_ms.is_some() {
+            debug!("{PERF_WARMUP_MS_ENV} is ignored, the integration controls the recording");
+        }
+        return Ok(Some("--delay=-1".into()));
+    }
+
+    let Some(value) = warmup_ms else {
+        return Ok(None);
+    };
+    let warmup_ms = value.trim().parse::<u64>().with_context(|| {
+        format!("Invalid {PERF_WARMUP_MS_ENV} value {value:?}, expected a number of milliseconds")
+    })?;
+    if warmup_ms == 0 {
+        return Ok(None);
+    }
+    debug!("Recording the whole run after a warmup of {warmup_ms}ms");
+    Ok(Some(format!("--delay={warmup_ms}")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_warmup_delay_only_applies_to_raw_runs() {
+        temp_env::with_var(PERF_WARMUP_MS_ENV, Some("250"), || {
+            assert_eq!(delay_flag(true).unwrap().as_deref(), Some("--delay=250"));
+            assert_eq!(delay_flag(false).unwrap().as_deref(), Some("--delay=-1"));
+        });
+        temp_env::with_var(PERF_WARMUP_MS_ENV, None::<&str>, || {
+            assert_eq!(delay_flag(true).unwrap(), None);
+            assert_eq!(delay_flag(false).unwrap().as_deref(), Some("--delay=-1"));
+        });
+        temp_env::with_var(PERF_WARMUP_MS_ENV, Some("soon"), || {
+            assert!(delay_flag(true).is_err());
+        });
+    }
+}
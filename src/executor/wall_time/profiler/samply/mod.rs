@@ -9,7 +9,7 @@ use crate::executor::shared::fifo::FifoBenchmarkData;
 use crate::executor::wall_time::profiler::Profiler;
 use crate::executor::wall_time::profiler::linux_sysctl::ensure_linux_profiling_sysctls;
 use crate::prelude::*;
-use crate::system::SystemInfo;
+use crate::system::{SystemInfo, capture_environment};
 use async_trait::async_trait;
 use runner_shared::artifacts::ArtifactExt;
 use runner_shared::artifacts::ExecutionTimestamps;
@@ -175,7 +175,7 @@ impl Profiler for SamplyProfiler {
         profile_folder: &Path,
     ) -> anyhow::Result<()> {
         let Some(integration) = fifo_data.integration.clone() else {
-            warn!("{NO_BENCHMARKS_DETECTED_WARNING}");
+            run_warning!("{NO_BENCHMARKS_DETECTED_WARNING}");
             return Ok(());
         };
 
@@ -185,6 +185,7 @@ impl Profiler for SamplyProfiler {
             integration,
             uri_by_ts: timestamps.uri_by_ts.clone(),
             markers: timestamps.markers.clone(),
+            environment: capture_environment(),
 
             // These fields aren't required in samply, since we symbolicate client-side.
             ignored_modules_by_pid: Default::default(),
@@ -192,6 +193,7 @@ impl Profiler for SamplyProfiler {
             mapped_process_debug_info_by_pid: Default::default(),
             mapped_process_unwind_data_by_pid: Default::default(),
             mapped_process_module_symbols: Default::default(),
+            module_symbols_line_count: Default::default(),
             path_key_to_path: Default::default(),
 
             // Deprecated fields below are no longer used
@@ -239,27 +241,12 @@ fn bash_in_path_is_compatible() -> anyhow::Result<bool> {
 
 #[cfg(target_os = "macos")]
 fn confirm_bash_install() -> anyhow::Result<()> {
-    use crate::local_logger::IS_TTY;
-    use console::Term;
+    use crate::prompt::{WithoutTty, confirm};
 
     // Non-interactive (CI): just install
-    if !*IS_TTY {
-        return Ok(());
-    }
-
-    eprintln!(
-        "CodSpeed depends on bash for benchmark execution, but can't use /bin/bash because system executables are signed in a way that prevents profiling. Because of this, we need to install bash with Homebrew. This is a one-time setup, your system bash is untouched."
-    );
-    eprint!("\nRun `brew install bash` now? [Y/n] ");
-    let line = Term::stderr().read_line().unwrap_or_default();
-    let answer = line.trim();
-
-    // Default to yes on empty input (just pressing Enter).
-    if !(answer.is_empty()
-        || answer.eq_ignore_ascii_case("y")
-        || answer.eq_ignore_ascii_case("yes"))
-    {
-        bail!("Declined; cannot continue without an unsigned bash");
-    }
-    Ok(())
+    confirm(
+        "CodSpeed depends on bash for benchmark execution, but can't use /bin/bash because system executables are signed in a way that prevents profiling. Because of this, we need to install bash with Homebrew. This is a one-time setup, your system bash is untouched.\n\nRun `brew install bash` now?",
+        WithoutTty::Proceed,
+    )
+    .context("Declined; cannot continue without an unsigned bash")
 }
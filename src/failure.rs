@@ -0,0 +1,138 @@
+//! Exit codes of the CLI, telling which step of the run failed, e.g. so that CI scripts can retry
+//! the failed uploads but not the failed benchmarks.
+//!
+//! | Code | Failure                                                        |
+//! |------|----------------------------------------------------------------|
+//! | 1    | Any other error                                                |
+//! | 2    | Setup: installing or configuring the tools (perf, valgrind)    |
+//! | 3    | Run: running the benchmarks or processing their profiles       |
+//! | 4    | Upload: uploading the results or polling for them              |
+//! | 5    | Auth: authenticating with CodSpeed                             |
+//! | 6    | Regression: a benchmark regressed, with `--fail-on-regression` |
+use crate::prelude::*;
+use std::fmt;
+
+/// Exit code of the errors without a [`FailureClass`].
+pub const GENERIC_EXIT_CODE: i32 = 1;
+
+/// The step of the run an error comes from, mapped to the exit code of the CLI.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FailureClass {
+    Setup,
+    Run,
+    Upload,
+    Auth,
+    Regression,
+}
+
+impl FailureClass {
+    pub fn exit_code(self) -> i32 {
+        match self {
+            FailureClass::Setup => 2,
+            FailureClass::Run => 3,
+            FailureClass::Upload => 4,
+            FailureClass::Auth => 5,
+            FailureClass::Regression => 6,
+        }
+    }
+
+    /// Tag `error` with this class, unless it is already tagged: the innermost class is the most
+    /// specific one, e.g. an authentication failure during the upload.
+    pub fn wrap(self, error: Error) -> Error {
+        if failure_class(&error).is_some() {
+            return error;
+        }
+        Error::new(ClassifiedError { class: self, error })
+    }
+}
+
+/// Error tagged with its [`FailureClass`]. It displays as the tagged error and has the same
+/// causes, so the tag never shows up in the error messages.
+#[derive(Debug)]
+struct ClassifiedError {
+    class: FailureClass,
+    error: Error,
+}
+
+impl fmt::Display for ClassifiedError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.error)
+    }
+}
+
+impl std::error::Error for ClassifiedError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        self.error.source()
+    }
+}
+
+pub trait WithFailureClass<T> {
+    /// Tag the error with `class`, see [`FailureClass::wrap`].
+    fn with_failure_class(self, class: FailureClass) -> Result<T>;
+}
+
+impl<T> WithFailureClass<T> for Result<T> {
+    fn with_failure_class(self, class: FailureClass) -> Result<T> {
+        self.map_err(|error| class.wrap(error))
+    }
+}
+
+fn failure_class(error: &Error) -> Option<FailureClass> {
+    error
+        .chain()
+        .find_map(|cause| cause.downcast_ref::<ClassifiedError>())
+        .map(|classified| classified.class)
+}
+
+/// The exit code of the CLI when it fails with `error`.
+pub fn exit_code(error: &Error) -> i32 {
+    failure_class(error).map_or(GENERIC_EXIT_CODE, FailureClass::exit_code)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unclassified_error_exits_with_the_generic_code() {
+        let error = anyhow!("Invalid configuration");
+        assert_eq!(exit_code(&error), GENERIC_EXIT_CODE);
+    }
+
+    #[test]
+    fn test_classified_errors_exit_with_their_code() {
+        let cases = [
+            (FailureClass::Setup, "Failed to install perf", 2),
+            (FailureClass::Run, "The benchmarks failed", 3),
+            (FailureClass::Upload, "Failed to upload the profile", 4),
+            (FailureClass::Auth, "Your session has expired", 5),
+            (FailureClass::Regression, "2 benchmarks regressed", 6),
+        ];
+        for (class, message, expected_code) in cases {
+            let error = Err::<(), _>(anyhow!(message))
+                .with_failure_class(class)
+                .context("Failed to run the benchmarks")
+                .unwrap_err();
+            assert_eq!(exit_code(&error), expected_code, "{class:?}");
+        }
+    }
+
+    #[test]
+    fn test_innermost_class_wins() {
+        let error = Err::<(), _>(anyhow!("Invalid token"))
+            .with_failure_class(FailureClass::Auth)
+            .with_failure_class(FailureClass::Upload)
+            .unwrap_err();
+        assert_eq!(exit_code(&error), FailureClass::Auth.exit_code());
+    }
+
+    #[test]
+    fn test_classification_keeps_the_error_messages() {
+        let error = Err::<(), _>(anyhow!("perf not found"))
+            .context("Failed to install perf")
+            .with_failure_class(FailureClass::Setup)
+            .unwrap_err();
+        let messages = error.chain().map(|cause| cause.to_string()).collect_vec();
+        assert_eq!(messages, vec!["Failed to install perf", "perf not found"]);
+    }
+}
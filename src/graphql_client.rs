@@ -0,0 +1,175 @@
+//! GraphQL client of the CodSpeed API, built on the HTTP client of [`crate::request_client`] so
+//! that the queries go through the same proxy and trust the same CA bundle as the uploads.
+use crate::prelude::*;
+use crate::request_client::{client_builder, request_error};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::time::Duration;
+
+#[derive(Serialize)]
+struct GraphQLRequest<'a, V> {
+    query: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    variables: Option<V>,
+}
+
+#[derive(Deserialize)]
+struct GraphQLResponse {
+    data: Option<serde_json::Value>,
+    #[serde(default)]
+    errors: Vec<GraphQLErrorMessage>,
+}
+
+#[derive(Debug, Deserialize)]
+struct GraphQLErrorMessage {
+    message: String,
+    #[serde(default)]
+    extensions: Option<serde_json::Value>,
+}
+
+/// Error of a GraphQL query: the request failed, or the API answered with errors, possibly along
+/// with partial data.
+#[derive(Debug)]
+pub struct GraphQLError {
+    message: String,
+    errors: Vec<GraphQLErrorMessage>,
+    data: Option<serde_json::Value>,
+}
+
+impl GraphQLError {
+    fn request(message: impl Into<String>) -> Self {
+        Self {
+            message: message.into(),
+            errors: vec![],
+            data: None,
+        }
+    }
+
+    /// Whether one of the errors has the `code` extension, e.g. `UNAUTHENTICATED`.
+    pub fn contains_error_code(&self, code: &str) -> bool {
+        self.errors.iter().any(|error| {
+            error
+                .extensions
+                .as_ref()
+                .and_then(|extensions| extensions.get("code"))
+                .and_then(|code| code.as_str())
+                == Some(code)
+        })
+    }
+
+    /// The partial data returned along with the errors, if any.
+    pub fn data<T: DeserializeOwned>(&self) -> Option<Result<T, serde_json::Error>> {
+        self.data
+            .clone()
+            .filter(|data| !data.is_null())
+            .map(serde_json::from_value)
+    }
+}
+
+impl std::fmt::Display for GraphQLError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.message)?;
+        if !self.errors.is_empty() {
+            let messages = self.errors.iter().map(|error| &error.message).join(", ");
+            write!(f, ": {messages}")?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for GraphQLError {}
+
+pub struct GraphQLClient {
+    client: reqwest::Client,
+    endpoint: String,
+    headers: HashMap<String, String>,
+}
+
+impl GraphQLClient {
+    pub fn new(endpoint: String, headers: HashMap<String, String>, timeout: Duration) -> Self {
+        Self {
+            client: client_builder().timeout(timeout).build().unwrap(),
+            endpoint,
+            headers,
+        }
+    }
+
+    pub async fn query_unwrap<T: DeserializeOwned>(&self, query: &str) -> Result<T, GraphQLError> {
+        self.send::<T, ()>(query, None).await
+    }
+
+    pub async fn query_with_vars_unwrap<T: DeserializeOwned, V: Serialize>(
+        &self,
+        query: &str,
+        variables: V,
+    ) -> Result<T, GraphQLError> {
+        self.send(query, Some(variables)).await
+    }
+
+    async fn send<T: DeserializeOwned, V: Serialize>(
+        &self,
+        query: &str,
+        variables: Option<V>,
+    ) -> Result<T, GraphQLError> {
+        let request = self
+            .headers
+            .iter()
+            .fold(
+                self.client.post(&self.endpoint),
+                |request, (name, value)| request.header(name, value),
+            )
+            .json(&GraphQLRequest { query, variables });
+        let response = request
+            .send()
+            .await
+            .map_err(|error| GraphQLError::request(format!("{:#}", request_error(error))))?;
+
+        let status = response.status();
+        let response: GraphQLResponse = response.json().await.map_err(|error| {
+            GraphQLError::request(format!("Invalid response from the API ({status}): {error}"))
+        })?;
+        if !response.errors.is_empty() {
+            return Err(GraphQLError {
+                message: "The API returned errors".to_string(),
+                errors: response.errors,
+                data: response.data,
+            });
+        }
+        let data = response
+            .data
+            .filter(|data| !data.is_null())
+            .ok_or_else(|| GraphQLError::request("The API returned no data"))?;
+        serde_json::from_value(data).map_err(|error| {
+            GraphQLError::request(format!("Failed to parse the response of the API: {error}"))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_error_codes_and_partial_data() {
+        let response: GraphQLResponse = serde_json::from_str(
+            r#"{
+                "data": { "session": { "user": null } },
+                "errors": [{ "message": "Not found", "extensions": { "code": "REPOSITORY_NOT_FOUND" } }]
+            }"#,
+        )
+        .unwrap();
+        let error = GraphQLError {
+            message: "The API returned errors".to_string(),
+            errors: response.errors,
+            data: response.data,
+        };
+
+        assert!(error.contains_error_code("REPOSITORY_NOT_FOUND"));
+        assert!(!error.contains_error_code("UNAUTHENTICATED"));
+        assert_eq!(error.to_string(), "The API returned errors: Not found");
+        let data = error.data::<serde_json::Value>().unwrap().unwrap();
+        assert_eq!(data["session"]["user"], serde_json::Value::Null);
+        assert!(GraphQLError::request("timeout").data::<()>().is_none());
+    }
+}
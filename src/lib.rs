@@ -6,18 +6,23 @@ mod binary_pins;
 pub mod cli;
 mod config;
 mod executor;
+mod failure;
+mod graphql_client;
 mod instruments;
 mod local_logger;
 pub mod logger;
 mod prelude;
 mod project_config;
+mod prompt;
 mod request_client;
 mod run_environment;
 mod runner_mode;
+mod seed;
 mod shell_session_store;
 mod system;
 mod upload;
 
+pub use failure::exit_code;
 pub use local_logger::clean_logger;
 pub use project_config::{ProjectConfig, ProjectOptions, Target, TargetOptions, WalltimeOptions};
 pub use runner_mode::RunnerMode;
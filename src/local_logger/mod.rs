@@ -4,7 +4,7 @@ pub mod rolling_buffer;
 use std::{
     env,
     sync::{Arc, Mutex},
-    time::Duration,
+    time::{Duration, Instant},
 };
 
 use crate::prelude::*;
@@ -16,6 +16,7 @@ use std::io::Write;
 use std::sync::LazyLock;
 
 use crate::logger::{GroupEvent, JsonEvent, get_group_event, get_json_event};
+use crate::run_environment::escape_multiline_message;
 use icons::Icon;
 
 pub const CODSPEED_U8_COLOR_CODE: u8 = 208; // #FF8700
@@ -23,16 +24,105 @@ pub const CODSPEED_U8_COLOR_CODE: u8 = 208; // #FF8700
 /// Spinner tick characters - smooth animation for a polished feel
 pub(crate) const SPINNER_TICKS: &[&str] = &["  ", ". ", "..", " ."];
 
-/// Interval between spinner animation ticks (milliseconds)
-pub(crate) const TICK_INTERVAL_MS: u64 = 300;
+/// ASCII-only spinner tick characters, for terminals that can't render the default ones
+const ASCII_SPINNER_TICKS: &[&str] = &["| ", "/ ", "- ", "\\ "];
+
+/// Default interval between spinner animation ticks (milliseconds)
+const DEFAULT_TICK_INTERVAL_MS: u64 = 300;
+
+/// Spinner animation, selected with `CODSPEED_SPINNER=off|dots|ascii`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum SpinnerStyle {
+    /// No animated spinner, group messages are printed once as on a non-TTY
+    Off,
+    Dots,
+    Ascii,
+}
+
+impl SpinnerStyle {
+    fn from_env_value(value: Option<&str>) -> Self {
+        match value.map(|v| v.trim().to_ascii_lowercase()).as_deref() {
+            Some("off") => SpinnerStyle::Off,
+            Some("ascii") => SpinnerStyle::Ascii,
+            _ => SpinnerStyle::Dots,
+        }
+    }
+
+    /// The tick strings to animate; a single blank tick when the spinner is off.
+    pub(crate) fn ticks(self) -> &'static [&'static str] {
+        match self {
+            SpinnerStyle::Off => &["  "],
+            SpinnerStyle::Dots => SPINNER_TICKS,
+            SpinnerStyle::Ascii => ASCII_SPINNER_TICKS,
+        }
+    }
+}
+
+/// Layout of the logs, selected with `CODSPEED_OUTPUT=compact`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum OutputMode {
+    Default,
+    /// Each group is a single line printed when it completes, without its info logs
+    Compact,
+}
+
+impl OutputMode {
+    fn from_env_value(value: Option<&str>) -> Self {
+        match value.map(|v| v.trim().to_ascii_lowercase()).as_deref() {
+            Some("compact") => OutputMode::Compact,
+            _ => OutputMode::Default,
+        }
+    }
+}
+
+fn parse_tick_interval(value: Option<&str>) -> Duration {
+    let millis = value
+        .and_then(|v| v.trim().parse::<u64>().ok())
+        .filter(|ms| *ms > 0)
+        .unwrap_or(DEFAULT_TICK_INTERVAL_MS);
+    Duration::from_millis(millis)
+}
+
+pub(crate) static SPINNER_STYLE: LazyLock<SpinnerStyle> =
+    LazyLock::new(|| SpinnerStyle::from_env_value(env::var("CODSPEED_SPINNER").ok().as_deref()));
+/// Interval between spinner animation ticks, overridable with `CODSPEED_SPINNER_INTERVAL_MS`
+pub(crate) static TICK_INTERVAL: LazyLock<Duration> =
+    LazyLock::new(|| parse_tick_interval(env::var("CODSPEED_SPINNER_INTERVAL_MS").ok().as_deref()));
 
 pub static SPINNER: LazyLock<Arc<Mutex<Option<ProgressBar>>>> =
     LazyLock::new(|| Arc::new(Mutex::new(None)));
 pub static IS_TTY: LazyLock<bool> =
     LazyLock::new(|| std::io::IsTerminal::is_terminal(&std::io::stdout()));
-static CURRENT_GROUP_NAME: LazyLock<Arc<Mutex<Option<String>>>> =
+/// Name and start time of the current (closed) group, for its completion message
+static CURRENT_GROUP: LazyLock<Arc<Mutex<Option<(String, Instant)>>>> =
     LazyLock::new(|| Arc::new(Mutex::new(None)));
 
+/// Default duration above which a group is reported as slow
+const DEFAULT_SLOW_STEP_SECS: u64 = 120;
+
+/// Duration above which a group is reported as slow, overridable with `CODSPEED_SLOW_STEP_SECS`
+static SLOW_STEP_THRESHOLD: LazyLock<Duration> = LazyLock::new(|| {
+    parse_slow_step_threshold(env::var("CODSPEED_SLOW_STEP_SECS").ok().as_deref())
+});
+
+fn parse_slow_step_threshold(value: Option<&str>) -> Duration {
+    let secs = value
+        .and_then(|v| v.trim().parse::<u64>().ok())
+        .unwrap_or(DEFAULT_SLOW_STEP_SECS);
+    Duration::from_secs(secs)
+}
+
+/// The warning to show when the group `name` took longer than `threshold`.
+fn slow_step_warning(name: &str, elapsed: Duration, threshold: Duration) -> Option<String> {
+    (elapsed > threshold).then(|| {
+        format!(
+            "\"{name}\" took {}, which is longer than expected (over {})",
+            format_elapsed(elapsed),
+            format_elapsed(threshold)
+        )
+    })
+}
+
 /// Log records deferred while the rolling buffer owns the terminal.
 /// Flushed in `draw_frame` before each redraw.
 static DEFERRED_LOGS: LazyLock<Mutex<Vec<DeferredLog>>> = LazyLock::new(|| Mutex::new(Vec::new()));
@@ -63,24 +153,110 @@ pub fn suspend_progress_bar<F: FnOnce() -> R, R>(f: F) -> R {
     f()
 }
 
+/// Verbosity set with `CODSPEED_LOG`: a level, optionally followed by per-target levels, e.g.
+/// `info,executor=debug,codspeed_runner::executor::wall_time=trace`.
+#[derive(Debug, PartialEq)]
+pub(crate) struct LogFilter {
+    pub(crate) default: log::LevelFilter,
+    /// Levels of the records whose target starts with the prefix, the longest prefix wins
+    targets: Vec<(String, log::LevelFilter)>,
+}
+
+impl LogFilter {
+    /// Parse a comma-separated list of `level` and `target=level` directives, ignoring the
+    /// invalid ones. The default level is `info` when none is given.
+    pub(crate) fn parse(spec: &str) -> Self {
+        let mut filter = LogFilter {
+            default: log::LevelFilter::Info,
+            targets: vec![],
+        };
+        for directive in spec.split(',').map(str::trim).filter(|d| !d.is_empty()) {
+            match directive.split_once('=') {
+                Some((target, level)) => {
+                    if let Ok(level) = level.trim().parse() {
+                        filter.targets.push((target.trim().to_string(), level));
+                    }
+                }
+                None => {
+                    if let Ok(level) = directive.parse() {
+                        filter.default = level;
+                    }
+                }
+            }
+        }
+        filter
+    }
+
+    /// The level of the records of `target`, matching the prefixes on `::` boundaries.
+    fn level(&self, target: &str) -> log::LevelFilter {
+        self.targets
+            .iter()
+            .filter(|(prefix, _)| {
+                target
+                    .strip_prefix(prefix.as_str())
+                    .is_some_and(|rest| rest.is_empty() || rest.starts_with("::"))
+            })
+            .max_by_key(|(prefix, _)| prefix.len())
+            .map_or(self.default, |(_, level)| *level)
+    }
+
+    /// The most verbose level of any target.
+    fn max_level(&self) -> log::LevelFilter {
+        self.targets
+            .iter()
+            .map(|(_, level)| *level)
+            .fold(self.default, Ord::max)
+    }
+}
+
 pub struct LocalLogger {
-    log_level: log::LevelFilter,
+    filter: LogFilter,
+    output_mode: OutputMode,
+    /// Emit GitHub Actions workflow commands for groups and annotations instead of spinners
+    github_actions: bool,
 }
 
 impl LocalLogger {
     pub fn new() -> Self {
-        let log_level = env::var("CODSPEED_LOG")
-            .ok()
-            .and_then(|log_level| log_level.parse::<log::LevelFilter>().ok())
-            .unwrap_or(log::LevelFilter::Info);
+        let filter = LogFilter::parse(&env::var("CODSPEED_LOG").unwrap_or_default());
+        let output_mode = OutputMode::from_env_value(env::var("CODSPEED_OUTPUT").ok().as_deref());
+        let github_actions = env::var("GITHUB_ACTIONS").is_ok_and(|v| v == "true");
+
+        LocalLogger {
+            filter,
+            output_mode,
+            github_actions,
+        }
+    }
+}
 
-        LocalLogger { log_level }
+/// Format a group event as a GitHub Actions workflow command.
+///
+/// See https://docs.github.com/en/actions/using-workflows/workflow-commands-for-github-actions#grouping-log-lines
+fn format_github_actions_group_event(group_event: &GroupEvent) -> String {
+    match group_event {
+        GroupEvent::Start(name) | GroupEvent::StartOpened(name) => format!("::group::{name}"),
+        GroupEvent::End => "::endgroup::".to_string(),
     }
 }
 
+/// Format error and warning records as GitHub Actions annotations, other levels are
+/// printed as usual.
+fn format_github_actions_annotation(level: log::Level, message: &str) -> Option<String> {
+    let command = match level {
+        log::Level::Error => "error",
+        log::Level::Warn => "warning",
+        _ => return None,
+    };
+    Some(format!(
+        "::{command}::{}",
+        escape_multiline_message(message)
+    ))
+}
+
 impl Log for LocalLogger {
     fn enabled(&self, metadata: &log::Metadata) -> bool {
-        metadata.level() <= self.log_level
+        metadata.level() <= self.filter.level(metadata.target())
     }
 
     fn log(&self, record: &log::Record) {
@@ -89,6 +265,18 @@ impl Log for LocalLogger {
         }
 
         if let Some(group_event) = get_group_event(record) {
+            if self.github_actions && !*IS_TTY {
+                eprintln!("{}", format_github_actions_group_event(&group_event));
+                return;
+            }
+
+            if self.output_mode == OutputMode::Compact {
+                for line in compact_group_lines(&group_event, &CURRENT_GROUP) {
+                    eprintln!("{line}");
+                }
+                return;
+            }
+
             match group_event {
                 GroupEvent::Start(ref name) | GroupEvent::StartOpened(ref name) => {
                     let opened = matches!(group_event, GroupEvent::StartOpened(_));
@@ -101,15 +289,17 @@ impl Log for LocalLogger {
 
                     // Opened groups don't get a spinner or closing checkmark
                     if !opened {
-                        // Store current group name for completion message
-                        if let Ok(mut current) = CURRENT_GROUP_NAME.lock() {
-                            *current = Some(name.clone());
+                        // Store current group name and start time for completion message
+                        if let Ok(mut current) = CURRENT_GROUP.lock() {
+                            *current = Some((name.clone(), Instant::now()));
                         }
 
                         install_spinner(&name);
                     }
                 }
                 GroupEvent::End => {
+                    let current_group = CURRENT_GROUP.lock().ok().and_then(|mut c| c.take());
+
                     if *IS_TTY {
                         let mut spinner = SPINNER.lock().unwrap();
                         if let Some(pb) = spinner.as_mut() {
@@ -117,18 +307,24 @@ impl Log for LocalLogger {
                             pb.finish_and_clear();
 
                             // Show completion message with checkmark
-                            if let Ok(mut current) = CURRENT_GROUP_NAME.lock() {
-                                if let Some(name) = current.take() {
-                                    let elapsed_str = format_elapsed(elapsed);
-                                    eprintln!(
-                                        "{} {}",
-                                        format_checkmark(&name, true),
-                                        style(elapsed_str).dim(),
-                                    );
-                                }
+                            if let Some((name, _)) = &current_group {
+                                let elapsed_str = format_elapsed(elapsed);
+                                eprintln!(
+                                    "{} {}",
+                                    format_checkmark(name, true),
+                                    style(elapsed_str).dim(),
+                                );
                             }
                         }
                     }
+
+                    if let Some((name, started_at)) = current_group {
+                        if let Some(warning) =
+                            slow_step_warning(&name, started_at.elapsed(), *SLOW_STEP_THRESHOLD)
+                        {
+                            eprintln!("{}", format_log(log::Level::Warn, &warning, ""));
+                        }
+                    }
                 }
             }
 
@@ -140,6 +336,14 @@ impl Log for LocalLogger {
             return;
         }
 
+        // The info logs of a group are summarized by its completion line
+        if self.output_mode == OutputMode::Compact
+            && record.level() == log::Level::Info
+            && CURRENT_GROUP.lock().is_ok_and(|group| group.is_some())
+        {
+            return;
+        }
+
         // When the rolling buffer is active it owns the terminal region and uses
         // cursor manipulation to redraw.  Any direct stderr output would corrupt
         // the display, so we defer log records and flush them before each redraw.
@@ -159,6 +363,15 @@ impl Log for LocalLogger {
             }
         }
 
+        if self.github_actions && !*IS_TTY {
+            if let Some(annotation) =
+                format_github_actions_annotation(record.level(), &record.args().to_string())
+            {
+                eprintln!("{annotation}");
+                return;
+            }
+        }
+
         suspend_progress_bar(|| print_record(record));
     }
 
@@ -167,6 +380,41 @@ impl Log for LocalLogger {
     }
 }
 
+/// The lines printed for a group event in compact mode: nothing when a group starts, and a single
+/// `› <group> ✓ <elapsed>` line when it ends, followed by the slow step warning, if any. Opened
+/// groups have no end, so their header is printed right away.
+fn compact_group_lines(
+    group_event: &GroupEvent,
+    current_group: &Mutex<Option<(String, Instant)>>,
+) -> Vec<String> {
+    match group_event {
+        GroupEvent::Start(name) => {
+            if let Ok(mut current) = current_group.lock() {
+                *current = Some((name.clone(), Instant::now()));
+            }
+            vec![]
+        }
+        GroupEvent::StartOpened(name) => vec![format_group_header(name)],
+        GroupEvent::End => {
+            let Some((name, started_at)) = current_group.lock().ok().and_then(|mut c| c.take())
+            else {
+                return vec![];
+            };
+            let elapsed = started_at.elapsed();
+            let mut lines = vec![format!(
+                "{} {} {}",
+                format_group_header(&name),
+                style(Icon::Checkmark.to_string()).green().bold(),
+                style(format_elapsed(elapsed)).dim(),
+            )];
+            if let Some(warning) = slow_step_warning(&name, elapsed, *SLOW_STEP_THRESHOLD) {
+                lines.push(format_log(log::Level::Warn, &warning, ""));
+            }
+            lines
+        }
+    }
+}
+
 /// Format a group header with styled prefix
 fn format_group_header(name: &str) -> String {
     let prefix = style(Icon::GroupArrow.to_string())
@@ -191,7 +439,7 @@ pub(crate) fn format_checkmark(label: &str, dim: bool) -> String {
 }
 
 /// Format elapsed duration in a compact human-readable way
-fn format_elapsed(duration: Duration) -> String {
+pub(crate) fn format_elapsed(duration: Duration) -> String {
     let secs = duration.as_secs();
     let millis = duration.as_millis();
 
@@ -291,7 +539,7 @@ pub(crate) fn flush_deferred_logs(term: &console::Term) {
 
 impl SharedLogger for LocalLogger {
     fn level(&self) -> log::LevelFilter {
-        self.log_level
+        self.filter.max_level()
     }
 
     fn config(&self) -> Option<&simplelog::Config> {
@@ -316,7 +564,8 @@ pub fn init_local_logger() -> Result<()> {
 /// Create a styled spinner progress bar with CodSpeed branding.
 fn create_spinner(message: &str) -> ProgressBar {
     let spinner = ProgressBar::new_spinner();
-    let tick_strings: Vec<String> = SPINNER_TICKS
+    let tick_strings: Vec<String> = SPINNER_STYLE
+        .ticks()
         .iter()
         .map(|s| format!("{}", style(s).color256(CODSPEED_U8_COLOR_CODE).dim()))
         .collect();
@@ -330,13 +579,13 @@ fn create_spinner(message: &str) -> ProgressBar {
         .tick_strings(&tick_strs),
     );
     spinner.set_message({ message }.to_string());
-    spinner.enable_steady_tick(Duration::from_millis(TICK_INTERVAL_MS));
+    spinner.enable_steady_tick(*TICK_INTERVAL);
     spinner
 }
 
 /// Install a spinner into the global slot so log records suspend it.
 fn install_spinner(message: &str) {
-    if *IS_TTY {
+    if *IS_TTY && *SPINNER_STYLE != SpinnerStyle::Off {
         let spinner = create_spinner(message);
         SPINNER.lock().unwrap().replace(spinner);
     } else {
@@ -361,9 +610,211 @@ pub fn stop_spinner() {
     }
 }
 
+/// Clear the spinner, if any, so the terminal isn't left with a dangling progress bar.
+///
+/// Called on the error and signal paths, so it must not panic: a poisoned lock (e.g.
+/// after a panic in a logging thread) is recovered instead of masking the original error.
 pub fn clean_logger() {
-    let mut spinner = SPINNER.lock().unwrap();
+    clear_spinner(&SPINNER);
+}
+
+fn clear_spinner(spinner: &Mutex<Option<ProgressBar>>) {
+    let mut spinner = spinner.lock().unwrap_or_else(|e| e.into_inner());
     if let Some(spinner) = spinner.as_mut() {
         spinner.finish_and_clear();
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_spinner_style_from_env_value() {
+        assert_eq!(SpinnerStyle::from_env_value(None), SpinnerStyle::Dots);
+        assert_eq!(
+            SpinnerStyle::from_env_value(Some("dots")),
+            SpinnerStyle::Dots
+        );
+        assert_eq!(
+            SpinnerStyle::from_env_value(Some("ASCII")),
+            SpinnerStyle::Ascii
+        );
+        assert_eq!(SpinnerStyle::from_env_value(Some("off")), SpinnerStyle::Off);
+        assert_eq!(
+            SpinnerStyle::from_env_value(Some("unknown")),
+            SpinnerStyle::Dots
+        );
+
+        assert_eq!(SpinnerStyle::Ascii.ticks(), ASCII_SPINNER_TICKS);
+        assert_eq!(SpinnerStyle::Dots.ticks(), SPINNER_TICKS);
+    }
+
+    #[test]
+    fn test_github_actions_group_workflow_commands() {
+        assert_eq!(
+            format_github_actions_group_event(&GroupEvent::Start("Installing tools".into())),
+            "::group::Installing tools"
+        );
+        assert_eq!(
+            format_github_actions_group_event(&GroupEvent::End),
+            "::endgroup::"
+        );
+    }
+
+    #[test]
+    fn test_github_actions_annotations() {
+        assert_eq!(
+            format_github_actions_annotation(log::Level::Error, "failed\nto install"),
+            Some("::error::failed%0Ato install".to_string())
+        );
+        assert_eq!(
+            format_github_actions_annotation(log::Level::Warn, "careful"),
+            Some("::warning::careful".to_string())
+        );
+        assert_eq!(
+            format_github_actions_annotation(log::Level::Info, "hi"),
+            None
+        );
+    }
+
+    #[test]
+    fn test_clear_spinner_tolerates_poisoned_lock() {
+        let spinner = Arc::new(Mutex::new(Some(ProgressBar::hidden())));
+
+        let poisoner = spinner.clone();
+        let _ = std::thread::spawn(move || {
+            let _guard = poisoner.lock().unwrap();
+            panic!("poisoning the spinner lock");
+        })
+        .join();
+        assert!(spinner.is_poisoned());
+
+        clear_spinner(&spinner);
+        let spinner = spinner.lock().unwrap_or_else(|e| e.into_inner());
+        assert!(spinner.as_ref().unwrap().is_finished());
+    }
+
+    #[test]
+    fn test_slow_step_warning() {
+        let threshold = parse_slow_step_threshold(None);
+        assert_eq!(threshold, Duration::from_secs(120));
+
+        let warning = slow_step_warning("Parsing results", Duration::from_secs(130), threshold);
+        assert_eq!(
+            warning.as_deref(),
+            Some("\"Parsing results\" took 2m 10s, which is longer than expected (over 2m 0s)")
+        );
+        assert_eq!(
+            slow_step_warning("Parsing results", Duration::from_secs(30), threshold),
+            None
+        );
+    }
+
+    #[test]
+    fn test_parse_slow_step_threshold() {
+        assert_eq!(
+            parse_slow_step_threshold(Some("10")),
+            Duration::from_secs(10)
+        );
+        assert_eq!(
+            parse_slow_step_threshold(Some("soon")),
+            Duration::from_secs(120)
+        );
+    }
+
+    #[test]
+    fn test_parse_tick_interval() {
+        assert_eq!(parse_tick_interval(None), Duration::from_millis(300));
+        assert_eq!(parse_tick_interval(Some("100")), Duration::from_millis(100));
+        assert_eq!(parse_tick_interval(Some("0")), Duration::from_millis(300));
+        assert_eq!(
+            parse_tick_interval(Some("fast")),
+            Duration::from_millis(300)
+        );
+    }
+
+    #[test]
+    fn test_parse_log_filter() {
+        assert_eq!(
+            LogFilter::parse("debug"),
+            LogFilter {
+                default: log::LevelFilter::Debug,
+                targets: vec![],
+            }
+        );
+        assert_eq!(LogFilter::parse("").default, log::LevelFilter::Info);
+
+        let filter =
+            LogFilter::parse("warn, executor=debug,codspeed_runner::upload=trace,bad=loud");
+        assert_eq!(
+            filter,
+            LogFilter {
+                default: log::LevelFilter::Warn,
+                targets: vec![
+                    ("executor".to_string(), log::LevelFilter::Debug),
+                    (
+                        "codspeed_runner::upload".to_string(),
+                        log::LevelFilter::Trace
+                    ),
+                ],
+            }
+        );
+        assert_eq!(filter.max_level(), log::LevelFilter::Trace);
+    }
+
+    #[test]
+    fn test_log_filter_matches_target_prefixes() {
+        let filter = LogFilter::parse(
+            "info,codspeed_runner::executor=debug,codspeed_runner::executor::wall_time=trace",
+        );
+
+        assert_eq!(
+            filter.level("codspeed_runner::upload"),
+            log::LevelFilter::Info
+        );
+        assert_eq!(
+            filter.level("codspeed_runner::executor"),
+            log::LevelFilter::Debug
+        );
+        assert_eq!(
+            filter.level("codspeed_runner::executor::valgrind"),
+            log::LevelFilter::Debug
+        );
+        assert_eq!(
+            filter.level("codspeed_runner::executor::wall_time::profiler::perf"),
+            log::LevelFilter::Trace
+        );
+        // Prefixes only match whole path segments
+        assert_eq!(
+            filter.level("codspeed_runner::executor_config"),
+            log::LevelFilter::Info
+        );
+    }
+
+    #[test]
+    fn test_output_mode_from_env_value() {
+        assert_eq!(OutputMode::from_env_value(None), OutputMode::Default);
+        assert_eq!(
+            OutputMode::from_env_value(Some("Compact")),
+            OutputMode::Compact
+        );
+        assert_eq!(
+            OutputMode::from_env_value(Some("verbose")),
+            OutputMode::Default
+        );
+    }
+
+    #[test]
+    fn test_compact_group_is_a_single_completion_line() {
+        let current_group = Mutex::new(None);
+
+        let mut lines = compact_group_lines(&GroupEvent::Start("Building".into()), &current_group);
+        lines.extend(compact_group_lines(&GroupEvent::End, &current_group));
+
+        assert_eq!(lines.len(), 1);
+        let line = console::strip_ansi_codes(&lines[0]).to_string();
+        assert!(line.starts_with("\u{203a} Building \u{2713} "), "{line}");
+        assert!(current_group.lock().unwrap().is_none());
+    }
+}
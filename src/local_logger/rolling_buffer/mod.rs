@@ -1,10 +1,10 @@
 use std::collections::VecDeque;
 use std::sync::Mutex;
 use std::sync::atomic::{AtomicBool, Ordering};
-use std::time::{Duration, Instant};
+use std::time::Instant;
 
 use super::{
-    CODSPEED_U8_COLOR_CODE, IS_TTY, SPINNER, SPINNER_TICKS, TICK_INTERVAL_MS, format_checkmark,
+    CODSPEED_U8_COLOR_CODE, IS_TTY, SPINNER, SPINNER_STYLE, TICK_INTERVAL, format_checkmark,
     icons::Icon,
 };
 use console::{Term, style};
@@ -104,8 +104,9 @@ impl RollingBuffer {
 
     fn spinner_tick(&self) -> &'static str {
         let elapsed_ms = self.start.elapsed().as_millis();
-        let idx = (elapsed_ms / TICK_INTERVAL_MS as u128) as usize % SPINNER_TICKS.len();
-        SPINNER_TICKS[idx]
+        let ticks = SPINNER_STYLE.ticks();
+        let idx = (elapsed_ms / TICK_INTERVAL.as_millis().max(1)) as usize % ticks.len();
+        ticks[idx]
     }
 
     fn render_title_line(&self) -> String {
@@ -305,7 +306,7 @@ pub fn activate_rolling_buffer(title: &str) {
     TICK_STOP.store(false, Ordering::Relaxed);
     std::thread::spawn(|| {
         while !TICK_STOP.load(Ordering::Relaxed) {
-            std::thread::sleep(Duration::from_millis(TICK_INTERVAL_MS));
+            std::thread::sleep(*TICK_INTERVAL);
             if TICK_STOP.load(Ordering::Relaxed) {
                 break;
             }
@@ -1,3 +1,5 @@
+use std::sync::Mutex;
+
 /// This target is used exclusively to handle group events.
 pub const GROUP_TARGET: &str = "codspeed::group";
 pub const OPENED_GROUP_TARGET: &str = "codspeed::group::opened";
@@ -57,6 +59,65 @@ macro_rules! announcement {
     };
 }
 
+#[macro_export]
+/// Log a warning worth repeating in the summary of the run, see [`RUN_WARNINGS`].
+macro_rules! run_warning {
+    ($($arg:tt)+) => {{
+        let warning = format!($($arg)+);
+        log::warn!("{warning}");
+        $crate::logger::RUN_WARNINGS.push(warning);
+    }};
+}
+
+/// Thread-safe list of the notable warnings of a run, e.g. missing symbols or benchmarks with too
+/// few samples. They are repeated at the end of the run, as they are easy to miss in long outputs.
+pub struct WarningCollector {
+    warnings: Mutex<Vec<String>>,
+}
+
+impl WarningCollector {
+    pub const fn new() -> Self {
+        Self {
+            warnings: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Collect `warning`, unless it was already collected, e.g. by another mode.
+    pub fn push(&self, warning: String) {
+        let mut warnings = self.warnings.lock().unwrap_or_else(|e| e.into_inner());
+        if !warnings.contains(&warning) {
+            warnings.push(warning);
+        }
+    }
+
+    /// Remove and return the collected warnings.
+    pub fn take(&self) -> Vec<String> {
+        std::mem::take(&mut *self.warnings.lock().unwrap_or_else(|e| e.into_inner()))
+    }
+}
+
+impl Default for WarningCollector {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The warnings collected with [`run_warning!`] during the run.
+pub static RUN_WARNINGS: WarningCollector = WarningCollector::new();
+
+/// The summary of the warnings of a completed run, if any.
+pub fn warnings_summary(warnings: &[String]) -> Option<String> {
+    if warnings.is_empty() {
+        return None;
+    }
+    let plural = if warnings.len() == 1 { "" } else { "s" };
+    let mut summary = format!("The run emitted {} warning{plural}:", warnings.len());
+    for warning in warnings {
+        summary.push_str(&format!("\n- {warning}"));
+    }
+    Some(summary)
+}
+
 pub enum GroupEvent {
     Start(String),
     StartOpened(String),
@@ -113,3 +174,28 @@ pub(super) fn get_json_event(record: &log::Record) -> Option<JsonEvent> {
 
     Some(JsonEvent(record.args().to_string()))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_warnings_summary() {
+        let collector = WarningCollector::new();
+        assert_eq!(warnings_summary(&collector.take()), None);
+
+        collector.push("Benchmark bench::a only got 3 samples".to_string());
+        collector.push("Excluding the symbols of libfoo.so from the profile".to_string());
+        collector.push("Benchmark bench::a only got 3 samples".to_string());
+
+        assert_eq!(
+            warnings_summary(&collector.take()).as_deref(),
+            Some(
+                "The run emitted 2 warnings:\n\
+                - Benchmark bench::a only got 3 samples\n\
+                - Excluding the symbols of libfoo.so from the profile"
+            )
+        );
+        assert!(collector.take().is_empty());
+    }
+}
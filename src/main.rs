@@ -1,9 +1,28 @@
-use codspeed_runner::{clean_logger, cli};
+use codspeed_runner::{clean_logger, cli, exit_code};
 use console::style;
 use log::log_enabled;
 
+/// Clear the spinner on SIGINT, SIGTERM or SIGHUP, then terminate with the default disposition.
+///
+/// The handler runs on a dedicated thread: the runtime is `current_thread`, so a handler polled
+/// by it would never run while synchronous work blocks it.
+fn install_termination_handler() {
+    // Keep the default signal behavior if the handler can't be installed
+    let _ = ctrlc::set_handler(|| {
+        // Clear the spinner instead of leaving a dangling progress bar
+        clean_logger();
+        // The handler doesn't tell the signals apart: terminate as interrupted, so the
+        // parent sees the conventional 128 + SIGINT status
+        unsafe {
+            libc::signal(libc::SIGINT, libc::SIG_DFL);
+            libc::raise(libc::SIGINT);
+        }
+    });
+}
+
 #[tokio::main(flavor = "current_thread")]
 async fn main() {
+    install_termination_handler();
     let res = cli::run().await;
     if let Err(err) = res {
         // Show the primary error
@@ -22,6 +41,6 @@ async fn main() {
             }
         }
         clean_logger();
-        std::process::exit(1);
+        std::process::exit(exit_code(&err));
     }
 }
@@ -1,4 +1,4 @@
-pub use crate::{announcement, end_group, log_json, start_group, start_opened_group};
+pub use crate::{announcement, end_group, log_json, run_warning, start_group, start_opened_group};
 #[allow(unused_imports)]
 pub use anyhow::{Context, Error, Result, anyhow, bail, ensure};
 pub use itertools::Itertools;
@@ -1,3 +1,5 @@
+use crate::cli::UnwindingMode;
+use crate::runner_mode::RunnerMode;
 use schemars::JsonSchema;
 use serde::{Deserialize, Deserializer, Serialize};
 
@@ -54,6 +56,12 @@ pub struct TargetOptions {
 pub struct ProjectOptions {
     /// Working directory where commands will be executed (relative to config file)
     pub working_directory: Option<String>,
+    /// Modes to run the benchmarks in when neither `--mode` nor the shell session sets them
+    pub modes: Option<Vec<RunnerMode>>,
+    /// Unwinding mode used by perf to collect the call stacks, unless `--perf-unwinding-mode` is set
+    pub perf_unwinding_mode: Option<UnwindingMode>,
+    /// Globs of the modules ignored by the perf profiler, unless `--ignore-object` is set
+    pub ignore_objects: Option<Vec<String>>,
     /// Walltime execution configuration (flattened)
     #[serde(flatten)]
     pub walltime: Option<WalltimeOptions>,
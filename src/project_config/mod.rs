@@ -1,4 +1,5 @@
 use crate::prelude::*;
+use serde_yaml::Value;
 use std::fs;
 use std::path::Path;
 
@@ -22,12 +23,74 @@ impl ProjectConfig {
             )
         })?;
 
+        // Serde silently drops the unknown keys, warn about them since they are likely typos
+        let raw_config: Value = serde_yaml::from_slice(&config_content)?;
+        for key in config.unknown_keys(&raw_config)? {
+            warn!(
+                "Unknown key `{key}` in CodSpeed project config at {}, ignoring it",
+                path.display()
+            );
+        }
+
         // Validate the config
         config.validate()?;
 
         Ok(config)
     }
 
+    /// The keys of `raw_config` that are not part of the config, e.g. `options.max-tme`.
+    ///
+    /// Serializing the config back emits every known key, so the keys of `raw_config` missing
+    /// from it are the ones ignored when deserializing.
+    fn unknown_keys(&self, raw_config: &Value) -> Result<Vec<String>> {
+        fn collect(raw: &Value, known: &Value, path: &str, unknown_keys: &mut Vec<String>) {
+            match (raw, known) {
+                (Value::Mapping(raw), Value::Mapping(known)) => {
+                    for (key, raw_value) in raw {
+                        let key_name = match key {
+                            Value::String(key) => key.clone(),
+                            key => serde_yaml::to_string(key)
+                                .unwrap_or_default()
+                                .trim_end()
+                                .to_string(),
+                        };
+                        let key_path = if path.is_empty() {
+                            key_name
+                        } else {
+                            format!("{path}.{key_name}")
+                        };
+                        match known.get(key) {
+                            Some(known_value) => {
+                                collect(raw_value, known_value, &key_path, unknown_keys)
+                            }
+                            None => unknown_keys.push(key_path),
+                        }
+                    }
+                }
+                (Value::Sequence(raw), Value::Sequence(known)) => {
+                    for (index, (raw_value, known_value)) in raw.iter().zip(known).enumerate() {
+                        collect(
+                            raw_value,
+                            known_value,
+                            &format!("{path}[{index}]"),
+                            unknown_keys,
+                        );
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        let mut unknown_keys = vec![];
+        collect(
+            raw_config,
+            &serde_yaml::to_value(self)?,
+            "",
+            &mut unknown_keys,
+        );
+        Ok(unknown_keys)
+    }
+
     /// Validate the configuration
     ///
     /// Checks for invalid combinations of options, particularly in walltime config
@@ -1,4 +1,7 @@
 use super::*;
+use crate::cli::UnwindingMode;
+use crate::cli::run::RunArgs;
+use crate::runner_mode::RunnerMode;
 use std::fs;
 use tempfile::TempDir;
 
@@ -60,6 +63,9 @@ fn test_validate_conflicting_min_time_max_rounds() {
                 min_rounds: None,
             }),
             working_directory: None,
+            modes: None,
+            perf_unwinding_mode: None,
+            ignore_objects: None,
         }),
         benchmarks: None,
     };
@@ -86,6 +92,9 @@ fn test_validate_conflicting_max_time_min_rounds() {
                 min_rounds: Some(5),
             }),
             working_directory: None,
+            modes: None,
+            perf_unwinding_mode: None,
+            ignore_objects: None,
         }),
         benchmarks: None,
     };
@@ -112,6 +121,9 @@ fn test_validate_valid_config() {
                 min_rounds: None,
             }),
             working_directory: Some("./bench".to_string()),
+            modes: None,
+            perf_unwinding_mode: None,
+            ignore_objects: None,
         }),
         benchmarks: None,
     };
@@ -148,6 +160,139 @@ fn test_load_from_path_invalid_yaml() {
     assert!(result.is_err());
 }
 
+#[test]
+fn test_load_from_path_invalid_option_value() {
+    let temp_dir = TempDir::new().unwrap();
+    let config_path = temp_dir.path().join("codspeed.yaml");
+
+    fs::write(
+        &config_path,
+        r#"
+options:
+  modes: [turbo]
+"#,
+    )
+    .unwrap();
+
+    let error = ProjectConfig::load_from_path(&config_path).unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        format!(
+            "Failed to parse CodSpeed project config at {}",
+            config_path.display()
+        )
+    );
+}
+
+#[test]
+fn test_deserialize_run_options() {
+    let yaml = r#"
+options:
+  modes: [walltime, simulation]
+  perf-unwinding-mode: fp
+  ignore-objects:
+    - "*/libasan.so*"
+"#;
+    let config: ProjectConfig = serde_yaml::from_str(yaml).unwrap();
+    let options = config.options.unwrap();
+
+    assert_eq!(
+        options.modes,
+        Some(vec![RunnerMode::Walltime, RunnerMode::Simulation])
+    );
+    assert_eq!(
+        options.perf_unwinding_mode,
+        Some(UnwindingMode::FramePointer)
+    );
+    assert_eq!(
+        options.ignore_objects,
+        Some(vec!["*/libasan.so*".to_string()])
+    );
+}
+
+#[test]
+fn test_unknown_keys() {
+    let yaml = r#"
+options:
+  warmup-time: 1s
+  max-tme: 10s
+benchmarks:
+  - exec: ls
+    nme: list
+    options:
+      min-rounds: 10
+      min-round: 5
+unknown-section: {}
+"#;
+    let config: ProjectConfig = serde_yaml::from_str(yaml).unwrap();
+    let raw_config: serde_yaml::Value = serde_yaml::from_str(yaml).unwrap();
+
+    assert_eq!(
+        config.unknown_keys(&raw_config).unwrap(),
+        vec![
+            "options.max-tme",
+            "benchmarks[0].nme",
+            "benchmarks[0].options.min-round",
+            "unknown-section",
+        ]
+    );
+}
+
+#[test]
+fn test_merge_precedence() {
+    let options: ProjectOptions = serde_yaml::from_str(
+        r#"
+modes: [walltime]
+perf-unwinding-mode: fp
+ignore-objects: ["*/libasan.so*"]
+"#,
+    )
+    .unwrap();
+
+    // The CLI and the environment variables, both parsed by clap, win over the config file
+    let mut args = RunArgs::test();
+    args.shared.mode = vec![RunnerMode::Simulation];
+    args.shared.profiler_run_args.perf.perf_unwinding_mode = Some(UnwindingMode::Dwarf);
+    args.shared.ignore_objects = vec!["*/libfoo.so".to_string()];
+    args.shared.merge_with_project_options(Some(&options));
+    assert_eq!(
+        args.shared.resolve_modes().unwrap(),
+        vec![RunnerMode::Simulation]
+    );
+    assert_eq!(
+        args.shared.profiler_run_args.perf.perf_unwinding_mode,
+        Some(UnwindingMode::Dwarf)
+    );
+    assert_eq!(args.shared.ignore_objects, vec!["*/libfoo.so".to_string()]);
+
+    // The config file wins over the defaults
+    let mut args = RunArgs::test();
+    args.shared.mode = vec![];
+    args.shared.merge_with_project_options(Some(&options));
+    assert_eq!(args.shared.project_modes, vec![RunnerMode::Walltime]);
+    assert_eq!(
+        args.shared.profiler_run_args.perf.perf_unwinding_mode,
+        Some(UnwindingMode::FramePointer)
+    );
+    assert_eq!(
+        args.shared.ignore_objects,
+        vec!["*/libasan.so*".to_string()]
+    );
+
+    // The walltime defaults of the config file are left out of the other modes
+    let mut args = RunArgs::test();
+    args.shared.mode = vec![RunnerMode::Simulation];
+    args.shared.merge_with_project_options(Some(&options));
+    assert_eq!(args.shared.profiler_run_args.perf.perf_unwinding_mode, None);
+    assert!(args.shared.ignore_objects.is_empty());
+
+    // Without a config file, the defaults are kept
+    let mut args = RunArgs::test();
+    args.shared.merge_with_project_options(None);
+    assert_eq!(args.shared.profiler_run_args.perf.perf_unwinding_mode, None);
+    assert!(args.shared.ignore_objects.is_empty());
+}
+
 #[test]
 fn test_discover_with_explicit_path() {
     let temp_dir = TempDir::new().unwrap();
@@ -0,0 +1,101 @@
+//! Interactive confirmations for actions that change the user's system or
+//! delete files.
+//!
+//! `--yes` / `CODSPEED_ASSUME_YES` answers every confirmation up front, so the
+//! CLI never blocks waiting for input in scripts and CI. Without it, the user
+//! is asked on a TTY; on a non-TTY each call site decides whether to proceed
+//! or refuse through [`WithoutTty`].
+
+use crate::local_logger::IS_TTY;
+use crate::prelude::*;
+use console::Term;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static ASSUME_YES: AtomicBool = AtomicBool::new(false);
+
+/// Record whether `--yes` / `CODSPEED_ASSUME_YES` was passed for this invocation.
+pub fn set_assume_yes(assume_yes: bool) {
+    ASSUME_YES.store(assume_yes, Ordering::Relaxed);
+}
+
+/// How a confirmation resolves when nobody can be asked and `--yes` was not passed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WithoutTty {
+    /// Proceed, keeping the historical CI behavior for routine setup steps.
+    Proceed,
+    /// Refuse, for destructive actions that must be explicitly acknowledged.
+    Refuse,
+}
+
+/// Ask the user to confirm `question`. An empty input answers yes, except for the actions
+/// refused without a TTY, which are only confirmed explicitly.
+///
+/// Returns an error if the user declines or if the action is refused
+/// because there is no TTY to ask on.
+pub fn confirm(question: &str, without_tty: WithoutTty) -> Result<()> {
+    confirm_with(
+        question,
+        without_tty,
+        ASSUME_YES.load(Ordering::Relaxed),
+        *IS_TTY,
+    )
+}
+
+/// [`confirm`] with an explicit `--yes` and TTY state.
+pub(crate) fn confirm_with(
+    question: &str,
+    without_tty: WithoutTty,
+    assume_yes: bool,
+    is_tty: bool,
+) -> Result<()> {
+    if assume_yes {
+        debug!("Assuming yes: {question}");
+        return Ok(());
+    }
+
+    if !is_tty {
+        return match without_tty {
+            WithoutTty::Proceed => Ok(()),
+            WithoutTty::Refuse => bail!(
+                "{question}\nRefusing to continue without confirmation in a non-interactive session. \
+                Pass `--yes` (or set CODSPEED_ASSUME_YES=true) to proceed."
+            ),
+        };
+    }
+
+    let default_yes = without_tty == WithoutTty::Proceed;
+    eprint!(
+        "{question} {} ",
+        if default_yes { "[Y/n]" } else { "[y/N]" }
+    );
+    // An answer that can't be read is not a confirmation
+    let line = Term::stderr()
+        .read_line()
+        .with_context(|| format!("Failed to read the answer, not confirming: {question}"))?;
+    let answer = line.trim();
+
+    if (answer.is_empty() && default_yes)
+        || answer.eq_ignore_ascii_case("y")
+        || answer.eq_ignore_ascii_case("yes")
+    {
+        Ok(())
+    } else {
+        bail!("Declined: {question}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn assume_yes_skips_the_question() {
+        assert!(confirm_with("Delete?", WithoutTty::Refuse, true, false).is_ok());
+    }
+
+    #[test]
+    fn non_tty_follows_call_site_policy() {
+        assert!(confirm_with("Install?", WithoutTty::Proceed, false, false).is_ok());
+        assert!(confirm_with("Delete?", WithoutTty::Refuse, false, false).is_err());
+    }
+}
@@ -1,42 +1,299 @@
-use std::sync::LazyLock;
+use std::path::Path;
+use std::sync::{LazyLock, Mutex, OnceLock};
+use std::time::{Duration, SystemTime};
 
-use reqwest::ClientBuilder;
+use crate::prelude::*;
+use rand::RngExt;
+use rand::rngs::StdRng;
+use reqwest::{Certificate, ClientBuilder};
 use reqwest_middleware::{ClientBuilder as ClientWithMiddlewareBuilder, ClientWithMiddleware};
-use reqwest_retry::{RetryTransientMiddleware, policies::ExponentialBackoff};
+use reqwest_retry::policies::{ExponentialBackoff, ExponentialBackoffBuilder};
+use reqwest_retry::{Jitter, RetryDecision, RetryPolicy, RetryTransientMiddleware};
 
 pub const UPLOAD_RETRY_COUNT: u32 = 3;
 const OIDC_RETRY_COUNT: u32 = 10;
 const USER_AGENT: &str = "codspeed-runner";
 
+/// Exponential backoff whose full jitter is drawn from the seeded RNG of the invocation
+/// (see [`crate::seed`]), so that the retries of runs with the same `--seed` wait the same.
+pub struct SeededBackoff {
+    /// The backoff without jitter
+    backoff: ExponentialBackoff,
+    rng: Mutex<StdRng>,
+}
+
+impl SeededBackoff {
+    fn new(builder: ExponentialBackoffBuilder, max_retries: u32, rng: StdRng) -> Self {
+        Self {
+            backoff: builder
+                .jitter(Jitter::None)
+                .build_with_max_retries(max_retries),
+            rng: Mutex::new(rng),
+        }
+    }
+
+    /// A random fraction of `wait`, as the default full jitter of reqwest-retry.
+    fn jitter(&self, wait: Duration) -> Duration {
+        let factor: f64 = self.rng.lock().unwrap().random();
+        wait.mul_f64(factor)
+    }
+}
+
+impl RetryPolicy for SeededBackoff {
+    fn should_retry(&self, request_start_time: SystemTime, n_past_retries: u32) -> RetryDecision {
+        match self
+            .backoff
+            .should_retry(request_start_time, n_past_retries)
+        {
+            RetryDecision::Retry { execute_after } => {
+                let now = SystemTime::now();
+                let wait = execute_after.duration_since(now).unwrap_or_default();
+                RetryDecision::Retry {
+                    execute_after: now + self.jitter(wait),
+                }
+            }
+            RetryDecision::DoNotRetry => RetryDecision::DoNotRetry,
+        }
+    }
+}
+
 /// Shared backoff policy for upload retries, used both by the retry middleware on
 /// [`REQUEST_CLIENT`] and by the manual stream-retry loop in the uploader. Under
 /// `cfg(test)` the intervals are shrunk to milliseconds so retry tests don't sleep
 /// through the real exponential backoff (1s, 2s, 4s).
-pub fn upload_backoff() -> ExponentialBackoff {
+pub fn upload_backoff() -> SeededBackoff {
     let builder = ExponentialBackoff::builder();
     #[cfg(test)]
-    let builder = builder.retry_bounds(
-        std::time::Duration::from_millis(1),
-        std::time::Duration::from_millis(5),
+    let builder = builder.retry_bounds(Duration::from_millis(1), Duration::from_millis(5));
+    SeededBackoff::new(builder, UPLOAD_RETRY_COUNT, crate::seed::seeded_rng())
+}
+
+/// Extra root certificates trusted by the HTTP clients, set with `--ca-bundle` /
+/// `CODSPEED_CA_BUNDLE`, e.g. for a corporate proxy intercepting the TLS traffic.
+static CA_CERTIFICATES: OnceLock<Vec<Certificate>> = OnceLock::new();
+
+/// Load the CA bundle of this invocation, before any of the HTTP clients is built.
+pub fn init_ca_bundle(path: Option<&Path>) -> Result<()> {
+    let Some(path) = path else {
+        return Ok(());
+    };
+    let certificates = load_ca_bundle(path)
+        .with_context(|| format!("Failed to load the CA bundle {}", path.display()))?;
+    debug!(
+        "Trusting {} extra certificates from {}",
+        certificates.len(),
+        path.display()
     );
-    builder.build_with_max_retries(UPLOAD_RETRY_COUNT)
+    if CA_CERTIFICATES.set(certificates).is_err() {
+        warn!("The CA bundle was already initialized, ignoring the new value");
+    }
+    Ok(())
+}
+
+fn load_ca_bundle(path: &Path) -> Result<Vec<Certificate>> {
+    let pem = std::fs::read(path)?;
+    let certificates = Certificate::from_pem_bundle(&pem)?;
+    ensure!(!certificates.is_empty(), "No PEM certificate found");
+    Ok(certificates)
+}
+
+/// Builder of every HTTP client of the runner, the GraphQL one included. The proxy is read from
+/// the `HTTPS_PROXY`, `HTTP_PROXY`, `ALL_PROXY` and `NO_PROXY` environment variables by reqwest
+/// itself.
+pub fn client_builder() -> ClientBuilder {
+    let builder = ClientBuilder::new().user_agent(USER_AGENT);
+    CA_CERTIFICATES
+        .get()
+        .into_iter()
+        .flatten()
+        .fold(builder, |builder, certificate| {
+            builder.add_root_certificate(certificate.clone())
+        })
+}
+
+/// Explain why a request failed to get a response, telling the TLS failures, e.g. a proxy
+/// intercepting the traffic with an untrusted certificate, apart from the network ones.
+pub fn request_error(error: impl Into<Error>) -> Error {
+    let error = error.into();
+    let is_certificate_error = error
+        .chain()
+        .any(|cause| cause.to_string().to_lowercase().contains("certificate"));
+    let is_connect_error = error
+        .chain()
+        .filter_map(
+            |cause| match cause.downcast_ref::<reqwest_middleware::Error>() {
+                Some(reqwest_middleware::Error::Reqwest(error)) => Some(error),
+                _ => cause.downcast_ref::<reqwest::Error>(),
+            },
+        )
+        .any(|error| error.is_connect() || error.is_timeout());
+
+    if is_certificate_error {
+        error.context(
+            "TLS error, the certificate of the server is not trusted. If a proxy intercepts the \
+             traffic, set CODSPEED_CA_BUNDLE to the path of its CA certificate",
+        )
+    } else if is_connect_error {
+        error.context(
+            "Network error, failed to reach the server. Check the connection and the \
+             HTTPS_PROXY, HTTP_PROXY and NO_PROXY environment variables",
+        )
+    } else {
+        error
+    }
 }
 
 pub static REQUEST_CLIENT: LazyLock<ClientWithMiddleware> = LazyLock::new(|| {
-    ClientWithMiddlewareBuilder::new(ClientBuilder::new().user_agent(USER_AGENT).build().unwrap())
+    ClientWithMiddlewareBuilder::new(client_builder().build().unwrap())
         .with(RetryTransientMiddleware::new_with_policy(upload_backoff()))
         .build()
 });
 
 /// Client without retry middleware for streaming uploads (can't be cloned)
 pub static STREAMING_CLIENT: LazyLock<reqwest::Client> =
-    LazyLock::new(|| ClientBuilder::new().user_agent(USER_AGENT).build().unwrap());
+    LazyLock::new(|| client_builder().build().unwrap());
 
 /// Client with retry middleware for OIDC token requests
 pub static OIDC_CLIENT: LazyLock<ClientWithMiddleware> = LazyLock::new(|| {
-    ClientWithMiddlewareBuilder::new(ClientBuilder::new().user_agent(USER_AGENT).build().unwrap())
+    ClientWithMiddlewareBuilder::new(client_builder().build().unwrap())
         .with(RetryTransientMiddleware::new_with_policy(
-            ExponentialBackoff::builder().build_with_max_retries(OIDC_RETRY_COUNT),
+            SeededBackoff::new(
+                ExponentialBackoff::builder(),
+                OIDC_RETRY_COUNT,
+                crate::seed::seeded_rng(),
+            ),
         ))
         .build()
 });
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+
+    /// Spawn a proxy answering a single request, returning its URL and the request it received.
+    fn spawn_mock_proxy() -> (String, std::thread::JoinHandle<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let handle = std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut buf = [0u8; 2048];
+            let n = stream.read(&mut buf).unwrap();
+            let body = "proxied";
+            let resp = format!(
+                "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                body.len(),
+                body
+            );
+            stream.write_all(resp.as_bytes()).unwrap();
+            String::from_utf8_lossy(&buf[..n]).into_owned()
+        });
+        (url, handle)
+    }
+
+    /// Setting `HTTP_PROXY` in the test process would route the requests of the tests running in
+    /// parallel through the mock proxy, so the request is sent from a child process, running
+    /// [`proxied_request`] alone.
+    #[test]
+    fn test_client_honors_the_proxy_env() {
+        let (proxy_url, proxy) = spawn_mock_proxy();
+        let status = std::process::Command::new(std::env::current_exe().unwrap())
+            .args([
+                "request_client::tests::proxied_request",
+                "--exact",
+                "--ignored",
+            ])
+            .env("HTTP_PROXY", &proxy_url)
+            .env_remove("http_proxy")
+            .env_remove("ALL_PROXY")
+            .env_remove("all_proxy")
+            .env_remove("NO_PROXY")
+            .env_remove("no_proxy")
+            .status()
+            .unwrap();
+
+        assert!(status.success());
+        let request = proxy.join().unwrap();
+        assert!(
+            request.starts_with("GET http://codspeed.invalid/ping "),
+            "{request}"
+        );
+    }
+
+    #[tokio::test]
+    #[ignore = "run by test_client_honors_the_proxy_env, with HTTP_PROXY set"]
+    async fn proxied_request() {
+        if std::env::var_os("HTTP_PROXY").is_none() {
+            return;
+        }
+        let response = client_builder()
+            .build()
+            .unwrap()
+            .get("http://codspeed.invalid/ping")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.text().await.unwrap(), "proxied");
+    }
+
+    #[test]
+    fn test_same_seed_gives_the_same_retry_waits() {
+        use rand::SeedableRng;
+
+        let waits = |seed| {
+            let backoff = SeededBackoff::new(
+                ExponentialBackoff::builder(),
+                UPLOAD_RETRY_COUNT,
+                StdRng::seed_from_u64(seed),
+            );
+            (1..=5)
+                .map(|secs| backoff.jitter(Duration::from_secs(secs)))
+                .collect_vec()
+        };
+
+        assert_eq!(waits(42), waits(42));
+        assert_ne!(waits(42), waits(43));
+        assert!(
+            waits(42)
+                .iter()
+                .zip(1..=5)
+                .all(|(wait, secs)| *wait <= Duration::from_secs(secs))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_request_error_explains_network_failures() {
+        // Bind then drop a listener to get a port nothing listens on
+        let addr = TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap();
+        let client = temp_env::with_vars(
+            [
+                ("HTTP_PROXY", None::<&str>),
+                ("http_proxy", None),
+                ("ALL_PROXY", None),
+                ("all_proxy", None),
+            ],
+            || client_builder().build().unwrap(),
+        );
+
+        let error = client
+            .get(format!("http://{addr}/"))
+            .send()
+            .await
+            .map_err(request_error)
+            .unwrap_err();
+        assert!(error.to_string().starts_with("Network error"), "{error}");
+    }
+
+    #[test]
+    fn test_ca_bundle_without_certificate_is_rejected() {
+        let bundle = tempfile::NamedTempFile::new().unwrap();
+        std::fs::write(bundle.path(), "not a certificate").unwrap();
+
+        let error = load_ca_bundle(bundle.path()).unwrap_err();
+        assert_eq!(error.to_string(), "No PEM certificate found");
+    }
+}
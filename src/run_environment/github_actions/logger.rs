@@ -107,7 +107,7 @@ impl SharedLogger for GithubActionLogger {
 ///
 /// One exception: trailing newlines are preserved as actual newlines to maintain formatting.
 /// Otherwise, the message gets displayed with extra `%0A` at the end.
-fn escape_multiline_message(message: &str) -> String {
+pub(crate) fn escape_multiline_message(message: &str) -> String {
     let trailing_newlines = message.len() - message.trim_end_matches('\n').len();
     if trailing_newlines > 0 {
         let stripped = &message[..message.len() - trailing_newlines];
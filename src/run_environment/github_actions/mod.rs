@@ -1,4 +1,5 @@
 mod logger;
 mod provider;
 
+pub(crate) use logger::escape_multiline_message;
 pub use provider::GitHubActionsProvider;
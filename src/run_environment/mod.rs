@@ -14,6 +14,7 @@ use crate::prelude::*;
 
 pub use self::interfaces::*;
 pub use self::provider::RunEnvironmentProvider;
+pub(crate) use github_actions::escape_multiline_message;
 
 // RunEnvironment Provider implementations
 mod buildkite;
@@ -21,6 +22,11 @@ mod github_actions;
 mod gitlab_ci;
 mod local;
 
+/// Whether the runner is running in one of the supported CI environments.
+pub fn is_ci() -> bool {
+    BuildkiteProvider::detect() || GitHubActionsProvider::detect() || GitLabCIProvider::detect()
+}
+
 pub async fn get_provider(
     config: &OrchestratorConfig,
     api_client: &CodSpeedAPIClient,
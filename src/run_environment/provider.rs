@@ -135,6 +135,7 @@ pub trait RunEnvironmentProvider {
             profile_encoding: profile_archive.content.encoding(),
             commit_hash,
             allow_empty: config.allow_empty,
+            correlation_id: api_client.correlation_id().map(str::to_owned),
             runner: Runner {
                 name: "codspeed-runner".into(),
                 version: crate::VERSION.into(),
@@ -1,10 +1,11 @@
 use crate::prelude::*;
 use crate::shell_session_store::{self, SessionKind};
 use clap::ValueEnum;
+use schemars::JsonSchema;
 use serde::Deserialize;
 use serde::Serialize;
 
-#[derive(ValueEnum, Clone, Debug, Serialize, Deserialize, PartialEq)]
+#[derive(ValueEnum, Clone, Debug, Serialize, Deserialize, PartialEq, JsonSchema)]
 #[serde(rename_all = "lowercase")]
 pub enum RunnerMode {
     #[deprecated(note = "Use `RunnerMode::Simulation` instead")]
@@ -13,6 +14,64 @@ pub enum RunnerMode {
     Walltime,
     #[cfg(target_os = "linux")]
     Memory,
+    /// Pick walltime or simulation depending on what the machine allows.
+    /// Resolved to a concrete mode when creating the orchestrator.
+    Auto,
+}
+
+impl RunnerMode {
+    /// Whether the executors of the two modes can't profile the same command concurrently.
+    ///
+    /// The walltime mode measures time, so it never runs alongside another mode, whose CPU load
+    /// would skew its measurements. The walltime and memory executors also talk to the
+    /// integration through the runner FIFO, whose paths are fixed, so two of them would mix up
+    /// their commands and acks. Finally, two valgrind-based modes would run the same tool twice.
+    pub fn conflicts_with(&self, other: &RunnerMode) -> bool {
+        #[allow(deprecated)]
+        let is_valgrind = |mode: &RunnerMode| {
+            matches!(mode, RunnerMode::Simulation | RunnerMode::Instrumentation)
+        };
+        let uses_runner_fifo = |mode: &RunnerMode| match mode {
+            RunnerMode::Walltime => true,
+            #[cfg(target_os = "linux")]
+            RunnerMode::Memory => true,
+            _ => false,
+        };
+
+        self == other
+            || *self == RunnerMode::Walltime
+            || *other == RunnerMode::Walltime
+            || (uses_runner_fifo(self) && uses_runner_fifo(other))
+            || (is_valgrind(self) && is_valgrind(other))
+    }
+}
+
+/// What the machine allows, deciding the mode `auto` resolves to.
+#[derive(Debug, Clone, Copy)]
+pub struct AutoModeEnvironment {
+    /// The walltime executor can run on this machine
+    pub walltime_supported: bool,
+    /// The simulation executor can run on this machine
+    pub simulation_supported: bool,
+    /// The profiling sysctls are already set, or can be set without prompting for a password
+    pub can_profile: bool,
+    /// Running in a CI environment
+    pub is_ci: bool,
+}
+
+impl AutoModeEnvironment {
+    /// Walltime when it can run and profile, simulation otherwise.
+    ///
+    /// Outside of CI, walltime is picked even when profiling isn't allowed yet, since the runner
+    /// asks for permission to set the profiling sysctls.
+    pub fn resolve(&self) -> RunnerMode {
+        let walltime_allowed = self.can_profile || !self.is_ci;
+        if self.walltime_supported && (walltime_allowed || !self.simulation_supported) {
+            RunnerMode::Walltime
+        } else {
+            RunnerMode::Simulation
+        }
+    }
 }
 
 /// Register the active runner mode(s) for the current shell session.
@@ -25,3 +84,76 @@ pub(crate) fn register_shell_session_mode(modes: &[RunnerMode]) -> Result<()> {
 pub(crate) fn load_shell_session_mode() -> Result<Vec<RunnerMode>> {
     Ok(shell_session_store::load::<Vec<RunnerMode>>(SessionKind::Mode)?.unwrap_or_default())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CAPABLE_MACHINE: AutoModeEnvironment = AutoModeEnvironment {
+        walltime_supported: true,
+        simulation_supported: true,
+        can_profile: true,
+        is_ci: true,
+    };
+
+    #[test]
+    fn test_auto_mode_picks_walltime_when_profiling_is_allowed() {
+        assert_eq!(CAPABLE_MACHINE.resolve(), RunnerMode::Walltime);
+    }
+
+    #[test]
+    fn test_auto_mode_picks_simulation_in_ci_without_profiling_permissions() {
+        let environment = AutoModeEnvironment {
+            can_profile: false,
+            ..CAPABLE_MACHINE
+        };
+        assert_eq!(environment.resolve(), RunnerMode::Simulation);
+
+        // Locally, the runner asks for the permissions instead
+        let environment = AutoModeEnvironment {
+            is_ci: false,
+            ..environment
+        };
+        assert_eq!(environment.resolve(), RunnerMode::Walltime);
+    }
+
+    #[test]
+    fn test_auto_mode_falls_back_to_the_supported_mode() {
+        let environment = AutoModeEnvironment {
+            walltime_supported: false,
+            ..CAPABLE_MACHINE
+        };
+        assert_eq!(environment.resolve(), RunnerMode::Simulation);
+
+        // e.g. macOS, where simulation can't run
+        let environment = AutoModeEnvironment {
+            simulation_supported: false,
+            can_profile: false,
+            ..CAPABLE_MACHINE
+        };
+        assert_eq!(environment.resolve(), RunnerMode::Walltime);
+    }
+
+    #[test]
+    fn test_conflict_matrix() {
+        #[allow(deprecated)]
+        let conflicting = [
+            (RunnerMode::Simulation, RunnerMode::Walltime),
+            (RunnerMode::Instrumentation, RunnerMode::Walltime),
+            (RunnerMode::Simulation, RunnerMode::Instrumentation),
+            (RunnerMode::Walltime, RunnerMode::Walltime),
+        ];
+        for (a, b) in conflicting {
+            assert!(a.conflicts_with(&b), "{a} should conflict with {b}");
+            assert!(b.conflicts_with(&a), "{b} should conflict with {a}");
+        }
+
+        #[cfg(target_os = "linux")]
+        {
+            assert!(RunnerMode::Walltime.conflicts_with(&RunnerMode::Memory));
+            assert!(RunnerMode::Memory.conflicts_with(&RunnerMode::Walltime));
+            assert!(!RunnerMode::Simulation.conflicts_with(&RunnerMode::Memory));
+            assert!(!RunnerMode::Memory.conflicts_with(&RunnerMode::Simulation));
+        }
+    }
+}
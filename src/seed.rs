@@ -0,0 +1,42 @@
+//! Seed for the randomized behavior of the runner, set with `--seed` / `CODSPEED_SEED`.
+//!
+//! Without an explicit seed, a random one is drawn once per invocation. The
+//! randomized behavior draws from a [`seeded_rng`], so that it is reproducible
+//! across runs with the same seed: for now, the jitter of the HTTP retries.
+//!
+//! Temporary profile folder names and the run and correlation IDs are
+//! intentionally not seeded: reusing the same folder across runs would mix the
+//! artifacts of different runs, and the IDs must identify a single run in the
+//! backend.
+
+use crate::prelude::*;
+use rand::SeedableRng;
+use rand::distr::{Distribution, StandardUniform};
+use rand::rngs::StdRng;
+use std::sync::OnceLock;
+
+static SEED: OnceLock<u64> = OnceLock::new();
+
+fn random_seed() -> u64 {
+    StandardUniform.sample(&mut rand::rng())
+}
+
+fn seed() -> u64 {
+    *SEED.get_or_init(random_seed)
+}
+
+/// Record the seed for this invocation; a random seed is used when `None`.
+pub fn init_seed(seed: Option<u64>) {
+    if SEED.set(seed.unwrap_or_else(random_seed)).is_err() {
+        warn!("The seed was already initialized, ignoring the new value");
+    }
+    debug!("Using seed {}", self::seed());
+}
+
+/// A random number generator seeded with the seed of this invocation.
+///
+/// Each generator starts over from the seed, so that the draws of a component don't depend on
+/// how many values the other components drew before it.
+pub fn seeded_rng() -> StdRng {
+    StdRng::seed_from_u64(seed())
+}
@@ -0,0 +1,87 @@
+use std::collections::HashMap;
+use sysinfo::System;
+
+/// Environment variables describing the toolchain of a run, captured for reproducibility.
+///
+/// Only these are captured: the environment may hold secrets (tokens, credentials), so any
+/// other variable is left out.
+const CAPTURED_ENV_VARS: &[&str] = &[
+    "CC",
+    "CXX",
+    "CFLAGS",
+    "CXXFLAGS",
+    "RUSTC",
+    "RUSTFLAGS",
+    "CARGO_BUILD_TARGET",
+    "GOVERSION",
+    "NODE_VERSION",
+    "PYTHON_VERSION",
+];
+
+/// Capture the allow-listed environment variables of the run, along with the `uname`
+/// information of the machine under `uname.*` keys.
+pub fn capture_environment() -> HashMap<String, String> {
+    let mut environment = capture_env_vars(std::env::vars());
+    let uname = [
+        ("uname.sysname", System::name()),
+        ("uname.release", System::kernel_version()),
+        ("uname.machine", Some(System::cpu_arch())),
+    ];
+    for (key, value) in uname {
+        if let Some(value) = value {
+            environment.insert(key.to_string(), value);
+        }
+    }
+    environment
+}
+
+fn capture_env_vars(vars: impl IntoIterator<Item = (String, String)>) -> HashMap<String, String> {
+    vars.into_iter()
+        .filter(|(key, _)| CAPTURED_ENV_VARS.contains(&key.as_str()))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_capture_only_keeps_allow_listed_vars() {
+        let vars = [
+            ("RUSTC", "/home/user/.cargo/bin/rustc"),
+            ("PYTHON_VERSION", "3.12.4"),
+            ("CODSPEED_TOKEN", "secret-token"),
+            ("AWS_SECRET_ACCESS_KEY", "secret-key"),
+            ("GITHUB_TOKEN", "ghp_secret"),
+            ("RUSTC_WRAPPER", "sccache"),
+        ]
+        .map(|(key, value)| (key.to_string(), value.to_string()));
+
+        let environment = capture_env_vars(vars);
+
+        assert_eq!(
+            environment,
+            HashMap::from([
+                (
+                    "RUSTC".to_string(),
+                    "/home/user/.cargo/bin/rustc".to_string()
+                ),
+                ("PYTHON_VERSION".to_string(), "3.12.4".to_string()),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_capture_environment_includes_uname() {
+        let environment = temp_env::with_var("CODSPEED_TOKEN", Some("secret-token"), || {
+            capture_environment()
+        });
+
+        assert_eq!(environment["uname.machine"], System::cpu_arch());
+        assert!(
+            environment
+                .keys()
+                .all(|key| key.starts_with("uname.") || CAPTURED_ENV_VARS.contains(&key.as_str()))
+        );
+    }
+}
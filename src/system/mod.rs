@@ -1,5 +1,7 @@
+mod environment;
 mod info;
 mod os;
 
+pub use environment::capture_environment;
 pub use info::SystemInfo;
 pub use os::{LinuxDistribution, SupportedOs};
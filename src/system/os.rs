@@ -4,6 +4,10 @@ use serde::{Deserialize, Serialize};
 use sysinfo::System;
 
 use crate::prelude::*;
+
+/// The operating systems CodSpeed runs on.
+pub const SUPPORTED_PLATFORMS: &str = "Linux and macOS";
+
 /// Typed representation of the host operating system.
 ///
 /// Only operating systems that CodSpeed can run on are represented here.
@@ -31,7 +35,9 @@ impl SupportedOs {
             "macos" => Ok(Self::Macos {
                 version: os_version,
             }),
-            unsupported => bail!("Unsupported operating system: {unsupported}"),
+            unsupported => bail!(
+                "Unsupported operating system: {unsupported}, CodSpeed only runs on {SUPPORTED_PLATFORMS}"
+            ),
         }
     }
 
@@ -135,6 +141,19 @@ mod tests {
     #[test]
     fn from_os_bails_on_unsupported() {
         let err = SupportedOs::from_os("windows").unwrap_err();
-        assert_eq!(err.to_string(), "Unsupported operating system: windows");
+        assert_eq!(
+            err.to_string(),
+            "Unsupported operating system: windows, CodSpeed only runs on Linux and macOS"
+        );
+    }
+
+    #[cfg(not(any(target_os = "linux", target_os = "macos")))]
+    #[test]
+    fn current_system_is_unsupported() {
+        let err = SupportedOs::from_os(std::env::consts::OS).unwrap_err();
+        assert!(
+            err.to_string()
+                .ends_with("CodSpeed only runs on Linux and macOS")
+        );
     }
 }
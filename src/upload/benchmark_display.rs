@@ -4,8 +4,13 @@ use crate::api_client::{
 use crate::cli::run::helpers;
 use crate::executor::ExecutorName;
 use crate::local_logger::icons::Icon;
+use clap::ValueEnum;
 use console::style;
+use itertools::Itertools;
+use std::borrow::Cow;
+use std::cmp::Ordering;
 use std::collections::HashMap;
+use std::path::PathBuf;
 use tabled::settings::object::{Columns, Rows};
 use tabled::settings::panel::Panel;
 use tabled::settings::style::HorizontalLine;
@@ -15,6 +20,116 @@ use tabled::{Table, Tabled};
 /// Changes below this threshold are displayed as "~0%" to avoid noise.
 pub(super) const CHANGE_DISPLAY_EPSILON: f64 = 0.005;
 
+/// Order of the benchmarks in the results tables.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ResultsSort {
+    /// Biggest regressions first, then slowest first. Without a base run, same as `time`
+    Impact,
+    /// Slowest (or biggest, in the memory mode) first
+    Time,
+    /// Alphabetical order of the benchmark names
+    Name,
+}
+
+/// Format of the results table of a run.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum ResultsFormat {
+    /// Tables drawn for the terminal
+    #[default]
+    Table,
+    /// Comma-separated values, in a single table with an executor column
+    Csv,
+    /// GitHub-flavored Markdown tables, one per instrument
+    Markdown,
+}
+
+/// How the results are ordered, truncated and formatted before being displayed.
+#[derive(Debug, Clone, Default)]
+pub struct ResultsView {
+    /// Defaults to the order of the report
+    pub sort: Option<ResultsSort>,
+    /// Only display the first `top` results
+    pub top: Option<usize>,
+    pub format: ResultsFormat,
+    /// File the table is written to, instead of being displayed
+    pub output: Option<PathBuf>,
+}
+
+/// A benchmark result that can be ordered by a [`ResultsView`].
+pub trait SortableResult {
+    fn name(&self) -> &str;
+    fn value(&self) -> Option<f64>;
+    /// Relative change against the base run, negative for regressions
+    fn change(&self) -> Option<f64>;
+}
+
+impl SortableResult for FetchLocalRunBenchmarkResult {
+    fn name(&self) -> &str {
+        &self.benchmark.name
+    }
+
+    fn value(&self) -> Option<f64> {
+        Some(self.value)
+    }
+
+    fn change(&self) -> Option<f64> {
+        None
+    }
+}
+
+impl SortableResult for CompareRunsBenchmarkResult {
+    fn name(&self) -> &str {
+        &self.benchmark.name
+    }
+
+    fn value(&self) -> Option<f64> {
+        self.value
+    }
+
+    fn change(&self) -> Option<f64> {
+        self.change
+    }
+}
+
+/// Order the present values with `cmp`, the missing ones last.
+fn cmp_present_first(a: Option<f64>, b: Option<f64>, cmp: fn(&f64, &f64) -> Ordering) -> Ordering {
+    match (a, b) {
+        (Some(a), Some(b)) => cmp(&a, &b),
+        (Some(_), None) => Ordering::Less,
+        (None, Some(_)) => Ordering::Greater,
+        (None, None) => Ordering::Equal,
+    }
+}
+
+impl ResultsView {
+    /// Sort `results` in place and return the ones to display, with a footnote when some were
+    /// left out. The sort is stable, so ties keep the order of the report.
+    pub fn apply<'a, T: SortableResult>(&self, results: &'a mut [T]) -> (&'a [T], Option<String>) {
+        let by_value_desc =
+            |a: &T, b: &T| cmp_present_first(a.value(), b.value(), |a, b| b.total_cmp(a));
+        match self.sort {
+            None => {}
+            Some(ResultsSort::Impact) => results.sort_by(|a, b| {
+                cmp_present_first(a.change(), b.change(), f64::total_cmp)
+                    .then_with(|| by_value_desc(a, b))
+            }),
+            Some(ResultsSort::Time) => results.sort_by(by_value_desc),
+            Some(ResultsSort::Name) => results.sort_by(|a, b| a.name().cmp(b.name())),
+        }
+
+        match self.top {
+            Some(top) if top < results.len() => {
+                let footnote = format!(
+                    "Showing the top {top} of {} benchmarks, use --results-top to show more",
+                    results.len()
+                );
+                (&results[..top], Some(footnote))
+            }
+            _ => (results, None),
+        }
+    }
+}
+
 fn format_with_thousands_sep(n: u64) -> String {
     let s = n.to_string();
     let mut result = String::new();
@@ -131,8 +246,8 @@ fn build_table_with_style<T: Tabled>(rows: &[T], instrument: &str, icon: Icon) -
     table.to_string()
 }
 
-fn build_simulation_table(results: &[&FetchLocalRunBenchmarkResult]) -> String {
-    let rows: Vec<SimulationRow> = results
+fn simulation_rows(results: &[&FetchLocalRunBenchmarkResult]) -> Vec<SimulationRow> {
+    results
         .iter()
         .map(|result| {
             let (instructions, cache, memory, sys_time) = result
@@ -172,16 +287,11 @@ fn build_simulation_table(results: &[&FetchLocalRunBenchmarkResult]) -> String {
                 sys_time,
             }
         })
-        .collect();
-    build_table_with_style(
-        &rows,
-        ExecutorName::Valgrind.label(),
-        ExecutorName::Valgrind.icon(),
-    )
+        .collect()
 }
 
-fn build_walltime_table(results: &[&FetchLocalRunBenchmarkResult]) -> String {
-    let rows: Vec<WalltimeRow> = results
+fn walltime_rows(results: &[&FetchLocalRunBenchmarkResult]) -> Vec<WalltimeRow> {
+    results
         .iter()
         .map(|result| {
             let (time_best, iterations, rel_stdev, run_time) = if let Some(wt) = &result.walltime {
@@ -214,16 +324,11 @@ fn build_walltime_table(results: &[&FetchLocalRunBenchmarkResult]) -> String {
                 run_time,
             }
         })
-        .collect();
-    build_table_with_style(
-        &rows,
-        ExecutorName::WallTime.label(),
-        ExecutorName::WallTime.icon(),
-    )
+        .collect()
 }
 
-fn build_memory_table(results: &[&FetchLocalRunBenchmarkResult]) -> String {
-    let rows: Vec<MemoryRow> = results
+fn memory_rows(results: &[&FetchLocalRunBenchmarkResult]) -> Vec<MemoryRow> {
+    results
         .iter()
         .map(|result| {
             let (peak_memory, total_allocated, alloc_calls) = if let Some(mem) = &result.memory {
@@ -252,15 +357,139 @@ fn build_memory_table(results: &[&FetchLocalRunBenchmarkResult]) -> String {
                 alloc_calls,
             }
         })
-        .collect();
-    build_table_with_style(
-        &rows,
-        ExecutorName::Memory.label(),
-        ExecutorName::Memory.icon(),
-    )
+        .collect()
+}
+
+/// Quote a CSV field when it contains a separator, a quote or a line break.
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+/// Escape the characters of a Markdown table cell that would break the table.
+fn markdown_cell(cell: &str) -> String {
+    cell.replace('\\', "\\\\")
+        .replace('|', "\\|")
+        .replace('\n', " ")
+}
+
+fn plain_fields(fields: Vec<Cow<'_, str>>) -> Vec<String> {
+    fields
+        .iter()
+        .map(|field| console::strip_ansi_codes(field).into_owned())
+        .collect_vec()
+}
+
+/// Plain headers and lines of the rows of an executor, without colors.
+struct PlainTable<'a> {
+    executor: &'a ExecutorName,
+    headers: Vec<String>,
+    lines: Vec<Vec<String>>,
+}
+
+impl<'a> PlainTable<'a> {
+    fn new<T: Tabled>(rows: &[T], executor: &'a ExecutorName) -> Self {
+        Self {
+            executor,
+            headers: plain_fields(T::headers()),
+            lines: rows.iter().map(|row| plain_fields(row.fields())).collect(),
+        }
+    }
+
+    fn to_markdown(&self) -> String {
+        let markdown_line =
+            |line: &[String]| format!("| {} |", line.iter().map(|c| markdown_cell(c)).join(" | "));
+        let separator = format!("|{}", " --- |".repeat(self.headers.len()));
+        let mut output = vec![format!("### {}", self.executor.label()), String::new()];
+        output.push(markdown_line(&self.headers));
+        output.push(separator);
+        output.extend(self.lines.iter().map(|line| markdown_line(line)));
+        output.join("\n")
+    }
+}
+
+/// Render the tables of all the executors as a single CSV table, with a leading executor column.
+/// The columns are the union of the ones of the executors, left empty for the others.
+fn build_csv(tables: &[PlainTable<'_>]) -> String {
+    let mut headers: Vec<&str> = Vec::new();
+    for header in tables.iter().flat_map(|table| &table.headers) {
+        if !headers.contains(&header.as_str()) {
+            headers.push(header);
+        }
+    }
+    let headers = &headers;
+
+    let header_line = std::iter::once("Executor")
+        .chain(headers.iter().copied())
+        .map(csv_field)
+        .join(",");
+    let lines = tables.iter().flat_map(|table| {
+        table.lines.iter().map(move |line| {
+            let cells = headers.iter().map(|header| {
+                table
+                    .headers
+                    .iter()
+                    .position(|h| h == header)
+                    .map_or("", |i| line[i].as_str())
+            });
+            std::iter::once(table.executor.label())
+                .chain(cells)
+                .map(csv_field)
+                .join(",")
+        })
+    });
+    std::iter::once(header_line).chain(lines).join("\n")
+}
+
+/// Collects the tables of the executors, with the same columns as the terminal tables, and
+/// renders them in `format`.
+struct TablesBuilder<'a> {
+    format: ResultsFormat,
+    tables: Vec<String>,
+    plain_tables: Vec<PlainTable<'a>>,
+}
+
+impl<'a> TablesBuilder<'a> {
+    fn new(format: ResultsFormat) -> Self {
+        Self {
+            format,
+            tables: Vec::new(),
+            plain_tables: Vec::new(),
+        }
+    }
+
+    fn push<T: Tabled>(&mut self, rows: &[T], executor: &'a ExecutorName) {
+        match self.format {
+            ResultsFormat::Table => self.tables.push(build_table_with_style(
+                rows,
+                executor.label(),
+                executor.icon(),
+            )),
+            ResultsFormat::Csv => self.plain_tables.push(PlainTable::new(rows, executor)),
+            ResultsFormat::Markdown => self
+                .tables
+                .push(PlainTable::new(rows, executor).to_markdown()),
+        }
+    }
+
+    fn build(self) -> String {
+        match self.format {
+            ResultsFormat::Table => self.tables.join("\n"),
+            ResultsFormat::Csv => build_csv(&self.plain_tables),
+            ResultsFormat::Markdown => self.tables.join("\n\n"),
+        }
+    }
 }
 
-pub fn build_benchmark_table(results: &[FetchLocalRunBenchmarkResult]) -> String {
+/// Render the results of a run in `format`, with one table per instrument, merged into a single
+/// one in CSV.
+pub fn build_benchmark_table(
+    results: &[FetchLocalRunBenchmarkResult],
+    format: ResultsFormat,
+) -> String {
     // Group results by executor
     let mut grouped: HashMap<&ExecutorName, Vec<&FetchLocalRunBenchmarkResult>> = HashMap::new();
     for result in results {
@@ -277,22 +506,18 @@ pub fn build_benchmark_table(results: &[FetchLocalRunBenchmarkResult]) -> String
         ExecutorName::Memory,
     ];
 
-    let mut output = String::new();
+    let mut tables = TablesBuilder::new(format);
     for executor in &executor_order {
         if let Some(executor_results) = grouped.get(executor) {
-            if !output.is_empty() {
-                output.push('\n');
+            match executor {
+                ExecutorName::Valgrind => tables.push(&simulation_rows(executor_results), executor),
+                ExecutorName::WallTime => tables.push(&walltime_rows(executor_results), executor),
+                ExecutorName::Memory => tables.push(&memory_rows(executor_results), executor),
             }
-            let table = match executor {
-                ExecutorName::Valgrind => build_simulation_table(executor_results),
-                ExecutorName::WallTime => build_walltime_table(executor_results),
-                ExecutorName::Memory => build_memory_table(executor_results),
-            };
-            output.push_str(&table);
         }
     }
 
-    output
+    tables.build()
 }
 
 pub fn build_detailed_summary(result: &FetchLocalRunBenchmarkResult) -> String {
@@ -345,7 +570,12 @@ struct ComparisonRow {
     status: String,
 }
 
-pub fn build_comparison_table(results: &[CompareRunsBenchmarkResult]) -> String {
+/// Render the comparison with a base run in `format`, with one table per instrument, merged into
+/// a single one in CSV.
+pub fn build_comparison_table(
+    results: &[CompareRunsBenchmarkResult],
+    format: ResultsFormat,
+) -> String {
     let mut grouped: HashMap<&ExecutorName, Vec<&CompareRunsBenchmarkResult>> = HashMap::new();
     for result in results {
         grouped
@@ -360,12 +590,9 @@ pub fn build_comparison_table(results: &[CompareRunsBenchmarkResult]) -> String
         ExecutorName::Memory,
     ];
 
-    let mut output = String::new();
+    let mut tables = TablesBuilder::new(format);
     for executor in &executor_order {
         if let Some(executor_results) = grouped.get(executor) {
-            if !output.is_empty() {
-                output.push('\n');
-            }
             let rows: Vec<ComparisonRow> = executor_results
                 .iter()
                 .map(|result| {
@@ -416,22 +643,19 @@ pub fn build_comparison_table(results: &[CompareRunsBenchmarkResult]) -> String
                 })
                 .collect();
 
-            output.push_str(&build_table_with_style(
-                &rows,
-                executor.label(),
-                executor.icon(),
-            ));
+            tables.push(&rows, executor);
         }
     }
 
-    output
+    tables.build()
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::api_client::{
-        FetchLocalRunBenchmark, MemoryResult, TimeDistribution, ValgrindResult, WallTimeResult,
+        BenchmarkReportStatus, CompareRunsBenchmark, FetchLocalRunBenchmark, MemoryResult,
+        TimeDistribution, ValgrindResult, WallTimeResult,
     };
 
     #[test]
@@ -538,7 +762,7 @@ mod tests {
             },
         ];
 
-        let table = build_benchmark_table(&results);
+        let table = build_benchmark_table(&results, ResultsFormat::Table);
 
         // Strip ANSI codes for readable snapshot
         let table = console::strip_ansi_codes(&table).to_string();
@@ -609,4 +833,234 @@ mod tests {
         let summary = console::strip_ansi_codes(&summary).to_string();
         insta::assert_snapshot!(summary, @"benchmark_mem: peak 1 MB (total allocated: 5 MB, 500 allocations)");
     }
+
+    #[test]
+    fn test_benchmark_table_labels_each_mode() {
+        let result = |executor: ExecutorName| FetchLocalRunBenchmarkResult {
+            benchmark: FetchLocalRunBenchmark {
+                name: "bench_parse".to_string(),
+                executor,
+            },
+            value: 0.001234,
+            issues: None,
+            valgrind: None,
+            walltime: None,
+            memory: None,
+        };
+        let results = vec![
+            result(ExecutorName::WallTime),
+            result(ExecutorName::Valgrind),
+        ];
+
+        let table = build_benchmark_table(&results, ResultsFormat::Table);
+        let table = console::strip_ansi_codes(&table).to_string();
+
+        // The same benchmark shows up once per mode, under that mode's header
+        let simulation = table.find(ExecutorName::Valgrind.label()).unwrap();
+        let walltime = table.find(ExecutorName::WallTime.label()).unwrap();
+        assert!(simulation < walltime);
+        let rows: Vec<usize> = table.match_indices("bench_parse").map(|(i, _)| i).collect();
+        assert_eq!(rows.len(), 2);
+        assert!(simulation < rows[0] && rows[0] < walltime && walltime < rows[1]);
+    }
+
+    fn run_result(name: &str, value: f64) -> FetchLocalRunBenchmarkResult {
+        FetchLocalRunBenchmarkResult {
+            benchmark: FetchLocalRunBenchmark {
+                name: name.to_string(),
+                executor: ExecutorName::WallTime,
+            },
+            value,
+            issues: None,
+            valgrind: None,
+            walltime: None,
+            memory: None,
+        }
+    }
+
+    fn sorted_names(sort: Option<ResultsSort>) -> Vec<String> {
+        let mut results = vec![
+            run_result("b_fast", 0.001),
+            run_result("c_slow", 0.5),
+            run_result("a_medium", 0.01),
+            run_result("d_slow", 0.5),
+        ];
+        let view = ResultsView {
+            sort,
+            top: None,
+            ..Default::default()
+        };
+        let (shown, footnote) = view.apply(&mut results);
+        assert!(footnote.is_none());
+        shown.iter().map(|r| r.benchmark.name.clone()).collect()
+    }
+
+    #[test]
+    fn test_results_view_keeps_report_order_by_default() {
+        assert_eq!(
+            sorted_names(None),
+            vec!["b_fast", "c_slow", "a_medium", "d_slow"]
+        );
+    }
+
+    #[test]
+    fn test_results_view_sorts_by_time_keeping_ties_in_report_order() {
+        assert_eq!(
+            sorted_names(Some(ResultsSort::Time)),
+            vec!["c_slow", "d_slow", "a_medium", "b_fast"]
+        );
+    }
+
+    #[test]
+    fn test_results_view_sorts_by_name() {
+        assert_eq!(
+            sorted_names(Some(ResultsSort::Name)),
+            vec!["a_medium", "b_fast", "c_slow", "d_slow"]
+        );
+    }
+
+    #[test]
+    fn test_results_view_sorts_single_run_by_impact_as_time() {
+        assert_eq!(
+            sorted_names(Some(ResultsSort::Impact)),
+            sorted_names(Some(ResultsSort::Time))
+        );
+    }
+
+    #[test]
+    fn test_results_view_sorts_comparison_by_impact() {
+        let comparison_result = |name: &str, change: Option<f64>| CompareRunsBenchmarkResult {
+            value: Some(0.01),
+            base_value: change.map(|_| 0.01),
+            change,
+            category: ResultComparisonCategory::Untouched,
+            status: BenchmarkReportStatus::NoChange,
+            benchmark: CompareRunsBenchmark {
+                name: name.to_string(),
+                executor: ExecutorName::WallTime,
+            },
+            result: None,
+        };
+        let mut results = vec![
+            comparison_result("new", None),
+            comparison_result("improved", Some(0.2)),
+            comparison_result("regressed", Some(-0.3)),
+            comparison_result("unchanged", Some(0.0)),
+        ];
+
+        let view = ResultsView {
+            sort: Some(ResultsSort::Impact),
+            top: None,
+            ..Default::default()
+        };
+        let (shown, _) = view.apply(&mut results);
+
+        let names = shown.iter().map(|r| r.name()).collect::<Vec<_>>();
+        assert_eq!(names, vec!["regressed", "unchanged", "improved", "new"]);
+    }
+
+    #[test]
+    fn test_results_view_truncates_with_footnote() {
+        let mut results = vec![
+            run_result("b_fast", 0.001),
+            run_result("c_slow", 0.5),
+            run_result("a_medium", 0.01),
+        ];
+        let view = ResultsView {
+            sort: Some(ResultsSort::Time),
+            top: Some(2),
+            ..Default::default()
+        };
+
+        let (shown, footnote) = view.apply(&mut results);
+
+        let names = shown.iter().map(|r| r.name()).collect::<Vec<_>>();
+        assert_eq!(names, vec!["c_slow", "a_medium"]);
+        assert_eq!(
+            footnote.as_deref(),
+            Some("Showing the top 2 of 3 benchmarks, use --results-top to show more")
+        );
+
+        let view = ResultsView {
+            sort: None,
+            top: Some(3),
+            ..Default::default()
+        };
+        assert!(view.apply(&mut results).1.is_none());
+    }
+
+    #[test]
+    fn test_csv_quotes_names_with_commas() {
+        let results = vec![
+            run_result("parse, small input", 0.001),
+            run_result("say \"hello\"", 0.5),
+        ];
+
+        let csv = build_benchmark_table(&results, ResultsFormat::Csv);
+
+        insta::assert_snapshot!(csv, @r#"
+        Executor,Benchmark,Time (best),Iterations,StdDev,Total time
+        Walltime,"parse, small input",1.00 ms,-,-,-
+        Walltime,"say ""hello""",500.00 ms,-,-,-
+        "#);
+    }
+
+    #[test]
+    fn test_csv_has_a_single_header_for_all_executors() {
+        let mut results = vec![
+            run_result("bench_walltime", 0.001),
+            run_result("bench_simulation", 0.002),
+            run_result("bench_memory", 2048.0),
+        ];
+        results[1].benchmark.executor = ExecutorName::Valgrind;
+        results[2].benchmark.executor = ExecutorName::Memory;
+
+        let csv = build_benchmark_table(&results, ResultsFormat::Csv);
+
+        insta::assert_snapshot!(csv, @r"
+        Executor,Benchmark,Time,Instr.,Cache,Memory,Sys. Time,Time (best),Iterations,StdDev,Total time,Peak memory,Total allocated,Allocations
+        CPU Simulation,bench_simulation,2.00 ms,-,-,-,-,,,,,,,
+        Walltime,bench_walltime,,,,,,1.00 ms,-,-,-,,,
+        Memory,bench_memory,,,,,,,,,,2 KB,-,-
+        ");
+    }
+
+    #[test]
+    fn test_markdown_escapes_pipes() {
+        let results = vec![run_result("bench[a|b]", 0.001)];
+
+        let markdown = build_benchmark_table(&results, ResultsFormat::Markdown);
+
+        insta::assert_snapshot!(markdown, @r"
+        ### Walltime
+
+        | Benchmark | Time (best) | Iterations | StdDev | Total time |
+        | --- | --- | --- | --- | --- |
+        | bench[a\|b] | 1.00 ms | - | - | - |
+        ");
+    }
+
+    #[test]
+    fn test_comparison_csv_has_no_colors() {
+        let results = vec![CompareRunsBenchmarkResult {
+            value: Some(0.01),
+            base_value: Some(0.0125),
+            change: Some(0.2),
+            category: ResultComparisonCategory::Improvement,
+            status: BenchmarkReportStatus::NoChange,
+            benchmark: CompareRunsBenchmark {
+                name: "bench_parse".to_string(),
+                executor: ExecutorName::WallTime,
+            },
+            result: None,
+        }];
+
+        let csv = build_comparison_table(&results, ResultsFormat::Csv);
+
+        assert_eq!(
+            csv,
+            "Executor,Benchmark,Base,Head,Change,Status\n\
+            Walltime,bench_parse,12.50 ms,10.00 ms,+20.0%,Improvement"
+        );
+    }
 }
@@ -20,6 +20,9 @@ pub struct UploadMetadata {
     pub run_part: Option<RunPart>,
     pub commit_hash: String,
     pub allow_empty: bool,
+    /// Run-scoped ID shared by all the requests of a CLI invocation
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub correlation_id: Option<String>,
     #[serde(flatten)]
     pub run_environment_metadata: RunEnvironmentMetadata,
 }
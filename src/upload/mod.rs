@@ -6,6 +6,7 @@ mod run_index_state;
 mod upload_metadata;
 mod uploader;
 
+pub use benchmark_display::{ResultsFormat, ResultsSort, ResultsView};
 pub use interfaces::*;
 pub use profile_archive::ProfileArchive;
 pub use run_index_state::RunIndexState;
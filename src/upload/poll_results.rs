@@ -1,24 +1,83 @@
+use std::fmt;
 use std::future::Future;
+use std::sync::LazyLock;
 use std::time::Duration;
 
 use console::style;
 use tokio::time::{Instant, sleep};
 
 use super::benchmark_display::{
-    self, build_benchmark_table, build_comparison_table, build_detailed_summary,
+    self, ResultsFormat, ResultsView, build_benchmark_table, build_comparison_table,
+    build_detailed_summary,
 };
 use crate::api_client::{
-    CodSpeedAPIClient, CompareRunsOutcome, CompareRunsResponse, CompareRunsVars,
-    FetchLocalRunResponse, FetchLocalRunVars, RunStatus,
+    CodSpeedAPIClient, CompareRunsBenchmarkResult, CompareRunsOutcome, CompareRunsResponse,
+    CompareRunsVars, FetchLocalRunResponse, FetchLocalRunVars, ResultComparisonCategory, RunStatus,
 };
+use crate::failure::FailureClass;
 use crate::local_logger::icons::Icon;
-use crate::local_logger::{IS_TTY, start_spinner, stop_spinner};
+use crate::local_logger::{IS_TTY, format_elapsed, start_spinner, stop_spinner};
 use crate::prelude::*;
 
 use super::UploadResult;
 
-const RUN_PROCESSING_MAX_DURATION: Duration = Duration::from_secs(60 * 5); // 5 minutes
-const POLLING_INTERVAL: Duration = Duration::from_secs(1);
+const DEFAULT_POLL_TIMEOUT_SECS: u64 = 60 * 5; // 5 minutes
+const DEFAULT_POLL_INTERVAL_MS: u64 = 1000;
+
+/// How long to wait for the backend to process a run, and how often to ask.
+#[derive(Debug, Clone, Copy)]
+struct PollSettings {
+    timeout: Duration,
+    interval: Duration,
+}
+
+/// Read from `CODSPEED_POLL_TIMEOUT` (seconds) and `CODSPEED_POLL_INTERVAL_MS`.
+static POLL_SETTINGS: LazyLock<PollSettings> = LazyLock::new(|| {
+    PollSettings::from_env_values(
+        std::env::var("CODSPEED_POLL_TIMEOUT").ok().as_deref(),
+        std::env::var("CODSPEED_POLL_INTERVAL_MS").ok().as_deref(),
+    )
+});
+
+impl PollSettings {
+    /// Invalid or zero values fall back to the defaults.
+    fn from_env_values(timeout: Option<&str>, interval: Option<&str>) -> Self {
+        let parse = |value: Option<&str>| {
+            value
+                .and_then(|v| v.trim().parse::<u64>().ok())
+                .filter(|v| *v > 0)
+        };
+        Self {
+            timeout: Duration::from_secs(parse(timeout).unwrap_or(DEFAULT_POLL_TIMEOUT_SECS)),
+            interval: Duration::from_millis(parse(interval).unwrap_or(DEFAULT_POLL_INTERVAL_MS)),
+        }
+    }
+}
+
+/// The backend did not finish processing the run before the poll timeout.
+#[derive(Debug)]
+pub struct PollTimeoutError {
+    pub timeout: Duration,
+    /// URL of the run, if the backend returned it while polling
+    pub run_url: Option<String>,
+}
+
+impl fmt::Display for PollTimeoutError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Timed out after {} waiting for the results to be processed. \
+            The run may still complete on CodSpeed, check it later",
+            format_elapsed(self.timeout)
+        )?;
+        match &self.run_url {
+            Some(url) => write!(f, " at {url}"),
+            None => write!(f, "."),
+        }
+    }
+}
+
+impl std::error::Error for PollTimeoutError {}
 
 /// Options controlling poll_results display behavior.
 #[derive(Debug, Clone)]
@@ -27,13 +86,25 @@ pub struct PollResultsOptions {
     pub output_json: bool,
     /// If set, compare the uploaded run against this base run ID
     pub base_run_id: Option<String>,
+    /// If true, fail with [`FailureClass::Regression`] when a benchmark regressed against the base
+    /// run
+    pub fail_on_regression: bool,
+    /// Order and truncation of the results table
+    pub results_view: ResultsView,
 }
 
 impl PollResultsOptions {
-    pub fn new(output_json: bool, base_run_id: Option<String>) -> Self {
+    pub fn new(
+        output_json: bool,
+        base_run_id: Option<String>,
+        fail_on_regression: bool,
+        results_view: ResultsView,
+    ) -> Self {
         Self {
             output_json,
             base_run_id,
+            fail_on_regression,
+            results_view,
         }
     }
 }
@@ -45,7 +116,8 @@ pub async fn poll_results(
 ) -> Result<()> {
     if let Some(base_run_id) = &options.base_run_id {
         start_spinner("Waiting for results");
-        let compare_result = poll_compare_runs(api_client, upload_result, base_run_id).await;
+        let compare_result =
+            poll_compare_runs(api_client, upload_result, base_run_id, *POLL_SETTINGS).await;
         stop_spinner();
 
         match compare_result? {
@@ -67,36 +139,54 @@ pub async fn poll_results(
     }
 
     start_spinner("Waiting for results");
-    let response = poll_local_run(api_client, upload_result).await;
+    let response = poll_local_run(api_client, upload_result, *POLL_SETTINGS).await;
     stop_spinner();
 
-    display_single_run_results(upload_result, options, response?).await
+    display_single_run_results(upload_result, options, response?).await?;
+
+    // The gate can't be evaluated without a comparison, which must not pass silently
+    ensure!(
+        !options.fail_on_regression,
+        "--fail-on-regression could not compare the results against the base run"
+    );
+    Ok(())
 }
 
 /// Poll using `fetch` until `get_status` returns neither Pending nor Processing, then return
 /// the response or an error if the status is Failure or polling times out.
 ///
+/// On timeout, the error is a [`PollTimeoutError`] holding the last URL returned by `get_url`.
 /// If `fetch` returns `Ok(None)`, polling stops immediately and `Ok(None)` is returned.
 async fn poll_until_processed<T, Fut>(
     fetch: impl Fn() -> Fut,
     get_status: impl Fn(&T) -> &RunStatus,
+    get_url: impl Fn(&T) -> &str,
+    settings: PollSettings,
 ) -> Result<Option<T>>
 where
     Fut: Future<Output = Result<Option<T>>>,
 {
     let start = Instant::now();
+    let mut run_url = None;
     debug!("Waiting for results to be processed...");
 
     loop {
-        if start.elapsed() > RUN_PROCESSING_MAX_DURATION {
-            bail!("Polling results timed out after 5 minutes. Please try again later.");
+        if start.elapsed() > settings.timeout {
+            return Err(PollTimeoutError {
+                timeout: settings.timeout,
+                run_url,
+            }
+            .into());
         }
 
         let Some(response) = fetch().await? else {
             return Ok(None);
         };
         match get_status(&response) {
-            RunStatus::Pending | RunStatus::Processing => sleep(POLLING_INTERVAL).await,
+            RunStatus::Pending | RunStatus::Processing => {
+                run_url = Some(get_url(&response).to_owned());
+                sleep(settings.interval).await
+            }
             RunStatus::Failure => bail!("Run failed to be processed, try again in a few minutes"),
             _ => return Ok(Some(response)),
         }
@@ -106,6 +196,7 @@ where
 async fn poll_local_run(
     api_client: &CodSpeedAPIClient,
     upload_result: &UploadResult,
+    settings: PollSettings,
 ) -> Result<FetchLocalRunResponse> {
     let vars = FetchLocalRunVars {
         owner: upload_result.owner.clone(),
@@ -116,6 +207,8 @@ async fn poll_local_run(
     poll_until_processed(
         || async { api_client.fetch_local_run(vars.clone()).await.map(Some) },
         |r: &FetchLocalRunResponse| &r.run.status,
+        |r: &FetchLocalRunResponse| &r.run.url,
+        settings,
     )
     .await?
     .ok_or_else(|| anyhow::anyhow!("unexpected None response from fetch_local_run"))
@@ -125,6 +218,7 @@ async fn poll_compare_runs(
     api_client: &CodSpeedAPIClient,
     upload_result: &UploadResult,
     base_run_id: &str,
+    settings: PollSettings,
 ) -> Result<CompareRunsOutcome> {
     let vars = CompareRunsVars {
         owner: upload_result.owner.clone(),
@@ -134,18 +228,26 @@ async fn poll_compare_runs(
     };
 
     let start = Instant::now();
+    let mut run_url = None;
     debug!("Waiting for results to be processed...");
 
     loop {
-        if start.elapsed() > RUN_PROCESSING_MAX_DURATION {
-            bail!("Polling results timed out after 5 minutes. Please try again later.");
+        if start.elapsed() > settings.timeout {
+            return Err(PollTimeoutError {
+                timeout: settings.timeout,
+                run_url,
+            }
+            .into());
         }
 
         match api_client.compare_runs(vars.clone()).await? {
             outcome @ (CompareRunsOutcome::BaseRunNotFound
             | CompareRunsOutcome::ExecutorMismatch) => return Ok(outcome),
             CompareRunsOutcome::Success(response) => match &response.comparison.head_run.status {
-                RunStatus::Pending | RunStatus::Processing => sleep(POLLING_INTERVAL).await,
+                RunStatus::Pending | RunStatus::Processing => {
+                    run_url = Some(response.comparison.url.clone());
+                    sleep(settings.interval).await
+                }
                 RunStatus::Failure => {
                     bail!("Run failed to be processed, try again in a few minutes")
                 }
@@ -158,7 +260,7 @@ async fn poll_compare_runs(
 async fn display_single_run_results(
     upload_result: &UploadResult,
     options: &PollResultsOptions,
-    response: FetchLocalRunResponse,
+    mut response: FetchLocalRunResponse,
 ) -> Result<()> {
     if options.output_json {
         log_json!(format!(
@@ -175,12 +277,17 @@ async fn display_single_run_results(
         end_group!();
         start_opened_group!("Benchmark results");
 
-        if response.run.results.len() == 1 {
+        let view = &options.results_view;
+        if response.run.results.len() == 1
+            && view.format == ResultsFormat::Table
+            && view.output.is_none()
+        {
             let summary = build_detailed_summary(&response.run.results[0]);
             info!("{summary}\n");
         } else {
-            let table = build_benchmark_table(&response.run.results);
-            info!("{table}\n");
+            let (shown_results, footnote) = view.apply(&mut response.run.results);
+            let table = build_benchmark_table(shown_results, view.format);
+            print_results_table(view, &table, footnote)?;
         }
 
         if options.output_json {
@@ -217,6 +324,27 @@ async fn display_single_run_results(
     Ok(())
 }
 
+/// Display the results `table` in the logs, unless it is exported: the CSV and Markdown tables
+/// are printed on the standard output, apart from the logs, or written to the `--results-output`
+/// file.
+fn print_results_table(view: &ResultsView, table: &str, footnote: Option<String>) -> Result<()> {
+    match &view.output {
+        Some(path) => {
+            let table = console::strip_ansi_codes(table);
+            std::fs::write(path, format!("{table}\n")).with_context(|| {
+                format!("Failed to write the results table to {}", path.display())
+            })?;
+            info!("Results table written to {}", path.display());
+        }
+        None if view.format == ResultsFormat::Table => info!("{table}\n"),
+        None => println!("{table}"),
+    }
+    if let Some(footnote) = footnote {
+        info!("{}\n", style(footnote).dim());
+    }
+    Ok(())
+}
+
 fn warn_callgraph_failures(names: &[&str]) {
     if names.is_empty() {
         return;
@@ -241,9 +369,9 @@ fn show_comparison_suggestion(run_id: &str) {
 async fn display_comparison_results(
     upload_result: &UploadResult,
     options: &PollResultsOptions,
-    response: CompareRunsResponse,
+    mut response: CompareRunsResponse,
 ) -> Result<()> {
-    let comparison = &response.comparison;
+    let comparison = &mut response.comparison;
 
     if options.output_json {
         log_json!(format!(
@@ -281,8 +409,10 @@ async fn display_comparison_results(
             info!("{arrow} Impact: {impact_text}");
         }
 
-        let table = build_comparison_table(&comparison.result_comparisons);
-        info!("{table}\n");
+        let view = &options.results_view;
+        let (shown_results, footnote) = view.apply(&mut comparison.result_comparisons);
+        let table = build_comparison_table(shown_results, view.format);
+        print_results_table(view, &table, footnote)?;
 
         if options.output_json {
             for result in &comparison.result_comparisons {
@@ -316,5 +446,145 @@ async fn display_comparison_results(
         show_comparison_suggestion(&upload_result.run_id);
     }
 
+    if options.fail_on_regression {
+        check_regressions(&comparison.result_comparisons)?;
+    }
+
     Ok(())
 }
+
+/// Fail with [`FailureClass::Regression`] when a benchmark regressed. The backend already
+/// categorizes the changes beyond the allowed regression of the project as regressions, and the
+/// acknowledged ones are not.
+fn check_regressions(results: &[CompareRunsBenchmarkResult]) -> Result<()> {
+    let regressed = results
+        .iter()
+        .filter(|r| r.category == ResultComparisonCategory::Regression)
+        .map(|r| match r.change {
+            Some(change) => format!("{} ({:+.1}%)", r.benchmark.name, change * 100.0),
+            None => r.benchmark.name.clone(),
+        })
+        .collect_vec();
+    if regressed.is_empty() {
+        return Ok(());
+    }
+    Err(FailureClass::Regression.wrap(anyhow!(
+        "{} benchmark{} regressed beyond the allowed regression: {}",
+        regressed.len(),
+        if regressed.len() == 1 { "" } else { "s" },
+        regressed.join(", ")
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api_client::{BenchmarkReportStatus, CompareRunsBenchmark};
+    use crate::executor::ExecutorName;
+    use std::cell::Cell;
+
+    struct PendingRun {
+        status: RunStatus,
+        url: String,
+    }
+
+    #[test]
+    fn poll_settings_fall_back_to_defaults() {
+        let settings = PollSettings::from_env_values(None, Some("0"));
+        assert_eq!(settings.timeout, Duration::from_secs(300));
+        assert_eq!(settings.interval, Duration::from_secs(1));
+
+        let settings = PollSettings::from_env_values(Some("30"), Some("250"));
+        assert_eq!(settings.timeout, Duration::from_secs(30));
+        assert_eq!(settings.interval, Duration::from_millis(250));
+    }
+
+    #[tokio::test]
+    async fn times_out_when_results_never_arrive() {
+        let calls = Cell::new(0);
+        let settings = PollSettings {
+            timeout: Duration::from_millis(50),
+            interval: Duration::from_millis(5),
+        };
+
+        let err = poll_until_processed(
+            || {
+                calls.set(calls.get() + 1);
+                async {
+                    Ok(Some(PendingRun {
+                        status: RunStatus::Processing,
+                        url: "https://codspeed.io/owner/repo/runs/123".to_string(),
+                    }))
+                }
+            },
+            |r: &PendingRun| &r.status,
+            |r: &PendingRun| &r.url,
+            settings,
+        )
+        .await
+        .unwrap_err();
+
+        assert!(calls.get() > 1);
+        let timeout = err.downcast_ref::<PollTimeoutError>().unwrap();
+        assert_eq!(timeout.timeout, settings.timeout);
+        assert_eq!(
+            timeout.run_url.as_deref(),
+            Some("https://codspeed.io/owner/repo/runs/123")
+        );
+        assert!(err.to_string().contains("may still complete"));
+    }
+
+    fn comparison_result(
+        name: &str,
+        change: Option<f64>,
+        category: ResultComparisonCategory,
+    ) -> CompareRunsBenchmarkResult {
+        CompareRunsBenchmarkResult {
+            value: Some(0.01),
+            base_value: change.map(|_| 0.01),
+            change,
+            category,
+            status: BenchmarkReportStatus::NoChange,
+            benchmark: CompareRunsBenchmark {
+                name: name.to_string(),
+                executor: ExecutorName::WallTime,
+            },
+            result: None,
+        }
+    }
+
+    #[test]
+    fn changes_within_the_allowed_regression_pass() {
+        let results = vec![
+            comparison_result("improved", Some(0.2), ResultComparisonCategory::Improvement),
+            comparison_result("slower", Some(-0.03), ResultComparisonCategory::Untouched),
+            comparison_result(
+                "acknowledged",
+                Some(-0.4),
+                ResultComparisonCategory::Acknowledged,
+            ),
+            comparison_result("new", None, ResultComparisonCategory::New),
+        ];
+
+        assert!(check_regressions(&results).is_ok());
+    }
+
+    #[test]
+    fn regressions_fail_with_their_exit_code() {
+        let results = vec![
+            comparison_result("improved", Some(0.2), ResultComparisonCategory::Improvement),
+            comparison_result("parse", Some(-0.25), ResultComparisonCategory::Regression),
+            comparison_result("render", Some(-0.1), ResultComparisonCategory::Regression),
+        ];
+
+        let err = check_regressions(&results).unwrap_err();
+        assert_eq!(
+            crate::failure::exit_code(&err),
+            FailureClass::Regression.exit_code()
+        );
+        assert_eq!(
+            err.to_string(),
+            "2 benchmarks regressed beyond the allowed regression: parse (-25.0%), render (-10.0%)"
+        );
+    }
+}
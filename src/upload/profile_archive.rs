@@ -14,6 +14,7 @@ pub enum ProfileArchiveContent {
     CompressedInMemory { data: Vec<u8> },
     UncompressedOnDisk { path: PathBuf },
     CompressedOnDisk { path: PathBuf },
+    ZstdCompressedOnDisk { path: PathBuf },
 }
 
 impl ProfileArchive {
@@ -56,6 +57,19 @@ impl ProfileArchive {
             content: ProfileArchiveContent::CompressedOnDisk { path },
         })
     }
+
+    pub fn new_zstd_compressed_on_disk(path: PathBuf) -> Result<Self> {
+        let metadata = std::fs::metadata(&path)?;
+        if !metadata.is_file() {
+            return Err(anyhow!("The provided path is not a file"));
+        }
+        let buffer = std::fs::read(&path)?;
+        let hash = general_purpose::STANDARD.encode(md5::compute(&buffer).0);
+        Ok(ProfileArchive {
+            hash,
+            content: ProfileArchiveContent::ZstdCompressedOnDisk { path },
+        })
+    }
 }
 
 impl ProfileArchiveContent {
@@ -63,7 +77,8 @@ impl ProfileArchiveContent {
         match &self {
             ProfileArchiveContent::CompressedInMemory { data } => Ok(data.len() as u64),
             ProfileArchiveContent::UncompressedOnDisk { path }
-            | ProfileArchiveContent::CompressedOnDisk { path } => {
+            | ProfileArchiveContent::CompressedOnDisk { path }
+            | ProfileArchiveContent::ZstdCompressedOnDisk { path } => {
                 let metadata = tokio::fs::metadata(path).await?;
                 Ok(metadata.len())
             }
@@ -74,6 +89,7 @@ impl ProfileArchiveContent {
         match self {
             ProfileArchiveContent::CompressedInMemory { .. } => Some("gzip".to_string()),
             ProfileArchiveContent::CompressedOnDisk { .. } => Some("gzip".to_string()),
+            ProfileArchiveContent::ZstdCompressedOnDisk { .. } => Some("zstd".to_string()),
             _ => None,
         }
     }
@@ -82,7 +98,8 @@ impl ProfileArchiveContent {
 impl Drop for ProfileArchiveContent {
     fn drop(&mut self) {
         if let ProfileArchiveContent::UncompressedOnDisk { path }
-        | ProfileArchiveContent::CompressedOnDisk { path } = self
+        | ProfileArchiveContent::CompressedOnDisk { path }
+        | ProfileArchiveContent::ZstdCompressedOnDisk { path } = self
         {
             if path.exists() {
                 let _ = std::fs::remove_file(path);
@@ -42,6 +42,7 @@ mod tests {
             run_environment: RunEnvironment::GithubActions,
             commit_hash: "5bd77cb0da72bef094893ed45fb793ff16ecfbe3".into(),
             allow_empty: false,
+            correlation_id: None,
             run_environment_metadata: RunEnvironmentMetadata {
                 ref_: "refs/pull/29/merge".into(),
                 head_ref: Some("chore/native-action-runner".into()),
@@ -121,6 +122,7 @@ mod tests {
             run_environment: RunEnvironment::Local,
             commit_hash: "aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa".into(),
             allow_empty: false,
+            correlation_id: None,
             run_environment_metadata: RunEnvironmentMetadata {
                 ref_: "aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa".into(),
                 head_ref: None,
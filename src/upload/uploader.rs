@@ -2,13 +2,14 @@ use crate::api_client::CodSpeedAPIClient;
 use crate::executor::ExecutionContext;
 use crate::executor::ExecutorName;
 use crate::executor::Orchestrator;
+use crate::failure::FailureClass;
 use crate::run_environment::RunEnvironment;
 use crate::upload::{UploadError, profile_archive::ProfileArchiveContent};
 use crate::{
     prelude::*,
-    request_client::{REQUEST_CLIENT, STREAMING_CLIENT, upload_backoff},
+    request_client::{REQUEST_CLIENT, STREAMING_CLIENT, request_error, upload_backoff},
 };
-use async_compression::tokio::write::GzipEncoder;
+use async_compression::tokio::write::{GzipEncoder, ZstdEncoder};
 use console::style;
 use reqwest::StatusCode;
 use reqwest_retry::{
@@ -20,6 +21,7 @@ use std::time::SystemTime;
 use tokio::fs::File;
 use tokio::io::AsyncWriteExt;
 use tokio_tar::Builder;
+use url::Url;
 
 use super::interfaces::{UploadData, UploadMetadata};
 use super::profile_archive::ProfileArchive;
@@ -52,17 +54,41 @@ async fn calculate_folder_size(path: &std::path::Path) -> Result<u64> {
     Ok(total_size)
 }
 
+/// Write a zstd-compressed tar archive of `profile_folder` to `archive_path`.
+///
+/// Entries keep their path relative to the profile folder, as expected by the backend.
+async fn write_zstd_archive(
+    profile_folder: &std::path::Path,
+    archive_path: &std::path::Path,
+) -> Result<()> {
+    let file = File::create(archive_path).await?;
+    let mut tar = Builder::new(ZstdEncoder::new(file));
+    tar.append_dir_all(".", profile_folder).await?;
+    let mut zstd_encoder = tar.into_inner().await?;
+    zstd_encoder.shutdown().await?;
+    zstd_encoder.into_inner().sync_all().await?;
+    Ok(())
+}
+
 /// Create a profile archive from the profile folder and return its md5 hash encoded in base64
 ///
-/// For Valgrind, we create a gzip-compressed tar archive of the entire profile folder.
+/// With `compress_artifacts`, we create a zstd-compressed tar archive on disk for every executor.
+/// Otherwise, for Valgrind, we create a gzip-compressed tar archive of the entire profile folder.
 /// For WallTime, we check the folder size and create either a compressed or uncompressed tar archive
 /// based on the MAX_UNCOMPRESSED_PROFILE_SIZE_BYTES threshold.
 async fn create_profile_archive(
     profile_folder: &std::path::Path,
     executor_name: ExecutorName,
+    compress_artifacts: bool,
 ) -> Result<ProfileArchive> {
     let time_start = std::time::Instant::now();
     let profile_archive = match executor_name {
+        _ if compress_artifacts => {
+            debug!("Creating zstd-compressed tar archive on disk");
+            let archive_path = tempfile::NamedTempFile::new()?.into_temp_path().keep()?;
+            write_zstd_archive(profile_folder, &archive_path).await?;
+            ProfileArchive::new_zstd_compressed_on_disk(archive_path)?
+        }
         ExecutorName::Valgrind => {
             debug!("Creating compressed tar archive for Valgrind");
             let enc = GzipEncoder::new(Vec::new());
@@ -126,15 +152,15 @@ async fn create_profile_archive(
 }
 
 async fn retrieve_upload_data(
-    orchestrator: &Orchestrator,
+    upload_url: &Url,
     api_client: &CodSpeedAPIClient,
     upload_metadata: &UploadMetadata,
 ) -> Result<UploadData> {
     let mut upload_request = REQUEST_CLIENT
-        .post(orchestrator.config.upload_url.clone())
+        .post(upload_url.clone())
         .json(&upload_metadata);
-    if let Some(token) = api_client.token() {
-        upload_request = upload_request.header("Authorization", token.to_owned());
+    for (name, value) in api_client.request_headers() {
+        upload_request = upload_request.header(name, value);
     }
 
     let response = upload_request.send().await;
@@ -171,18 +197,22 @@ async fn retrieve_upload_data(
                     upload_metadata.run_environment_metadata.repository
                 );
 
-                bail!(
+                let error = anyhow!(
                     "Failed to retrieve upload data: {}\n  -> {} {}",
                     status,
                     style("Reason:").bold(),
                     // we have to manually apply the style to the error message, because nesting styles is not supported by the console crate: https://github.com/console-rs/console/issues/106
                     style(error_message).red()
                 );
+                if status == StatusCode::UNAUTHORIZED {
+                    return Err(FailureClass::Auth.wrap(error));
+                }
+                return Err(error);
             }
 
             Ok(response.json().await?)
         }
-        Err(err) => Err(err.into()),
+        Err(err) => Err(request_error(err)),
     }
 }
 
@@ -239,7 +269,7 @@ async fn send_streamed_with_retry(
             }
         }
 
-        return Ok(result?);
+        return result.map_err(request_error);
     }
 }
 
@@ -263,10 +293,15 @@ async fn upload_profile_archive(
                 request = request.header("Content-Encoding", encoding);
             }
 
-            request.body(data.clone()).send().await?
+            request
+                .body(data.clone())
+                .send()
+                .await
+                .map_err(request_error)?
         }
         content @ ProfileArchiveContent::UncompressedOnDisk { path }
-        | content @ ProfileArchiveContent::CompressedOnDisk { path } => {
+        | content @ ProfileArchiveContent::CompressedOnDisk { path }
+        | content @ ProfileArchiveContent::ZstdCompressedOnDisk { path } => {
             send_streamed_with_retry(
                 upload_data,
                 path,
@@ -306,8 +341,12 @@ pub async fn upload(
     executor_name: ExecutorName,
     run_part_suffix: BTreeMap<String, Value>,
 ) -> Result<UploadResult> {
-    let profile_archive =
-        create_profile_archive(&execution_context.profile_folder, executor_name.clone()).await?;
+    let profile_archive = create_profile_archive(
+        &execution_context.profile_folder,
+        executor_name.clone(),
+        orchestrator.config.compress_artifacts,
+    )
+    .await?;
 
     debug!(
         "Run Environment provider detected: {:?}",
@@ -332,7 +371,12 @@ pub async fn upload(
     }
 
     debug!("Preparing upload...");
-    let upload_data = retrieve_upload_data(orchestrator, api_client, &upload_metadata).await?;
+    let upload_data = retrieve_upload_data(
+        &orchestrator.config.upload_url,
+        api_client,
+        &upload_metadata,
+    )
+    .await?;
     debug!("runId: {}", upload_data.run_id);
 
     debug!(
@@ -352,7 +396,6 @@ pub async fn upload(
 mod tests {
     use crate::api_client::CodSpeedAPIClient;
     use temp_env::async_with_vars;
-    use url::Url;
 
     use super::*;
     use std::path::PathBuf;
@@ -478,6 +521,39 @@ mod tests {
         }
     }
 
+    #[tokio::test]
+    async fn zstd_archive_round_trips_profile_folder() {
+        use async_compression::tokio::bufread::ZstdDecoder;
+
+        let profile_folder = tempfile::tempdir().unwrap();
+        let files: [(&str, &[u8]); 3] = [
+            ("0-libc.so.6.symbols.map", b"1000 10 malloc\n1010 20 free\n"),
+            ("0-libc.so.6.unwind_data", &[0, 1, 2, 3, 255]),
+            ("perf.metadata", b"{\"version\": 1}"),
+        ];
+        for (name, content) in files {
+            std::fs::write(profile_folder.path().join(name), content).unwrap();
+        }
+
+        let archive = create_profile_archive(profile_folder.path(), ExecutorName::WallTime, true)
+            .await
+            .unwrap();
+        assert_eq!(archive.content.encoding().as_deref(), Some("zstd"));
+        let ProfileArchiveContent::ZstdCompressedOnDisk { path } = &archive.content else {
+            panic!("expected a zstd archive on disk");
+        };
+
+        let unpacked = tempfile::tempdir().unwrap();
+        let file = tokio::io::BufReader::new(File::open(path).await.unwrap());
+        tokio_tar::Archive::new(ZstdDecoder::new(file))
+            .unpack(unpacked.path())
+            .await
+            .unwrap();
+        for (name, content) in files {
+            assert_eq!(std::fs::read(unpacked.path().join(name)).unwrap(), content);
+        }
+    }
+
     /// On-disk archives stream through `send_streamed_with_retry`, which retries
     /// transient failures itself since `STREAMING_CLIENT` has no retry middleware.
     #[tokio::test]
@@ -528,4 +604,117 @@ mod tests {
             "in-memory upload should be attempted 1 + UPLOAD_RETRY_COUNT times"
         );
     }
+
+    /// Answers the upload data to the next request, then exits. Returns the URL and the
+    /// server's join handle, which yields the lowercased head of the received request.
+    fn spawn_mock_upload_endpoint() -> (Url, std::thread::JoinHandle<String>) {
+        use std::io::{Read, Write};
+        use std::net::TcpListener;
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = Url::parse(&format!("http://{}/upload", listener.local_addr().unwrap())).unwrap();
+        let handle = std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = Vec::new();
+            let mut buf = [0u8; 4096];
+            // Read the whole request, so that the client doesn't see the connection reset
+            let head = loop {
+                let n = stream.read(&mut buf).unwrap();
+                assert_ne!(n, 0, "connection closed before the end of the request");
+                request.extend_from_slice(&buf[..n]);
+                let Some(end) = request.windows(4).position(|w| w == b"\r\n\r\n") else {
+                    continue;
+                };
+                let head = String::from_utf8_lossy(&request[..end]).to_lowercase();
+                let content_length = head
+                    .lines()
+                    .find_map(|line| line.strip_prefix("content-length:"))
+                    .map_or(0, |length| length.trim().parse::<usize>().unwrap());
+                if request.len() >= end + 4 + content_length {
+                    break head;
+                }
+            };
+            let body =
+                r#"{"status":"success","uploadUrl":"http://localhost/archive","runId":"run-id"}"#;
+            let response = format!(
+                "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+                body.len()
+            );
+            stream.write_all(response.as_bytes()).unwrap();
+            head
+        });
+        (url, handle)
+    }
+
+    fn upload_metadata() -> UploadMetadata {
+        use crate::run_environment::{RepositoryProvider, RunEnvironmentMetadata, RunEvent};
+        use crate::system::SystemInfo;
+        use crate::upload::{LATEST_UPLOAD_METADATA_VERSION, Runner};
+
+        UploadMetadata {
+            repository_provider: RepositoryProvider::GitHub,
+            version: Some(LATEST_UPLOAD_METADATA_VERSION),
+            tokenless: false,
+            profile_md5: "jp/k05RKuqP3ERQuIIvx4Q==".into(),
+            profile_encoding: None,
+            runner: Runner {
+                name: "codspeed-runner".into(),
+                version: "2.1.0".into(),
+                instruments: vec![],
+                executor: ExecutorName::Valgrind,
+                system_info: SystemInfo::test(),
+            },
+            run_environment: RunEnvironment::Local,
+            commit_hash: "5bd77cb0da72bef094893ed45fb793ff16ecfbe3".into(),
+            allow_empty: false,
+            correlation_id: Some("2f1c8a5e-run".into()),
+            run_environment_metadata: RunEnvironmentMetadata {
+                ref_: "refs/heads/main".into(),
+                head_ref: None,
+                base_ref: None,
+                owner: "CodSpeedHQ".into(),
+                repository: "codspeed".into(),
+                event: RunEvent::Local,
+                gh_data: None,
+                sender: None,
+                gl_data: None,
+                local_data: None,
+                repository_root_path: "/repo/".into(),
+            },
+            run_part: None,
+        }
+    }
+
+    /// The upload request and the GraphQL requests used to poll the results
+    /// must carry the same correlation ID.
+    #[tokio::test]
+    async fn upload_and_api_requests_share_correlation_id() {
+        use crate::api_client::CORRELATION_ID_HEADER;
+
+        let mut api_client = CodSpeedAPIClient::create_test_client();
+        api_client.set_correlation_id("2f1c8a5e-run".into());
+
+        let (url, server) = spawn_mock_upload_endpoint();
+        let upload_data = retrieve_upload_data(&url, &api_client, &upload_metadata())
+            .await
+            .unwrap();
+        let request_head = server.join().unwrap();
+
+        assert_eq!(upload_data.run_id, "run-id");
+        assert!(
+            request_head
+                .lines()
+                .any(|line| line == format!("{CORRELATION_ID_HEADER}: 2f1c8a5e-run").to_lowercase()),
+            "{request_head}"
+        );
+
+        let token_client = api_client.with_token("token".into());
+        assert_eq!(
+            token_client
+                .request_headers()
+                .get(CORRELATION_ID_HEADER)
+                .map(String::as_str),
+            Some("2f1c8a5e-run")
+        );
+    }
 }
@@ -0,0 +1,64 @@
+#!/usr/bin/env python3
+"""Build the build-id MMAP2 fixtures. The fixtures are committed, rebuild them after editing this
+script.
+
+Each fixture is a perf pipe stream with a single MMAP2 record of `aliases.so`, carrying a build-id
+as recorded by `perf record --buildid-mmap`. Its path is relative to the crate root, where the
+tests run. `aliases.pipedata` has the build-id of the committed file, `aliases_rebuilt.pipedata`
+the one of another build of it.
+"""
+
+import os
+import struct
+
+PERF_RECORD_MMAP2 = 10
+PERF_RECORD_HEADER_ATTR = 64
+PERF_RECORD_MISC_USER = 2
+PERF_RECORD_MISC_MMAP_BUILD_ID = 1 << 14
+
+MODULE_PATH = b"testdata/module_symbols/aliases.so"
+# `readelf -n ../module_symbols/aliases.so`
+BUILD_ID = bytes.fromhex("19a4d69f16f4afe0b4e585b676d848240bdedb73")
+REBUILT_BUILD_ID = bytes.fromhex("e6a4d69f16f4afe0b4e585b676d848240bdedb73")
+
+
+def record(record_type, misc, body):
+    return struct.pack("<IHH", record_type, misc, 8 + len(body)) + body
+
+
+def attr_record():
+    # struct perf_event_attr, PERF_ATTR_SIZE_VER7: cpu-clock software event sampling the tid and
+    # time, with mmap2 (bit 23) and build_id (bit 34) set
+    sample_type = 0x2 | 0x4
+    flags = (1 << 23) | (1 << 34)
+    attr = struct.pack("<IIQQQQQ", 1, 128, 0, 4000, sample_type, 0, flags)
+    attr = attr.ljust(128, b"\0")
+    return record(PERF_RECORD_HEADER_ATTR, 0, attr + struct.pack("<Q", 1))
+
+
+def mmap2_record(build_id):
+    pid = 1000
+    # The executable segment of aliases.so: offset 0x1000, vaddr 0x1000
+    address, length, page_offset = 0x7F00_0000_1000, 0x1000, 0x1000
+    prot_read_exec, map_private = 0x5, 0x2
+    path = MODULE_PATH + b"\0"
+    path = path.ljust((len(path) + 7) // 8 * 8, b"\0")
+    body = struct.pack("<IIQQQ", pid, pid, address, length, page_offset)
+    body += struct.pack("<BBH", len(build_id), 0, 0) + build_id.ljust(20, b"\0")
+    body += struct.pack("<II", prot_read_exec, map_private) + path
+    return record(
+        PERF_RECORD_MMAP2,
+        PERF_RECORD_MISC_USER | PERF_RECORD_MISC_MMAP_BUILD_ID,
+        body,
+    )
+
+
+def pipe_stream(build_id):
+    header = b"PERFILE2" + struct.pack("<Q", 16)
+    return header + attr_record() + mmap2_record(build_id)
+
+
+os.chdir(os.path.dirname(os.path.abspath(__file__)))
+for name, build_id in [("aliases", BUILD_ID), ("aliases_rebuilt", REBUILT_BUILD_ID)]:
+    with open(f"{name}.pipedata", "wb") as f:
+        f.write(pipe_stream(build_id))
@@ -0,0 +1,10 @@
+#!/usr/bin/env bash
+# Build the load bias fixtures. The fixtures are committed, rebuild them after editing their
+# sources.
+set -euo pipefail
+cd "$(dirname "$0")"
+
+gcc -nostdlib -static -no-pie -O1 -Wl,--build-id=none -o non_pie.bin main.c
+gcc -nostdlib -static-pie -fPIE -O1 -Wl,--build-id=none -o pie.bin main.c
+gcc -nostdlib -static-pie -fPIE -O1 -Wl,--build-id=none -Wl,--section-start=.text_far=0x200000 \
+  -o two_text_segments.bin two_text_segments.c
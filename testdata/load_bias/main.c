@@ -0,0 +1,5 @@
+// A minimal executable, built both as a non-PIE (`ET_EXEC`) and a PIE (`ET_DYN`) binary.
+void _start(void) {
+  for (;;) {
+  }
+}
@@ -0,0 +1,10 @@
+// An executable with two executable segments: `.text` and `.text_far`, placed at a distant
+// address by the linker.
+__attribute__((section(".text_far"), noinline)) int far_fn(int x) { return x * 3; }
+
+void _start(void) {
+  volatile int x = 1;
+  for (;;) {
+    x = far_fn(x);
+  }
+}
@@ -0,0 +1,7 @@
+// A function exported with a weak alias: the shared library has both symbols in its `.symtab`
+// and `.dynsym` tables, all four at the same address with the same size.
+int strong_fn(int x) { return x * 2 + 1; }
+
+int weak_alias(int x) __attribute__((weak, alias("strong_fn")));
+
+int other_fn(int x) { return x - 1; }
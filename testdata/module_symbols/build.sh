@@ -0,0 +1,9 @@
+#!/usr/bin/env bash
+# Build the module symbols fixtures. The fixtures are committed, rebuild them after editing their
+# sources.
+set -euo pipefail
+cd "$(dirname "$0")"
+
+for source in *.c; do
+  gcc -shared -fPIC -nostdlib -O1 -o "${source%.c}.so" "$source"
+done
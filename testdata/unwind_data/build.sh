@@ -0,0 +1,9 @@
+#!/usr/bin/env bash
+# Build the unwind data fixtures. The fixtures are committed, rebuild them after editing their
+# sources.
+set -euo pipefail
+cd "$(dirname "$0")"
+
+# A self-contained shared object with `.eh_frame` but no `.eh_frame_hdr`
+gcc -nostdlib -shared -fPIC -O1 -fasynchronous-unwind-tables -Wl,--no-eh-frame-hdr \
+  -Wl,--build-id=none -Wl,-soname,no_eh_frame_hdr.so -o no_eh_frame_hdr.so no_eh_frame_hdr.c
@@ -0,0 +1,10 @@
+// A shared object without any dependency, linked without `.eh_frame_hdr`.
+__attribute__((noinline)) static int fib(int n) { return n < 2 ? n : fib(n - 1) + fib(n - 2); }
+
+int entry(int n) { return fib(n); }
+
+void _start(void) {
+  entry(10);
+  for (;;) {
+  }
+}